@@ -1,27 +1,56 @@
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::sea_query::{Expr, LikeExpr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set, TransactionTrait,
 };
 
-use crate::entities::{active_plan, goal, plan, step};
+use crate::entities::{
+    active_plan, criterion, event_hook, goal, milestone, plan, plan_stack, plan_tag, session, step,
+    step_claim, step_tag, tag, webhook, webhook_delivery,
+};
 use crate::error::AppError;
+use crate::git::{self, Checkpoint, CommitTrailers, TrailerIds};
+use crate::import::{ImportedPlan, ImportedStep};
 use crate::model::{
-    GoalChanges, GoalQuery, GoalStatus, PlanChanges, PlanInput, PlanOrder, PlanStatus, StepChanges,
-    StepExecutor, StepOrder, StepQuery, StepStatus,
+    normalize_tag, CriterionStatus, EventKind, GoalChanges, GoalQuery, GoalStatus, MilestoneStatus,
+    PlanChanges, PlanInput, PlanOrder, PlanStatus, StepChanges, StepExecutor, StepOrder, StepQuery,
+    StepSchedule, StepStatus, TagMatch,
+};
+use crate::util::{
+    criteria_progress, format_datetime, format_step_detail, milestone_groups, milestone_progress,
+    step_tree,
 };
-use crate::util::format_step_detail;
+
+pub const DEFAULT_SESSION_TTL_MINUTES: i64 = 120;
+/// Maximum nesting of steps, counting top-level steps as the first level.
+pub const MAX_STEP_DEPTH: usize = 3;
 
 pub struct App {
     db: DatabaseConnection,
     session_id: String,
+    session_ttl: Duration,
+    repo_dir: Option<PathBuf>,
+    checkpoint: OnceLock<Option<Checkpoint>>,
 }
 
 pub struct StepDetail {
     pub step: step::Model,
     pub goals: Vec<goal::Model>,
+    pub tags: Vec<String>,
+    pub milestone: Option<MilestoneProgress>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MilestoneProgress {
+    pub milestone: milestone::Model,
+    pub done: usize,
+    pub total: usize,
 }
 
 pub struct GoalDetail {
@@ -33,13 +62,73 @@ pub struct GoalDetail {
 pub struct StepInput {
     pub content: String,
     pub executor: StepExecutor,
+    pub schedule: StepSchedule,
     pub goals: Vec<String>,
 }
 
+/// The fields shared by every step of one insert.
+struct StepTemplate {
+    status: StepStatus,
+    executor: StepExecutor,
+    schedule: StepSchedule,
+}
+
 pub struct PlanDetail {
     pub plan: plan::Model,
     pub steps: Vec<step::Model>,
     pub goals: HashMap<i64, Vec<goal::Model>>,
+    pub tags: Vec<String>,
+    pub milestones: Vec<milestone::Model>,
+    pub criteria: Vec<criterion::Model>,
+}
+
+/// A plan archived by `archive_done_plans`, with the sessions it was active in cleared and the
+/// plan resumed from the focus stack in its place, if any.
+#[derive(Clone, Debug)]
+pub struct ArchivedPlan {
+    pub plan: plan::Model,
+    pub cleared: bool,
+    pub resumed: Option<plan::Model>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TagTarget {
+    Plan,
+    Step,
+}
+
+#[derive(Clone, Debug)]
+pub struct TagUsage {
+    pub name: String,
+    pub plans: usize,
+    pub steps: usize,
+}
+
+/// The status of every plan, step and goal and the active plans, taken before and after a command
+/// to find the transitions it caused.
+#[derive(Clone, Debug, Default)]
+pub struct StatusSnapshot {
+    pub plans: HashMap<i64, String>,
+    pub steps: HashMap<i64, String>,
+    pub goals: HashMap<i64, String>,
+    pub activations: HashSet<(String, i64)>,
+}
+
+/// What happened to a claimed webhook delivery.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub cwd: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+    pub plan_id: Option<i64>,
+    pub claims: usize,
+    pub stale: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -58,34 +147,130 @@ pub struct PlanStatusChange {
     pub reason: String,
 }
 
+#[derive(Clone, Debug)]
+pub struct MilestoneStatusChange {
+    pub milestone_id: i64,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct ActivePlanCleared {
     pub plan_id: i64,
     pub reason: String,
 }
 
+#[derive(Clone, Debug)]
+pub struct ActivePlanResumed {
+    pub plan_id: i64,
+    pub reason: String,
+}
+
+/// What `git scan` did with an id named by a commit trailer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrailerOutcome {
+    Completed,
+    AlreadyDone,
+    NotFound,
+    Blocked(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct TrailerResult {
+    pub commit: String,
+    pub kind: &'static str,
+    pub id: i64,
+    pub plan_id: Option<i64>,
+    pub outcome: TrailerOutcome,
+}
+
 #[derive(Default, Debug)]
 pub struct StatusChanges {
     pub steps: Vec<StepStatusChange>,
     pub plans: Vec<PlanStatusChange>,
+    pub milestones: Vec<MilestoneStatusChange>,
     pub active_plans_cleared: Vec<ActivePlanCleared>,
+    pub active_plans_resumed: Vec<ActivePlanResumed>,
 }
 
 impl StatusChanges {
     pub fn merge(&mut self, other: StatusChanges) {
         self.steps.extend(other.steps);
         self.plans.extend(other.plans);
+        self.milestones.extend(other.milestones);
         self.active_plans_cleared.extend(other.active_plans_cleared);
+        self.active_plans_resumed.extend(other.active_plans_resumed);
     }
 
     pub fn is_empty(&self) -> bool {
-        self.steps.is_empty() && self.plans.is_empty() && self.active_plans_cleared.is_empty()
+        self.steps.is_empty()
+            && self.plans.is_empty()
+            && self.milestones.is_empty()
+            && self.active_plans_cleared.is_empty()
+            && self.active_plans_resumed.is_empty()
     }
 }
 
 impl App {
     pub fn new(db: DatabaseConnection, session_id: String) -> Self {
-        Self { db, session_id }
+        Self {
+            db,
+            session_id,
+            session_ttl: Duration::minutes(DEFAULT_SESSION_TTL_MINUTES),
+            repo_dir: None,
+            checkpoint: OnceLock::new(),
+        }
+    }
+
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Sets the working tree whose git state is recorded when steps and goals are completed.
+    pub fn with_repo_dir(mut self, dir: PathBuf) -> Self {
+        self.repo_dir = Some(dir);
+        self
+    }
+
+    pub fn repo_dir(&self) -> Option<&Path> {
+        self.repo_dir.as_deref()
+    }
+
+    /// Forgets the captured checkpoint so the next completion reads the git state again; used by
+    /// `serve`, where one `App` handles many requests.
+    pub fn reset_checkpoint(&mut self) {
+        self.checkpoint = OnceLock::new();
+    }
+
+    /// Returns the checkpoint to store on a record moving to `done`, capturing the git state
+    /// once per command. Records leaving `done` get `None`, which clears a stale checkpoint.
+    fn checkpoint_for(&self, done: bool) -> Option<&Checkpoint> {
+        if !done {
+            return None;
+        }
+        self.checkpoint
+            .get_or_init(|| self.repo_dir.as_deref().and_then(git::capture_checkpoint))
+            .as_ref()
+    }
+
+    /// Like `checkpoint_for`, but records `evidence` instead of the current git state when given,
+    /// e.g. the commit whose trailer completed the record.
+    fn checkpoint_or<'a>(
+        &'a self,
+        done: bool,
+        evidence: Option<&'a Checkpoint>,
+    ) -> Option<&'a Checkpoint> {
+        if done {
+            evidence.or_else(|| self.checkpoint_for(true))
+        } else {
+            None
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
     }
 
     pub async fn add_plan(&self, input: PlanInput) -> Result<plan::Model, AppError> {
@@ -97,6 +282,8 @@ impl App {
             content: Set(input.content),
             status: Set(PlanStatus::Todo.as_str().to_string()),
             last_session_id: Set(Some(self.session_id.clone())),
+            priority: Set(input.priority.map(|priority| priority.as_str().to_string())),
+            due: Set(input.due),
             created_at: Set(now),
             updated_at: Set(now),
             ..Default::default()
@@ -131,6 +318,8 @@ impl App {
                 content: Set(input.content),
                 status: Set(PlanStatus::Todo.as_str().to_string()),
                 last_session_id: Set(Some(self.session_id.clone())),
+                priority: Set(input.priority.map(|priority| priority.as_str().to_string())),
+                due: Set(input.due),
                 created_at: Set(now),
                 updated_at: Set(now),
                 ..Default::default()
@@ -151,6 +340,11 @@ impl App {
                     status: Set(StepStatus::Todo.as_str().to_string()),
                     executor: Set(step_input.executor.as_str().to_string()),
                     sort_order: Set((idx + 1) as i32),
+                    priority: Set(step_input
+                        .schedule
+                        .priority
+                        .map(|priority| priority.as_str().to_string())),
+                    due: Set(step_input.schedule.due),
                     created_at: Set(now),
                     updated_at: Set(now),
                     ..Default::default()
@@ -163,11 +357,12 @@ impl App {
                 step_count += 1;
 
                 if !step_input.goals.is_empty() {
-                    for goal_content in step_input.goals {
+                    for (goal_idx, goal_content) in step_input.goals.into_iter().enumerate() {
                         let goal_active = goal::ActiveModel {
                             step_id: Set(step_model.id),
                             content: Set(goal_content),
                             status: Set(GoalStatus::Todo.as_str().to_string()),
+                            sort_order: Set((goal_idx + 1) as i32),
                             created_at: Set(now),
                             updated_at: Set(now),
                             ..Default::default()
@@ -185,6 +380,346 @@ impl App {
         finalize_transaction(txn, result).await
     }
 
+    /// Deep-copies a plan with its steps, goals and tags into a new plan that records the source
+    /// plan id. Statuses are kept unless `reset_status` is set; comments only with `comments`.
+    pub async fn clone_plan(
+        &self,
+        id: i64,
+        title: Option<String>,
+        reset_status: bool,
+        comments: bool,
+    ) -> Result<(plan::Model, usize, usize), AppError> {
+        if let Some(title) = title.as_deref() {
+            ensure_non_empty("plan title", title)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(plan::Model, usize, usize), AppError> = async {
+            let source = plan::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {id}")))?;
+            let status = |value: &str| {
+                if reset_status {
+                    StepStatus::Todo.as_str().to_string()
+                } else {
+                    value.to_string()
+                }
+            };
+            let comment = |value: &Option<String>| if comments { value.clone() } else { None };
+
+            let now = Utc::now();
+            let active_plan = plan::ActiveModel {
+                title: Set(title.unwrap_or_else(|| format!("{} (copy)", source.title))),
+                content: Set(source.content.clone()),
+                status: Set(status(&source.status)),
+                comment: Set(comment(&source.comment)),
+                last_session_id: Set(Some(self.session_id.clone())),
+                priority: Set(source.priority.clone()),
+                due: Set(source.due),
+                source_plan_id: Set(Some(source.id)),
+                done_at: Set((status(&source.status) == PlanStatus::Done.as_str()).then_some(now)),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            };
+            let insert = plan::Entity::insert(active_plan).exec(&txn).await?;
+            let plan_id = insert.last_insert_id;
+
+            let steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(id))
+                .order_by_asc(step::Column::SortOrder)
+                .order_by_asc(step::Column::Id)
+                .all(&txn)
+                .await?;
+            let positions: HashMap<i64, usize> = steps
+                .iter()
+                .enumerate()
+                .map(|(idx, step)| (step.id, idx + 1))
+                .collect();
+            let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
+            let goals = goal::Entity::find()
+                .filter(goal::Column::StepId.is_in(step_ids.clone()))
+                .order_by_asc(goal::Column::SortOrder)
+                .order_by_asc(goal::Column::Id)
+                .all(&txn)
+                .await?;
+
+            for item in self.criteria_with_conn(&txn, &[id]).await? {
+                let active = criterion::ActiveModel {
+                    plan_id: Set(plan_id),
+                    content: Set(item.content.clone()),
+                    status: Set(status(&item.status)),
+                    sort_order: Set(item.sort_order),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                criterion::Entity::insert(active).exec(&txn).await?;
+            }
+
+            let mut cloned_milestones: HashMap<i64, i64> = HashMap::new();
+            for milestone_model in self.milestones_with_conn(&txn, &[id]).await? {
+                let active = milestone::ActiveModel {
+                    plan_id: Set(plan_id),
+                    title: Set(milestone_model.title.clone()),
+                    status: Set(status(&milestone_model.status)),
+                    sort_order: Set(milestone_model.sort_order),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = milestone::Entity::insert(active).exec(&txn).await?;
+                cloned_milestones.insert(milestone_model.id, insert.last_insert_id);
+            }
+
+            let mut cloned_ids: HashMap<i64, i64> = HashMap::new();
+            for (step_model, _) in step_tree(&steps) {
+                let active = step::ActiveModel {
+                    plan_id: Set(plan_id),
+                    parent_id: Set(step_model
+                        .parent_id
+                        .and_then(|parent_id| cloned_ids.get(&parent_id).copied())),
+                    milestone_id: Set(step_model
+                        .milestone_id
+                        .and_then(|milestone_id| cloned_milestones.get(&milestone_id).copied())),
+                    content: Set(step_model.content.clone()),
+                    status: Set(status(&step_model.status)),
+                    executor: Set(step_model.executor.clone()),
+                    sort_order: Set(positions[&step_model.id] as i32),
+                    comment: Set(comment(&step_model.comment)),
+                    priority: Set(step_model.priority.clone()),
+                    due: Set(step_model.due),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = step::Entity::insert(active).exec(&txn).await?;
+                cloned_ids.insert(step_model.id, insert.last_insert_id);
+            }
+
+            for goal_model in &goals {
+                let active = goal::ActiveModel {
+                    step_id: Set(cloned_ids[&goal_model.step_id]),
+                    content: Set(goal_model.content.clone()),
+                    status: Set(status(&goal_model.status)),
+                    sort_order: Set(goal_model.sort_order),
+                    comment: Set(comment(&goal_model.comment)),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                goal::Entity::insert(active).exec(&txn).await?;
+            }
+
+            for (_, tag_id) in self.tag_links_with_conn(&txn, TagTarget::Plan, &[id]).await? {
+                let link = plan_tag::ActiveModel {
+                    plan_id: Set(plan_id),
+                    tag_id: Set(tag_id),
+                    ..Default::default()
+                };
+                plan_tag::Entity::insert(link).exec(&txn).await?;
+            }
+            for (step_id, tag_id) in self
+                .tag_links_with_conn(&txn, TagTarget::Step, &step_ids)
+                .await?
+            {
+                let link = step_tag::ActiveModel {
+                    step_id: Set(cloned_ids[&step_id]),
+                    tag_id: Set(tag_id),
+                    ..Default::default()
+                };
+                step_tag::Entity::insert(link).exec(&txn).await?;
+            }
+
+            let plan_model = plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound("plan not found after insert".to_string()))?;
+            Ok((plan_model, steps.len(), goals.len()))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    /// Creates a plan from a parsed Markdown task list, keeping the checked state of steps, goals
+    /// and criteria. Statuses are then derived bottom-up, so a checked step with an unchecked goal
+    /// is reopened and the plan is done only when everything is.
+    pub async fn import_plan(
+        &self,
+        imported: ImportedPlan,
+    ) -> Result<(plan::Model, usize, usize, StatusChanges), AppError> {
+        fn flatten<'a>(
+            steps: &'a [ImportedStep],
+            parent: Option<usize>,
+            depth: usize,
+            out: &mut Vec<(Option<usize>, &'a ImportedStep)>,
+        ) -> Result<(), AppError> {
+            for step in steps {
+                if depth >= MAX_STEP_DEPTH {
+                    return Err(AppError::InvalidInput(format!(
+                        "step \"{}\" is nested deeper than the maximum sub-step depth \
+                         ({MAX_STEP_DEPTH} levels)",
+                        step.content
+                    )));
+                }
+                out.push((parent, step));
+                let idx = out.len() - 1;
+                flatten(&step.children, Some(idx), depth + 1, out)?;
+            }
+            Ok(())
+        }
+
+        ensure_non_empty("plan title", &imported.title)?;
+        ensure_non_empty("plan content", &imported.content)?;
+        let mut steps = Vec::new();
+        flatten(&imported.steps, None, 0, &mut steps)?;
+        for (_, step) in &steps {
+            ensure_non_empty("step content", &step.content)?;
+            for goal in &step.goals {
+                ensure_non_empty("goal content", &goal.content)?;
+            }
+        }
+        for (content, _) in &imported.criteria {
+            ensure_non_empty("criterion content", content)?;
+        }
+        for title in &imported.milestones {
+            ensure_non_empty("milestone title", title)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(plan::Model, usize, usize, StatusChanges), AppError> = async {
+            let now = Utc::now();
+            let active_plan = plan::ActiveModel {
+                title: Set(imported.title.clone()),
+                content: Set(imported.content.clone()),
+                status: Set(PlanStatus::Todo.as_str().to_string()),
+                last_session_id: Set(Some(self.session_id.clone())),
+                priority: Set(imported.priority.map(|priority| priority.as_str().to_string())),
+                due: Set(imported.due),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            };
+            let insert = plan::Entity::insert(active_plan).exec(&txn).await?;
+            let plan_id = insert.last_insert_id;
+
+            let mut tag_ids = HashSet::new();
+            for name in &imported.tags {
+                let tag_id = self.ensure_tag_with_conn(&txn, name).await?;
+                if tag_ids.insert(tag_id) {
+                    let link = plan_tag::ActiveModel {
+                        plan_id: Set(plan_id),
+                        tag_id: Set(tag_id),
+                        ..Default::default()
+                    };
+                    plan_tag::Entity::insert(link).exec(&txn).await?;
+                }
+            }
+
+            for (idx, (content, met)) in imported.criteria.iter().enumerate() {
+                let active = criterion::ActiveModel {
+                    plan_id: Set(plan_id),
+                    content: Set(content.clone()),
+                    status: Set(if *met {
+                        CriterionStatus::Done
+                    } else {
+                        CriterionStatus::Todo
+                    }
+                    .as_str()
+                    .to_string()),
+                    sort_order: Set((idx + 1) as i32),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                criterion::Entity::insert(active).exec(&txn).await?;
+            }
+
+            let mut milestone_ids = Vec::new();
+            for (idx, title) in imported.milestones.iter().enumerate() {
+                let active = milestone::ActiveModel {
+                    plan_id: Set(plan_id),
+                    title: Set(title.clone()),
+                    status: Set(MilestoneStatus::Todo.as_str().to_string()),
+                    sort_order: Set((idx + 1) as i32),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = milestone::Entity::insert(active).exec(&txn).await?;
+                milestone_ids.push(insert.last_insert_id);
+            }
+
+            let mut step_ids: Vec<i64> = Vec::new();
+            let mut goal_count = 0usize;
+            for (idx, (parent, step_input)) in steps.iter().enumerate() {
+                let active = step::ActiveModel {
+                    plan_id: Set(plan_id),
+                    parent_id: Set(parent.map(|parent| step_ids[parent])),
+                    milestone_id: Set(step_input
+                        .milestone
+                        .and_then(|milestone| milestone_ids.get(milestone).copied())),
+                    content: Set(step_input.content.clone()),
+                    status: Set(if step_input.done {
+                        StepStatus::Done
+                    } else {
+                        StepStatus::Todo
+                    }
+                    .as_str()
+                    .to_string()),
+                    executor: Set(step_input.executor.as_str().to_string()),
+                    sort_order: Set((idx + 1) as i32),
+                    comment: Set(step_input.comment.clone()),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = step::Entity::insert(active).exec(&txn).await?;
+                step_ids.push(insert.last_insert_id);
+
+                for (goal_idx, goal_input) in step_input.goals.iter().enumerate() {
+                    let active = goal::ActiveModel {
+                        step_id: Set(insert.last_insert_id),
+                        content: Set(goal_input.content.clone()),
+                        status: Set(if goal_input.done {
+                            GoalStatus::Done
+                        } else {
+                            GoalStatus::Todo
+                        }
+                        .as_str()
+                        .to_string()),
+                        sort_order: Set((goal_idx + 1) as i32),
+                        comment: Set(goal_input.comment.clone()),
+                        created_at: Set(now),
+                        updated_at: Set(now),
+                        ..Default::default()
+                    };
+                    goal::Entity::insert(active).exec(&txn).await?;
+                    goal_count += 1;
+                }
+            }
+
+            // Children were inserted after their parents, so refreshing in reverse settles each
+            // sub-tree before its parent is looked at.
+            let mut changes = StatusChanges::default();
+            for step_id in step_ids.iter().rev() {
+                changes.merge(self.refresh_step_status_with_conn(&txn, *step_id).await?);
+            }
+            changes.merge(self.refresh_plan_status_with_conn(&txn, plan_id).await?);
+
+            let plan_model = plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound("plan not found after insert".to_string()))?;
+            Ok((plan_model, step_ids.len(), goal_count, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
     pub async fn list_plans(
         &self,
         order: Option<PlanOrder>,
@@ -201,6 +736,21 @@ impl App {
             (PlanOrder::Created, false) => select = select.order_by_asc(plan::Column::CreatedAt),
             (PlanOrder::Updated, true) => select = select.order_by_desc(plan::Column::UpdatedAt),
             (PlanOrder::Updated, false) => select = select.order_by_asc(plan::Column::UpdatedAt),
+            (PlanOrder::Priority, desc) => {
+                select = select
+                    .order_by(Expr::col(plan::Column::Priority).is_null(), Order::Asc)
+                    .order_by(plan::Column::Priority, sort_direction(desc))
+                    .order_by_asc(plan::Column::Due);
+            }
+            (PlanOrder::Due, desc) => {
+                select = select
+                    .order_by(Expr::col(plan::Column::Due).is_null(), Order::Asc)
+                    .order_by(plan::Column::Due, sort_direction(desc))
+                    .order_by_asc(plan::Column::Priority);
+            }
+            // Progress depends on step counts; callers sort loaded details with
+            // `sort_plan_details_by_progress`, using recency as the tie-break.
+            (PlanOrder::Progress, _) => select = select.order_by_desc(plan::Column::UpdatedAt),
         }
         Ok(select.order_by_asc(plan::Column::Id).all(&self.db).await?)
     }
@@ -246,13 +796,40 @@ impl App {
         let (plan, steps) = self.plan_with_steps(id).await?;
         let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
         let goals = self.goals_for_steps(&step_ids).await?;
-        Ok(PlanDetail { plan, steps, goals })
+        let tags = self
+            .tags_for(TagTarget::Plan, &[plan.id])
+            .await?
+            .remove(&plan.id)
+            .unwrap_or_default();
+        let milestones = self.milestones_with_conn(&self.db, &[plan.id]).await?;
+        let criteria = self.criteria_with_conn(&self.db, &[plan.id]).await?;
+        Ok(PlanDetail {
+            plan,
+            steps,
+            goals,
+            tags,
+            milestones,
+            criteria,
+        })
     }
 
     pub async fn get_step_detail(&self, id: i64) -> Result<StepDetail, AppError> {
         let step = self.get_step(id).await?;
         let goals = self.goals_for_step(step.id).await?;
-        Ok(StepDetail { step, goals })
+        let tags = self
+            .tags_for(TagTarget::Step, &[step.id])
+            .await?
+            .remove(&step.id)
+            .unwrap_or_default();
+        let milestone = self
+            .milestone_progress_with_conn(&self.db, step.milestone_id)
+            .await?;
+        Ok(StepDetail {
+            step,
+            goals,
+            tags,
+            milestone,
+        })
     }
 
     pub async fn get_goal_detail(&self, id: i64) -> Result<GoalDetail, AppError> {
@@ -269,6 +846,18 @@ impl App {
             return Ok(Vec::new());
         }
         let plan_ids: Vec<i64> = plans.iter().map(|plan| plan.id).collect();
+        let mut tags_by_plan = self.tags_for(TagTarget::Plan, &plan_ids).await?;
+        let mut milestones_by_plan: HashMap<i64, Vec<milestone::Model>> = HashMap::new();
+        for milestone_model in self.milestones_with_conn(&self.db, &plan_ids).await? {
+            milestones_by_plan
+                .entry(milestone_model.plan_id)
+                .or_default()
+                .push(milestone_model);
+        }
+        let mut criteria_by_plan: HashMap<i64, Vec<criterion::Model>> = HashMap::new();
+        for item in self.criteria_with_conn(&self.db, &plan_ids).await? {
+            criteria_by_plan.entry(item.plan_id).or_default().push(item);
+        }
         let steps = step::Entity::find()
             .filter(step::Column::PlanId.is_in(plan_ids))
             .order_by_asc(step::Column::SortOrder)
@@ -296,6 +885,9 @@ impl App {
                 plan: plan.clone(),
                 steps,
                 goals,
+                tags: tags_by_plan.remove(&plan.id).unwrap_or_default(),
+                milestones: milestones_by_plan.remove(&plan.id).unwrap_or_default(),
+                criteria: criteria_by_plan.remove(&plan.id).unwrap_or_default(),
             });
         }
 
@@ -311,670 +903,3024 @@ impl App {
         }
         let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
         let goals_map = self.goals_for_steps(&step_ids).await?;
+        let mut tags_map = self.tags_for(TagTarget::Step, &step_ids).await?;
+        let mut progress: HashMap<i64, Option<MilestoneProgress>> = HashMap::new();
         let mut details = Vec::with_capacity(steps.len());
         for step in steps {
             let goals = goals_map.get(&step.id).cloned().unwrap_or_default();
+            let milestone = match step.milestone_id {
+                Some(id) => match progress.get(&id) {
+                    Some(cached) => cached.clone(),
+                    None => {
+                        let loaded = self.milestone_progress_with_conn(&self.db, Some(id)).await?;
+                        progress.insert(id, loaded.clone());
+                        loaded
+                    }
+                },
+                None => None,
+            };
             details.push(StepDetail {
                 step: step.clone(),
                 goals,
+                tags: tags_map.remove(&step.id).unwrap_or_default(),
+                milestone,
             });
         }
         Ok(details)
     }
 
-    pub async fn get_active_plan(&self) -> Result<Option<active_plan::Model>, AppError> {
-        Ok(active_plan::Entity::find()
-            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
-            .one(&self.db)
-            .await?)
-    }
-
-    pub async fn set_active_plan(
+    pub async fn tag(
         &self,
-        plan_id: i64,
-        takeover: bool,
-    ) -> Result<active_plan::Model, AppError> {
-        self.get_plan(plan_id).await?;
-        let now = Utc::now();
+        target: TagTarget,
+        id: i64,
+        names: &[String],
+    ) -> Result<Vec<String>, AppError> {
+        let names = normalize_tags(names)?;
+        self.ensure_tag_target_exists(target, id).await?;
         let txn = self.db.begin().await?;
-        if let Some(existing) = active_plan::Entity::find()
-            .filter(active_plan::Column::PlanId.eq(plan_id))
-            .one(&txn)
-            .await?
-        {
-            if existing.session_id != self.session_id && !takeover {
-                txn.rollback().await?;
-                return Err(AppError::InvalidInput(format!(
-                    "plan id {plan_id} is already active in session {} (use --force to take over)",
-                    existing.session_id
-                )));
+        let result: Result<(), AppError> = async {
+            let existing: HashSet<i64> = self
+                .tag_links_with_conn(&txn, target, &[id])
+                .await?
+                .into_iter()
+                .map(|(_, tag_id)| tag_id)
+                .collect();
+            for name in &names {
+                let tag_id = self.ensure_tag_with_conn(&txn, name).await?;
+                if existing.contains(&tag_id) {
+                    continue;
+                }
+                match target {
+                    TagTarget::Plan => {
+                        let link = plan_tag::ActiveModel {
+                            plan_id: Set(id),
+                            tag_id: Set(tag_id),
+                            ..Default::default()
+                        };
+                        plan_tag::Entity::insert(link).exec(&txn).await?;
+                    }
+                    TagTarget::Step => {
+                        let link = step_tag::ActiveModel {
+                            step_id: Set(id),
+                            tag_id: Set(tag_id),
+                            ..Default::default()
+                        };
+                        step_tag::Entity::insert(link).exec(&txn).await?;
+                    }
+                }
             }
+            Ok(())
         }
+        .await;
+        finalize_transaction(txn, result).await?;
+        Ok(self.tags_for(target, &[id]).await?.remove(&id).unwrap_or_default())
+    }
+
+    pub async fn untag(
+        &self,
+        target: TagTarget,
+        id: i64,
+        names: &[String],
+    ) -> Result<Vec<String>, AppError> {
+        let names = normalize_tags(names)?;
+        self.ensure_tag_target_exists(target, id).await?;
+        let txn = self.db.begin().await?;
+        let result: Result<(), AppError> = async {
+            let tag_ids: Vec<i64> = tag::Entity::find()
+                .filter(tag::Column::Name.is_in(names))
+                .all(&txn)
+                .await?
+                .into_iter()
+                .map(|tag| tag.id)
+                .collect();
+            match target {
+                TagTarget::Plan => {
+                    plan_tag::Entity::delete_many()
+                        .filter(plan_tag::Column::PlanId.eq(id))
+                        .filter(plan_tag::Column::TagId.is_in(tag_ids))
+                        .exec(&txn)
+                        .await?;
+                }
+                TagTarget::Step => {
+                    step_tag::Entity::delete_many()
+                        .filter(step_tag::Column::StepId.eq(id))
+                        .filter(step_tag::Column::TagId.is_in(tag_ids))
+                        .exec(&txn)
+                        .await?;
+                }
+            }
+            self.prune_unused_tags_with_conn(&txn).await
+        }
+        .await;
+        finalize_transaction(txn, result).await?;
+        Ok(self.tags_for(target, &[id]).await?.remove(&id).unwrap_or_default())
+    }
+
+    pub async fn tags_for(
+        &self,
+        target: TagTarget,
+        ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<String>>, AppError> {
+        let links = self.tag_links_with_conn(&self.db, target, ids).await?;
+        if links.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let tag_ids: Vec<i64> = links.iter().map(|(_, tag_id)| *tag_id).collect();
+        let names: HashMap<i64, String> = tag::Entity::find()
+            .filter(tag::Column::Id.is_in(tag_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|tag| (tag.id, tag.name))
+            .collect();
+        let mut grouped: HashMap<i64, Vec<String>> = HashMap::new();
+        for (owner_id, tag_id) in links {
+            if let Some(name) = names.get(&tag_id) {
+                grouped.entry(owner_id).or_default().push(name.clone());
+            }
+        }
+        for tags in grouped.values_mut() {
+            tags.sort();
+        }
+        Ok(grouped)
+    }
+
+    /// Returns the ids of plans or steps carrying any (or all) of the given tags.
+    pub async fn ids_with_tags(
+        &self,
+        target: TagTarget,
+        names: &[String],
+        mode: TagMatch,
+    ) -> Result<HashSet<i64>, AppError> {
+        let names = normalize_tags(names)?;
+        let tag_ids: Vec<i64> = tag::Entity::find()
+            .filter(tag::Column::Name.is_in(names.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|tag| tag.id)
+            .collect();
+        if tag_ids.is_empty() || (mode == TagMatch::All && tag_ids.len() < names.len()) {
+            return Ok(HashSet::new());
+        }
+        let links: Vec<(i64, i64)> = match target {
+            TagTarget::Plan => plan_tag::Entity::find()
+                .filter(plan_tag::Column::TagId.is_in(tag_ids.clone()))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|link| (link.plan_id, link.tag_id))
+                .collect(),
+            TagTarget::Step => step_tag::Entity::find()
+                .filter(step_tag::Column::TagId.is_in(tag_ids.clone()))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|link| (link.step_id, link.tag_id))
+                .collect(),
+        };
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for (owner_id, _) in links {
+            *counts.entry(owner_id).or_default() += 1;
+        }
+        let required = match mode {
+            TagMatch::Any => 1,
+            TagMatch::All => tag_ids.len(),
+        };
+        Ok(counts
+            .into_iter()
+            .filter(|(_, count)| *count >= required)
+            .map(|(owner_id, _)| owner_id)
+            .collect())
+    }
+
+    pub async fn list_tags(&self) -> Result<Vec<TagUsage>, AppError> {
+        let tags = tag::Entity::find()
+            .order_by_asc(tag::Column::Name)
+            .all(&self.db)
+            .await?;
+        let mut plan_counts: HashMap<i64, usize> = HashMap::new();
+        for link in plan_tag::Entity::find().all(&self.db).await? {
+            *plan_counts.entry(link.tag_id).or_default() += 1;
+        }
+        let mut step_counts: HashMap<i64, usize> = HashMap::new();
+        for link in step_tag::Entity::find().all(&self.db).await? {
+            *step_counts.entry(link.tag_id).or_default() += 1;
+        }
+        Ok(tags
+            .into_iter()
+            .map(|tag| TagUsage {
+                plans: plan_counts.get(&tag.id).copied().unwrap_or(0),
+                steps: step_counts.get(&tag.id).copied().unwrap_or(0),
+                name: tag.name,
+            })
+            .filter(|usage| usage.plans + usage.steps > 0)
+            .collect())
+    }
+
+    async fn ensure_tag_target_exists(&self, target: TagTarget, id: i64) -> Result<(), AppError> {
+        match target {
+            TagTarget::Plan => self.get_plan(id).await.map(|_| ()),
+            TagTarget::Step => self.get_step(id).await.map(|_| ()),
+        }
+    }
+
+    async fn ensure_tag_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        name: &str,
+    ) -> Result<i64, AppError> {
+        if let Some(existing) = tag::Entity::find()
+            .filter(tag::Column::Name.eq(name))
+            .one(db)
+            .await?
+        {
+            return Ok(existing.id);
+        }
+        let active = tag::ActiveModel {
+            name: Set(name.to_string()),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let insert = tag::Entity::insert(active).exec(db).await?;
+        Ok(insert.last_insert_id)
+    }
+
+    async fn tag_links_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        target: TagTarget,
+        ids: &[i64],
+    ) -> Result<Vec<(i64, i64)>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(match target {
+            TagTarget::Plan => plan_tag::Entity::find()
+                .filter(plan_tag::Column::PlanId.is_in(ids.to_vec()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|link| (link.plan_id, link.tag_id))
+                .collect(),
+            TagTarget::Step => step_tag::Entity::find()
+                .filter(step_tag::Column::StepId.is_in(ids.to_vec()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|link| (link.step_id, link.tag_id))
+                .collect(),
+        })
+    }
+
+    async fn prune_unused_tags_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+    ) -> Result<(), AppError> {
+        let used: HashSet<i64> = plan_tag::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|link| link.tag_id)
+            .chain(
+                step_tag::Entity::find()
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|link| link.tag_id),
+            )
+            .collect();
+        let unused: Vec<i64> = tag::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|tag| tag.id)
+            .filter(|id| !used.contains(id))
+            .collect();
+        if !unused.is_empty() {
+            tag::Entity::delete_many()
+                .filter(tag::Column::Id.is_in(unused))
+                .exec(db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Adds a milestone at the end of the plan's milestones and moves `step_ids` (top-level
+    /// steps, with their sub-steps) into it.
+    pub async fn add_milestone(
+        &self,
+        plan_id: i64,
+        title: String,
+        step_ids: &[i64],
+    ) -> Result<(milestone::Model, StatusChanges), AppError> {
+        ensure_non_empty("milestone title", &title)?;
+
+        let txn = self.db.begin().await?;
+        let result: Result<(milestone::Model, StatusChanges), AppError> = async {
+            plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {plan_id}")))?;
+            let existing = self.milestones_with_conn(&txn, &[plan_id]).await?;
+            let sort_order = existing
+                .iter()
+                .map(|milestone| milestone.sort_order)
+                .max()
+                .unwrap_or(0)
+                + 1;
+            let now = Utc::now();
+            let active = milestone::ActiveModel {
+                plan_id: Set(plan_id),
+                title: Set(title),
+                status: Set(MilestoneStatus::Todo.as_str().to_string()),
+                sort_order: Set(sort_order),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            };
+            let insert = milestone::Entity::insert(active).exec(&txn).await?;
+            let changes = self
+                .assign_milestone_with_conn(&txn, insert.last_insert_id, step_ids)
+                .await?;
+            let milestone_model = milestone::Entity::find_by_id(insert.last_insert_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound("milestone not found after insert".to_string()))?;
+            Ok((milestone_model, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    /// Moves top-level steps, with their sub-steps, into milestone `id`.
+    pub async fn assign_milestone(
+        &self,
+        id: i64,
+        step_ids: &[i64],
+    ) -> Result<(milestone::Model, StatusChanges), AppError> {
+        if step_ids.is_empty() {
+            return Err(AppError::InvalidInput("no step ids provided".to_string()));
+        }
+        let txn = self.db.begin().await?;
+        let result: Result<(milestone::Model, StatusChanges), AppError> = async {
+            let changes = self.assign_milestone_with_conn(&txn, id, step_ids).await?;
+            let milestone_model = milestone::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("milestone id {id}")))?;
+            Ok((milestone_model, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn assign_milestone_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: i64,
+        step_ids: &[i64],
+    ) -> Result<StatusChanges, AppError> {
+        let milestone_model = milestone::Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("milestone id {id}")))?;
+        let plan_id = milestone_model.plan_id;
+        let steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .all(db)
+            .await?;
+        let mut members = HashSet::new();
+        for step_id in unique_ids(step_ids) {
+            let Some(step_model) = steps.iter().find(|step| step.id == step_id) else {
+                let other = step::Entity::find_by_id(step_id)
+                    .one(db)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("step id {step_id}")))?;
+                return Err(AppError::InvalidInput(format!(
+                    "step id {step_id} belongs to plan id {}, not plan id {plan_id}",
+                    other.plan_id
+                )));
+            };
+            if let Some(parent_id) = step_model.parent_id {
+                return Err(AppError::InvalidInput(format!(
+                    "step id {step_id} is a sub-step of step id {parent_id}; \
+                     assign its top-level step instead"
+                )));
+            }
+            members.insert(step_id);
+            members.extend(descendant_ids(&steps, step_id));
+        }
+        if members.is_empty() {
+            return Ok(StatusChanges::default());
+        }
+
+        step::Entity::update_many()
+            .col_expr(step::Column::MilestoneId, Expr::value(id))
+            .col_expr(step::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(step::Column::Id.is_in(members))
+            .exec(db)
+            .await?;
+        let changes = self.refresh_plan_status_with_conn(db, plan_id).await?;
+        self.touch_plan_with_conn(db, plan_id).await?;
+        Ok(changes)
+    }
+
+    pub async fn get_milestone(&self, id: i64) -> Result<milestone::Model, AppError> {
+        milestone::Entity::find_by_id(id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("milestone id {id}")))
+    }
+
+    pub async fn list_milestones(&self, plan_id: i64) -> Result<Vec<MilestoneProgress>, AppError> {
+        let (_, steps) = self.plan_with_steps(plan_id).await?;
+        let milestones = self.milestones_with_conn(&self.db, &[plan_id]).await?;
+        Ok(milestones
+            .into_iter()
+            .map(|milestone| {
+                let (done, total) = milestone_progress(&steps, milestone.id);
+                MilestoneProgress {
+                    milestone,
+                    done,
+                    total,
+                }
+            })
+            .collect())
+    }
+
+    /// Marks a milestone done. Like `plan done`, this only succeeds once none of its steps is
+    /// pending; milestones with steps otherwise follow their steps automatically.
+    pub async fn complete_milestone(&self, id: i64) -> Result<milestone::Model, AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<milestone::Model, AppError> = async {
+            let milestone_model = milestone::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("milestone id {id}")))?;
+            let plan_id = milestone_model.plan_id;
+            let steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(plan_id))
+                .order_by_asc(step::Column::SortOrder)
+                .order_by_asc(step::Column::Id)
+                .all(&txn)
+                .await?;
+            let pending = actionable_steps(&steps, &[])
+                .into_iter()
+                .find(|step| step.milestone_id == Some(id))
+                .cloned();
+            if let Some(pending) = pending {
+                let goals = self.goals_for_step_with_conn(&txn, pending.id).await?;
+                let detail = format_step_detail(&pending, &goals, &[], None);
+                return Err(AppError::InvalidInput(format!(
+                    "cannot mark milestone done; next pending step:\n{detail}"
+                )));
+            }
+            if milestone_model.status == MilestoneStatus::Done.as_str() {
+                return Ok(milestone_model);
+            }
+            let mut active: milestone::ActiveModel = milestone_model.into();
+            active.status = Set(MilestoneStatus::Done.as_str().to_string());
+            active.updated_at = Set(Utc::now());
+            let updated = active.update(&txn).await?;
+            self.touch_plan_with_conn(&txn, plan_id).await?;
+            Ok(updated)
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn milestones_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_ids: &[i64],
+    ) -> Result<Vec<milestone::Model>, AppError> {
+        if plan_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(milestone::Entity::find()
+            .filter(milestone::Column::PlanId.is_in(plan_ids.to_vec()))
+            .order_by_asc(milestone::Column::PlanId)
+            .order_by_asc(milestone::Column::SortOrder)
+            .order_by_asc(milestone::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    async fn milestone_progress_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        milestone_id: Option<i64>,
+    ) -> Result<Option<MilestoneProgress>, AppError> {
+        let Some(milestone_id) = milestone_id else {
+            return Ok(None);
+        };
+        let Some(milestone_model) = milestone::Entity::find_by_id(milestone_id).one(db).await?
+        else {
+            return Ok(None);
+        };
+        let steps = step::Entity::find()
+            .filter(step::Column::MilestoneId.eq(milestone_id))
+            .all(db)
+            .await?;
+        let (done, total) = milestone_progress(&steps, milestone_id);
+        Ok(Some(MilestoneProgress {
+            milestone: milestone_model,
+            done,
+            total,
+        }))
+    }
+
+    /// Derives each milestone's status from its steps. Milestones without steps keep the status
+    /// set by `milestone done`.
+    async fn refresh_milestones_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_id: i64,
+    ) -> Result<StatusChanges, AppError> {
+        let mut changes = StatusChanges::default();
+        let milestones = self.milestones_with_conn(db, &[plan_id]).await?;
+        if milestones.is_empty() {
+            return Ok(changes);
+        }
+        let steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .all(db)
+            .await?;
+        for milestone_model in milestones {
+            let (done, total) = milestone_progress(&steps, milestone_model.id);
+            if total == 0 {
+                continue;
+            }
+            let (status, reason) = if done == total {
+                (MilestoneStatus::Done, format!("all steps are done ({done}/{total})"))
+            } else {
+                (MilestoneStatus::Todo, format!("steps done {done}/{total}"))
+            };
+            if milestone_model.status == status.as_str() {
+                continue;
+            }
+            let milestone_id = milestone_model.id;
+            let from = milestone_model.status.clone();
+            let mut active: milestone::ActiveModel = milestone_model.into();
+            active.status = Set(status.as_str().to_string());
+            active.updated_at = Set(Utc::now());
+            active.update(db).await?;
+            changes.milestones.push(MilestoneStatusChange {
+                milestone_id,
+                from,
+                to: status.as_str().to_string(),
+                reason,
+            });
+        }
+        Ok(changes)
+    }
+
+    /// Appends acceptance criteria to a plan. New criteria start unmet, so a done plan reopens.
+    pub async fn add_criteria(
+        &self,
+        plan_id: i64,
+        contents: Vec<String>,
+    ) -> Result<(Vec<criterion::Model>, StatusChanges), AppError> {
+        for content in &contents {
+            ensure_non_empty("criterion content", content)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<criterion::Model>, StatusChanges), AppError> = async {
+            plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {plan_id}")))?;
+            let existing = self.criteria_with_conn(&txn, &[plan_id]).await?;
+            let last = existing.iter().map(|item| item.sort_order).max().unwrap_or(0);
+            let now = Utc::now();
+            let mut created = Vec::with_capacity(contents.len());
+            for (idx, content) in contents.into_iter().enumerate() {
+                let active = criterion::ActiveModel {
+                    plan_id: Set(plan_id),
+                    content: Set(content),
+                    status: Set(CriterionStatus::Todo.as_str().to_string()),
+                    sort_order: Set(last + idx as i32 + 1),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = criterion::Entity::insert(active).exec(&txn).await?;
+                let model = criterion::Entity::find_by_id(insert.last_insert_id)
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::NotFound("criterion not found after insert".to_string())
+                    })?;
+                created.push(model);
+            }
+            let changes = self.refresh_plan_status_with_conn(&txn, plan_id).await?;
+            self.touch_plan_with_conn(&txn, plan_id).await?;
+            Ok((created, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn list_criteria(&self, plan_id: i64) -> Result<Vec<criterion::Model>, AppError> {
+        self.get_plan(plan_id).await?;
+        self.criteria_with_conn(&self.db, &[plan_id]).await
+    }
+
+    /// Checks (`done`) or unchecks (`todo`) criteria, then refreshes their plans.
+    pub async fn set_criteria_status(
+        &self,
+        ids: &[i64],
+        status: CriterionStatus,
+    ) -> Result<(Vec<criterion::Model>, StatusChanges), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<criterion::Model>, StatusChanges), AppError> = async {
+            let items = self.criteria_by_ids_with_conn(&txn, ids).await?;
+            let now = Utc::now();
+            let mut updated = Vec::with_capacity(items.len());
+            let mut plan_ids = Vec::new();
+            for item in items {
+                if !plan_ids.contains(&item.plan_id) {
+                    plan_ids.push(item.plan_id);
+                }
+                if item.status == status.as_str() {
+                    updated.push(item);
+                    continue;
+                }
+                let mut active: criterion::ActiveModel = item.into();
+                active.status = Set(status.as_str().to_string());
+                active.updated_at = Set(now);
+                updated.push(active.update(&txn).await?);
+            }
+            let mut changes = StatusChanges::default();
+            for plan_id in &plan_ids {
+                let refreshed = self.refresh_plan_status_with_conn(&txn, *plan_id).await?;
+                changes.merge(refreshed);
+            }
+            self.touch_plans_with_conn(&txn, &plan_ids).await?;
+            Ok((updated, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    /// Removes criteria and returns them, then refreshes their plans.
+    pub async fn delete_criteria(
+        &self,
+        ids: &[i64],
+    ) -> Result<(Vec<criterion::Model>, StatusChanges), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<criterion::Model>, StatusChanges), AppError> = async {
+            let items = self.criteria_by_ids_with_conn(&txn, ids).await?;
+            let mut plan_ids = Vec::new();
+            for item in &items {
+                if !plan_ids.contains(&item.plan_id) {
+                    plan_ids.push(item.plan_id);
+                }
+            }
+            criterion::Entity::delete_many()
+                .filter(criterion::Column::Id.is_in(unique_ids(ids)))
+                .exec(&txn)
+                .await?;
+            let mut changes = StatusChanges::default();
+            for plan_id in &plan_ids {
+                let refreshed = self.refresh_plan_status_with_conn(&txn, *plan_id).await?;
+                changes.merge(refreshed);
+            }
+            self.touch_plans_with_conn(&txn, &plan_ids).await?;
+            Ok((items, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn criteria_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_ids: &[i64],
+    ) -> Result<Vec<criterion::Model>, AppError> {
+        if plan_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(criterion::Entity::find()
+            .filter(criterion::Column::PlanId.is_in(plan_ids.to_vec()))
+            .order_by_asc(criterion::Column::PlanId)
+            .order_by_asc(criterion::Column::SortOrder)
+            .order_by_asc(criterion::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    async fn criteria_by_ids_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        ids: &[i64],
+    ) -> Result<Vec<criterion::Model>, AppError> {
+        if ids.is_empty() {
+            return Err(AppError::InvalidInput("no criterion ids provided".to_string()));
+        }
+        let unique_ids = unique_ids(ids);
+        let items = criterion::Entity::find()
+            .filter(criterion::Column::Id.is_in(unique_ids.clone()))
+            .order_by_asc(criterion::Column::Id)
+            .all(db)
+            .await?;
+        let existing: HashSet<i64> = items.iter().map(|item| item.id).collect();
+        let missing: Vec<i64> = unique_ids
+            .into_iter()
+            .filter(|id| !existing.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "criterion id(s) not found: {}",
+                join_ids(&missing)
+            )));
+        }
+        Ok(items)
+    }
+
+    pub async fn get_active_plan(&self) -> Result<Option<active_plan::Model>, AppError> {
+        Ok(active_plan::Entity::find()
+            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .one(&self.db)
+            .await?)
+    }
+
+    pub async fn list_active_plans(&self) -> Result<Vec<active_plan::Model>, AppError> {
+        Ok(active_plan::Entity::find()
+            .order_by_asc(active_plan::Column::SessionId)
+            .all(&self.db)
+            .await?)
+    }
+
+    pub async fn status_snapshot(&self) -> Result<StatusSnapshot, AppError> {
+        let plans = plan::Entity::find()
+            .select_only()
+            .columns([plan::Column::Id, plan::Column::Status])
+            .into_tuple::<(i64, String)>()
+            .all(&self.db)
+            .await?;
+        let steps = step::Entity::find()
+            .select_only()
+            .columns([step::Column::Id, step::Column::Status])
+            .into_tuple::<(i64, String)>()
+            .all(&self.db)
+            .await?;
+        let goals = goal::Entity::find()
+            .select_only()
+            .columns([goal::Column::Id, goal::Column::Status])
+            .into_tuple::<(i64, String)>()
+            .all(&self.db)
+            .await?;
+        let activations = self
+            .list_active_plans()
+            .await?
+            .into_iter()
+            .map(|state| (state.session_id, state.plan_id))
+            .collect();
+        Ok(StatusSnapshot {
+            plans: plans.into_iter().collect(),
+            steps: steps.into_iter().collect(),
+            goals: goals.into_iter().collect(),
+            activations,
+        })
+    }
+
+    pub async fn add_event_hook(
+        &self,
+        event: EventKind,
+        command: &str,
+        timeout_secs: u32,
+    ) -> Result<event_hook::Model, AppError> {
+        ensure_non_empty("hook command", command)?;
+        let active = event_hook::ActiveModel {
+            event: Set(event.as_str().to_string()),
+            command: Set(command.trim().to_string()),
+            timeout_secs: Set(timeout_secs as i32),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let insert = event_hook::Entity::insert(active).exec(&self.db).await?;
+        event_hook::Entity::find_by_id(insert.last_insert_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("event hook not found after insert".to_string()))
+    }
+
+    pub async fn list_event_hooks(&self) -> Result<Vec<event_hook::Model>, AppError> {
+        Ok(event_hook::Entity::find()
+            .order_by_asc(event_hook::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    pub async fn remove_event_hooks(&self, ids: &[i64]) -> Result<u64, AppError> {
+        let existing: HashSet<i64> = event_hook::Entity::find()
+            .filter(event_hook::Column::Id.is_in(ids.to_vec()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|hook| hook.id)
+            .collect();
+        let missing: Vec<i64> = ids
+            .iter()
+            .copied()
+            .filter(|id| !existing.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "event hook id(s) not found: {}",
+                join_ids(&missing)
+            )));
+        }
+        let result = event_hook::Entity::delete_many()
+            .filter(event_hook::Column::Id.is_in(ids.to_vec()))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    pub async fn add_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        events: &[EventKind],
+        timeout_secs: u32,
+    ) -> Result<webhook::Model, AppError> {
+        let url = url.trim();
+        let parsed = url::Url::parse(url)
+            .map_err(|err| AppError::InvalidInput(format!("invalid webhook url '{url}': {err}")))?;
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+            return Err(AppError::InvalidInput(format!(
+                "webhook url must be an http:// or https:// url with a host: {url}"
+            )));
+        }
+        ensure_non_empty("webhook secret", secret)?;
+        let mut names: Vec<&str> = events.iter().map(EventKind::as_str).collect();
+        names.sort_unstable();
+        names.dedup();
+        let active = webhook::ActiveModel {
+            url: Set(url.to_string()),
+            secret: Set(secret.to_string()),
+            events: Set(names.join(",")),
+            timeout_secs: Set(timeout_secs as i32),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let insert = webhook::Entity::insert(active).exec(&self.db).await?;
+        webhook::Entity::find_by_id(insert.last_insert_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("webhook not found after insert".to_string()))
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<webhook::Model>, AppError> {
+        Ok(webhook::Entity::find()
+            .order_by_asc(webhook::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Removes the webhooks along with their undelivered events.
+    pub async fn remove_webhooks(&self, ids: &[i64]) -> Result<u64, AppError> {
+        let existing: HashSet<i64> = webhook::Entity::find()
+            .filter(webhook::Column::Id.is_in(ids.to_vec()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|webhook| webhook.id)
+            .collect();
+        let missing: Vec<i64> = ids
+            .iter()
+            .copied()
+            .filter(|id| !existing.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "webhook id(s) not found: {}",
+                join_ids(&missing)
+            )));
+        }
+        let txn = self.db.begin().await?;
+        let result: Result<u64, AppError> = async {
+            webhook_delivery::Entity::delete_many()
+                .filter(webhook_delivery::Column::WebhookId.is_in(ids.to_vec()))
+                .exec(&txn)
+                .await?;
+            let result = webhook::Entity::delete_many()
+                .filter(webhook::Column::Id.is_in(ids.to_vec()))
+                .exec(&txn)
+                .await?;
+            Ok(result.rows_affected)
+        }
+        .await;
+        finalize_transaction(txn, result).await
+    }
+
+    /// Queues `(webhook id, event, payload)` deliveries in one transaction, so a command's events
+    /// are either all in the outbox or none are.
+    pub async fn enqueue_webhook_deliveries(
+        &self,
+        deliveries: &[(i64, EventKind, String)],
+    ) -> Result<Vec<webhook_delivery::Model>, AppError> {
+        if deliveries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let now = Utc::now();
+        let txn = self.db.begin().await?;
+        let result: Result<Vec<webhook_delivery::Model>, AppError> = async {
+            let mut queued = Vec::with_capacity(deliveries.len());
+            for (webhook_id, event, payload) in deliveries {
+                let active = webhook_delivery::ActiveModel {
+                    webhook_id: Set(*webhook_id),
+                    event: Set(event.as_str().to_string()),
+                    payload: Set(payload.clone()),
+                    attempts: Set(0),
+                    next_attempt_at: Set(now),
+                    last_error: Set(None),
+                    created_at: Set(now),
+                    ..Default::default()
+                };
+                queued.push(active.insert(&txn).await?);
+            }
+            Ok(queued)
+        }
+        .await;
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn has_due_webhook_deliveries(&self) -> Result<bool, AppError> {
+        let due = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::NextAttemptAt.lte(Utc::now()))
+            .count(&self.db)
+            .await?;
+        Ok(due > 0)
+    }
+
+    pub async fn list_webhook_deliveries(&self) -> Result<Vec<webhook_delivery::Model>, AppError> {
+        Ok(webhook_delivery::Entity::find()
+            .order_by_asc(webhook_delivery::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Claims the oldest pending delivery of each webhook not in `skip` if it is due (always when
+    /// `force` is set), counting the attempt and pushing the next one back by the retry delay up
+    /// front so another process does not send it again meanwhile. Later deliveries stay untouched
+    /// behind it so they arrive in order. Returns the rows as they were before the claim.
+    pub async fn claim_webhook_deliveries(
+        &self,
+        force: bool,
+        skip: &HashSet<i64>,
+    ) -> Result<Vec<(webhook::Model, webhook_delivery::Model)>, AppError> {
+        let now = Utc::now();
+        let txn = self.db.begin().await?;
+        let result: Result<_, AppError> = async {
+            let deliveries = webhook_delivery::Entity::find()
+                .order_by_asc(webhook_delivery::Column::Id)
+                .all(&txn)
+                .await?;
+            if deliveries.is_empty() {
+                return Ok(Vec::new());
+            }
+            let webhook_ids: HashSet<i64> =
+                deliveries.iter().map(|delivery| delivery.webhook_id).collect();
+            let webhooks: HashMap<i64, webhook::Model> = webhook::Entity::find()
+                .filter(webhook::Column::Id.is_in(webhook_ids))
+                .all(&txn)
+                .await?
+                .into_iter()
+                .map(|webhook| (webhook.id, webhook))
+                .collect();
+            let mut claimed = Vec::new();
+            let mut seen = HashSet::new();
+            for delivery in deliveries {
+                let Some(webhook) = webhooks.get(&delivery.webhook_id) else {
+                    continue;
+                };
+                if !seen.insert(webhook.id)
+                    || skip.contains(&webhook.id)
+                    || (!force && delivery.next_attempt_at > now)
+                {
+                    continue;
+                }
+                let attempts = delivery.attempts + 1;
+                let mut active: webhook_delivery::ActiveModel = delivery.clone().into();
+                active.attempts = Set(attempts);
+                active.next_attempt_at = Set(now + webhook_retry_delay(attempts));
+                active.update(&txn).await?;
+                claimed.push((webhook.clone(), delivery));
+            }
+            Ok(claimed)
+        }
+        .await;
+        finalize_transaction(txn, result).await
+    }
+
+    /// Deletes delivered rows and keeps the error of failed ones for the next retry.
+    pub async fn record_webhook_outcomes(
+        &self,
+        outcomes: &[(webhook_delivery::Model, DeliveryOutcome)],
+    ) -> Result<(), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(), AppError> = async {
+            for (delivery, outcome) in outcomes {
+                let update = webhook_delivery::Entity::update_many()
+                    .filter(webhook_delivery::Column::Id.eq(delivery.id));
+                match outcome {
+                    DeliveryOutcome::Delivered => {
+                        webhook_delivery::Entity::delete_by_id(delivery.id)
+                            .exec(&txn)
+                            .await?;
+                    }
+                    DeliveryOutcome::Failed(error) => {
+                        update
+                            .col_expr(
+                                webhook_delivery::Column::LastError,
+                                Expr::value(error.clone()),
+                            )
+                            .exec(&txn)
+                            .await?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        .await;
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn set_active_plan(
+        &self,
+        plan_id: i64,
+        takeover: bool,
+    ) -> Result<active_plan::Model, AppError> {
+        self.activate_plan(plan_id, takeover, false).await
+    }
+
+    pub async fn share_active_plan(&self, plan_id: i64) -> Result<active_plan::Model, AppError> {
+        self.activate_plan(plan_id, false, true).await
+    }
+
+    async fn activate_plan(
+        &self,
+        plan_id: i64,
+        takeover: bool,
+        shared: bool,
+    ) -> Result<active_plan::Model, AppError> {
+        self.get_plan(plan_id).await?;
+        let txn = self.db.begin().await?;
+        let result = self
+            .activate_plan_with_conn(&txn, plan_id, takeover, shared)
+            .await;
+        finalize_transaction(txn, result).await
+    }
+
+    async fn activate_plan_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_id: i64,
+        takeover: bool,
+        shared: bool,
+    ) -> Result<active_plan::Model, AppError> {
+        let now = Utc::now();
+        let archived = plan::Entity::find_by_id(plan_id)
+            .one(db)
+            .await?
+            .is_some_and(|plan| plan.archived_at.is_some());
+        if archived {
+            return Err(AppError::InvalidInput(format!(
+                "plan id {plan_id} is archived (use plan unarchive first)"
+            )));
+        }
+        if let Some(existing) = active_plan::Entity::find()
+            .filter(active_plan::Column::PlanId.eq(plan_id))
+            .filter(active_plan::Column::SessionId.ne(self.session_id.as_str()))
+            .one(db)
+            .await?
+        {
+            let last_seen = self
+                .session_last_seen_with_conn(db, &existing.session_id, existing.updated_at)
+                .await?;
+            let stale = now - last_seen > self.session_ttl;
+            if !takeover && !shared && !stale {
+                return Err(AppError::InvalidInput(format!(
+                    "plan id {plan_id} is already active in session {} (use --force to take over or --shared to work it in parallel)",
+                    existing.session_id
+                )));
+            }
+        }
+        active_plan::Entity::delete_many()
+            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .exec(db)
+            .await?;
+        if !shared {
+            active_plan::Entity::delete_many()
+                .filter(active_plan::Column::PlanId.eq(plan_id))
+                .exec(db)
+                .await?;
+        }
+
+        let active = active_plan::ActiveModel {
+            session_id: Set(self.session_id.clone()),
+            plan_id: Set(plan_id),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        active_plan::Entity::insert(active).exec(db).await?;
+        self.touch_plan_with_conn(db, plan_id).await?;
+        active_plan::Entity::find()
+            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("active plan not found after insert".to_string()))
+    }
+
+    pub async fn push_active_plan(
+        &self,
+        plan_id: i64,
+        takeover: bool,
+    ) -> Result<(active_plan::Model, Option<i64>), AppError> {
+        self.get_plan(plan_id).await?;
+        let txn = self.db.begin().await?;
+        let result: Result<(active_plan::Model, Option<i64>), AppError> = async {
+            let current = active_plan::Entity::find()
+                .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+                .one(&txn)
+                .await?;
+            if current.as_ref().is_some_and(|state| state.plan_id == plan_id) {
+                return Err(AppError::InvalidInput(format!(
+                    "plan id {plan_id} is already the active plan"
+                )));
+            }
+            let model = self
+                .activate_plan_with_conn(&txn, plan_id, takeover, false)
+                .await?;
+            let suspended = current.map(|state| state.plan_id);
+            if let Some(suspended_id) = suspended {
+                plan_stack::Entity::delete_many()
+                    .filter(plan_stack::Column::SessionId.eq(self.session_id.as_str()))
+                    .filter(plan_stack::Column::PlanId.eq(suspended_id))
+                    .exec(&txn)
+                    .await?;
+                let top = self.plan_stack_with_conn(&txn).await?;
+                let position = top.first().map(|entry| entry.position + 1).unwrap_or(1);
+                let entry = plan_stack::ActiveModel {
+                    session_id: Set(self.session_id.clone()),
+                    plan_id: Set(suspended_id),
+                    position: Set(position),
+                    pushed_at: Set(Utc::now()),
+                    ..Default::default()
+                };
+                plan_stack::Entity::insert(entry).exec(&txn).await?;
+            }
+            plan_stack::Entity::delete_many()
+                .filter(plan_stack::Column::SessionId.eq(self.session_id.as_str()))
+                .filter(plan_stack::Column::PlanId.eq(plan_id))
+                .exec(&txn)
+                .await?;
+            Ok((model, suspended))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn pop_active_plan(&self) -> Result<(Option<i64>, Option<plan::Model>), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(Option<i64>, Option<plan::Model>), AppError> = async {
+            let current = active_plan::Entity::find()
+                .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+                .one(&txn)
+                .await?
+                .map(|state| state.plan_id);
+            if current.is_some() {
+                active_plan::Entity::delete_many()
+                    .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+                    .exec(&txn)
+                    .await?;
+            }
+            let resumed = self.resume_suspended_plan_with_conn(&txn).await?;
+            Ok((current, resumed))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn suspended_plans(&self) -> Result<Vec<plan::Model>, AppError> {
+        let entries = self.plan_stack_with_conn(&self.db).await?;
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<i64> = entries.iter().map(|entry| entry.plan_id).collect();
+        let mut plans: HashMap<i64, plan::Model> = plan::Entity::find()
+            .filter(plan::Column::Id.is_in(ids.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|plan| (plan.id, plan))
+            .collect();
+        Ok(ids.into_iter().filter_map(|id| plans.remove(&id)).collect())
+    }
+
+    async fn plan_stack_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+    ) -> Result<Vec<plan_stack::Model>, AppError> {
+        Ok(plan_stack::Entity::find()
+            .filter(plan_stack::Column::SessionId.eq(self.session_id.as_str()))
+            .order_by_desc(plan_stack::Column::Position)
+            .order_by_desc(plan_stack::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    async fn resume_suspended_plan_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+    ) -> Result<Option<plan::Model>, AppError> {
+        for entry in self.plan_stack_with_conn(db).await? {
+            plan_stack::Entity::delete_by_id(entry.id).exec(db).await?;
+            let Some(plan_model) = plan::Entity::find_by_id(entry.plan_id).one(db).await? else {
+                continue;
+            };
+            if plan_model.status == PlanStatus::Done.as_str() {
+                continue;
+            }
+            // A plan that is archived or live in another session is skipped: resuming runs while
+            // finishing the top plan, which must not fail because of the stack. Activation checks
+            // these before writing anything, so the transaction is still clean.
+            match self
+                .activate_plan_with_conn(db, plan_model.id, false, false)
+                .await
+            {
+                Ok(_) => return Ok(Some(plan_model)),
+                Err(AppError::InvalidInput(_)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn clear_active_plan(&self) -> Result<(), AppError> {
+        active_plan::Entity::delete_many()
+            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn record_heartbeat(&self, cwd: Option<&str>) -> Result<(), AppError> {
+        let now = Utc::now();
+        let existing = session::Entity::find()
+            .filter(session::Column::SessionId.eq(self.session_id.as_str()))
+            .one(&self.db)
+            .await?;
+        match existing {
+            Some(existing) => {
+                let mut active: session::ActiveModel = existing.into();
+                active.last_seen_at = Set(now);
+                if let Some(cwd) = cwd {
+                    active.cwd = Set(Some(cwd.to_string()));
+                }
+                active.update(&self.db).await?;
+            }
+            None => {
+                let active = session::ActiveModel {
+                    session_id: Set(self.session_id.clone()),
+                    cwd: Set(cwd.map(str::to_string)),
+                    last_seen_at: Set(now),
+                    created_at: Set(now),
+                    ..Default::default()
+                };
+                session::Entity::insert(active).exec(&self.db).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bumps the heartbeat of this session if planpilot already knows it, in a single statement;
+    /// unknown sessions are left alone so unrelated tool calls add no rows.
+    pub async fn touch_session(&self, cwd: &str) -> Result<(), AppError> {
+        session::Entity::update_many()
+            .col_expr(session::Column::LastSeenAt, Expr::value(Utc::now()))
+            .col_expr(session::Column::Cwd, Expr::value(cwd))
+            .filter(session::Column::SessionId.eq(self.session_id.as_str()))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_sessions(
+        &self,
+        stale_after: Duration,
+    ) -> Result<Vec<SessionSummary>, AppError> {
+        self.session_summaries_with_conn(&self.db, stale_after).await
+    }
+
+    pub async fn prune_sessions(
+        &self,
+        stale_after: Duration,
+    ) -> Result<(Vec<SessionSummary>, Vec<i64>), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<SessionSummary>, Vec<i64>), AppError> = async {
+            let stale: Vec<SessionSummary> = self
+                .session_summaries_with_conn(&txn, stale_after)
+                .await?
+                .into_iter()
+                .filter(|summary| summary.stale && summary.session_id != self.session_id)
+                .collect();
+            let mut plan_ids = Vec::new();
+            for summary in &stale {
+                if let Some(plan_id) = summary.plan_id {
+                    plan_ids.push(plan_id);
+                }
+                active_plan::Entity::delete_many()
+                    .filter(active_plan::Column::SessionId.eq(summary.session_id.as_str()))
+                    .exec(&txn)
+                    .await?;
+                step_claim::Entity::delete_many()
+                    .filter(step_claim::Column::SessionId.eq(summary.session_id.as_str()))
+                    .exec(&txn)
+                    .await?;
+                plan_stack::Entity::delete_many()
+                    .filter(plan_stack::Column::SessionId.eq(summary.session_id.as_str()))
+                    .exec(&txn)
+                    .await?;
+                session::Entity::delete_many()
+                    .filter(session::Column::SessionId.eq(summary.session_id.as_str()))
+                    .exec(&txn)
+                    .await?;
+            }
+            Ok((stale, plan_ids))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn session_summaries_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        stale_after: Duration,
+    ) -> Result<Vec<SessionSummary>, AppError> {
+        let now = Utc::now();
+        let mut summaries: HashMap<String, SessionSummary> = HashMap::new();
+        for session_model in session::Entity::find().all(db).await? {
+            let summary = session_entry(
+                &mut summaries,
+                &session_model.session_id,
+                session_model.last_seen_at,
+            );
+            summary.cwd = session_model.cwd;
+        }
+        for activation in active_plan::Entity::find().all(db).await? {
+            let summary =
+                session_entry(&mut summaries, &activation.session_id, activation.updated_at);
+            summary.plan_id = Some(activation.plan_id);
+        }
+        for claim in step_claim::Entity::find().all(db).await? {
+            let summary = session_entry(&mut summaries, &claim.session_id, claim.claimed_at);
+            if claim.expires_at > now {
+                summary.claims += 1;
+            }
+        }
+
+        let mut ordered: Vec<SessionSummary> = summaries
+            .into_values()
+            .map(|mut summary| {
+                summary.stale = now - summary.last_seen_at > stale_after;
+                summary
+            })
+            .collect();
+        ordered.sort_by(|a, b| {
+            b.last_seen_at
+                .cmp(&a.last_seen_at)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        });
+        Ok(ordered)
+    }
+
+    async fn session_last_seen_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        session_id: &str,
+        fallback: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, AppError> {
+        let session_model = session::Entity::find()
+            .filter(session::Column::SessionId.eq(session_id))
+            .one(db)
+            .await?;
+        Ok(match session_model {
+            Some(model) if model.last_seen_at > fallback => model.last_seen_at,
+            _ => fallback,
+        })
+    }
+
+    async fn clear_active_plans_for_plan_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_id: i64,
+    ) -> Result<(bool, Option<plan::Model>), AppError> {
+        let cleared_current = active_plan::Entity::find()
+            .filter(active_plan::Column::PlanId.eq(plan_id))
+            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .one(db)
+            .await?
+            .is_some();
+        active_plan::Entity::delete_many()
+            .filter(active_plan::Column::PlanId.eq(plan_id))
+            .exec(db)
+            .await?;
+        plan_stack::Entity::delete_many()
+            .filter(plan_stack::Column::PlanId.eq(plan_id))
+            .exec(db)
+            .await?;
+        let resumed = if cleared_current {
+            self.resume_suspended_plan_with_conn(db).await?
+        } else {
+            None
+        };
+        Ok((cleared_current, resumed))
+    }
+
+    /// Archives a plan, deactivating it everywhere. Returns the plan, whether this session's
+    /// active plan was cleared, and the plan resumed from the focus stack, if any.
+    pub async fn archive_plan(
+        &self,
+        id: i64,
+    ) -> Result<(plan::Model, bool, Option<plan::Model>), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(plan::Model, bool, Option<plan::Model>), AppError> = async {
+            let plan_model = plan::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {id}")))?;
+            if plan_model.archived_at.is_some() {
+                return Err(AppError::InvalidInput(format!(
+                    "plan id {id} is already archived"
+                )));
+            }
+            let (cleared, resumed) = self.clear_active_plans_for_plan_with_conn(&txn, id).await?;
+            let archived = self.set_plan_archived_with_conn(&txn, plan_model, true).await?;
+            Ok((archived, cleared, resumed))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn unarchive_plan(&self, id: i64) -> Result<plan::Model, AppError> {
+        let plan_model = self.get_plan(id).await?;
+        if plan_model.archived_at.is_none() {
+            return Err(AppError::InvalidInput(format!(
+                "plan id {id} is not archived"
+            )));
+        }
+        self.set_plan_archived_with_conn(&self.db, plan_model, false)
+            .await
+    }
+
+    /// Archives every plan that has been done for at least `done_for`. Plans finished before the
+    /// completion time was recorded fall back to their last update.
+    pub async fn archive_done_plans(
+        &self,
+        done_for: Duration,
+    ) -> Result<Vec<ArchivedPlan>, AppError> {
+        let cutoff = Utc::now() - done_for;
+        let txn = self.db.begin().await?;
+        let result: Result<Vec<ArchivedPlan>, AppError> = async {
+            let plans = plan::Entity::find()
+                .filter(plan::Column::Status.eq(PlanStatus::Done.as_str()))
+                .filter(plan::Column::ArchivedAt.is_null())
+                .filter(
+                    Condition::any().add(plan::Column::DoneAt.lt(cutoff)).add(
+                        Condition::all()
+                            .add(plan::Column::DoneAt.is_null())
+                            .add(plan::Column::UpdatedAt.lt(cutoff)),
+                    ),
+                )
+                .order_by_asc(plan::Column::Id)
+                .all(&txn)
+                .await?;
+            let mut archived = Vec::with_capacity(plans.len());
+            for plan_model in plans {
+                let (cleared, resumed) = self
+                    .clear_active_plans_for_plan_with_conn(&txn, plan_model.id)
+                    .await?;
+                let plan = self.set_plan_archived_with_conn(&txn, plan_model, true).await?;
+                archived.push(ArchivedPlan {
+                    plan,
+                    cleared,
+                    resumed,
+                });
+            }
+            Ok(archived)
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn set_plan_archived_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_model: plan::Model,
+        archived: bool,
+    ) -> Result<plan::Model, AppError> {
+        // Keep `updated_at` untouched: it is the "done for N days" fallback for plans without
+        // `done_at`.
+        let mut active: plan::ActiveModel = plan_model.into();
+        active.archived_at = Set(archived.then(Utc::now));
+        Ok(active.update(db).await?)
+    }
+
+    pub async fn update_plan_with_active_clear(
+        &self,
+        id: i64,
+        changes: PlanChanges,
+    ) -> Result<(plan::Model, bool), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(plan::Model, bool), AppError> = async {
+            let plan = self.update_plan_with_conn(&txn, id, changes).await?;
+            let cleared = if plan.status == PlanStatus::Done.as_str() {
+                self.clear_active_plans_for_plan_with_conn(&txn, plan.id)
+                    .await?
+                    .0
+            } else {
+                false
+            };
+            Ok((plan, cleared))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn update_plan_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: i64,
+        changes: PlanChanges,
+    ) -> Result<plan::Model, AppError> {
+        if let Some(title) = changes.title.as_deref() {
+            ensure_non_empty("plan title", title)?;
+        }
+        if let Some(content) = changes.content.as_deref() {
+            ensure_non_empty("plan content", content)?;
+        }
+        if let Some(status) = changes.status {
+            if status == PlanStatus::Done {
+                let total = step::Entity::find()
+                    .filter(step::Column::PlanId.eq(id))
+                    .count(db)
+                    .await?;
+                if total > 0 {
+                    if let Some(pending) = self.next_step_with_conn(db, id).await? {
+                        let goals = self.goals_for_step_with_conn(db, pending.id).await?;
+                        let detail = format_step_detail(&pending, &goals, &[], None);
+                        return Err(AppError::InvalidInput(format!(
+                            "cannot mark plan done; next pending step:\n{detail}"
+                        )));
+                    }
+                }
+                let criteria = self.criteria_with_conn(db, &[id]).await?;
+                let unmet = criteria
+                    .iter()
+                    .find(|item| item.status != CriterionStatus::Done.as_str());
+                if let Some(unmet) = unmet {
+                    return Err(AppError::InvalidInput(format!(
+                        "cannot mark plan done; unmet acceptance criterion: {} (criterion id {})",
+                        unmet.content, unmet.id
+                    )));
+                }
+            }
+        }
+
+        let mut active = plan::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        };
+
+        if let Some(title) = changes.title {
+            active.title = Set(title);
+        }
+        if let Some(content) = changes.content {
+            active.content = Set(content);
+        }
+        if let Some(status) = changes.status {
+            let current = plan::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {id}")))?;
+            if current.status != status.as_str() {
+                active.done_at = Set((status == PlanStatus::Done).then(Utc::now));
+            }
+            active.status = Set(status.as_str().to_string());
+        }
+        if let Some(comment) = changes.comment {
+            active.comment = Set(Some(comment));
+        }
+        if let Some(priority) = changes.priority {
+            active.priority = Set(priority.map(|priority| priority.as_str().to_string()));
+        }
+        if let Some(due) = changes.due {
+            active.due = Set(due);
+        }
+        active.last_session_id = Set(Some(self.session_id.clone()));
+
+        active.updated_at = Set(Utc::now());
+
+        match active.update(db).await {
+            Ok(model) => Ok(model),
+            Err(sea_orm::DbErr::RecordNotFound(_)) | Err(sea_orm::DbErr::RecordNotUpdated) => {
+                Err(AppError::NotFound(format!("plan id {id}")))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn delete_plan(&self, id: i64) -> Result<(), AppError> {
+        let txn = self.db.begin().await?;
         active_plan::Entity::delete_many()
-            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+            .filter(active_plan::Column::PlanId.eq(id))
             .exec(&txn)
             .await?;
-        active_plan::Entity::delete_many()
-            .filter(active_plan::Column::PlanId.eq(plan_id))
+        plan_stack::Entity::delete_many()
+            .filter(plan_stack::Column::PlanId.eq(id))
+            .exec(&txn)
+            .await?;
+        let steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(id))
+            .all(&txn)
+            .await?;
+        let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
+        if !step_ids.is_empty() {
+            goal::Entity::delete_many()
+                .filter(goal::Column::StepId.is_in(step_ids.clone()))
+                .exec(&txn)
+                .await?;
+            self.release_step_claims_with_conn(&txn, &step_ids).await?;
+            step_tag::Entity::delete_many()
+                .filter(step_tag::Column::StepId.is_in(step_ids))
+                .exec(&txn)
+                .await?;
+            step::Entity::delete_many()
+                .filter(step::Column::PlanId.eq(id))
+                .exec(&txn)
+                .await?;
+        }
+        plan_tag::Entity::delete_many()
+            .filter(plan_tag::Column::PlanId.eq(id))
+            .exec(&txn)
+            .await?;
+        self.prune_unused_tags_with_conn(&txn).await?;
+        milestone::Entity::delete_many()
+            .filter(milestone::Column::PlanId.eq(id))
+            .exec(&txn)
+            .await?;
+        criterion::Entity::delete_many()
+            .filter(criterion::Column::PlanId.eq(id))
             .exec(&txn)
             .await?;
 
-        let active = active_plan::ActiveModel {
-            session_id: Set(self.session_id.clone()),
-            plan_id: Set(plan_id),
-            updated_at: Set(now),
-            ..Default::default()
+        let result = plan::Entity::delete_by_id(id).exec(&txn).await?;
+        if result.rows_affected == 0 {
+            txn.rollback().await?;
+            return Err(AppError::NotFound(format!("plan id {id}")));
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn goals_for_steps(
+        &self,
+        step_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<goal::Model>>, AppError> {
+        let mut grouped = HashMap::new();
+        if step_ids.is_empty() {
+            return Ok(grouped);
+        }
+
+        let goals = goal::Entity::find()
+            .filter(goal::Column::StepId.is_in(step_ids.to_vec()))
+            .order_by_asc(goal::Column::StepId)
+            .order_by_asc(goal::Column::SortOrder)
+            .order_by_asc(goal::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        for goal in goals {
+            grouped
+                .entry(goal.step_id)
+                .or_insert_with(Vec::new)
+                .push(goal);
+        }
+
+        Ok(grouped)
+    }
+
+    pub async fn goals_for_step(&self, step_id: i64) -> Result<Vec<goal::Model>, AppError> {
+        self.goals_for_step_with_conn(&self.db, step_id).await
+    }
+
+    pub async fn add_steps_batch(
+        &self,
+        plan_id: i64,
+        contents: Vec<String>,
+        status: StepStatus,
+        executor: StepExecutor,
+        schedule: StepSchedule,
+        at: Option<usize>,
+    ) -> Result<(Vec<step::Model>, StatusChanges), AppError> {
+        let plan_exists = plan::Entity::find_by_id(plan_id).one(&self.db).await?;
+        if plan_exists.is_none() {
+            return Err(AppError::NotFound(format!("plan id {plan_id}")));
+        }
+        if contents.is_empty() {
+            return Ok((Vec::new(), StatusChanges::default()));
+        }
+        for content in &contents {
+            ensure_non_empty("step content", content)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<step::Model>, StatusChanges), AppError> = async {
+            let template = StepTemplate {
+                status,
+                executor,
+                schedule,
+            };
+            let created = self
+                .insert_steps_with_conn(&txn, plan_id, contents, template, at)
+                .await?;
+            let changes = self.refresh_plan_status_with_conn(&txn, plan_id).await?;
+            self.touch_plan_with_conn(&txn, plan_id).await?;
+            Ok((created, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn add_sub_steps(
+        &self,
+        plan_id: i64,
+        parent_id: i64,
+        contents: Vec<String>,
+        status: StepStatus,
+        executor: StepExecutor,
+        schedule: StepSchedule,
+    ) -> Result<(Vec<step::Model>, StatusChanges), AppError> {
+        if contents.is_empty() {
+            return Ok((Vec::new(), StatusChanges::default()));
+        }
+        for content in &contents {
+            ensure_non_empty("step content", content)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<step::Model>, StatusChanges), AppError> = async {
+            let at = self
+                .sub_step_position_with_conn(&txn, plan_id, parent_id)
+                .await?;
+            let template = StepTemplate {
+                status,
+                executor,
+                schedule,
+            };
+            let created = self
+                .insert_steps_with_conn(&txn, plan_id, contents, template, Some(at))
+                .await?;
+            let created = self
+                .set_step_parent_with_conn(&txn, created, parent_id)
+                .await?;
+            let changes = self.refresh_step_status_with_conn(&txn, parent_id).await?;
+            self.touch_plan_with_conn(&txn, plan_id).await?;
+            Ok((created, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    async fn insert_steps_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_id: i64,
+        contents: Vec<String>,
+        template: StepTemplate,
+        at: Option<usize>,
+    ) -> Result<Vec<step::Model>, AppError> {
+        let mut existing = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .order_by_asc(step::Column::SortOrder)
+            .order_by_asc(step::Column::Id)
+            .all(db)
+            .await?;
+        self.normalize_steps_in_place(&mut existing, db).await?;
+
+        let total = existing.len();
+        let insert_pos = match at {
+            Some(pos) if pos > 0 => pos.min(total + 1),
+            Some(_) => 1,
+            None => total + 1,
+        };
+
+        let now = Utc::now();
+        let shift_by = contents.len() as i32;
+        if shift_by > 0 {
+            for step_model in existing.iter_mut().rev() {
+                if step_model.sort_order >= insert_pos as i32 {
+                    let mut active: step::ActiveModel = step_model.clone().into();
+                    active.sort_order = Set(step_model.sort_order + shift_by);
+                    active.updated_at = Set(now);
+                    active.update(db).await?;
+                    step_model.sort_order += shift_by;
+                    step_model.updated_at = now;
+                }
+            }
+        }
+
+        let mut created = Vec::with_capacity(contents.len());
+        for (idx, content) in contents.into_iter().enumerate() {
+            let sort_order = (insert_pos + idx) as i32;
+            let active = step::ActiveModel {
+                plan_id: Set(plan_id),
+                content: Set(content),
+                status: Set(template.status.as_str().to_string()),
+                executor: Set(template.executor.as_str().to_string()),
+                sort_order: Set(sort_order),
+                priority: Set(template
+                    .schedule
+                    .priority
+                    .map(|priority| priority.as_str().to_string())),
+                due: Set(template.schedule.due),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            };
+            let insert = step::Entity::insert(active).exec(db).await?;
+            let model = step::Entity::find_by_id(insert.last_insert_id)
+                .one(db)
+                .await?
+                .ok_or_else(|| AppError::NotFound("step not found after insert".to_string()))?;
+            created.push(model);
+        }
+
+        Ok(created)
+    }
+
+    /// Validates `parent_id` as a parent for new steps in `plan_id` and returns the position
+    /// right after its existing sub-tree.
+    async fn sub_step_position_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        plan_id: i64,
+        parent_id: i64,
+    ) -> Result<usize, AppError> {
+        let parent = step::Entity::find_by_id(parent_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("step id {parent_id}")))?;
+        if parent.plan_id != plan_id {
+            return Err(AppError::InvalidInput(format!(
+                "parent step id {parent_id} belongs to plan id {}, not plan id {plan_id}",
+                parent.plan_id
+            )));
+        }
+        let steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .order_by_asc(step::Column::SortOrder)
+            .order_by_asc(step::Column::Id)
+            .all(db)
+            .await?;
+        if step_depth(&steps, parent_id) + 1 >= MAX_STEP_DEPTH {
+            return Err(AppError::InvalidInput(format!(
+                "step id {parent_id} is at the maximum sub-step depth ({MAX_STEP_DEPTH} levels)"
+            )));
+        }
+        let mut subtree = descendant_ids(&steps, parent_id);
+        subtree.insert(parent_id);
+        let last = steps
+            .iter()
+            .rposition(|step| subtree.contains(&step.id))
+            .unwrap_or(0);
+        Ok(last + 2)
+    }
+
+    async fn set_step_parent_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        steps: Vec<step::Model>,
+        parent_id: i64,
+    ) -> Result<Vec<step::Model>, AppError> {
+        let milestone_id = step::Entity::find_by_id(parent_id)
+            .one(db)
+            .await?
+            .and_then(|parent| parent.milestone_id);
+        let mut updated = Vec::with_capacity(steps.len());
+        for step_model in steps {
+            let mut active: step::ActiveModel = step_model.into();
+            active.parent_id = Set(Some(parent_id));
+            active.milestone_id = Set(milestone_id);
+            updated.push(active.update(db).await?);
+        }
+        Ok(updated)
+    }
+
+    pub async fn add_step_tree(
+        &self,
+        plan_id: i64,
+        content: String,
+        executor: StepExecutor,
+        schedule: StepSchedule,
+        goals: Vec<String>,
+        parent_id: Option<i64>,
+    ) -> Result<(step::Model, Vec<goal::Model>, StatusChanges), AppError> {
+        ensure_non_empty("step content", &content)?;
+        for goal in &goals {
+            ensure_non_empty("goal content", goal)?;
+        }
+
+        let txn = self.db.begin().await?;
+        let result: Result<(step::Model, Vec<goal::Model>, StatusChanges), AppError> = async {
+            plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {plan_id}")))?;
+
+            let at = match parent_id {
+                Some(parent_id) => Some(
+                    self.sub_step_position_with_conn(&txn, plan_id, parent_id)
+                        .await?,
+                ),
+                None => None,
+            };
+            let template = StepTemplate {
+                status: StepStatus::Todo,
+                executor,
+                schedule,
+            };
+            let mut created = self
+                .insert_steps_with_conn(&txn, plan_id, vec![content], template, at)
+                .await?;
+            if let Some(parent_id) = parent_id {
+                created = self
+                    .set_step_parent_with_conn(&txn, created, parent_id)
+                    .await?;
+            }
+            let step_model = created
+                .pop()
+                .ok_or_else(|| AppError::NotFound("step not found after insert".to_string()))?;
+            let now = Utc::now();
+
+            let mut created_goals = Vec::new();
+            for (idx, goal_content) in goals.into_iter().enumerate() {
+                let goal_active = goal::ActiveModel {
+                    step_id: Set(step_model.id),
+                    content: Set(goal_content),
+                    status: Set(GoalStatus::Todo.as_str().to_string()),
+                    sort_order: Set((idx + 1) as i32),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                };
+                let insert = goal::Entity::insert(goal_active).exec(&txn).await?;
+                let goal_model = goal::Entity::find_by_id(insert.last_insert_id)
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("goal not found after insert".to_string()))?;
+                created_goals.push(goal_model);
+            }
+
+            let changes = match parent_id {
+                Some(parent_id) => self.refresh_step_status_with_conn(&txn, parent_id).await?,
+                None => self.refresh_plan_status_with_conn(&txn, plan_id).await?,
+            };
+            self.touch_plan_with_conn(&txn, plan_id).await?;
+            Ok((step_model, created_goals, changes))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn list_steps_filtered(
+        &self,
+        plan_id: i64,
+        query: &StepQuery,
+    ) -> Result<Vec<step::Model>, AppError> {
+        self.get_plan(plan_id).await?;
+        let mut select = step::Entity::find().filter(step::Column::PlanId.eq(plan_id));
+        if let Some(status) = query.status {
+            select = select.filter(step::Column::Status.eq(status.as_str()));
+        }
+        if let Some(executor) = query.executor.as_ref() {
+            select = select.filter(executor_condition(executor));
+        }
+        if !query.tags.is_empty() {
+            let ids = self
+                .ids_with_tags(TagTarget::Step, &query.tags, query.tag_match)
+                .await?;
+            select = select.filter(step::Column::Id.is_in(ids));
+        }
+        let order = query.order.unwrap_or(StepOrder::Order);
+        match (order, query.desc) {
+            (StepOrder::Order, true) => select = select.order_by_desc(step::Column::SortOrder),
+            (StepOrder::Order, false) => select = select.order_by_asc(step::Column::SortOrder),
+            (StepOrder::Id, true) => select = select.order_by_desc(step::Column::Id),
+            (StepOrder::Id, false) => select = select.order_by_asc(step::Column::Id),
+            (StepOrder::Created, true) => select = select.order_by_desc(step::Column::CreatedAt),
+            (StepOrder::Created, false) => select = select.order_by_asc(step::Column::CreatedAt),
+            (StepOrder::Priority, desc) => {
+                select = select
+                    .order_by(Expr::col(step::Column::Priority).is_null(), Order::Asc)
+                    .order_by(step::Column::Priority, sort_direction(desc))
+                    .order_by_asc(step::Column::SortOrder);
+            }
+            (StepOrder::Due, desc) => {
+                select = select
+                    .order_by(Expr::col(step::Column::Due).is_null(), Order::Asc)
+                    .order_by(step::Column::Due, sort_direction(desc))
+                    .order_by_asc(step::Column::SortOrder);
+            }
+        }
+        if let Some(limit) = query.limit {
+            select = select.limit(limit);
+        }
+        if let Some(offset) = query.offset {
+            select = select.offset(offset);
+        }
+        Ok(select.order_by_asc(step::Column::Id).all(&self.db).await?)
+    }
+
+    /// Returns a checkpointed step and the checkpoint its changes start from: the latest step
+    /// checkpoint in the plan recorded before the step or any of its sub-steps was completed.
+    pub async fn step_checkpoint_base(
+        &self,
+        id: i64,
+    ) -> Result<(step::Model, Option<step::Model>), AppError> {
+        let step = self.get_step(id).await?;
+        let Some(completed_at) = step.checkpoint_at else {
+            return Err(AppError::InvalidInput(format!(
+                "step id {id} has no checkpoint; it was not completed inside a git repository"
+            )));
         };
-        active_plan::Entity::insert(active).exec(&txn).await?;
-        self.touch_plan_with_conn(&txn, plan_id).await?;
-        let model = active_plan::Entity::find()
-            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
-            .one(&txn)
-            .await?
-            .ok_or_else(|| AppError::NotFound("active plan not found after insert".to_string()))?;
-        txn.commit().await?;
-        Ok(model)
+        let plan_steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(step.plan_id))
+            .all(&self.db)
+            .await?;
+        let mut subtree = descendant_ids(&plan_steps, id);
+        subtree.insert(id);
+        let started_at = plan_steps
+            .iter()
+            .filter(|item| subtree.contains(&item.id))
+            .filter_map(|item| item.checkpoint_at)
+            .min()
+            .unwrap_or(completed_at);
+        let base = plan_steps
+            .into_iter()
+            .filter(|item| !subtree.contains(&item.id) && item.checkpoint_commit.is_some())
+            .filter(|item| item.checkpoint_at.is_some_and(|at| at < started_at))
+            .max_by_key(|item| (item.checkpoint_at, item.id));
+        Ok((step, base))
     }
 
-    pub async fn clear_active_plan(&self) -> Result<(), AppError> {
-        active_plan::Entity::delete_many()
-            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+    pub async fn claim_step(
+        &self,
+        step_id: i64,
+        lease: Duration,
+        force: bool,
+    ) -> Result<step_claim::Model, AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<step_claim::Model, AppError> = async {
+            let step = step::Entity::find_by_id(step_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {step_id}")))?;
+            if step.status == StepStatus::Done.as_str() {
+                return Err(AppError::InvalidInput(format!(
+                    "cannot claim step id {step_id}; step is done"
+                )));
+            }
+            self.claim_step_with_conn(&txn, step_id, lease, force).await
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn release_step(&self, step_id: i64) -> Result<(), AppError> {
+        self.get_step(step_id).await?;
+        let result = step_claim::Entity::delete_many()
+            .filter(step_claim::Column::StepId.eq(step_id))
+            .filter(step_claim::Column::SessionId.eq(self.session_id.as_str()))
             .exec(&self.db)
             .await?;
+        if result.rows_affected == 0 {
+            return Err(AppError::NotFound(format!(
+                "claim for step id {step_id} in session {}",
+                self.session_id
+            )));
+        }
         Ok(())
     }
 
-    async fn clear_active_plans_for_plan_with_conn<C: ConnectionTrait>(
+    /// Claims the next step no other live session holds, except human steps, which are returned
+    /// unclaimed since an agent session should never own them.
+    pub async fn claim_next_step(
+        &self,
+        plan_id: i64,
+        lease: Duration,
+    ) -> Result<Option<step::Model>, AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<Option<step::Model>, AppError> = async {
+            let Some(step) = self.next_unclaimed_step_with_conn(&txn, plan_id).await? else {
+                return Ok(None);
+            };
+            let executor = StepExecutor::parse(&step.executor).unwrap_or_else(StepExecutor::ai);
+            if !executor.is_human() {
+                self.claim_step_with_conn(&txn, step.id, lease, false).await?;
+            }
+            Ok(Some(step))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    /// The next step of the plan that no other live session has claimed, without claiming it.
+    pub async fn next_unclaimed_step(&self, plan_id: i64) -> Result<Option<step::Model>, AppError> {
+        self.next_unclaimed_step_with_conn(&self.db, plan_id).await
+    }
+
+    async fn next_unclaimed_step_with_conn<C: ConnectionTrait>(
         &self,
         db: &C,
         plan_id: i64,
-    ) -> Result<bool, AppError> {
-        let cleared_current = active_plan::Entity::find()
-            .filter(active_plan::Column::PlanId.eq(plan_id))
-            .filter(active_plan::Column::SessionId.eq(self.session_id.as_str()))
+    ) -> Result<Option<step::Model>, AppError> {
+        let all_steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .order_by_asc(step::Column::SortOrder)
+            .order_by_asc(step::Column::Id)
+            .all(db)
+            .await?;
+        let milestones = self.milestones_with_conn(db, &[plan_id]).await?;
+        let steps = actionable_steps(&all_steps, &milestones);
+        let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
+        let held_elsewhere = self.steps_claimed_elsewhere_with_conn(db, &step_ids).await?;
+        Ok(steps
+            .into_iter()
+            .find(|step| !held_elsewhere.contains(&step.id))
+            .cloned())
+    }
+
+    async fn claim_step_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        step_id: i64,
+        lease: Duration,
+        force: bool,
+    ) -> Result<step_claim::Model, AppError> {
+        let now = Utc::now();
+        if let Some(existing) = step_claim::Entity::find()
+            .filter(step_claim::Column::StepId.eq(step_id))
             .one(db)
             .await?
-            .is_some();
-        active_plan::Entity::delete_many()
-            .filter(active_plan::Column::PlanId.eq(plan_id))
+        {
+            if existing.session_id != self.session_id && existing.expires_at > now && !force {
+                return Err(AppError::InvalidInput(format!(
+                    "step id {step_id} is claimed by session {} until {} (use --force to take over)",
+                    existing.session_id,
+                    format_datetime(existing.expires_at)
+                )));
+            }
+            step_claim::Entity::delete_by_id(existing.id).exec(db).await?;
+        }
+
+        let active = step_claim::ActiveModel {
+            step_id: Set(step_id),
+            session_id: Set(self.session_id.clone()),
+            claimed_at: Set(now),
+            expires_at: Set(now + lease),
+            ..Default::default()
+        };
+        let insert = step_claim::Entity::insert(active).exec(db).await?;
+        step_claim::Entity::find_by_id(insert.last_insert_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("step claim not found after insert".to_string()))
+    }
+
+    async fn steps_claimed_elsewhere_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        step_ids: &[i64],
+    ) -> Result<HashSet<i64>, AppError> {
+        if step_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let claims = step_claim::Entity::find()
+            .filter(step_claim::Column::StepId.is_in(step_ids.to_vec()))
+            .filter(step_claim::Column::SessionId.ne(self.session_id.as_str()))
+            .filter(step_claim::Column::ExpiresAt.gt(Utc::now()))
+            .all(db)
+            .await?;
+        Ok(claims.into_iter().map(|claim| claim.step_id).collect())
+    }
+
+    async fn release_step_claims_with_conn<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        step_ids: &[i64],
+    ) -> Result<(), AppError> {
+        if step_ids.is_empty() {
+            return Ok(());
+        }
+        step_claim::Entity::delete_many()
+            .filter(step_claim::Column::StepId.is_in(step_ids.to_vec()))
             .exec(db)
             .await?;
-        Ok(cleared_current)
+        Ok(())
     }
 
-    pub async fn update_plan_with_active_clear(
+    pub async fn count_steps(&self, plan_id: i64, query: &StepQuery) -> Result<u64, AppError> {
+        self.get_plan(plan_id).await?;
+        let mut select = step::Entity::find().filter(step::Column::PlanId.eq(plan_id));
+        if let Some(status) = query.status {
+            select = select.filter(step::Column::Status.eq(status.as_str()));
+        }
+        if let Some(executor) = query.executor.as_ref() {
+            select = select.filter(executor_condition(executor));
+        }
+        if !query.tags.is_empty() {
+            let ids = self
+                .ids_with_tags(TagTarget::Step, &query.tags, query.tag_match)
+                .await?;
+            select = select.filter(step::Column::Id.is_in(ids));
+        }
+        Ok(select.count(&self.db).await?)
+    }
+
+    pub async fn update_step(
         &self,
         id: i64,
-        changes: PlanChanges,
-    ) -> Result<(plan::Model, bool), AppError> {
+        changes: StepChanges,
+    ) -> Result<(step::Model, StatusChanges), AppError> {
         let txn = self.db.begin().await?;
-        let result: Result<(plan::Model, bool), AppError> = async {
-            let plan = self.update_plan_with_conn(&txn, id, changes).await?;
-            let cleared = if plan.status == PlanStatus::Done.as_str() {
-                self.clear_active_plans_for_plan_with_conn(&txn, plan.id)
-                    .await?
-            } else {
-                false
-            };
-            Ok((plan, cleared))
+        let result = self.update_step_with_conn(&txn, id, changes).await;
+        finalize_transaction(txn, result).await
+    }
+
+    pub async fn set_step_done_with_goals(
+        &self,
+        id: i64,
+        all_goals: bool,
+    ) -> Result<(step::Model, StatusChanges), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(step::Model, StatusChanges), AppError> = async {
+            let mut merged = StatusChanges::default();
+            if all_goals {
+                let changes = self.set_all_goals_done_for_step_with_conn(&txn, id).await?;
+                merged.merge(changes);
+            }
+            let (step, changes) = self
+                .update_step_with_conn(
+                    &txn,
+                    id,
+                    StepChanges {
+                        status: Some(StepStatus::Done),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            merged.merge(changes);
+            Ok((step, merged))
         }
         .await;
 
         finalize_transaction(txn, result).await
     }
 
-    async fn update_plan_with_conn<C: ConnectionTrait>(
+    async fn update_step_with_conn<C: ConnectionTrait>(
         &self,
         db: &C,
         id: i64,
-        changes: PlanChanges,
-    ) -> Result<plan::Model, AppError> {
-        if let Some(title) = changes.title.as_deref() {
-            ensure_non_empty("plan title", title)?;
-        }
+        changes: StepChanges,
+    ) -> Result<(step::Model, StatusChanges), AppError> {
+        self.update_step_with_evidence(db, id, changes, None).await
+    }
+
+    async fn update_step_with_evidence<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: i64,
+        changes: StepChanges,
+        evidence: Option<&Checkpoint>,
+    ) -> Result<(step::Model, StatusChanges), AppError> {
         if let Some(content) = changes.content.as_deref() {
-            ensure_non_empty("plan content", content)?;
+            ensure_non_empty("step content", content)?;
         }
         if let Some(status) = changes.status {
-            if status == PlanStatus::Done {
-                let total = step::Entity::find()
-                    .filter(step::Column::PlanId.eq(id))
+            if status == StepStatus::Done {
+                let goals = goal::Entity::find()
+                    .filter(goal::Column::StepId.eq(id))
                     .count(db)
                     .await?;
-                if total > 0 {
-                    if let Some(pending) = self.next_step_with_conn(db, id).await? {
-                        let goals = self.goals_for_step_with_conn(db, pending.id).await?;
-                        let detail = format_step_detail(&pending, &goals);
+                if goals > 0 {
+                    if let Some(goal) = self.next_goal_for_step_with_conn(db, id).await? {
                         return Err(AppError::InvalidInput(format!(
-                            "cannot mark plan done; next pending step:\n{detail}"
+                            "cannot mark step done; next pending goal: {} (id {})",
+                            goal.content, goal.id
                         )));
                     }
                 }
+                let pending_child = step::Entity::find()
+                    .filter(step::Column::ParentId.eq(id))
+                    .filter(step::Column::Status.eq(StepStatus::Todo.as_str()))
+                    .order_by_asc(step::Column::SortOrder)
+                    .order_by_asc(step::Column::Id)
+                    .one(db)
+                    .await?;
+                if let Some(child) = pending_child {
+                    return Err(AppError::InvalidInput(format!(
+                        "cannot mark step done; next pending sub-step: {} (id {})",
+                        child.content, child.id
+                    )));
+                }
             }
         }
 
-        let mut active = plan::ActiveModel {
+        let mut active = step::ActiveModel {
             id: Set(id),
             ..Default::default()
         };
 
-        if let Some(title) = changes.title {
-            active.title = Set(title);
-        }
         if let Some(content) = changes.content {
             active.content = Set(content);
         }
         if let Some(status) = changes.status {
+            let current = step::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
+            if current.status != status.as_str() {
+                let checkpoint = self.checkpoint_or(status == StepStatus::Done, evidence);
+                set_step_checkpoint(&mut active, checkpoint);
+            }
             active.status = Set(status.as_str().to_string());
         }
+        if let Some(executor) = changes.executor {
+            active.executor = Set(executor.as_str().to_string());
+        }
         if let Some(comment) = changes.comment {
             active.comment = Set(Some(comment));
         }
-        active.last_session_id = Set(Some(self.session_id.clone()));
+        if let Some(priority) = changes.priority {
+            active.priority = Set(priority.map(|priority| priority.as_str().to_string()));
+        }
+        if let Some(due) = changes.due {
+            active.due = Set(due);
+        }
 
         active.updated_at = Set(Utc::now());
 
         match active.update(db).await {
-            Ok(model) => Ok(model),
+            Ok(model) => {
+                let mut updates = StatusChanges::default();
+                if model.status == StepStatus::Done.as_str() {
+                    self.release_step_claims_with_conn(db, &[model.id]).await?;
+                }
+                if changes.status.is_some() {
+                    let refreshed = match model.parent_id {
+                        Some(parent_id) => {
+                            self.refresh_step_status_with_evidence(db, parent_id, evidence)
+                                .await?
+                        }
+                        None => {
+                            self.refresh_plan_status_with_conn(db, model.plan_id)
+                                .await?
+                        }
+                    };
+                    updates.merge(refreshed);
+                }
+                self.touch_plan_with_conn(db, model.plan_id).await?;
+                Ok((model, updates))
+            }
             Err(sea_orm::DbErr::RecordNotFound(_)) | Err(sea_orm::DbErr::RecordNotUpdated) => {
-                Err(AppError::NotFound(format!("plan id {id}")))
+                Err(AppError::NotFound(format!("step id {id}")))
             }
             Err(err) => Err(err.into()),
         }
     }
 
-    pub async fn delete_plan(&self, id: i64) -> Result<(), AppError> {
+    pub async fn delete_steps(&self, ids: &[i64]) -> Result<(u64, StatusChanges), AppError> {
         let txn = self.db.begin().await?;
-        active_plan::Entity::delete_many()
-            .filter(active_plan::Column::PlanId.eq(id))
-            .exec(&txn)
-            .await?;
-        let steps = step::Entity::find()
-            .filter(step::Column::PlanId.eq(id))
-            .all(&txn)
-            .await?;
-        let step_ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
-        if !step_ids.is_empty() {
+        let result: Result<(u64, StatusChanges), AppError> = async {
+            if ids.is_empty() {
+                return Ok((0, StatusChanges::default()));
+            }
+            let unique_ids = unique_ids(ids);
+            let steps = step::Entity::find()
+                .filter(step::Column::Id.is_in(unique_ids.clone()))
+                .all(&txn)
+                .await?;
+            let existing: HashSet<i64> = steps.iter().map(|step| step.id).collect();
+            let missing: Vec<i64> = unique_ids
+                .iter()
+                .cloned()
+                .filter(|id| !existing.contains(id))
+                .collect();
+            if !missing.is_empty() {
+                return Err(AppError::NotFound(format!(
+                    "step id(s) not found: {}",
+                    join_ids(&missing)
+                )));
+            }
+            let mut seen = HashSet::new();
+            let mut plan_ids = Vec::new();
+            for step in &steps {
+                if seen.insert(step.plan_id) {
+                    plan_ids.push(step.plan_id);
+                }
+            }
+
+            let plan_steps = step::Entity::find()
+                .filter(step::Column::PlanId.is_in(plan_ids.clone()))
+                .all(&txn)
+                .await?;
+            let mut removed: HashSet<i64> = existing.clone();
+            for id in &existing {
+                removed.extend(descendant_ids(&plan_steps, *id));
+            }
+            let mut parent_ids: Vec<i64> = steps
+                .iter()
+                .filter_map(|step| step.parent_id)
+                .filter(|parent_id| !removed.contains(parent_id))
+                .collect();
+            parent_ids.sort_unstable();
+            parent_ids.dedup();
+            let unique_ids: Vec<i64> = plan_steps
+                .iter()
+                .map(|step| step.id)
+                .filter(|id| removed.contains(id))
+                .collect();
+
             goal::Entity::delete_many()
-                .filter(goal::Column::StepId.is_in(step_ids.clone()))
+                .filter(goal::Column::StepId.is_in(unique_ids.clone()))
                 .exec(&txn)
                 .await?;
-            step::Entity::delete_many()
-                .filter(step::Column::PlanId.eq(id))
+            self.release_step_claims_with_conn(&txn, &unique_ids).await?;
+            step_tag::Entity::delete_many()
+                .filter(step_tag::Column::StepId.is_in(unique_ids.clone()))
                 .exec(&txn)
                 .await?;
-        }
+            self.prune_unused_tags_with_conn(&txn).await?;
+            let result = step::Entity::delete_many()
+                .filter(step::Column::Id.is_in(unique_ids))
+                .exec(&txn)
+                .await?;
+            for plan_id in &plan_ids {
+                self.normalize_steps_for_plan(&txn, *plan_id).await?;
+            }
 
-        let result = plan::Entity::delete_by_id(id).exec(&txn).await?;
-        if result.rows_affected == 0 {
-            txn.rollback().await?;
-            return Err(AppError::NotFound(format!("plan id {id}")));
+            let mut changes = StatusChanges::default();
+            for parent_id in &parent_ids {
+                let updated = self.refresh_step_status_with_conn(&txn, *parent_id).await?;
+                changes.merge(updated);
+            }
+            for plan_id in &plan_ids {
+                let updated = self.refresh_plan_status_with_conn(&txn, *plan_id).await?;
+                changes.merge(updated);
+            }
+            if !plan_ids.is_empty() {
+                self.touch_plans_with_conn(&txn, &plan_ids).await?;
+            }
+
+            Ok((result.rows_affected, changes))
         }
-        txn.commit().await?;
-        Ok(())
+        .await;
+
+        finalize_transaction(txn, result).await
     }
 
-    pub async fn goals_for_steps(
-        &self,
-        step_ids: &[i64],
-    ) -> Result<HashMap<i64, Vec<goal::Model>>, AppError> {
-        let mut grouped = HashMap::new();
-        if step_ids.is_empty() {
-            return Ok(grouped);
-        }
+    pub async fn move_step(&self, id: i64, to: usize) -> Result<Vec<step::Model>, AppError> {
+        let txn = self.db.begin().await?;
+        let target = step::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
+        let plan_id = target.plan_id;
 
-        let goals = goal::Entity::find()
-            .filter(goal::Column::StepId.is_in(step_ids.to_vec()))
-            .order_by_asc(goal::Column::StepId)
-            .order_by_asc(goal::Column::Id)
-            .all(&self.db)
+        let mut steps = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan_id))
+            .order_by_asc(step::Column::SortOrder)
+            .order_by_asc(step::Column::Id)
+            .all(&txn)
             .await?;
 
-        for goal in goals {
-            grouped
-                .entry(goal.step_id)
-                .or_insert_with(Vec::new)
-                .push(goal);
+        let current_index = steps
+            .iter()
+            .position(|step| step.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
+
+        let mut desired_index = to.saturating_sub(1);
+        if desired_index >= steps.len() {
+            desired_index = steps.len().saturating_sub(1);
         }
 
-        Ok(grouped)
-    }
+        let moving = steps.remove(current_index);
+        if desired_index >= steps.len() {
+            steps.push(moving);
+        } else {
+            steps.insert(desired_index, moving);
+        }
 
-    pub async fn goals_for_step(&self, step_id: i64) -> Result<Vec<goal::Model>, AppError> {
-        self.goals_for_step_with_conn(&self.db, step_id).await
+        let now = Utc::now();
+        for (idx, step_model) in steps.iter_mut().enumerate() {
+            let desired_order = (idx + 1) as i32;
+            if step_model.sort_order != desired_order {
+                let mut active: step::ActiveModel = step_model.clone().into();
+                active.sort_order = Set(desired_order);
+                active.updated_at = Set(now);
+                active.update(&txn).await?;
+                step_model.sort_order = desired_order;
+                step_model.updated_at = now;
+            }
+        }
+
+        txn.commit().await?;
+        Ok(steps)
     }
 
-    pub async fn add_steps_batch(
+    /// Moves a step, with its sub-steps and goals, to another plan. It becomes a top-level step
+    /// at position `to` (default: last) of the target plan.
+    pub async fn move_step_to_plan(
         &self,
+        id: i64,
         plan_id: i64,
-        contents: Vec<String>,
-        status: StepStatus,
-        executor: StepExecutor,
-        at: Option<usize>,
+        to: Option<usize>,
     ) -> Result<(Vec<step::Model>, StatusChanges), AppError> {
-        let plan_exists = plan::Entity::find_by_id(plan_id).one(&self.db).await?;
-        if plan_exists.is_none() {
-            return Err(AppError::NotFound(format!("plan id {plan_id}")));
-        }
-        if contents.is_empty() {
-            return Ok((Vec::new(), StatusChanges::default()));
-        }
-        for content in &contents {
-            ensure_non_empty("step content", content)?;
-        }
-
         let txn = self.db.begin().await?;
         let result: Result<(Vec<step::Model>, StatusChanges), AppError> = async {
-            let mut existing = step::Entity::find()
-                .filter(step::Column::PlanId.eq(plan_id))
+            let moving = step::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
+            plan::Entity::find_by_id(plan_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("plan id {plan_id}")))?;
+            let source_plan_id = moving.plan_id;
+            if source_plan_id == plan_id {
+                return Err(AppError::InvalidInput(format!(
+                    "step id {id} already belongs to plan id {plan_id} (use --to to reorder)"
+                )));
+            }
+
+            let source_steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(source_plan_id))
                 .order_by_asc(step::Column::SortOrder)
                 .order_by_asc(step::Column::Id)
                 .all(&txn)
                 .await?;
-            self.normalize_steps_in_place(&mut existing, &txn).await?;
+            let mut subtree = descendant_ids(&source_steps, id);
+            subtree.insert(id);
+            let moved: Vec<step::Model> = source_steps
+                .into_iter()
+                .filter(|step| subtree.contains(&step.id))
+                .collect();
 
-            let total = existing.len();
-            let insert_pos = match at {
+            let mut target_steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(plan_id))
+                .order_by_asc(step::Column::SortOrder)
+                .order_by_asc(step::Column::Id)
+                .all(&txn)
+                .await?;
+            self.normalize_steps_in_place(&mut target_steps, &txn).await?;
+            let total = target_steps.len();
+            let insert_pos = match to {
                 Some(pos) if pos > 0 => pos.min(total + 1),
                 Some(_) => 1,
                 None => total + 1,
             };
 
             let now = Utc::now();
-            let shift_by = contents.len() as i32;
-            if shift_by > 0 {
-                for step_model in existing.iter_mut().rev() {
-                    if step_model.sort_order >= insert_pos as i32 {
-                        let mut active: step::ActiveModel = step_model.clone().into();
-                        active.sort_order = Set(step_model.sort_order + shift_by);
-                        active.updated_at = Set(now);
-                        active.update(&txn).await?;
-                        step_model.sort_order += shift_by;
-                        step_model.updated_at = now;
-                    }
+            let shift_by = moved.len() as i32;
+            for step_model in target_steps.iter().rev() {
+                if step_model.sort_order >= insert_pos as i32 {
+                    let mut active: step::ActiveModel = step_model.clone().into();
+                    active.sort_order = Set(step_model.sort_order + shift_by);
+                    active.updated_at = Set(now);
+                    active.update(&txn).await?;
                 }
             }
-
-            let mut created = Vec::with_capacity(contents.len());
-            for (idx, content) in contents.into_iter().enumerate() {
-                let sort_order = (insert_pos + idx) as i32;
-                let active = step::ActiveModel {
-                    plan_id: Set(plan_id),
-                    content: Set(content),
-                    status: Set(status.as_str().to_string()),
-                    executor: Set(executor.as_str().to_string()),
-                    sort_order: Set(sort_order),
-                    created_at: Set(now),
-                    updated_at: Set(now),
-                    ..Default::default()
-                };
-                let insert = step::Entity::insert(active).exec(&txn).await?;
-                let model = step::Entity::find_by_id(insert.last_insert_id)
-                    .one(&txn)
-                    .await?
-                    .ok_or_else(|| AppError::NotFound("step not found after insert".to_string()))?;
-                created.push(model);
+            for (idx, step_model) in moved.into_iter().enumerate() {
+                let is_root = step_model.id == id;
+                let mut active: step::ActiveModel = step_model.into();
+                active.plan_id = Set(plan_id);
+                active.milestone_id = Set(None);
+                active.sort_order = Set((insert_pos + idx) as i32);
+                if is_root {
+                    active.parent_id = Set(None);
+                }
+                active.updated_at = Set(now);
+                active.update(&txn).await?;
             }
+            self.normalize_steps_for_plan(&txn, source_plan_id).await?;
 
-            let changes = self.refresh_plan_status_with_conn(&txn, plan_id).await?;
-            self.touch_plan_with_conn(&txn, plan_id).await?;
-            Ok((created, changes))
-        }
-        .await;
-
-        finalize_transaction(txn, result).await
-    }
-
-    pub async fn add_step_tree(
-        &self,
-        plan_id: i64,
-        content: String,
-        executor: StepExecutor,
-        goals: Vec<String>,
-    ) -> Result<(step::Model, Vec<goal::Model>, StatusChanges), AppError> {
-        ensure_non_empty("step content", &content)?;
-        for goal in &goals {
-            ensure_non_empty("goal content", goal)?;
-        }
-
-        let txn = self.db.begin().await?;
-        let result: Result<(step::Model, Vec<goal::Model>, StatusChanges), AppError> = async {
-            plan::Entity::find_by_id(plan_id)
-                .one(&txn)
-                .await?
-                .ok_or_else(|| AppError::NotFound(format!("plan id {plan_id}")))?;
+            let mut changes = StatusChanges::default();
+            if let Some(parent_id) = moving.parent_id {
+                let updated = self.refresh_step_status_with_conn(&txn, parent_id).await?;
+                changes.merge(updated);
+            }
+            for refreshed_plan in [source_plan_id, plan_id] {
+                let updated = self
+                    .refresh_plan_status_with_conn(&txn, refreshed_plan)
+                    .await?;
+                changes.merge(updated);
+            }
+            self.touch_plans_with_conn(&txn, &[source_plan_id, plan_id])
+                .await?;
 
-            let mut existing = step::Entity::find()
+            let steps = step::Entity::find()
                 .filter(step::Column::PlanId.eq(plan_id))
                 .order_by_asc(step::Column::SortOrder)
                 .order_by_asc(step::Column::Id)
                 .all(&txn)
                 .await?;
-            self.normalize_steps_in_place(&mut existing, &txn).await?;
+            Ok((steps, changes))
+        }
+        .await;
 
-            let sort_order = (existing.len() + 1) as i32;
-            let now = Utc::now();
-            let active = step::ActiveModel {
-                plan_id: Set(plan_id),
-                content: Set(content),
-                status: Set(StepStatus::Todo.as_str().to_string()),
-                executor: Set(executor.as_str().to_string()),
-                sort_order: Set(sort_order),
-                created_at: Set(now),
-                updated_at: Set(now),
-                ..Default::default()
-            };
-            let insert = step::Entity::insert(active).exec(&txn).await?;
-            let step_model = step::Entity::find_by_id(insert.last_insert_id)
+        finalize_transaction(txn, result).await
+    }
+
+    /// Moves a goal to position `to` (default: last) of `step_id`, or reorders it within its own
+    /// step when `step_id` is `None`. Returns the goals of the destination step in order.
+    pub async fn move_goal(
+        &self,
+        id: i64,
+        step_id: Option<i64>,
+        to: Option<usize>,
+    ) -> Result<(Vec<goal::Model>, StatusChanges), AppError> {
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<goal::Model>, StatusChanges), AppError> = async {
+            let moving = goal::Entity::find_by_id(id)
                 .one(&txn)
                 .await?
-                .ok_or_else(|| AppError::NotFound("step not found after insert".to_string()))?;
+                .ok_or_else(|| AppError::NotFound(format!("goal id {id}")))?;
+            let source_step_id = moving.step_id;
+            let target_step_id = step_id.unwrap_or(source_step_id);
+            let target_step = step::Entity::find_by_id(target_step_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {target_step_id}")))?;
 
-            let mut created_goals = Vec::new();
-            for goal_content in goals {
-                let goal_active = goal::ActiveModel {
-                    step_id: Set(step_model.id),
-                    content: Set(goal_content),
-                    status: Set(GoalStatus::Todo.as_str().to_string()),
-                    created_at: Set(now),
-                    updated_at: Set(now),
-                    ..Default::default()
-                };
-                let insert = goal::Entity::insert(goal_active).exec(&txn).await?;
-                let goal_model = goal::Entity::find_by_id(insert.last_insert_id)
+            let mut goals: Vec<goal::Model> = self
+                .goals_for_step_with_conn(&txn, target_step_id)
+                .await?
+                .into_iter()
+                .filter(|goal| goal.id != id)
+                .collect();
+            let insert_index = match to {
+                Some(pos) => pos.saturating_sub(1).min(goals.len()),
+                None => goals.len(),
+            };
+            goals.insert(insert_index, moving);
+
+            let now = Utc::now();
+            for (idx, goal_model) in goals.iter_mut().enumerate() {
+                let desired_order = (idx + 1) as i32;
+                if goal_model.sort_order != desired_order || goal_model.step_id != target_step_id {
+                    let mut active: goal::ActiveModel = goal_model.clone().into();
+                    active.step_id = Set(target_step_id);
+                    active.sort_order = Set(desired_order);
+                    active.updated_at = Set(now);
+                    *goal_model = active.update(&txn).await?;
+                }
+            }
+
+            let mut changes = StatusChanges::default();
+            if source_step_id != target_step_id {
+                self.normalize_goals_for_step(&txn, source_step_id).await?;
+                for refreshed_step in [source_step_id, target_step_id] {
+                    let updated = self
+                        .refresh_step_status_with_conn(&txn, refreshed_step)
+                        .await?;
+                    changes.merge(updated);
+                }
+                let source_plan_id = step::Entity::find_by_id(source_step_id)
                     .one(&txn)
                     .await?
-                    .ok_or_else(|| AppError::NotFound("goal not found after insert".to_string()))?;
-                created_goals.push(goal_model);
+                    .map(|step| step.plan_id)
+                    .unwrap_or(target_step.plan_id);
+                let mut plan_ids = vec![source_plan_id];
+                if target_step.plan_id != source_plan_id {
+                    plan_ids.push(target_step.plan_id);
+                }
+                self.touch_plans_with_conn(&txn, &plan_ids).await?;
+            } else {
+                self.touch_plan_with_conn(&txn, target_step.plan_id).await?;
             }
-
-            let changes = self.refresh_plan_status_with_conn(&txn, plan_id).await?;
-            self.touch_plan_with_conn(&txn, plan_id).await?;
-            Ok((step_model, created_goals, changes))
+            Ok((goals, changes))
         }
         .await;
 
         finalize_transaction(txn, result).await
     }
 
-    pub async fn list_steps_filtered(
+    /// Splits a step into several steps at its position. The original step keeps its id and
+    /// becomes the first part; goals move to the part they are assigned to (`(goal_id, part)`,
+    /// 1-based), and unassigned goals are spread over the parts in order.
+    pub async fn split_step(
         &self,
-        plan_id: i64,
-        query: &StepQuery,
-    ) -> Result<Vec<step::Model>, AppError> {
-        self.get_plan(plan_id).await?;
-        let mut select = step::Entity::find().filter(step::Column::PlanId.eq(plan_id));
-        if let Some(status) = query.status {
-            select = select.filter(step::Column::Status.eq(status.as_str()));
-        }
-        if let Some(executor) = query.executor {
-            select = select.filter(step::Column::Executor.eq(executor.as_str()));
-        }
-        let order = query.order.unwrap_or(StepOrder::Order);
-        match (order, query.desc) {
-            (StepOrder::Order, true) => select = select.order_by_desc(step::Column::SortOrder),
-            (StepOrder::Order, false) => select = select.order_by_asc(step::Column::SortOrder),
-            (StepOrder::Id, true) => select = select.order_by_desc(step::Column::Id),
-            (StepOrder::Id, false) => select = select.order_by_asc(step::Column::Id),
-            (StepOrder::Created, true) => select = select.order_by_desc(step::Column::CreatedAt),
-            (StepOrder::Created, false) => select = select.order_by_asc(step::Column::CreatedAt),
-        }
-        if let Some(limit) = query.limit {
-            select = select.limit(limit);
-        }
-        if let Some(offset) = query.offset {
-            select = select.offset(offset);
-        }
-        Ok(select.order_by_asc(step::Column::Id).all(&self.db).await?)
-    }
-
-    pub async fn next_step(&self, plan_id: i64) -> Result<Option<step::Model>, AppError> {
-        self.next_step_with_conn(&self.db, plan_id).await
-    }
-
-    pub async fn count_steps(&self, plan_id: i64, query: &StepQuery) -> Result<u64, AppError> {
-        self.get_plan(plan_id).await?;
-        let mut select = step::Entity::find().filter(step::Column::PlanId.eq(plan_id));
-        if let Some(status) = query.status {
-            select = select.filter(step::Column::Status.eq(status.as_str()));
+        id: i64,
+        parts: Vec<String>,
+        assignments: &[(i64, usize)],
+    ) -> Result<(Vec<step::Model>, StatusChanges), AppError> {
+        if parts.len() < 2 {
+            return Err(AppError::InvalidInput(
+                "split needs at least two parts".to_string(),
+            ));
         }
-        if let Some(executor) = query.executor {
-            select = select.filter(step::Column::Executor.eq(executor.as_str()));
+        for part in &parts {
+            ensure_non_empty("step content", part)?;
         }
-        Ok(select.count(&self.db).await?)
-    }
 
-    pub async fn update_step(
-        &self,
-        id: i64,
-        changes: StepChanges,
-    ) -> Result<(step::Model, StatusChanges), AppError> {
         let txn = self.db.begin().await?;
-        let result = self.update_step_with_conn(&txn, id, changes).await;
-        finalize_transaction(txn, result).await
-    }
+        let result: Result<(Vec<step::Model>, StatusChanges), AppError> = async {
+            let original = step::Entity::find_by_id(id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
+            let goals = self.goals_for_step_with_conn(&txn, id).await?;
+            let goal_parts = assign_goals_to_parts(&goals, parts.len(), assignments, id)?;
 
-    pub async fn set_step_done_with_goals(
-        &self,
-        id: i64,
-        all_goals: bool,
-    ) -> Result<(step::Model, StatusChanges), AppError> {
-        let txn = self.db.begin().await?;
-        let result: Result<(step::Model, StatusChanges), AppError> = async {
-            let mut merged = StatusChanges::default();
-            if all_goals {
-                let changes = self.set_all_goals_done_for_step_with_conn(&txn, id).await?;
-                merged.merge(changes);
-            }
-            let (step, changes) = self
-                .update_step_with_conn(
+            let plan_steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(original.plan_id))
+                .order_by_asc(step::Column::SortOrder)
+                .order_by_asc(step::Column::Id)
+                .all(&txn)
+                .await?;
+            let mut subtree = descendant_ids(&plan_steps, id);
+            subtree.insert(id);
+            let last = plan_steps
+                .iter()
+                .rposition(|step| subtree.contains(&step.id))
+                .unwrap_or(0);
+
+            let mut contents = parts.into_iter();
+            let first = contents.next().unwrap_or_default();
+            let status = if original.status == StepStatus::Done.as_str() {
+                StepStatus::Done
+            } else {
+                StepStatus::Todo
+            };
+            let executor = StepExecutor::parse(&original.executor).unwrap_or_else(StepExecutor::ai);
+            let created = self
+                .insert_steps_with_conn(
                     &txn,
-                    id,
-                    StepChanges {
-                        status: Some(StepStatus::Done),
-                        ..Default::default()
+                    original.plan_id,
+                    contents.collect(),
+                    StepTemplate {
+                        status,
+                        executor,
+                        schedule: StepSchedule::default(),
                     },
+                    Some(last + 2),
                 )
                 .await?;
-            merged.merge(changes);
-            Ok((step, merged))
-        }
-        .await;
 
-        finalize_transaction(txn, result).await
-    }
+            let now = Utc::now();
+            let mut active: step::ActiveModel = original.clone().into();
+            active.content = Set(first);
+            active.updated_at = Set(now);
+            let mut split = vec![active.update(&txn).await?];
+            for step_model in created {
+                let mut active: step::ActiveModel = step_model.into();
+                active.parent_id = Set(original.parent_id);
+                active.milestone_id = Set(original.milestone_id);
+                active.priority = Set(original.priority.clone());
+                active.due = Set(original.due);
+                split.push(active.update(&txn).await?);
+            }
 
-    async fn update_step_with_conn<C: ConnectionTrait>(
-        &self,
-        db: &C,
-        id: i64,
-        changes: StepChanges,
-    ) -> Result<(step::Model, StatusChanges), AppError> {
-        if let Some(content) = changes.content.as_deref() {
-            ensure_non_empty("step content", content)?;
-        }
-        if let Some(status) = changes.status {
-            if status == StepStatus::Done {
-                let goals = goal::Entity::find()
-                    .filter(goal::Column::StepId.eq(id))
-                    .count(db)
-                    .await?;
-                if goals > 0 {
-                    if let Some(goal) = self.next_goal_for_step_with_conn(db, id).await? {
-                        return Err(AppError::InvalidInput(format!(
-                            "cannot mark step done; next pending goal: {} (id {})",
-                            goal.content, goal.id
-                        )));
-                    }
+            let tag_ids: Vec<i64> = self
+                .tag_links_with_conn(&txn, TagTarget::Step, &[id])
+                .await?
+                .into_iter()
+                .map(|(_, tag_id)| tag_id)
+                .collect();
+            for step_model in split.iter().skip(1) {
+                for tag_id in &tag_ids {
+                    let link = step_tag::ActiveModel {
+                        step_id: Set(step_model.id),
+                        tag_id: Set(*tag_id),
+                        ..Default::default()
+                    };
+                    step_tag::Entity::insert(link).exec(&txn).await?;
                 }
             }
-        }
-
-        let mut active = step::ActiveModel {
-            id: Set(id),
-            ..Default::default()
-        };
-
-        if let Some(content) = changes.content {
-            active.content = Set(content);
-        }
-        if let Some(status) = changes.status {
-            active.status = Set(status.as_str().to_string());
-        }
-        if let Some(executor) = changes.executor {
-            active.executor = Set(executor.as_str().to_string());
-        }
-        if let Some(comment) = changes.comment {
-            active.comment = Set(Some(comment));
-        }
-
-        active.updated_at = Set(Utc::now());
 
-        match active.update(db).await {
-            Ok(model) => {
-                let mut updates = StatusChanges::default();
-                if changes.status.is_some() {
-                    let refreshed = self
-                        .refresh_plan_status_with_conn(db, model.plan_id)
-                        .await?;
-                    updates.merge(refreshed);
+            for (goal_model, part) in goals.into_iter().zip(goal_parts) {
+                if part == 0 {
+                    continue;
                 }
-                self.touch_plan_with_conn(db, model.plan_id).await?;
-                Ok((model, updates))
+                let mut active: goal::ActiveModel = goal_model.into();
+                active.step_id = Set(split[part].id);
+                active.updated_at = Set(now);
+                active.update(&txn).await?;
             }
-            Err(sea_orm::DbErr::RecordNotFound(_)) | Err(sea_orm::DbErr::RecordNotUpdated) => {
-                Err(AppError::NotFound(format!("step id {id}")))
+
+            let mut changes = StatusChanges::default();
+            for step_model in &split {
+                self.normalize_goals_for_step(&txn, step_model.id).await?;
+                let updated = self.refresh_step_status_with_conn(&txn, step_model.id).await?;
+                changes.merge(updated);
             }
-            Err(err) => Err(err.into()),
+            let updated = self
+                .refresh_plan_status_with_conn(&txn, original.plan_id)
+                .await?;
+            changes.merge(updated);
+            self.touch_plan_with_conn(&txn, original.plan_id).await?;
+
+            let ids: Vec<i64> = split.iter().map(|step| step.id).collect();
+            let mut refreshed = step::Entity::find()
+                .filter(step::Column::Id.is_in(ids.clone()))
+                .all(&txn)
+                .await?;
+            refreshed.sort_by_key(|step| ids.iter().position(|id| *id == step.id));
+            Ok((refreshed, changes))
         }
+        .await;
+
+        finalize_transaction(txn, result).await
     }
 
-    pub async fn delete_steps(&self, ids: &[i64]) -> Result<(u64, StatusChanges), AppError> {
+    /// Merges `source_id` into `target_id`: goals, sub-steps and tags move to the target, the
+    /// comments are combined, and the source step is removed.
+    pub async fn merge_steps(
+        &self,
+        target_id: i64,
+        source_id: i64,
+    ) -> Result<(step::Model, StatusChanges), AppError> {
+        if target_id == source_id {
+            return Err(AppError::InvalidInput(format!(
+                "cannot merge step id {target_id} into itself"
+            )));
+        }
+
         let txn = self.db.begin().await?;
-        let result: Result<(u64, StatusChanges), AppError> = async {
-            if ids.is_empty() {
-                return Ok((0, StatusChanges::default()));
+        let result: Result<(step::Model, StatusChanges), AppError> = async {
+            let target = step::Entity::find_by_id(target_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {target_id}")))?;
+            let source = step::Entity::find_by_id(source_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {source_id}")))?;
+            if target.plan_id != source.plan_id {
+                return Err(AppError::InvalidInput(format!(
+                    "cannot merge steps from different plans (plan id {} and plan id {})",
+                    target.plan_id, source.plan_id
+                )));
             }
-            let unique_ids = unique_ids(ids);
-            let steps = step::Entity::find()
-                .filter(step::Column::Id.is_in(unique_ids.clone()))
+            let plan_id = target.plan_id;
+            let plan_steps = step::Entity::find()
+                .filter(step::Column::PlanId.eq(plan_id))
                 .all(&txn)
                 .await?;
-            let existing: HashSet<i64> = steps.iter().map(|step| step.id).collect();
-            let missing: Vec<i64> = unique_ids
-                .iter()
-                .cloned()
-                .filter(|id| !existing.contains(id))
-                .collect();
-            if !missing.is_empty() {
-                return Err(AppError::NotFound(format!(
-                    "step id(s) not found: {}",
-                    join_ids(&missing)
+            if descendant_ids(&plan_steps, source_id).contains(&target_id) {
+                return Err(AppError::InvalidInput(format!(
+                    "cannot merge step id {source_id} into its own sub-step id {target_id}"
                 )));
             }
-            let mut seen = HashSet::new();
-            let mut plan_ids = Vec::new();
-            for step in &steps {
-                if seen.insert(step.plan_id) {
-                    plan_ids.push(step.plan_id);
-                }
+            if step_depth(&plan_steps, target_id) + subtree_height(&plan_steps, source_id)
+                >= MAX_STEP_DEPTH
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "merging step id {source_id} into step id {target_id} would exceed the \
+                     maximum sub-step depth ({MAX_STEP_DEPTH} levels)"
+                )));
+            }
+
+            let now = Utc::now();
+            let kept = self.normalize_goals_for_step(&txn, target_id).await?.len();
+            let moved = self.goals_for_step_with_conn(&txn, source_id).await?;
+            for (idx, goal_model) in moved.into_iter().enumerate() {
+                let mut active: goal::ActiveModel = goal_model.into();
+                active.step_id = Set(target_id);
+                active.sort_order = Set((kept + idx + 1) as i32);
+                active.updated_at = Set(now);
+                active.update(&txn).await?;
             }
-
-            goal::Entity::delete_many()
-                .filter(goal::Column::StepId.is_in(unique_ids.clone()))
+            step::Entity::update_many()
+                .col_expr(step::Column::ParentId, Expr::value(target_id))
+                .col_expr(step::Column::UpdatedAt, Expr::value(now))
+                .filter(step::Column::ParentId.eq(source_id))
                 .exec(&txn)
                 .await?;
-            let result = step::Entity::delete_many()
-                .filter(step::Column::Id.is_in(unique_ids))
-                .exec(&txn)
+            let adopted = descendant_ids(&plan_steps, source_id);
+            if !adopted.is_empty() && source.milestone_id != target.milestone_id {
+                step::Entity::update_many()
+                    .col_expr(step::Column::MilestoneId, Expr::value(target.milestone_id))
+                    .filter(step::Column::Id.is_in(adopted))
+                    .exec(&txn)
+                    .await?;
+            }
+
+            let links = self
+                .tag_links_with_conn(&txn, TagTarget::Step, &[target_id, source_id])
                 .await?;
-            for plan_id in &plan_ids {
-                self.normalize_steps_for_plan(&txn, *plan_id).await?;
+            let target_tags: HashSet<i64> = links
+                .iter()
+                .filter(|(step_id, _)| *step_id == target_id)
+                .map(|(_, tag_id)| *tag_id)
+                .collect();
+            for (step_id, tag_id) in &links {
+                if *step_id == source_id && !target_tags.contains(tag_id) {
+                    let link = step_tag::ActiveModel {
+                        step_id: Set(target_id),
+                        tag_id: Set(*tag_id),
+                        ..Default::default()
+                    };
+                    step_tag::Entity::insert(link).exec(&txn).await?;
+                }
             }
+            step_tag::Entity::delete_many()
+                .filter(step_tag::Column::StepId.eq(source_id))
+                .exec(&txn)
+                .await?;
 
-            let mut changes = StatusChanges::default();
-            for plan_id in &plan_ids {
-                let updated = self.refresh_plan_status_with_conn(&txn, *plan_id).await?;
+            let comment = [target.comment.as_deref(), source.comment.as_deref()]
+                .into_iter()
+                .flatten()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut active: step::ActiveModel = target.clone().into();
+            active.comment = Set((!comment.is_empty()).then_some(comment));
+            active.updated_at = Set(now);
+            active.update(&txn).await?;
+
+            self.release_step_claims_with_conn(&txn, &[source_id]).await?;
+            step::Entity::delete_by_id(source_id).exec(&txn).await?;
+            self.normalize_steps_for_plan(&txn, plan_id).await?;
+
+            let mut changes = self.refresh_step_status_with_conn(&txn, target_id).await?;
+            if let Some(parent_id) = source.parent_id.filter(|id| *id != target_id) {
+                let updated = self.refresh_step_status_with_conn(&txn, parent_id).await?;
                 changes.merge(updated);
             }
-            if !plan_ids.is_empty() {
-                self.touch_plans_with_conn(&txn, &plan_ids).await?;
-            }
+            let updated = self.refresh_plan_status_with_conn(&txn, plan_id).await?;
+            changes.merge(updated);
+            self.touch_plan_with_conn(&txn, plan_id).await?;
 
-            Ok((result.rows_affected, changes))
+            let merged = step::Entity::find_by_id(target_id)
+                .one(&txn)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("step id {target_id}")))?;
+            Ok((merged, changes))
         }
         .await;
 
         finalize_transaction(txn, result).await
     }
 
-    pub async fn move_step(&self, id: i64, to: usize) -> Result<Vec<step::Model>, AppError> {
-        let txn = self.db.begin().await?;
-        let target = step::Entity::find_by_id(id)
-            .one(&txn)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
-        let plan_id = target.plan_id;
-
-        let mut steps = step::Entity::find()
-            .filter(step::Column::PlanId.eq(plan_id))
-            .order_by_asc(step::Column::SortOrder)
-            .order_by_asc(step::Column::Id)
-            .all(&txn)
-            .await?;
-
-        let current_index = steps
-            .iter()
-            .position(|step| step.id == id)
-            .ok_or_else(|| AppError::NotFound(format!("step id {id}")))?;
-
-        let mut desired_index = to.saturating_sub(1);
-        if desired_index >= steps.len() {
-            desired_index = steps.len().saturating_sub(1);
-        }
-
-        let moving = steps.remove(current_index);
-        if desired_index >= steps.len() {
-            steps.push(moving);
-        } else {
-            steps.insert(desired_index, moving);
-        }
-
-        let now = Utc::now();
-        for (idx, step_model) in steps.iter_mut().enumerate() {
-            let desired_order = (idx + 1) as i32;
-            if step_model.sort_order != desired_order {
-                let mut active: step::ActiveModel = step_model.clone().into();
-                active.sort_order = Set(desired_order);
-                active.updated_at = Set(now);
-                active.update(&txn).await?;
-                step_model.sort_order = desired_order;
-                step_model.updated_at = now;
-            }
-        }
-
-        txn.commit().await?;
-        Ok(steps)
-    }
-
     async fn refresh_plan_status_with_conn<C: ConnectionTrait>(
         &self,
         db: &C,
         plan_id: i64,
     ) -> Result<StatusChanges, AppError> {
+        let mut changes = self.refresh_milestones_with_conn(db, plan_id).await?;
         let total = step::Entity::find()
             .filter(step::Column::PlanId.eq(plan_id))
             .count(db)
             .await?;
-        if total == 0 {
-            return Ok(StatusChanges::default());
+        let criteria = self.criteria_with_conn(db, &[plan_id]).await?;
+        let (met, criteria_total) = criteria_progress(&criteria);
+        if total == 0 && criteria_total == 0 {
+            return Ok(changes);
         }
         let done = step::Entity::find()
             .filter(step::Column::PlanId.eq(plan_id))
             .filter(step::Column::Status.eq(StepStatus::Done.as_str()))
             .count(db)
             .await?;
-        let status = if done == total {
+        let complete = done == total && met == criteria_total;
+        let status = if complete {
             PlanStatus::Done
         } else {
             PlanStatus::Todo
@@ -984,19 +3930,16 @@ impl App {
         let Some(plan) = plan else {
             return Err(AppError::NotFound(format!("plan {plan_id}")));
         };
-        let mut changes = StatusChanges::default();
         if plan.status != status.as_str() {
-            let reason = if done == total {
-                format!("all steps are done ({done}/{total})")
-            } else {
-                format!("steps done {done}/{total}")
-            };
+            let reason = plan_status_reason((done as usize, total as usize), (met, criteria_total));
             let mut active = plan::ActiveModel {
                 id: Set(plan_id),
                 ..Default::default()
             };
+            let now = Utc::now();
             active.status = Set(status.as_str().to_string());
-            active.updated_at = Set(Utc::now());
+            active.done_at = Set((status == PlanStatus::Done).then_some(now));
+            active.updated_at = Set(now);
             active.update(db).await?;
             changes.plans.push(PlanStatusChange {
                 plan_id,
@@ -1005,7 +3948,7 @@ impl App {
                 reason,
             });
             if status == PlanStatus::Done {
-                let cleared = self
+                let (cleared, resumed) = self
                     .clear_active_plans_for_plan_with_conn(db, plan_id)
                     .await?;
                 if cleared {
@@ -1014,65 +3957,113 @@ impl App {
                         reason: "plan marked done".to_string(),
                     });
                 }
+                if let Some(resumed) = resumed {
+                    changes.active_plans_resumed.push(ActivePlanResumed {
+                        plan_id: resumed.id,
+                        reason: format!("resumed from focus stack after plan {plan_id} finished"),
+                    });
+                }
             }
         }
 
         Ok(changes)
     }
 
+    /// Derives a step's status from its goals and sub-steps, then rolls the result up through
+    /// its ancestors and finally the plan. Steps with neither goals nor sub-steps are left as is.
     async fn refresh_step_status_with_conn<C: ConnectionTrait>(
         &self,
         db: &C,
         step_id: i64,
     ) -> Result<StatusChanges, AppError> {
-        let goals = goal::Entity::find()
-            .filter(goal::Column::StepId.eq(step_id))
-            .all(db)
-            .await?;
-        if goals.is_empty() {
-            return Ok(StatusChanges::default());
-        }
-
-        let done = goals
-            .iter()
-            .filter(|goal| goal.status == GoalStatus::Done.as_str())
-            .count();
-        let total = goals.len();
-        let status = if done == total {
-            StepStatus::Done
-        } else {
-            StepStatus::Todo
-        };
+        self.refresh_step_status_with_evidence(db, step_id, None).await
+    }
 
-        let step = step::Entity::find_by_id(step_id).one(db).await?;
-        let Some(step) = step else {
-            return Err(AppError::NotFound(format!("step {step_id}")));
-        };
+    async fn refresh_step_status_with_evidence<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        step_id: i64,
+        evidence: Option<&Checkpoint>,
+    ) -> Result<StatusChanges, AppError> {
         let mut changes = StatusChanges::default();
-        if step.status != status.as_str() {
-            let mut active = step::ActiveModel {
-                id: Set(step_id),
-                ..Default::default()
+        let mut current = step_id;
+        loop {
+            let step = step::Entity::find_by_id(current).one(db).await?;
+            let Some(step) = step else {
+                return Err(AppError::NotFound(format!("step {current}")));
             };
-            active.status = Set(status.as_str().to_string());
-            active.updated_at = Set(Utc::now());
-            active.update(db).await?;
-            let reason = if done == total {
-                format!("all goals are done ({done}/{total})")
+            let goals = goal::Entity::find()
+                .filter(goal::Column::StepId.eq(current))
+                .all(db)
+                .await?;
+            let children = step::Entity::find()
+                .filter(step::Column::ParentId.eq(current))
+                .all(db)
+                .await?;
+            if goals.is_empty() && children.is_empty() {
+                if current == step_id {
+                    return Ok(changes);
+                }
             } else {
-                format!("goals done {done}/{total}")
-            };
-            changes.steps.push(StepStatusChange {
-                step_id,
-                from: step.status,
-                to: status.as_str().to_string(),
-                reason,
-            });
+                let goals_done = goals
+                    .iter()
+                    .filter(|goal| goal.status == GoalStatus::Done.as_str())
+                    .count();
+                let children_done = children
+                    .iter()
+                    .filter(|child| child.status == StepStatus::Done.as_str())
+                    .count();
+                let all_done = goals_done == goals.len() && children_done == children.len();
+                let status = if all_done {
+                    StepStatus::Done
+                } else {
+                    StepStatus::Todo
+                };
+                if step.status != status.as_str() {
+                    let mut active = step::ActiveModel {
+                        id: Set(current),
+                        ..Default::default()
+                    };
+                    active.status = Set(status.as_str().to_string());
+                    active.updated_at = Set(Utc::now());
+                    let checkpoint = self.checkpoint_or(status == StepStatus::Done, evidence);
+                    set_step_checkpoint(&mut active, checkpoint);
+                    active.update(db).await?;
+                    if status == StepStatus::Done {
+                        self.release_step_claims_with_conn(db, &[current]).await?;
+                    }
+                    let mut counts = Vec::new();
+                    if !goals.is_empty() {
+                        counts.push(format!("goals {goals_done}/{}", goals.len()));
+                    }
+                    if !children.is_empty() {
+                        counts.push(format!("sub-steps {children_done}/{}", children.len()));
+                    }
+                    let reason = match (all_done, children.is_empty()) {
+                        (true, true) => {
+                            format!("all goals are done ({goals_done}/{})", goals.len())
+                        }
+                        (true, false) => format!("all done ({})", counts.join(", ")),
+                        (false, true) => format!("goals done {goals_done}/{}", goals.len()),
+                        (false, false) => format!("done {}", counts.join(", ")),
+                    };
+                    changes.steps.push(StepStatusChange {
+                        step_id: current,
+                        from: step.status.clone(),
+                        to: status.as_str().to_string(),
+                        reason,
+                    });
+                }
+            }
+            match step.parent_id {
+                Some(parent_id) => current = parent_id,
+                None => {
+                    let plan_changes = self.refresh_plan_status_with_conn(db, step.plan_id).await?;
+                    changes.merge(plan_changes);
+                    return Ok(changes);
+                }
+            }
         }
-
-        let plan_changes = self.refresh_plan_status_with_conn(db, step.plan_id).await?;
-        changes.merge(plan_changes);
-        Ok(changes)
     }
 
     async fn next_step_with_conn<C: ConnectionTrait>(
@@ -1080,13 +4071,17 @@ impl App {
         db: &C,
         plan_id: i64,
     ) -> Result<Option<step::Model>, AppError> {
-        Ok(step::Entity::find()
+        let steps = step::Entity::find()
             .filter(step::Column::PlanId.eq(plan_id))
-            .filter(step::Column::Status.eq(StepStatus::Todo.as_str()))
             .order_by_asc(step::Column::SortOrder)
             .order_by_asc(step::Column::Id)
-            .one(db)
-            .await?)
+            .all(db)
+            .await?;
+        let milestones = self.milestones_with_conn(db, &[plan_id]).await?;
+        Ok(actionable_steps(&steps, &milestones)
+            .into_iter()
+            .next()
+            .cloned())
     }
 
     async fn next_goal_for_step_with_conn<C: ConnectionTrait>(
@@ -1097,6 +4092,7 @@ impl App {
         Ok(goal::Entity::find()
             .filter(goal::Column::StepId.eq(step_id))
             .filter(goal::Column::Status.eq(GoalStatus::Todo.as_str()))
+            .order_by_asc(goal::Column::SortOrder)
             .order_by_asc(goal::Column::Id)
             .one(db)
             .await?)
@@ -1109,6 +4105,7 @@ impl App {
     ) -> Result<Vec<goal::Model>, AppError> {
         Ok(goal::Entity::find()
             .filter(goal::Column::StepId.eq(step_id))
+            .order_by_asc(goal::Column::SortOrder)
             .order_by_asc(goal::Column::Id)
             .all(db)
             .await?)
@@ -1134,14 +4131,16 @@ impl App {
                 .await?
                 .ok_or_else(|| AppError::NotFound(format!("step id {step_id}")))?;
             let plan_id = step.plan_id;
+            let existing = self.normalize_goals_for_step(&txn, step_id).await?;
 
             let now = Utc::now();
             let mut created = Vec::with_capacity(contents.len());
-            for content in contents.into_iter() {
+            for (idx, content) in contents.into_iter().enumerate() {
                 let active = goal::ActiveModel {
                     step_id: Set(step_id),
                     content: Set(content),
                     status: Set(status.as_str().to_string()),
+                    sort_order: Set((existing.len() + idx + 1) as i32),
                     created_at: Set(now),
                     updated_at: Set(now),
                     ..Default::default()
@@ -1179,7 +4178,11 @@ impl App {
         if let Some(offset) = query.offset {
             select = select.offset(offset);
         }
-        Ok(select.order_by_asc(goal::Column::Id).all(&self.db).await?)
+        Ok(select
+            .order_by_asc(goal::Column::SortOrder)
+            .order_by_asc(goal::Column::Id)
+            .all(&self.db)
+            .await?)
     }
 
     pub async fn count_goals(&self, step_id: i64, query: &GoalQuery) -> Result<u64, AppError> {
@@ -1453,6 +4456,13 @@ impl App {
             active.content = Set(content);
         }
         if let Some(status) = changes.status {
+            let current = goal::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("goal id {id}")))?;
+            if current.status != status.as_str() {
+                set_goal_checkpoint(&mut active, self.checkpoint_for(status == GoalStatus::Done));
+            }
             active.status = Set(status.as_str().to_string());
         }
         if let Some(comment) = changes.comment {
@@ -1482,6 +4492,16 @@ impl App {
         db: &C,
         ids: &[i64],
         status: GoalStatus,
+    ) -> Result<(u64, StatusChanges), AppError> {
+        self.set_goals_status_with_evidence(db, ids, status, None).await
+    }
+
+    async fn set_goals_status_with_evidence<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        ids: &[i64],
+        status: GoalStatus,
+        evidence: Option<&Checkpoint>,
     ) -> Result<(u64, StatusChanges), AppError> {
         if ids.is_empty() {
             return Ok((0, StatusChanges::default()));
@@ -1512,6 +4532,10 @@ impl App {
                 step_ids.push(goal_model.step_id);
             }
             let mut active: goal::ActiveModel = goal_model.clone().into();
+            if goal_model.status != status.as_str() {
+                let checkpoint = self.checkpoint_or(status == GoalStatus::Done, evidence);
+                set_goal_checkpoint(&mut active, checkpoint);
+            }
             active.status = Set(status.as_str().to_string());
             active.updated_at = Set(now);
             active.update(db).await?;
@@ -1519,7 +4543,9 @@ impl App {
 
         let mut changes = StatusChanges::default();
         for step_id in &step_ids {
-            let updated = self.refresh_step_status_with_conn(db, *step_id).await?;
+            let updated = self
+                .refresh_step_status_with_evidence(db, *step_id, evidence)
+                .await?;
             changes.merge(updated);
         }
 
@@ -1574,6 +4600,164 @@ impl App {
         finalize_transaction(txn, result).await
     }
 
+    /// Marks the goals and steps named by commit trailers done, oldest commit first, and records
+    /// the commit as their checkpoint. A step trailer also completes the step's pending goals.
+    pub async fn apply_commit_trailers(
+        &self,
+        commits: &[CommitTrailers],
+    ) -> Result<(Vec<TrailerResult>, StatusChanges), AppError> {
+        let branch = self.checkpoint_for(true).and_then(|current| current.branch.clone());
+        let txn = self.db.begin().await?;
+        let result: Result<(Vec<TrailerResult>, StatusChanges), AppError> = async {
+            let mut results = Vec::new();
+            let mut merged = StatusChanges::default();
+            for trailers in commits {
+                let evidence = Checkpoint {
+                    commit: trailers.commit.clone(),
+                    branch: branch.clone(),
+                    dirty: false,
+                };
+                let mut record = |kind, id, plan_id, outcome| {
+                    results.push(TrailerResult {
+                        commit: trailers.commit.clone(),
+                        kind,
+                        id,
+                        plan_id,
+                        outcome,
+                    });
+                };
+                for &id in &trailers.ids.goal_ids {
+                    let Some(goal_model) = goal::Entity::find_by_id(id).one(&txn).await? else {
+                        record("goal", id, None, TrailerOutcome::NotFound);
+                        continue;
+                    };
+                    let plan_id = step::Entity::find_by_id(goal_model.step_id)
+                        .one(&txn)
+                        .await?
+                        .map(|step_model| step_model.plan_id);
+                    if goal_model.status == GoalStatus::Done.as_str() {
+                        record("goal", id, plan_id, TrailerOutcome::AlreadyDone);
+                        continue;
+                    }
+                    let (_, changes) = self
+                        .set_goals_status_with_evidence(
+                            &txn,
+                            &[id],
+                            GoalStatus::Done,
+                            Some(&evidence),
+                        )
+                        .await?;
+                    merged.merge(changes);
+                    record("goal", id, plan_id, TrailerOutcome::Completed);
+                }
+                for &id in &trailers.ids.step_ids {
+                    let Some(step_model) = step::Entity::find_by_id(id).one(&txn).await? else {
+                        record("step", id, None, TrailerOutcome::NotFound);
+                        continue;
+                    };
+                    let plan_id = Some(step_model.plan_id);
+                    if step_model.status == StepStatus::Done.as_str() {
+                        record("step", id, plan_id, TrailerOutcome::AlreadyDone);
+                        continue;
+                    }
+                    let pending_child = step::Entity::find()
+                        .filter(step::Column::ParentId.eq(id))
+                        .filter(step::Column::Status.eq(StepStatus::Todo.as_str()))
+                        .order_by_asc(step::Column::SortOrder)
+                        .order_by_asc(step::Column::Id)
+                        .one(&txn)
+                        .await?;
+                    if let Some(child) = pending_child {
+                        let reason =
+                            format!("pending sub-step: {} (id {})", child.content, child.id);
+                        record("step", id, plan_id, TrailerOutcome::Blocked(reason));
+                        continue;
+                    }
+                    let pending_goals: Vec<i64> = self
+                        .goals_for_step_with_conn(&txn, id)
+                        .await?
+                        .into_iter()
+                        .filter(|goal_model| goal_model.status != GoalStatus::Done.as_str())
+                        .map(|goal_model| goal_model.id)
+                        .collect();
+                    let (_, changes) = self
+                        .set_goals_status_with_evidence(
+                            &txn,
+                            &pending_goals,
+                            GoalStatus::Done,
+                            Some(&evidence),
+                        )
+                        .await?;
+                    merged.merge(changes);
+                    let refreshed = step::Entity::find_by_id(id).one(&txn).await?;
+                    if refreshed.is_some_and(|model| model.status != StepStatus::Done.as_str()) {
+                        let changes = StepChanges {
+                            status: Some(StepStatus::Done),
+                            ..Default::default()
+                        };
+                        let (_, changes) = self
+                            .update_step_with_evidence(&txn, id, changes, Some(&evidence))
+                            .await?;
+                        merged.merge(changes);
+                    }
+                    record("step", id, plan_id, TrailerOutcome::Completed);
+                }
+            }
+            Ok((results, merged))
+        }
+        .await;
+
+        finalize_transaction(txn, result).await
+    }
+
+    /// Fails with one line per problem when trailers name ids that are malformed or missing.
+    pub async fn validate_trailer_ids(&self, ids: &TrailerIds) -> Result<(), AppError> {
+        let mut problems: Vec<String> = ids
+            .invalid
+            .iter()
+            .map(|trailer| format!("invalid trailer value: {trailer}"))
+            .collect();
+        let goals: HashSet<i64> = goal::Entity::find()
+            .filter(goal::Column::Id.is_in(ids.goal_ids.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|goal_model| goal_model.id)
+            .collect();
+        let missing: Vec<i64> = ids
+            .goal_ids
+            .iter()
+            .copied()
+            .filter(|id| !goals.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("goal id(s) not found: {}", join_ids(&missing)));
+        }
+        let steps: HashSet<i64> = step::Entity::find()
+            .filter(step::Column::Id.is_in(ids.step_ids.clone()))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|step_model| step_model.id)
+            .collect();
+        let missing: Vec<i64> = ids
+            .step_ids
+            .iter()
+            .copied()
+            .filter(|id| !steps.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("step id(s) not found: {}", join_ids(&missing)));
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::InvalidInput(format!(
+            "commit message has invalid Planpilot trailers:\n{}",
+            problems.join("\n")
+        )))
+    }
+
     pub async fn delete_goals(&self, ids: &[i64]) -> Result<(u64, StatusChanges), AppError> {
         let txn = self.db.begin().await?;
         let result: Result<(u64, StatusChanges), AppError> = async {
@@ -1673,6 +4857,27 @@ impl App {
         }
         Ok(())
     }
+
+    async fn normalize_goals_for_step<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        step_id: i64,
+    ) -> Result<Vec<goal::Model>, AppError> {
+        let mut goals = self.goals_for_step_with_conn(db, step_id).await?;
+        let now = Utc::now();
+        for (idx, goal_model) in goals.iter_mut().enumerate() {
+            let desired_order = (idx + 1) as i32;
+            if goal_model.sort_order != desired_order {
+                let mut active: goal::ActiveModel = goal_model.clone().into();
+                active.sort_order = Set(desired_order);
+                active.updated_at = Set(now);
+                active.update(db).await?;
+                goal_model.sort_order = desired_order;
+                goal_model.updated_at = now;
+            }
+        }
+        Ok(goals)
+    }
 }
 
 impl App {
@@ -1704,26 +4909,228 @@ impl App {
         for plan_id in plan_ids {
             self.touch_plan_with_conn(db, *plan_id).await?;
         }
-        Ok(())
+        Ok(())
+    }
+}
+
+async fn finalize_transaction<T>(
+    txn: DatabaseTransaction,
+    result: Result<T, AppError>,
+) -> Result<T, AppError> {
+    match result {
+        Ok(value) => {
+            txn.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            if let Err(rollback_err) = txn.rollback().await {
+                return Err(rollback_err.into());
+            }
+            Err(err)
+        }
+    }
+}
+
+fn normalize_tags(names: &[String]) -> Result<Vec<String>, AppError> {
+    if names.is_empty() {
+        return Err(AppError::InvalidInput("no tags provided".to_string()));
+    }
+    let mut tags = Vec::with_capacity(names.len());
+    for name in names {
+        let tag = normalize_tag(name).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "invalid tag '{name}', expected letters, digits or -_./:"
+            ))
+        })?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    Ok(tags)
+}
+
+/// Todo steps without pending sub-steps, in working order: milestone by milestone, then the
+/// steps without a milestone.
+fn actionable_steps<'a>(
+    steps: &'a [step::Model],
+    milestones: &[milestone::Model],
+) -> Vec<&'a step::Model> {
+    let todo = StepStatus::Todo.as_str();
+    let blocked: HashSet<i64> = steps
+        .iter()
+        .filter(|step| step.status == todo)
+        .filter_map(|step| step.parent_id)
+        .collect();
+    milestone_groups(steps, milestones)
+        .into_iter()
+        .flat_map(|(_, entries)| entries)
+        .map(|(step, _)| step)
+        .filter(|step| step.status == todo && !blocked.contains(&step.id))
+        .collect()
+}
+
+fn step_depth(steps: &[step::Model], step_id: i64) -> usize {
+    let parents: HashMap<i64, Option<i64>> =
+        steps.iter().map(|step| (step.id, step.parent_id)).collect();
+    let mut depth = 0;
+    let mut current = parents.get(&step_id).copied().flatten();
+    while let Some(parent_id) = current {
+        depth += 1;
+        current = parents.get(&parent_id).copied().flatten();
+    }
+    depth
+}
+
+/// Number of levels below `step_id` (0 for a step without sub-steps).
+fn subtree_height(steps: &[step::Model], step_id: i64) -> usize {
+    steps
+        .iter()
+        .filter(|step| step.parent_id == Some(step_id))
+        .map(|step| subtree_height(steps, step.id) + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Maps each goal (in order) to a 0-based split part. Explicit assignments win; the remaining
+/// goals are spread over the parts in contiguous runs.
+fn assign_goals_to_parts(
+    goals: &[goal::Model],
+    parts: usize,
+    assignments: &[(i64, usize)],
+    step_id: i64,
+) -> Result<Vec<usize>, AppError> {
+    let mut explicit = HashMap::new();
+    for (goal_id, part) in assignments {
+        if !goals.iter().any(|goal| goal.id == *goal_id) {
+            return Err(AppError::InvalidInput(format!(
+                "goal id {goal_id} does not belong to step id {step_id}"
+            )));
+        }
+        if *part == 0 || *part > parts {
+            return Err(AppError::InvalidInput(format!(
+                "split part {part} is out of range (1-{parts})"
+            )));
+        }
+        explicit.insert(*goal_id, part - 1);
+    }
+    let unassigned = goals
+        .iter()
+        .filter(|goal| !explicit.contains_key(&goal.id))
+        .count();
+    let per_part = unassigned.div_ceil(parts).max(1);
+    let mut next = 0;
+    Ok(goals
+        .iter()
+        .map(|goal| match explicit.get(&goal.id) {
+            Some(part) => *part,
+            None => {
+                let part = next / per_part;
+                next += 1;
+                part
+            }
+        })
+        .collect())
+}
+
+fn descendant_ids(steps: &[step::Model], step_id: i64) -> HashSet<i64> {
+    let mut found = HashSet::new();
+    let mut pending = vec![step_id];
+    while let Some(parent_id) = pending.pop() {
+        for step in steps.iter().filter(|step| step.parent_id == Some(parent_id)) {
+            if found.insert(step.id) {
+                pending.push(step.id);
+            }
+        }
     }
+    found
 }
 
-async fn finalize_transaction<T>(
-    txn: DatabaseTransaction,
-    result: Result<T, AppError>,
-) -> Result<T, AppError> {
-    match result {
-        Ok(value) => {
-            txn.commit().await?;
-            Ok(value)
-        }
-        Err(err) => {
-            if let Err(rollback_err) = txn.rollback().await {
-                return Err(rollback_err.into());
-            }
-            Err(err)
+/// Explains a derived plan status from its `(done, total)` steps and `(met, total)` criteria.
+fn plan_status_reason(steps: (usize, usize), criteria: (usize, usize)) -> String {
+    let ((done, total), (met, required)) = (steps, criteria);
+    let steps_text = if done == total {
+        format!("all steps are done ({done}/{total})")
+    } else {
+        format!("steps done {done}/{total}")
+    };
+    let criteria_text = if met == required {
+        format!("all criteria are met ({met}/{required})")
+    } else {
+        format!("criteria met {met}/{required}")
+    };
+    match (total, required) {
+        (_, 0) => steps_text,
+        (0, _) => criteria_text,
+        _ if done == total && met == required => format!("{steps_text} and {criteria_text}"),
+        _ => format!("{steps_text}, {criteria_text}"),
+    }
+}
+
+fn set_step_checkpoint(active: &mut step::ActiveModel, checkpoint: Option<&Checkpoint>) {
+    active.checkpoint_commit = Set(checkpoint.map(|checkpoint| checkpoint.commit.clone()));
+    active.checkpoint_branch = Set(checkpoint.and_then(|checkpoint| checkpoint.branch.clone()));
+    active.checkpoint_dirty = Set(checkpoint.map(|checkpoint| checkpoint.dirty));
+    active.checkpoint_at = Set(checkpoint.map(|_| Utc::now()));
+}
+
+fn set_goal_checkpoint(active: &mut goal::ActiveModel, checkpoint: Option<&Checkpoint>) {
+    active.checkpoint_commit = Set(checkpoint.map(|checkpoint| checkpoint.commit.clone()));
+    active.checkpoint_branch = Set(checkpoint.and_then(|checkpoint| checkpoint.branch.clone()));
+    active.checkpoint_dirty = Set(checkpoint.map(|checkpoint| checkpoint.dirty));
+    active.checkpoint_at = Set(checkpoint.map(|_| Utc::now()));
+}
+
+fn sort_direction(desc: bool) -> Order {
+    if desc {
+        Order::Desc
+    } else {
+        Order::Asc
+    }
+}
+
+pub fn plan_progress(detail: &PlanDetail) -> (usize, usize) {
+    let done = detail
+        .steps
+        .iter()
+        .filter(|step| step.status == StepStatus::Done.as_str())
+        .count();
+    (done, detail.steps.len())
+}
+
+pub fn sort_plan_details_by_progress(details: &mut [PlanDetail], desc: bool) {
+    details.sort_by(|left, right| {
+        let (left_done, left_total) = plan_progress(left);
+        let (right_done, right_total) = plan_progress(right);
+        // Compare done/total ratios without floats; plans without steps count as 0%.
+        let left_key = left_done * right_total.max(1);
+        let right_key = right_done * left_total.max(1);
+        if desc {
+            right_key.cmp(&left_key)
+        } else {
+            left_key.cmp(&right_key)
         }
+    });
+}
+
+fn session_entry<'a>(
+    summaries: &'a mut HashMap<String, SessionSummary>,
+    session_id: &str,
+    seen_at: DateTime<Utc>,
+) -> &'a mut SessionSummary {
+    let summary = summaries
+        .entry(session_id.to_string())
+        .or_insert_with(|| SessionSummary {
+            session_id: session_id.to_string(),
+            cwd: None,
+            last_seen_at: seen_at,
+            plan_id: None,
+            claims: 0,
+            stale: false,
+        });
+    if seen_at > summary.last_seen_at {
+        summary.last_seen_at = seen_at;
     }
+    summary
 }
 
 fn unique_ids(ids: &[i64]) -> Vec<i64> {
@@ -1751,6 +5158,24 @@ fn normalize_comment_entries(entries: Vec<(i64, String)>) -> Vec<(i64, String)>
     ordered
 }
 
+fn executor_condition(executor: &StepExecutor) -> Condition {
+    if executor.agent().is_some() {
+        return Condition::all().add(step::Column::Executor.eq(executor.as_str()));
+    }
+    // Kinds may contain `_`, which LIKE would treat as a wildcard.
+    let kind = executor.kind().replace('_', "\\_");
+    Condition::any()
+        .add(step::Column::Executor.eq(executor.kind()))
+        .add(step::Column::Executor.like(LikeExpr::new(format!("{kind}:%")).escape('\\')))
+}
+
+/// How long to wait after the given attempt before retrying a webhook delivery: 30 seconds,
+/// doubling with each attempt, up to an hour. Deliveries are retried until they succeed.
+pub fn webhook_retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.clamp(1, 8) - 1;
+    Duration::seconds((30_i64 << doublings).min(3600))
+}
+
 fn join_ids(ids: &[i64]) -> String {
     ids.iter()
         .map(|id| id.to_string())
@@ -1770,9 +5195,11 @@ mod tests {
     use super::*;
     use crate::db;
     use crate::model::{
-        GoalChanges, GoalStatus, PlanChanges, PlanInput, PlanStatus, StepChanges, StepExecutor,
-        StepStatus,
+        CriterionStatus, GoalChanges, GoalStatus, PlanChanges, PlanInput, PlanStatus, Priority,
+        StepChanges, StepExecutor, StepStatus,
     };
+    use chrono::NaiveDate;
+    use sea_orm::{DatabaseBackend, Statement};
     use tempfile::TempDir;
 
     const TEST_CONVERSATION_ID: &str = "test-session";
@@ -1790,6 +5217,7 @@ mod tests {
         app.add_plan(PlanInput {
             title: title.to_string(),
             content: "Content".to_string(),
+            ..Default::default()
         })
         .await
         .expect("add plan")
@@ -1801,7 +5229,8 @@ mod tests {
                 plan_id,
                 vec![content.to_string()],
                 status,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -1826,7 +5255,8 @@ mod tests {
                 plan.id,
                 vec!["Step".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -1860,7 +5290,8 @@ mod tests {
                 plan.id,
                 vec!["Step".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -1888,7 +5319,8 @@ mod tests {
                 plan.id,
                 vec!["Step".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -1925,7 +5357,8 @@ mod tests {
                 plan.id,
                 vec!["Step".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -2072,7 +5505,8 @@ mod tests {
             plan.id,
             vec!["A".to_string(), "B".to_string(), "C".to_string()],
             StepStatus::Todo,
-            StepExecutor::Ai,
+            StepExecutor::ai(),
+            StepSchedule::default(),
             None,
         )
         .await
@@ -2082,7 +5516,8 @@ mod tests {
             plan.id,
             vec!["X".to_string(), "Y".to_string()],
             StepStatus::Todo,
-            StepExecutor::Ai,
+            StepExecutor::ai(),
+            StepSchedule::default(),
             Some(2),
         )
         .await
@@ -2095,6 +5530,33 @@ mod tests {
         assert_eq!(orders, vec![1, 2, 3, 4, 5]);
     }
 
+    #[tokio::test]
+    async fn add_steps_batch_sets_schedule_on_every_step() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let due = NaiveDate::from_ymd_opt(2030, 5, 1).expect("date");
+
+        let (steps, _) = app
+            .add_steps_batch(
+                plan.id,
+                vec!["A".to_string(), "B".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule {
+                    priority: Some(Priority::P1),
+                    due: Some(due),
+                },
+                None,
+            )
+            .await
+            .expect("add steps");
+
+        for step in steps {
+            assert_eq!(step.priority.as_deref(), Some("p1"));
+            assert_eq!(step.due, Some(due));
+        }
+    }
+
     #[tokio::test]
     async fn move_step_reorders_bounds() {
         let (_dir, app) = setup_app().await;
@@ -2105,7 +5567,8 @@ mod tests {
                 plan.id,
                 vec!["A".to_string(), "B".to_string(), "C".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -2145,88 +5608,305 @@ mod tests {
             .await
             .expect("delete goal");
 
-        let step_after = app.get_step(step.id).await.expect("get step");
-        let plan_after = app.get_plan(plan.id).await.expect("get plan");
-        assert_eq!(step_after.status, StepStatus::Done.as_str());
-        assert_eq!(plan_after.status, PlanStatus::Done.as_str());
-        assert!(!changes.steps.is_empty());
-        assert!(!changes.plans.is_empty());
+        let step_after = app.get_step(step.id).await.expect("get step");
+        let plan_after = app.get_plan(plan.id).await.expect("get plan");
+        assert_eq!(step_after.status, StepStatus::Done.as_str());
+        assert_eq!(plan_after.status, PlanStatus::Done.as_str());
+        assert!(!changes.steps.is_empty());
+        assert!(!changes.plans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_steps_updates_plan_status_when_remaining_done() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+
+        let (steps, _) = app
+            .add_steps_batch(
+                plan.id,
+                vec!["Done".to_string(), "Todo".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+                None,
+            )
+            .await
+            .expect("add steps");
+
+        app.update_step(
+            steps[0].id,
+            StepChanges {
+                status: Some(StepStatus::Done),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("set step done");
+
+        let (_deleted, changes) = app.delete_steps(&[steps[1].id]).await.expect("delete step");
+
+        let plan_after = app.get_plan(plan.id).await.expect("get plan");
+        assert_eq!(plan_after.status, PlanStatus::Done.as_str());
+        assert!(!changes.plans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_steps_reorders_remaining() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+
+        let (steps, _) = app
+            .add_steps_batch(
+                plan.id,
+                vec!["A".to_string(), "B".to_string(), "C".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+                None,
+            )
+            .await
+            .expect("add steps");
+
+        app.delete_steps(&[steps[1].id]).await.expect("delete step");
+
+        let (_plan, remaining) = app.plan_with_steps(plan.id).await.expect("plan steps");
+        let contents: Vec<_> = remaining.iter().map(|step| step.content.as_str()).collect();
+        let orders: Vec<_> = remaining.iter().map(|step| step.sort_order).collect();
+        assert_eq!(contents, vec!["A", "C"]);
+        assert_eq!(orders, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn delete_plan_clears_active_plan() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        app.set_active_plan(plan.id, false)
+            .await
+            .expect("set active");
+
+        app.delete_plan(plan.id).await.expect("delete plan");
+
+        let active = app.get_active_plan().await.expect("get active");
+        assert!(active.is_none());
+    }
+
+    #[tokio::test]
+    async fn active_plan_is_scoped_to_session() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
+
+        let db_a = db::connect(&db_path).await.expect("connect db a");
+        db::ensure_schema(&db_a).await.expect("ensure schema a");
+        let db_b = db::connect(&db_path).await.expect("connect db b");
+        db::ensure_schema(&db_b).await.expect("ensure schema b");
+
+        let app_a = App::new(db_a, "session-a".to_string());
+        let app_b = App::new(db_b, "session-b".to_string());
+
+        let plan_a = create_plan(&app_a, "Plan A").await;
+        let plan_b = create_plan(&app_a, "Plan B").await;
+
+        app_a
+            .set_active_plan(plan_a.id, false)
+            .await
+            .expect("set active a");
+        app_b
+            .set_active_plan(plan_b.id, false)
+            .await
+            .expect("set active b");
+
+        let active_a = app_a.get_active_plan().await.expect("get active a");
+        let active_b = app_b.get_active_plan().await.expect("get active b");
+
+        assert_eq!(active_a.expect("active a").plan_id, plan_a.id);
+        assert_eq!(active_b.expect("active b").plan_id, plan_b.id);
+    }
+
+    #[tokio::test]
+    async fn active_plan_is_unique_per_plan() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
+
+        let db_a = db::connect(&db_path).await.expect("connect db a");
+        db::ensure_schema(&db_a).await.expect("ensure schema a");
+        let db_b = db::connect(&db_path).await.expect("connect db b");
+        db::ensure_schema(&db_b).await.expect("ensure schema b");
+
+        let app_a = App::new(db_a, "session-a".to_string());
+        let app_b = App::new(db_b, "session-b".to_string());
+
+        let plan = create_plan(&app_a, "Plan A").await;
+
+        app_a
+            .set_active_plan(plan.id, false)
+            .await
+            .expect("set active a");
+        let err = app_b.set_active_plan(plan.id, false).await.unwrap_err();
+        match err {
+            AppError::InvalidInput(message) => {
+                assert!(message.contains("already active in session"));
+                assert!(message.contains("session-a"));
+            }
+            _ => panic!("unexpected error type"),
+        }
+
+        let active_a = app_a.get_active_plan().await.expect("get active a");
+        let active_b = app_b.get_active_plan().await.expect("get active b");
+
+        assert_eq!(active_a.expect("active a").plan_id, plan.id);
+        assert!(active_b.is_none());
+    }
+
+    #[tokio::test]
+    async fn active_plan_takeover_reassigns_plan() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
+
+        let db_a = db::connect(&db_path).await.expect("connect db a");
+        db::ensure_schema(&db_a).await.expect("ensure schema a");
+        let db_b = db::connect(&db_path).await.expect("connect db b");
+        db::ensure_schema(&db_b).await.expect("ensure schema b");
+
+        let app_a = App::new(db_a, "session-a".to_string());
+        let app_b = App::new(db_b, "session-b".to_string());
+
+        let plan = create_plan(&app_a, "Plan A").await;
+
+        app_a
+            .set_active_plan(plan.id, false)
+            .await
+            .expect("set active a");
+        app_b
+            .set_active_plan(plan.id, true)
+            .await
+            .expect("set active b");
+
+        let active_a = app_a.get_active_plan().await.expect("get active a");
+        let active_b = app_b.get_active_plan().await.expect("get active b");
+
+        assert!(active_a.is_none());
+        assert_eq!(active_b.expect("active b").plan_id, plan.id);
+    }
+
+    #[tokio::test]
+    async fn shared_activation_keeps_other_sessions_active() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
+
+        let db_a = db::connect(&db_path).await.expect("connect db a");
+        db::ensure_schema(&db_a).await.expect("ensure schema a");
+        let db_b = db::connect(&db_path).await.expect("connect db b");
+        db::ensure_schema(&db_b).await.expect("ensure schema b");
+
+        let app_a = App::new(db_a, "session-a".to_string());
+        let app_b = App::new(db_b, "session-b".to_string());
+
+        let plan = create_plan(&app_a, "Plan").await;
+        app_a
+            .set_active_plan(plan.id, false)
+            .await
+            .expect("set active a");
+        app_b
+            .share_active_plan(plan.id)
+            .await
+            .expect("share active b");
+
+        let active_a = app_a.get_active_plan().await.expect("get active a");
+        let active_b = app_b.get_active_plan().await.expect("get active b");
+        assert_eq!(active_a.expect("active a").plan_id, plan.id);
+        assert_eq!(active_b.expect("active b").plan_id, plan.id);
     }
 
     #[tokio::test]
-    async fn delete_steps_updates_plan_status_when_remaining_done() {
-        let (_dir, app) = setup_app().await;
-        let plan = create_plan(&app, "Plan").await;
+    async fn claim_next_step_skips_steps_claimed_by_other_sessions() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
 
-        let (steps, _) = app
-            .add_steps_batch(
-                plan.id,
-                vec!["Done".to_string(), "Todo".to_string()],
-                StepStatus::Todo,
-                StepExecutor::Ai,
-                None,
-            )
-            .await
-            .expect("add steps");
+        let db_a = db::connect(&db_path).await.expect("connect db a");
+        db::ensure_schema(&db_a).await.expect("ensure schema a");
+        let db_b = db::connect(&db_path).await.expect("connect db b");
+        db::ensure_schema(&db_b).await.expect("ensure schema b");
 
-        app.update_step(
-            steps[0].id,
-            StepChanges {
-                status: Some(StepStatus::Done),
-                ..Default::default()
-            },
-        )
-        .await
-        .expect("set step done");
+        let app_a = App::new(db_a, "session-a".to_string());
+        let app_b = App::new(db_b, "session-b".to_string());
 
-        let (_deleted, changes) = app.delete_steps(&[steps[1].id]).await.expect("delete step");
+        let plan = create_plan(&app_a, "Plan").await;
+        let step_1 = add_step(&app_a, plan.id, "Step 1", StepStatus::Todo).await;
+        let step_2 = add_step(&app_a, plan.id, "Step 2", StepStatus::Todo).await;
+        let lease = Duration::minutes(30);
 
-        let plan_after = app.get_plan(plan.id).await.expect("get plan");
-        assert_eq!(plan_after.status, PlanStatus::Done.as_str());
-        assert!(!changes.plans.is_empty());
+        let next_a = app_a
+            .claim_next_step(plan.id, lease)
+            .await
+            .expect("claim a")
+            .expect("step a");
+        assert_eq!(next_a.id, step_1.id);
+        let again_a = app_a
+            .claim_next_step(plan.id, lease)
+            .await
+            .expect("claim a again")
+            .expect("step a again");
+        assert_eq!(again_a.id, step_1.id);
+
+        let next_b = app_b
+            .claim_next_step(plan.id, lease)
+            .await
+            .expect("claim b")
+            .expect("step b");
+        assert_eq!(next_b.id, step_2.id);
+        let unclaimed = app_b.next_unclaimed_step(plan.id).await.expect("unclaimed b");
+        assert_eq!(unclaimed.map(|step| step.id), Some(step_2.id));
+        let err = app_a
+            .claim_step(step_2.id, lease, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+
+        app_b.release_step(step_2.id).await.expect("release b");
+        app_a
+            .set_step_done_with_goals(step_1.id, false)
+            .await
+            .expect("step 1 done");
+        let next_a = app_a
+            .claim_next_step(plan.id, lease)
+            .await
+            .expect("claim a after release")
+            .expect("step a after release");
+        assert_eq!(next_a.id, step_2.id);
     }
 
     #[tokio::test]
-    async fn delete_steps_reorders_remaining() {
+    async fn claim_next_step_never_claims_human_steps() {
         let (_dir, app) = setup_app().await;
         let plan = create_plan(&app, "Plan").await;
-
         let (steps, _) = app
             .add_steps_batch(
                 plan.id,
-                vec!["A".to_string(), "B".to_string(), "C".to_string()],
+                vec!["Review".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::parse("human").expect("executor"),
+                StepSchedule::default(),
                 None,
             )
             .await
             .expect("add steps");
-
-        app.delete_steps(&[steps[1].id]).await.expect("delete step");
-
-        let (_plan, remaining) = app.plan_with_steps(plan.id).await.expect("plan steps");
-        let contents: Vec<_> = remaining.iter().map(|step| step.content.as_str()).collect();
-        let orders: Vec<_> = remaining.iter().map(|step| step.sort_order).collect();
-        assert_eq!(contents, vec!["A", "C"]);
-        assert_eq!(orders, vec![1, 2]);
-    }
-
-    #[tokio::test]
-    async fn delete_plan_clears_active_plan() {
-        let (_dir, app) = setup_app().await;
-        let plan = create_plan(&app, "Plan").await;
-        app.set_active_plan(plan.id, false)
+        let next = app
+            .claim_next_step(plan.id, Duration::minutes(30))
             .await
-            .expect("set active");
-
-        app.delete_plan(plan.id).await.expect("delete plan");
-
-        let active = app.get_active_plan().await.expect("get active");
-        assert!(active.is_none());
+            .expect("claim")
+            .expect("step");
+        assert_eq!(next.id, steps[0].id);
+        let claims = step_claim::Entity::find().all(&app.db).await.expect("claims");
+        assert!(claims.is_empty());
     }
 
     #[tokio::test]
-    async fn active_plan_is_scoped_to_session() {
+    async fn expired_claims_do_not_block_other_sessions() {
         let dir = TempDir::new().expect("temp dir");
         let db_path = db::resolve_db_path(dir.path());
         db::ensure_parent_dir(&db_path).expect("ensure parent");
@@ -2239,27 +5919,72 @@ mod tests {
         let app_a = App::new(db_a, "session-a".to_string());
         let app_b = App::new(db_b, "session-b".to_string());
 
-        let plan_a = create_plan(&app_a, "Plan A").await;
-        let plan_b = create_plan(&app_a, "Plan B").await;
-
+        let plan = create_plan(&app_a, "Plan").await;
+        let step = add_step(&app_a, plan.id, "Step 1", StepStatus::Todo).await;
         app_a
-            .set_active_plan(plan_a.id, false)
+            .claim_step(step.id, Duration::minutes(-1), false)
             .await
-            .expect("set active a");
-        app_b
-            .set_active_plan(plan_b.id, false)
+            .expect("claim a");
+
+        let next_b = app_b
+            .claim_next_step(plan.id, Duration::minutes(30))
             .await
-            .expect("set active b");
+            .expect("claim b")
+            .expect("step b");
+        assert_eq!(next_b.id, step.id);
+    }
 
-        let active_a = app_a.get_active_plan().await.expect("get active a");
-        let active_b = app_b.get_active_plan().await.expect("get active b");
+    #[tokio::test]
+    async fn touch_session_only_updates_known_sessions() {
+        let (_dir, app) = setup_app().await;
+        app.touch_session("/work").await.expect("touch unknown");
+        assert!(app.list_sessions(Duration::hours(2)).await.expect("list").is_empty());
+
+        app.record_heartbeat(None).await.expect("heartbeat");
+        backdate_session(&app, TEST_CONVERSATION_ID, Duration::hours(3)).await;
+        app.touch_session("/work").await.expect("touch known");
+        let sessions = app.list_sessions(Duration::hours(2)).await.expect("list");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].cwd.as_deref(), Some("/work"));
+        assert!(!sessions[0].stale);
+    }
 
-        assert_eq!(active_a.expect("active a").plan_id, plan_a.id);
-        assert_eq!(active_b.expect("active b").plan_id, plan_b.id);
+    async fn backdate_session(app: &App, session_id: &str, age: Duration) {
+        let seen_at = Utc::now() - age;
+        for activation in active_plan::Entity::find()
+            .filter(active_plan::Column::SessionId.eq(session_id))
+            .all(&app.db)
+            .await
+            .expect("find activations")
+        {
+            let mut active: active_plan::ActiveModel = activation.into();
+            active.updated_at = Set(seen_at);
+            active.update(&app.db).await.expect("backdate activation");
+        }
+        for session_model in session::Entity::find()
+            .filter(session::Column::SessionId.eq(session_id))
+            .all(&app.db)
+            .await
+            .expect("find sessions")
+        {
+            let mut active: session::ActiveModel = session_model.into();
+            active.last_seen_at = Set(seen_at);
+            active.update(&app.db).await.expect("backdate session");
+        }
+        for claim in step_claim::Entity::find()
+            .filter(step_claim::Column::SessionId.eq(session_id))
+            .all(&app.db)
+            .await
+            .expect("find claims")
+        {
+            let mut active: step_claim::ActiveModel = claim.into();
+            active.claimed_at = Set(seen_at);
+            active.update(&app.db).await.expect("backdate claim");
+        }
     }
 
     #[tokio::test]
-    async fn active_plan_is_unique_per_plan() {
+    async fn stale_session_activation_does_not_block_activation() {
         let dir = TempDir::new().expect("temp dir");
         let db_path = db::resolve_db_path(dir.path());
         db::ensure_parent_dir(&db_path).expect("ensure parent");
@@ -2272,30 +5997,95 @@ mod tests {
         let app_a = App::new(db_a, "session-a".to_string());
         let app_b = App::new(db_b, "session-b".to_string());
 
-        let plan = create_plan(&app_a, "Plan A").await;
-
+        let plan = create_plan(&app_a, "Plan").await;
+        app_a.record_heartbeat(None).await.expect("heartbeat a");
         app_a
             .set_active_plan(plan.id, false)
             .await
             .expect("set active a");
-        let err = app_b.set_active_plan(plan.id, false).await.unwrap_err();
-        match err {
-            AppError::InvalidInput(message) => {
-                assert!(message.contains("already active in session"));
-                assert!(message.contains("session-a"));
-            }
-            _ => panic!("unexpected error type"),
-        }
+        backdate_session(&app_a, "session-a", Duration::hours(3)).await;
 
+        app_b
+            .set_active_plan(plan.id, false)
+            .await
+            .expect("activate over stale session");
         let active_a = app_a.get_active_plan().await.expect("get active a");
-        let active_b = app_b.get_active_plan().await.expect("get active b");
+        assert!(active_a.is_none());
+    }
 
-        assert_eq!(active_a.expect("active a").plan_id, plan.id);
-        assert!(active_b.is_none());
+    #[tokio::test]
+    async fn push_and_pop_active_plan_restore_previous_focus() {
+        let (_dir, app) = setup_app().await;
+        let feature = create_plan(&app, "Feature").await;
+        let bugfix = create_plan(&app, "Bugfix").await;
+        app.set_active_plan(feature.id, false)
+            .await
+            .expect("activate feature");
+
+        let (active, suspended) = app
+            .push_active_plan(bugfix.id, false)
+            .await
+            .expect("push bugfix");
+        assert_eq!(active.plan_id, bugfix.id);
+        assert_eq!(suspended, Some(feature.id));
+        let stack = app.suspended_plans().await.expect("suspended plans");
+        assert_eq!(
+            stack.iter().map(|plan| plan.id).collect::<Vec<_>>(),
+            vec![feature.id]
+        );
+        let err = app
+            .push_active_plan(bugfix.id, false)
+            .await
+            .expect_err("already active");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+
+        let (previous, resumed) = app.pop_active_plan().await.expect("pop");
+        assert_eq!(previous, Some(bugfix.id));
+        assert_eq!(resumed.map(|plan| plan.id), Some(feature.id));
+        let active = app.get_active_plan().await.expect("get active");
+        assert_eq!(active.map(|state| state.plan_id), Some(feature.id));
+        assert!(app.suspended_plans().await.expect("stack").is_empty());
+
+        let (previous, resumed) = app.pop_active_plan().await.expect("pop empty");
+        assert_eq!(previous, Some(feature.id));
+        assert!(resumed.is_none());
+        assert!(app.get_active_plan().await.expect("get active").is_none());
     }
 
     #[tokio::test]
-    async fn active_plan_takeover_reassigns_plan() {
+    async fn finishing_pushed_plan_resumes_suspended_plan() {
+        let (_dir, app) = setup_app().await;
+        let feature = create_plan(&app, "Feature").await;
+        add_step(&app, feature.id, "Feature step", StepStatus::Todo).await;
+        let bugfix = create_plan(&app, "Bugfix").await;
+        let fix = add_step(&app, bugfix.id, "Fix", StepStatus::Todo).await;
+        app.set_active_plan(feature.id, false)
+            .await
+            .expect("activate feature");
+        app.push_active_plan(bugfix.id, false)
+            .await
+            .expect("push bugfix");
+
+        let (_, changes) = app
+            .update_step(
+                fix.id,
+                StepChanges {
+                    status: Some(StepStatus::Done),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("finish fix");
+        assert_eq!(changes.active_plans_cleared.len(), 1);
+        assert_eq!(changes.active_plans_resumed.len(), 1);
+        assert_eq!(changes.active_plans_resumed[0].plan_id, feature.id);
+        let active = app.get_active_plan().await.expect("get active");
+        assert_eq!(active.map(|state| state.plan_id), Some(feature.id));
+        assert!(app.suspended_plans().await.expect("stack").is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_sessions_removes_stale_activations_and_claims() {
         let dir = TempDir::new().expect("temp dir");
         let db_path = db::resolve_db_path(dir.path());
         db::ensure_parent_dir(&db_path).expect("ensure parent");
@@ -2308,22 +6098,51 @@ mod tests {
         let app_a = App::new(db_a, "session-a".to_string());
         let app_b = App::new(db_b, "session-b".to_string());
 
-        let plan = create_plan(&app_a, "Plan A").await;
-
+        let plan_a = create_plan(&app_a, "Plan A").await;
+        let plan_b = create_plan(&app_b, "Plan B").await;
+        let step = add_step(&app_a, plan_a.id, "Step", StepStatus::Todo).await;
+        app_a.record_heartbeat(Some("/work/a")).await.expect("heartbeat a");
+        app_b.record_heartbeat(Some("/work/b")).await.expect("heartbeat b");
         app_a
-            .set_active_plan(plan.id, false)
+            .set_active_plan(plan_a.id, false)
             .await
             .expect("set active a");
+        app_a
+            .claim_step(step.id, Duration::hours(8), false)
+            .await
+            .expect("claim a");
         app_b
-            .set_active_plan(plan.id, true)
+            .set_active_plan(plan_b.id, false)
             .await
             .expect("set active b");
+        backdate_session(&app_a, "session-a", Duration::hours(3)).await;
 
-        let active_a = app_a.get_active_plan().await.expect("get active a");
-        let active_b = app_b.get_active_plan().await.expect("get active b");
+        let sessions = app_b
+            .list_sessions(Duration::hours(2))
+            .await
+            .expect("list sessions");
+        assert_eq!(sessions.len(), 2);
+        let stale: Vec<&str> = sessions
+            .iter()
+            .filter(|summary| summary.stale)
+            .map(|summary| summary.session_id.as_str())
+            .collect();
+        assert_eq!(stale, vec!["session-a"]);
 
-        assert!(active_a.is_none());
-        assert_eq!(active_b.expect("active b").plan_id, plan.id);
+        let (pruned, plan_ids) = app_b
+            .prune_sessions(Duration::hours(2))
+            .await
+            .expect("prune sessions");
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].cwd.as_deref(), Some("/work/a"));
+        assert_eq!(plan_ids, vec![plan_a.id]);
+        assert!(app_a.get_active_plan().await.expect("active a").is_none());
+        assert!(app_b.get_active_plan().await.expect("active b").is_some());
+        let claims = step_claim::Entity::find()
+            .count(&app_b.db)
+            .await
+            .expect("count claims");
+        assert_eq!(claims, 0);
     }
 
     #[tokio::test]
@@ -2339,6 +6158,261 @@ mod tests {
         }
     }
 
+    async fn create_scheduled_plan(
+        app: &App,
+        title: &str,
+        priority: Option<Priority>,
+        due: Option<&str>,
+    ) -> plan::Model {
+        app.add_plan(PlanInput {
+            title: title.to_string(),
+            content: "Content".to_string(),
+            priority,
+            due: due.map(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").expect("date")),
+        })
+        .await
+        .expect("add plan")
+    }
+
+    #[tokio::test]
+    async fn list_plans_orders_by_priority_and_due_with_unset_last() {
+        let (_dir, app) = setup_app().await;
+        let unset = create_scheduled_plan(&app, "Unset", None, None).await;
+        let low = create_scheduled_plan(&app, "Low", Some(Priority::P3), Some("2026-01-05")).await;
+        let urgent =
+            create_scheduled_plan(&app, "Urgent", Some(Priority::P0), Some("2026-03-01")).await;
+
+        let ids = |plans: Vec<plan::Model>| plans.iter().map(|plan| plan.id).collect::<Vec<_>>();
+        let by_priority = app
+            .list_plans(Some(PlanOrder::Priority), false)
+            .await
+            .expect("by priority");
+        assert_eq!(ids(by_priority), vec![urgent.id, low.id, unset.id]);
+        let by_due = app
+            .list_plans(Some(PlanOrder::Due), false)
+            .await
+            .expect("by due");
+        assert_eq!(ids(by_due), vec![low.id, urgent.id, unset.id]);
+        let by_due_desc = app
+            .list_plans(Some(PlanOrder::Due), true)
+            .await
+            .expect("by due desc");
+        assert_eq!(ids(by_due_desc), vec![urgent.id, low.id, unset.id]);
+
+        let updated = app
+            .update_plan_with_active_clear(
+                unset.id,
+                PlanChanges {
+                    priority: Some(Some(Priority::P1)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("set priority")
+            .0;
+        assert_eq!(updated.priority.as_deref(), Some("p1"));
+        let cleared = app
+            .update_plan_with_active_clear(
+                low.id,
+                PlanChanges {
+                    due: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("clear due")
+            .0;
+        assert!(cleared.due.is_none());
+        assert_eq!(cleared.priority.as_deref(), Some("p3"));
+    }
+
+    #[tokio::test]
+    async fn sort_plan_details_by_progress_uses_done_ratio() {
+        let (_dir, app) = setup_app().await;
+        let half = create_plan(&app, "Half").await;
+        add_step(&app, half.id, "Done", StepStatus::Done).await;
+        add_step(&app, half.id, "Todo", StepStatus::Todo).await;
+        let empty = create_plan(&app, "Empty").await;
+        let most = create_plan(&app, "Most").await;
+        for status in [StepStatus::Done, StepStatus::Done, StepStatus::Todo] {
+            add_step(&app, most.id, "Step", status).await;
+        }
+
+        let plans = app.list_plans(None, false).await.expect("list plans");
+        let mut details = app.get_plan_details(&plans).await.expect("details");
+        sort_plan_details_by_progress(&mut details, true);
+        let ids: Vec<i64> = details.iter().map(|detail| detail.plan.id).collect();
+        assert_eq!(ids, vec![most.id, half.id, empty.id]);
+    }
+
+    #[tokio::test]
+    async fn ensure_schema_adds_schedule_columns_to_existing_tables() {
+        let dir = TempDir::new().expect("temp dir");
+        let db_path = db::resolve_db_path(dir.path());
+        db::ensure_parent_dir(&db_path).expect("ensure parent");
+        let conn = db::connect(&db_path).await.expect("connect db");
+        for sql in [
+            "CREATE TABLE plans (id integer NOT NULL PRIMARY KEY AUTOINCREMENT, \
+             title varchar NOT NULL, content varchar NOT NULL, status varchar NOT NULL, \
+             comment varchar NULL, last_session_id varchar NULL, \
+             created_at timestamp_with_timezone_text NOT NULL, \
+             updated_at timestamp_with_timezone_text NOT NULL);",
+            "CREATE TABLE steps (id integer NOT NULL PRIMARY KEY AUTOINCREMENT, \
+             plan_id bigint NOT NULL, content varchar NOT NULL, status varchar NOT NULL, \
+             executor varchar NOT NULL, sort_order integer NOT NULL, comment varchar NULL, \
+             created_at timestamp_with_timezone_text NOT NULL, \
+             updated_at timestamp_with_timezone_text NOT NULL);",
+        ] {
+            conn.execute(Statement::from_string(DatabaseBackend::Sqlite, sql))
+                .await
+                .expect("create legacy table");
+        }
+        db::ensure_schema(&conn).await.expect("migrate schema");
+        db::ensure_schema(&conn).await.expect("migrate schema twice");
+
+        let app = App::new(conn, TEST_CONVERSATION_ID.to_string());
+        let plan =
+            create_scheduled_plan(&app, "Legacy", Some(Priority::P2), Some("2026-05-01")).await;
+        assert_eq!(plan.priority.as_deref(), Some("p2"));
+        let step = add_step(&app, plan.id, "Step", StepStatus::Todo).await;
+        assert!(step.due.is_none());
+    }
+
+    #[tokio::test]
+    async fn tags_filter_plans_and_steps_with_any_or_all() {
+        let (_dir, app) = setup_app().await;
+        let backend = create_plan(&app, "Backend").await;
+        let infra = create_plan(&app, "Infra").await;
+        let tags = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        let applied = app
+            .tag(TagTarget::Plan, backend.id, &tags(&["Backend", "docs", "backend"]))
+            .await
+            .expect("tag backend");
+        assert_eq!(applied, vec!["backend".to_string(), "docs".to_string()]);
+        app.tag(TagTarget::Plan, infra.id, &tags(&["infra", "docs"]))
+            .await
+            .expect("tag infra");
+
+        let all = app
+            .ids_with_tags(TagTarget::Plan, &tags(&["docs", "backend"]), TagMatch::All)
+            .await
+            .expect("all");
+        assert_eq!(all, HashSet::from([backend.id]));
+        let any = app
+            .ids_with_tags(TagTarget::Plan, &tags(&["infra", "backend"]), TagMatch::Any)
+            .await
+            .expect("any");
+        assert_eq!(any, HashSet::from([backend.id, infra.id]));
+
+        let tagged = add_step(&app, backend.id, "Tagged", StepStatus::Todo).await;
+        add_step(&app, backend.id, "Plain", StepStatus::Todo).await;
+        app.tag(TagTarget::Step, tagged.id, &tags(&["docs"]))
+            .await
+            .expect("tag step");
+        let query = StepQuery {
+            tags: tags(&["docs"]),
+            ..Default::default()
+        };
+        let steps = app
+            .list_steps_filtered(backend.id, &query)
+            .await
+            .expect("list steps");
+        assert_eq!(steps.iter().map(|step| step.id).collect::<Vec<_>>(), vec![tagged.id]);
+        assert_eq!(app.count_steps(backend.id, &query).await.expect("count"), 1);
+
+        let err = app
+            .tag(TagTarget::Plan, backend.id, &tags(&["bad tag"]))
+            .await
+            .expect_err("invalid tag");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn list_tags_counts_usage_and_drops_unused_tags() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let step = add_step(&app, plan.id, "Step", StepStatus::Todo).await;
+        let names = vec!["docs".to_string(), "infra".to_string()];
+        app.tag(TagTarget::Plan, plan.id, &names)
+            .await
+            .expect("tag plan");
+        app.tag(TagTarget::Step, step.id, &names[..1])
+            .await
+            .expect("tag step");
+
+        let usage = app.list_tags().await.expect("list tags");
+        let summary: Vec<(String, usize, usize)> = usage
+            .into_iter()
+            .map(|tag| (tag.name, tag.plans, tag.steps))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("docs".to_string(), 1, 1), ("infra".to_string(), 1, 0)]
+        );
+
+        let remaining = app
+            .untag(TagTarget::Plan, plan.id, &names[1..])
+            .await
+            .expect("untag");
+        assert_eq!(remaining, vec!["docs".to_string()]);
+        app.delete_plan(plan.id).await.expect("delete plan");
+        assert!(app.list_tags().await.expect("list tags").is_empty());
+        let orphans = tag::Entity::find().count(&app.db).await.expect("count tags");
+        assert_eq!(orphans, 0);
+    }
+
+    #[tokio::test]
+    async fn list_steps_filters_executor_kind_and_agent() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        for (content, executor) in [
+            ("Build", "ai"),
+            ("Review", "ai:reviewer"),
+            ("Approve", "human:alice"),
+            ("Deploy", "ci"),
+            ("Lint", "c_i:linter"),
+            ("Audit", "cxi:auditor"),
+        ] {
+            app.add_steps_batch(
+                plan.id,
+                vec![content.to_string()],
+                StepStatus::Todo,
+                StepExecutor::parse(executor).expect("executor"),
+                StepSchedule::default(),
+                None,
+            )
+            .await
+            .expect("add steps");
+        }
+
+        let contents = |steps: Vec<step::Model>| -> Vec<String> {
+            steps.into_iter().map(|step| step.content).collect()
+        };
+        let query = |executor: &str| StepQuery {
+            executor: StepExecutor::parse(executor),
+            ..Default::default()
+        };
+
+        let ai = app
+            .list_steps_filtered(plan.id, &query("ai"))
+            .await
+            .expect("list ai");
+        assert_eq!(contents(ai), vec!["Build", "Review"]);
+        let reviewer = app
+            .list_steps_filtered(plan.id, &query("ai:reviewer"))
+            .await
+            .expect("list reviewer");
+        assert_eq!(contents(reviewer), vec!["Review"]);
+        let ci = app.count_steps(plan.id, &query("ci")).await.expect("count ci");
+        assert_eq!(ci, 1);
+        let lint = app
+            .list_steps_filtered(plan.id, &query("c_i"))
+            .await
+            .expect("list c_i");
+        assert_eq!(contents(lint), vec!["Lint"]);
+    }
+
     #[tokio::test]
     async fn count_steps_missing_plan_errors() {
         let (_dir, app) = setup_app().await;
@@ -2388,7 +6462,8 @@ mod tests {
                 plan.id,
                 Vec::new(),
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -2472,7 +6547,8 @@ mod tests {
                 plan.id,
                 vec!["A".to_string(), "B".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -2526,7 +6602,8 @@ mod tests {
             plan.id,
             vec!["A".to_string(), "B".to_string()],
             StepStatus::Done,
-            StepExecutor::Ai,
+            StepExecutor::ai(),
+            StepSchedule::default(),
             None,
         )
         .await
@@ -2579,6 +6656,7 @@ mod tests {
             .add_plan(PlanInput {
                 title: "   ".to_string(),
                 content: "Content".to_string(),
+                ..Default::default()
             })
             .await
             .unwrap_err();
@@ -2597,6 +6675,7 @@ mod tests {
             .add_plan(PlanInput {
                 title: "Title".to_string(),
                 content: "   ".to_string(),
+                ..Default::default()
             })
             .await
             .unwrap_err();
@@ -2662,7 +6741,8 @@ mod tests {
                 plan.id,
                 vec!["   ".to_string()],
                 StepStatus::Todo,
-                StepExecutor::Ai,
+                StepExecutor::ai(),
+                StepSchedule::default(),
                 None,
             )
             .await
@@ -2765,4 +6845,789 @@ mod tests {
             .expect("goal exists");
         assert_eq!(goal_after.content, "Goal 1");
     }
+
+    #[tokio::test]
+    async fn sub_steps_roll_up_status_and_drive_next_step() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let parent = add_step(&app, plan.id, "Parent", StepStatus::Todo).await;
+        let after = add_step(&app, plan.id, "After", StepStatus::Todo).await;
+        let (children, _) = app
+            .add_sub_steps(
+                plan.id,
+                parent.id,
+                vec!["Child A".to_string(), "Child B".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-steps");
+        assert!(children.iter().all(|child| child.parent_id == Some(parent.id)));
+        let (grandchild, _, _) = app
+            .add_step_tree(
+                plan.id,
+                "Grandchild".to_string(),
+                StepExecutor::ai(),
+                StepSchedule::default(),
+                vec!["Goal".to_string()],
+                Some(children[0].id),
+            )
+            .await
+            .expect("add grandchild");
+
+        let ids: Vec<i64> = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan.id))
+            .order_by_asc(step::Column::SortOrder)
+            .all(&app.db)
+            .await
+            .expect("steps")
+            .iter()
+            .map(|step| step.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![parent.id, children[0].id, grandchild.id, children[1].id, after.id]
+        );
+
+        let next = app.next_unclaimed_step(plan.id).await.expect("next").expect("step");
+        assert_eq!(next.id, grandchild.id);
+        let err = app
+            .set_step_done_with_goals(parent.id, false)
+            .await
+            .expect_err("parent with pending sub-steps");
+        match err {
+            AppError::InvalidInput(message) => assert!(message.contains("next pending sub-step")),
+            other => panic!("unexpected error: {other:?}"),
+        }
+        let err = app
+            .add_sub_steps(
+                plan.id,
+                grandchild.id,
+                vec!["Too deep".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect_err("depth limit");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+
+        let (_, changes) = app
+            .set_step_done_with_goals(grandchild.id, true)
+            .await
+            .expect("finish grandchild");
+        let rolled: Vec<i64> = changes.steps.iter().map(|change| change.step_id).collect();
+        assert!(rolled.contains(&children[0].id));
+        assert!(!rolled.contains(&parent.id));
+        let next = app.next_unclaimed_step(plan.id).await.expect("next").expect("step");
+        assert_eq!(next.id, children[1].id);
+
+        let (_, changes) = app
+            .set_step_done_with_goals(children[1].id, false)
+            .await
+            .expect("finish child");
+        assert!(changes.steps.iter().any(|change| change.step_id == parent.id));
+        let parent_after = app.get_step(parent.id).await.expect("parent");
+        assert_eq!(parent_after.status, StepStatus::Done.as_str());
+        let next = app.next_unclaimed_step(plan.id).await.expect("next").expect("step");
+        assert_eq!(next.id, after.id);
+
+        let (deleted, _) = app.delete_steps(&[children[0].id]).await.expect("delete");
+        assert_eq!(deleted, 2);
+        let (_, changes) = app
+            .add_sub_steps(
+                plan.id,
+                parent.id,
+                vec!["Reopened".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-step to done parent");
+        assert!(changes
+            .steps
+            .iter()
+            .any(|change| change.step_id == parent.id && change.to == "todo"));
+    }
+
+    #[tokio::test]
+    async fn split_step_distributes_goals_and_keeps_position() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let big = add_step(&app, plan.id, "Big", StepStatus::Todo).await;
+        let tail = add_step(&app, plan.id, "Tail", StepStatus::Todo).await;
+        let done_a = add_goal(&app, big.id, "A", GoalStatus::Done).await;
+        let done_b = add_goal(&app, big.id, "B", GoalStatus::Done).await;
+        let todo_c = add_goal(&app, big.id, "C", GoalStatus::Todo).await;
+        let todo_d = add_goal(&app, big.id, "D", GoalStatus::Todo).await;
+        app.comment_steps(vec![(big.id, "keep me".to_string())])
+            .await
+            .expect("comment");
+
+        let (parts, changes) = app
+            .split_step(
+                big.id,
+                vec!["First half".to_string(), "Second half".to_string()],
+                &[(todo_d.id, 1)],
+            )
+            .await
+            .expect("split");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].id, big.id);
+        assert_eq!(parts[0].content, "First half");
+        assert_eq!(parts[0].comment.as_deref(), Some("keep me"));
+        assert_eq!(parts[0].sort_order, 1);
+        assert_eq!(parts[1].sort_order, 2);
+        assert_eq!(app.get_step(tail.id).await.expect("tail").sort_order, 3);
+
+        let first_goals: Vec<i64> = app
+            .goals_for_step(big.id)
+            .await
+            .expect("goals")
+            .iter()
+            .map(|goal| goal.id)
+            .collect();
+        assert_eq!(first_goals, vec![done_a.id, done_b.id, todo_d.id]);
+        let second_goals = app.goals_for_step(parts[1].id).await.expect("goals");
+        assert_eq!(second_goals.len(), 1);
+        assert_eq!(second_goals[0].id, todo_c.id);
+        let moved_done = goal::Entity::find_by_id(done_a.id)
+            .one(&app.db)
+            .await
+            .expect("query goal")
+            .expect("goal exists");
+        assert_eq!(moved_done.status, GoalStatus::Done.as_str());
+        assert!(changes.steps.is_empty());
+
+        let err = app
+            .split_step(big.id, vec!["Only one".to_string()], &[])
+            .await
+            .expect_err("one part");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+        let err = app
+            .split_step(
+                big.id,
+                vec!["X".to_string(), "Y".to_string()],
+                &[(todo_c.id, 1)],
+            )
+            .await
+            .expect_err("foreign goal");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn merge_steps_combines_goals_comments_and_renumbers() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let target = add_step(&app, plan.id, "Target", StepStatus::Todo).await;
+        let middle = add_step(&app, plan.id, "Middle", StepStatus::Todo).await;
+        let source = add_step(&app, plan.id, "Source", StepStatus::Todo).await;
+        let last = add_step(&app, plan.id, "Last", StepStatus::Todo).await;
+        add_goal(&app, target.id, "Target goal", GoalStatus::Done).await;
+        let moved = add_goal(&app, source.id, "Source goal", GoalStatus::Done).await;
+        app.comment_steps(vec![
+            (target.id, "first note".to_string()),
+            (source.id, "second note".to_string()),
+        ])
+        .await
+        .expect("comment");
+
+        let (merged, _) = app.merge_steps(target.id, source.id).await.expect("merge");
+        assert_eq!(merged.comment.as_deref(), Some("first note\nsecond note"));
+        assert_eq!(merged.status, StepStatus::Done.as_str());
+        let goals = app.goals_for_step(target.id).await.expect("goals");
+        assert_eq!(goals.len(), 2);
+        assert!(goals.iter().any(|goal| goal.id == moved.id));
+        assert!(matches!(
+            app.get_step(source.id).await,
+            Err(AppError::NotFound(_))
+        ));
+        let orders: Vec<(i64, i32)> = step::Entity::find()
+            .filter(step::Column::PlanId.eq(plan.id))
+            .order_by_asc(step::Column::SortOrder)
+            .all(&app.db)
+            .await
+            .expect("steps")
+            .iter()
+            .map(|step| (step.id, step.sort_order))
+            .collect();
+        assert_eq!(orders, vec![(target.id, 1), (middle.id, 2), (last.id, 3)]);
+
+        let err = app.merge_steps(target.id, target.id).await.expect_err("self merge");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+        let other = create_plan(&app, "Other").await;
+        let foreign = add_step(&app, other.id, "Foreign", StepStatus::Todo).await;
+        let err = app.merge_steps(target.id, foreign.id).await.expect_err("cross plan");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn move_goal_reorders_and_refreshes_both_steps() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Plan").await;
+        let first = add_step(&app, plan.id, "First", StepStatus::Todo).await;
+        let second = add_step(&app, plan.id, "Second", StepStatus::Todo).await;
+        let a = add_goal(&app, first.id, "A", GoalStatus::Done).await;
+        let b = add_goal(&app, first.id, "B", GoalStatus::Todo).await;
+        let c = add_goal(&app, first.id, "C", GoalStatus::Done).await;
+        add_goal(&app, second.id, "D", GoalStatus::Done).await;
+
+        let (goals, _) = app.move_goal(c.id, None, Some(1)).await.expect("reorder");
+        let ids: Vec<i64> = goals.iter().map(|goal| goal.id).collect();
+        assert_eq!(ids, vec![c.id, a.id, b.id]);
+        let next = app
+            .next_goal_for_step_with_conn(&app.db, first.id)
+            .await
+            .expect("next goal")
+            .expect("goal");
+        assert_eq!(next.id, b.id);
+
+        let (goals, changes) = app
+            .move_goal(b.id, Some(second.id), Some(1))
+            .await
+            .expect("move across steps");
+        assert_eq!(goals[0].id, b.id);
+        assert_eq!(goals[0].step_id, second.id);
+        assert!(changes
+            .steps
+            .iter()
+            .any(|change| change.step_id == first.id && change.to == "done"));
+        assert!(changes
+            .steps
+            .iter()
+            .any(|change| change.step_id == second.id && change.to == "todo"));
+        let orders: Vec<i32> = app
+            .goals_for_step(first.id)
+            .await
+            .expect("goals")
+            .iter()
+            .map(|goal| goal.sort_order)
+            .collect();
+        assert_eq!(orders, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn move_step_to_plan_carries_sub_steps_and_refreshes_plans() {
+        let (_dir, app) = setup_app().await;
+        let source = create_plan(&app, "Source").await;
+        let target = create_plan(&app, "Target").await;
+        let keep = add_step(&app, source.id, "Keep", StepStatus::Done).await;
+        let moving = add_step(&app, source.id, "Moving", StepStatus::Todo).await;
+        let (children, _) = app
+            .add_sub_steps(
+                source.id,
+                moving.id,
+                vec!["Child".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-step");
+        let existing = add_step(&app, target.id, "Existing", StepStatus::Done).await;
+        app.refresh_plan_status_with_conn(&app.db, target.id)
+            .await
+            .expect("refresh target");
+
+        let (steps, changes) = app
+            .move_step_to_plan(moving.id, target.id, Some(1))
+            .await
+            .expect("move step");
+        let order: Vec<(i64, i32)> = steps.iter().map(|step| (step.id, step.sort_order)).collect();
+        assert_eq!(order, vec![(moving.id, 1), (children[0].id, 2), (existing.id, 3)]);
+        assert_eq!(steps[1].parent_id, Some(moving.id));
+        assert!(changes
+            .plans
+            .iter()
+            .any(|change| change.plan_id == source.id && change.to == "done"));
+        assert!(changes
+            .plans
+            .iter()
+            .any(|change| change.plan_id == target.id && change.to == "todo"));
+        assert_eq!(app.get_step(keep.id).await.expect("keep").sort_order, 1);
+
+        let err = app
+            .move_step_to_plan(moving.id, target.id, None)
+            .await
+            .expect_err("same plan");
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn clone_plan_deep_copies_tree_and_records_source() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Migrate billing").await;
+        let parent = add_step(&app, plan.id, "Parent", StepStatus::Todo).await;
+        let (children, _) = app
+            .add_sub_steps(
+                plan.id,
+                parent.id,
+                vec!["Child".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-step");
+        add_goal(&app, children[0].id, "Goal", GoalStatus::Done).await;
+        app.comment_steps(vec![(parent.id, "note".to_string())])
+            .await
+            .expect("comment");
+        app.tag(TagTarget::Plan, plan.id, &["billing".to_string()])
+            .await
+            .expect("tag");
+
+        let (copy, steps, goals) = app
+            .clone_plan(plan.id, None, false, false)
+            .await
+            .expect("clone");
+        assert_eq!((steps, goals), (2, 1));
+        assert_eq!(copy.title, "Migrate billing (copy)");
+        assert_eq!(copy.source_plan_id, Some(plan.id));
+        let detail = app.get_plan_detail(copy.id).await.expect("detail");
+        assert_eq!(detail.tags, vec!["billing".to_string()]);
+        let copied_parent = &detail.steps[0];
+        let copied_child = &detail.steps[1];
+        assert_eq!(copied_parent.comment, None);
+        assert_eq!(copied_child.parent_id, Some(copied_parent.id));
+        assert_eq!(copied_child.status, StepStatus::Done.as_str());
+        let copied_goals = &detail.goals[&copied_child.id];
+        assert_eq!(copied_goals[0].status, GoalStatus::Done.as_str());
+
+        let (fresh, _, _) = app
+            .clone_plan(plan.id, Some("Migrate payments".to_string()), true, true)
+            .await
+            .expect("clone reset");
+        let detail = app.get_plan_detail(fresh.id).await.expect("detail");
+        assert_eq!(fresh.title, "Migrate payments");
+        assert_eq!(detail.steps[0].comment.as_deref(), Some("note"));
+        assert!(detail.steps.iter().all(|step| step.status == "todo"));
+        assert!(detail
+            .goals
+            .values()
+            .flatten()
+            .all(|goal| goal.status == "todo"));
+    }
+
+    #[tokio::test]
+    async fn import_plan_derives_statuses_from_checked_items() {
+        let (_dir, app) = setup_app().await;
+        let imported = crate::import::parse_markdown_plan(
+            "# Release\n\n- [x] Freeze\n  - [x] Branch cut\n- [x] Notes\n  - [ ] Changelog\n\
+             - [ ] Announce\n",
+            "TODO.md",
+        )
+        .expect("parse");
+
+        let (plan, steps, goals, changes) = app.import_plan(imported).await.expect("import");
+        assert_eq!((steps, goals), (3, 2));
+        assert_eq!(plan.title, "Release");
+        assert_eq!(plan.content, "Imported from TODO.md");
+        assert_eq!(plan.status, "todo");
+        let detail = app.get_plan_detail(plan.id).await.expect("detail");
+        let statuses: Vec<&str> = detail.steps.iter().map(|step| step.status.as_str()).collect();
+        assert_eq!(statuses, vec!["done", "todo", "todo"]);
+        assert_eq!(changes.steps.len(), 1);
+        assert_eq!(changes.steps[0].step_id, detail.steps[1].id);
+        assert_eq!(changes.steps[0].reason, "goals done 0/1");
+    }
+
+    #[tokio::test]
+    async fn archive_plan_deactivates_and_blocks_activation() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Old work").await;
+        app.set_active_plan(plan.id, false)
+            .await
+            .expect("activate");
+        let before = app.get_plan(plan.id).await.expect("plan");
+
+        let (archived, cleared, resumed) = app.archive_plan(plan.id).await.expect("archive");
+        assert!(archived.archived_at.is_some());
+        assert_eq!(archived.updated_at, before.updated_at);
+        assert!(cleared);
+        assert!(resumed.is_none());
+        assert!(app.get_active_plan().await.expect("active").is_none());
+        let err = app.set_active_plan(plan.id, false).await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(message) if message.contains("archived")));
+        let err = app.archive_plan(plan.id).await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+
+        let restored = app.unarchive_plan(plan.id).await.expect("unarchive");
+        assert!(restored.archived_at.is_none());
+        app.set_active_plan(plan.id, false)
+            .await
+            .expect("activate restored");
+        let err = app.unarchive_plan(plan.id).await.unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn archive_done_plans_only_takes_done_plans_past_cutoff() {
+        let (_dir, app) = setup_app().await;
+        let todo = create_plan(&app, "Still open").await;
+        let done = create_plan(&app, "Finished").await;
+        app.update_plan_with_active_clear(
+            done.id,
+            PlanChanges {
+                status: Some(PlanStatus::Done),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("mark done");
+
+        let archived = app
+            .archive_done_plans(Duration::days(7))
+            .await
+            .expect("archive recent");
+        assert!(archived.is_empty());
+
+        let archived = app
+            .archive_done_plans(Duration::zero())
+            .await
+            .expect("archive all done");
+        let ids: Vec<i64> = archived.iter().map(|archived| archived.plan.id).collect();
+        assert_eq!(ids, vec![done.id]);
+        assert!(app.get_plan(todo.id).await.expect("todo").archived_at.is_none());
+        let again = app
+            .archive_done_plans(Duration::zero())
+            .await
+            .expect("archive again");
+        assert!(again.is_empty());
+    }
+
+    #[tokio::test]
+    async fn archive_done_plans_measures_from_completion_not_last_update() {
+        let (_dir, app) = setup_app().await;
+        let done = create_plan(&app, "Finished").await;
+        app.update_plan_with_active_clear(
+            done.id,
+            PlanChanges {
+                status: Some(PlanStatus::Done),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("mark done");
+        let done_at = app.get_plan(done.id).await.expect("plan").done_at;
+        assert!(done_at.is_some());
+
+        let mut active: plan::ActiveModel = app.get_plan(done.id).await.expect("plan").into();
+        active.done_at = Set(Some(Utc::now() - Duration::days(10)));
+        active.update(&app.db).await.expect("backdate completion");
+        app.comment_plans(vec![(done.id, "Late note".to_string())])
+            .await
+            .expect("comment after completion");
+
+        let archived = app
+            .archive_done_plans(Duration::days(7))
+            .await
+            .expect("archive");
+        let ids: Vec<i64> = archived.iter().map(|archived| archived.plan.id).collect();
+        assert_eq!(ids, vec![done.id]);
+    }
+
+    #[tokio::test]
+    async fn milestones_roll_up_step_status_and_order_next_step() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Release").await;
+        let polish = add_step(&app, plan.id, "Polish", StepStatus::Todo).await;
+        let build = add_step(&app, plan.id, "Build", StepStatus::Todo).await;
+        let loose = add_step(&app, plan.id, "Loose end", StepStatus::Todo).await;
+
+        let (first, _) = app
+            .add_milestone(plan.id, "Build".to_string(), &[build.id])
+            .await
+            .expect("add milestone");
+        let (second, _) = app
+            .add_milestone(plan.id, "Ship".to_string(), &[polish.id])
+            .await
+            .expect("add second milestone");
+        assert_eq!((first.sort_order, second.sort_order), (1, 2));
+        let next = app.next_unclaimed_step(plan.id).await.expect("next").expect("step");
+        assert_eq!(next.id, build.id);
+
+        let (children, _) = app
+            .add_sub_steps(
+                plan.id,
+                build.id,
+                vec!["Compile".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-step");
+        assert_eq!(children[0].milestone_id, Some(first.id));
+        let detail = app.get_step_detail(children[0].id).await.expect("detail");
+        let progress = detail.milestone.expect("milestone progress");
+        assert_eq!((progress.done, progress.total), (0, 2));
+
+        let (_, changes) = app
+            .update_step(
+                children[0].id,
+                StepChanges {
+                    status: Some(StepStatus::Done),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("finish sub-step");
+        let change = changes.milestones.first().expect("milestone change");
+        assert_eq!(change.milestone_id, first.id);
+        assert_eq!(change.to, "done");
+        let next = app.next_unclaimed_step(plan.id).await.expect("next").expect("step");
+        assert_eq!(next.id, polish.id);
+
+        let listed = app.list_milestones(plan.id).await.expect("list");
+        let summary: Vec<(&str, usize, usize)> = listed
+            .iter()
+            .map(|item| (item.milestone.status.as_str(), item.done, item.total))
+            .collect();
+        assert_eq!(summary, vec![("done", 2, 2), ("todo", 0, 1)]);
+        let detail = app.get_plan_detail(plan.id).await.expect("plan detail");
+        assert_eq!(detail.milestones.len(), 2);
+        assert_eq!(app.get_step(loose.id).await.expect("loose").milestone_id, None);
+    }
+
+    #[tokio::test]
+    async fn milestone_done_and_assign_validate_steps() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Release").await;
+        let other = create_plan(&app, "Other").await;
+        let parent = add_step(&app, plan.id, "Parent", StepStatus::Todo).await;
+        let foreign = add_step(&app, other.id, "Foreign", StepStatus::Todo).await;
+        let (children, _) = app
+            .add_sub_steps(
+                plan.id,
+                parent.id,
+                vec!["Child".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add sub-step");
+
+        let (milestone, _) = app
+            .add_milestone(plan.id, "Prepare".to_string(), &[])
+            .await
+            .expect("add milestone");
+        let err = app
+            .assign_milestone(milestone.id, &[children[0].id])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(message) if message.contains("sub-step")));
+        let err = app
+            .assign_milestone(milestone.id, &[foreign.id])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+
+        let empty = app
+            .add_milestone(plan.id, "Announce".to_string(), &[])
+            .await
+            .expect("add empty milestone")
+            .0;
+        let done = app.complete_milestone(empty.id).await.expect("complete empty");
+        assert_eq!(done.status, "done");
+
+        app.assign_milestone(milestone.id, &[parent.id])
+            .await
+            .expect("assign parent");
+        assert_eq!(
+            app.get_step(children[0].id).await.expect("child").milestone_id,
+            Some(milestone.id)
+        );
+        let err = app.complete_milestone(milestone.id).await.unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::InvalidInput(message) if message.contains("next pending step")
+        ));
+    }
+
+    #[tokio::test]
+    async fn plan_waits_for_acceptance_criteria_before_completing() {
+        let (_dir, app) = setup_app().await;
+        let plan = create_plan(&app, "Launch").await;
+        let step = add_step(&app, plan.id, "Deploy", StepStatus::Todo).await;
+        let (criteria, _) = app
+            .add_criteria(
+                plan.id,
+                vec!["p95 latency under 200ms".to_string(), "Docs updated".to_string()],
+            )
+            .await
+            .expect("add criteria");
+
+        let (_, changes) = app
+            .update_step(
+                step.id,
+                StepChanges {
+                    status: Some(StepStatus::Done),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("finish step");
+        assert!(changes.plans.is_empty());
+        assert_eq!(app.get_plan(plan.id).await.expect("plan").status, "todo");
+        let err = app
+            .update_plan_with_active_clear(
+                plan.id,
+                PlanChanges {
+                    status: Some(PlanStatus::Done),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::InvalidInput(message) if message.contains("unmet acceptance criterion")
+        ));
+
+        let (_, changes) = app
+            .set_criteria_status(&[criteria[0].id], CriterionStatus::Done)
+            .await
+            .expect("check first");
+        assert!(changes.plans.is_empty());
+        let (_, changes) = app
+            .set_criteria_status(&[criteria[1].id], CriterionStatus::Done)
+            .await
+            .expect("check second");
+        let change = changes.plans.first().expect("plan change");
+        assert_eq!(change.to, "done");
+        assert_eq!(
+            change.reason,
+            "all steps are done (1/1) and all criteria are met (2/2)"
+        );
+
+        let (_, changes) = app
+            .set_criteria_status(&[criteria[1].id], CriterionStatus::Todo)
+            .await
+            .expect("uncheck");
+        assert_eq!(changes.plans[0].reason, "all steps are done (1/1), criteria met 1/2");
+        let (removed, changes) = app
+            .delete_criteria(&[criteria[1].id])
+            .await
+            .expect("remove");
+        assert_eq!(removed.len(), 1);
+        assert_eq!(changes.plans[0].to, "done");
+        let err = app.delete_criteria(&[criteria[1].id]).await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn step_checkpoint_base_skips_own_sub_steps() {
+        let (dir, app) = setup_app().await;
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(&repo).expect("repo dir");
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(["-c", "user.name=T", "-c", "user.email=t@example.com"])
+                .args(args)
+                .status()
+                .expect("run git");
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "init"]);
+        let app = app.with_repo_dir(repo);
+        let plan = create_plan(&app, "Checkpoints").await;
+        let first = add_step(&app, plan.id, "First", StepStatus::Todo).await;
+        let parent = add_step(&app, plan.id, "Parent", StepStatus::Todo).await;
+        let (children, _) = app
+            .add_sub_steps(
+                plan.id,
+                parent.id,
+                vec!["Child".to_string()],
+                StepStatus::Todo,
+                StepExecutor::ai(),
+                StepSchedule::default(),
+            )
+            .await
+            .expect("add child");
+        for id in [first.id, children[0].id] {
+            app.update_step(
+                id,
+                StepChanges {
+                    status: Some(StepStatus::Done),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("finish step");
+        }
+
+        let (parent_after, base) = app.step_checkpoint_base(parent.id).await.expect("parent");
+        assert!(parent_after.checkpoint_commit.is_some());
+        assert_eq!(base.map(|step| step.id), Some(first.id));
+        let (_, base) = app.step_checkpoint_base(children[0].id).await.expect("child");
+        assert_eq!(base.map(|step| step.id), Some(first.id));
+        let (_, base) = app.step_checkpoint_base(first.id).await.expect("first");
+        assert!(base.is_none());
+    }
+
+    #[tokio::test]
+    async fn webhook_outbox_keeps_deliveries_until_they_succeed() {
+        let (_dir, app) = setup_app().await;
+        let webhook = app
+            .add_webhook("http://127.0.0.1:9/hook", "secret", &[EventKind::StepDone], 5)
+            .await
+            .expect("add webhook");
+        assert_eq!(webhook.events, "step.done");
+        assert!(app.add_webhook("ftp://example.com", "secret", &[], 5).await.is_err());
+        let queued = app
+            .enqueue_webhook_deliveries(&[
+                (webhook.id, EventKind::StepDone, "{}".to_string()),
+                (webhook.id, EventKind::StepDone, "{}".to_string()),
+            ])
+            .await
+            .expect("enqueue");
+        let (first, second) = (&queued[0], &queued[1]);
+        assert!(app.has_due_webhook_deliveries().await.expect("due"));
+
+        let no_skip = HashSet::new();
+        let claimed = app.claim_webhook_deliveries(false, &no_skip).await.expect("claim");
+        let ids: Vec<i64> = claimed.iter().map(|(_, delivery)| delivery.id).collect();
+        assert_eq!(ids, vec![first.id]);
+        let pending = app.list_webhook_deliveries().await.expect("list");
+        assert_eq!(pending[1].attempts, 0);
+        app.record_webhook_outcomes(&[(
+            claimed[0].1.clone(),
+            DeliveryOutcome::Failed("refused".to_string()),
+        )])
+        .await
+        .expect("record");
+        let pending = app.list_webhook_deliveries().await.expect("list");
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(pending[0].last_error.as_deref(), Some("refused"));
+        assert!(pending[0].next_attempt_at > Utc::now());
+        assert_eq!(pending[1].attempts, 0);
+        // The second delivery is due but waits behind the first one.
+        assert!(app.claim_webhook_deliveries(false, &no_skip).await.expect("claim").is_empty());
+        let skip = HashSet::from([webhook.id]);
+        assert!(app.claim_webhook_deliveries(true, &skip).await.expect("claim").is_empty());
+
+        let claimed = app.claim_webhook_deliveries(true, &no_skip).await.expect("claim");
+        assert_eq!(claimed[0].1.id, first.id);
+        app.record_webhook_outcomes(&[(claimed[0].1.clone(), DeliveryOutcome::Delivered)])
+            .await
+            .expect("record");
+        let pending = app.list_webhook_deliveries().await.expect("list");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second.id);
+        let claimed = app.claim_webhook_deliveries(false, &no_skip).await.expect("claim");
+        assert_eq!(claimed[0].1.id, second.id);
+
+        app.remove_webhooks(&[webhook.id]).await.expect("remove");
+        assert!(app.list_webhook_deliveries().await.expect("list").is_empty());
+        assert_eq!(webhook_retry_delay(1), Duration::seconds(30));
+        assert_eq!(webhook_retry_delay(3), Duration::seconds(120));
+        assert_eq!(webhook_retry_delay(20), Duration::seconds(3600));
+    }
 }
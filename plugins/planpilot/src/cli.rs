@@ -1,7 +1,21 @@
 use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use serde::Deserialize;
+
+use crate::model::{StepExecutor, StepSchedule};
+
+const DEFAULT_CLAIM_LEASE_MINUTES: i64 = 30;
+
+const EXECUTOR_HELP: &str =
+    "Executor: ai, human, or <kind>:<name> (e.g. ai:reviewer, human:alice, ci)";
+
+const DUE_HELP: &str = "Due date as YYYY-MM-DD (use 'none' to clear)";
+
+const TAG_FILTER_HELP: &str = "Only include items tagged with TAG (repeatable)";
+
+const ARCHIVED_HELP: &str = "Only include archived plans, of any status (hidden otherwise)";
+
+const PARENT_HELP: &str = "Add as a sub-step of STEP_ID, after its existing sub-steps";
 
 #[derive(Parser, Debug)]
 #[command(
@@ -37,6 +51,28 @@ pub enum Command {
     #[command(subcommand)]
     Goal(GoalCommand),
     #[command(subcommand)]
+    Milestone(MilestoneCommand),
+    #[command(subcommand)]
+    Criterion(CriterionCommand),
+    #[command(subcommand)]
+    Session(SessionCommand),
+    #[command(subcommand)]
+    Tag(TagCommand),
+    #[command(subcommand)]
+    Git(GitCommand),
+    #[command(about = "Export steps and goals as CSV or TSV, one row per goal")]
+    Export(TableExport),
+    #[command(about = "Serve a JSON API and a live dashboard over HTTP")]
+    Serve(Serve),
+    #[command(about = "Browse plans and update steps and goals interactively")]
+    Tui,
+    #[command(about = "Print plan, step and goal changes as they happen")]
+    Watch(Watch),
+    #[command(subcommand, about = "Run commands when goals, steps and plans change")]
+    Event(EventCommand),
+    #[command(subcommand, about = "Post goal, step and plan events to webhook URLs")]
+    Webhook(WebhookCommand),
+    #[command(subcommand)]
     Hook(HookCommand),
 }
 
@@ -49,6 +85,10 @@ pub enum PlanCommand {
     Search(PlanSearch),
     Show(PlanShow),
     Export(PlanExport),
+    #[command(name = "import-md")]
+    ImportMd(PlanImportMd),
+    #[command(name = "import-github")]
+    ImportGithub(PlanImportGithub),
     Comment(PlanComment),
     Update(PlanUpdate),
     Done(PlanDone),
@@ -57,6 +97,13 @@ pub enum PlanCommand {
     #[command(name = "show-active")]
     Active(PlanActive),
     Deactivate(PlanDeactivate),
+    Push(PlanPush),
+    Pop(PlanPop),
+    Tag(PlanTag),
+    Untag(PlanUntag),
+    Clone(PlanClone),
+    Archive(PlanArchive),
+    Unarchive(PlanUnarchive),
 }
 
 #[derive(Subcommand, Debug)]
@@ -66,12 +113,19 @@ pub enum StepCommand {
     AddTree(StepAddTree),
     List(StepList),
     Show(StepShow),
+    Diff(StepDiff),
     #[command(name = "show-next")]
     ShowNext(StepShowNext),
+    Claim(StepClaim),
+    Release(StepRelease),
+    Tag(StepTag),
+    Untag(StepUntag),
     Comment(StepComment),
     Update(StepUpdate),
     Done(StepDone),
     Move(StepMove),
+    Split(StepSplit),
+    Merge(StepMerge),
     Remove(StepRemove),
 }
 
@@ -83,9 +137,65 @@ pub enum GoalCommand {
     Comment(GoalComment),
     Update(GoalUpdate),
     Done(GoalDone),
+    Move(GoalMove),
     Remove(GoalRemove),
 }
 
+#[derive(Subcommand, Debug)]
+pub enum MilestoneCommand {
+    Add(MilestoneAdd),
+    List(MilestoneList),
+    Assign(MilestoneAssign),
+    Done(MilestoneDone),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CriterionCommand {
+    Add(CriterionAdd),
+    List(CriterionList),
+    Check(CriterionCheck),
+    Uncheck(CriterionUncheck),
+    Remove(CriterionRemove),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EventCommand {
+    Add(EventAdd),
+    List(EventList),
+    Remove(EventRemove),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WebhookCommand {
+    Add(WebhookAdd),
+    List(WebhookList),
+    Remove(WebhookRemove),
+    #[command(about = "List the events waiting to be delivered")]
+    Outbox(WebhookOutbox),
+    #[command(about = "Deliver every pending event now, ignoring the retry delay")]
+    Deliver(WebhookDeliver),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GitCommand {
+    Scan(GitScan),
+    #[command(name = "install-hook")]
+    InstallHook(GitInstallHook),
+    #[command(name = "check-msg")]
+    CheckMsg(GitCheckMsg),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    List(SessionList),
+    Prune(SessionPrune),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommand {
+    List(TagList),
+}
+
 #[derive(Subcommand, Debug)]
 pub enum HookCommand {
     #[command(name = "pretooluse")]
@@ -97,18 +207,56 @@ pub enum HookCommand {
 pub struct PlanAdd {
     pub title: String,
     pub content: String,
+    #[arg(long, value_enum)]
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanClone {
+    pub id: i64,
+    #[arg(long)]
+    pub title: Option<String>,
+    #[arg(long, help = "Set every step and goal of the copy back to todo")]
+    pub reset_status: bool,
+    #[arg(long, help = "Copy plan, step and goal comments too")]
+    pub with_comments: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanArchive {
+    #[arg(required_unless_present = "done_days")]
+    pub id: Option<i64>,
+    #[arg(
+        long,
+        value_name = "DAYS",
+        conflicts_with = "id",
+        help = "Archive every plan that has been done for at least DAYS days"
+    )]
+    pub done_days: Option<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanUnarchive {
+    pub id: i64,
 }
 
 #[derive(Args, Debug)]
 pub struct PlanAddTree {
     pub title: String,
     pub content: String,
+    #[arg(long, value_enum)]
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
     #[arg(
         value_name = "ARGS",
         num_args = 1..,
         trailing_var_arg = true,
         allow_hyphen_values = true,
-        help = "Use --step <content> [--executor ai|human] [--goal <goal> ...] repeating per step"
+        help = "Use --step <content> [--executor <executor>] [--priority <priority>] \
+                [--due <date>] [--goal <goal> ...] repeating per step"
     )]
     pub args: Vec<String>,
 }
@@ -116,7 +264,8 @@ pub struct PlanAddTree {
 #[derive(Clone, Debug)]
 pub struct StepSpec {
     pub content: String,
-    pub executor: Option<StepExecutorArg>,
+    pub executor: Option<StepExecutor>,
+    pub schedule: StepSchedule,
     pub goals: Option<Vec<String>>,
 }
 
@@ -126,6 +275,18 @@ pub struct PlanList {
     pub all: bool,
     #[arg(long)]
     pub project: bool,
+    #[arg(long, value_enum)]
+    pub sort: Option<PlanSortArg>,
+    #[arg(long)]
+    pub desc: bool,
+    #[arg(long, help = "Only list plans past their due date that are not done")]
+    pub overdue: bool,
+    #[arg(long = "tag", value_name = "TAG", help = TAG_FILTER_HELP)]
+    pub tags: Vec<String>,
+    #[arg(long, value_enum, help = "Match any or all --tag values (default: all)")]
+    pub tag_mode: Option<TagModeArg>,
+    #[arg(long, help = ARCHIVED_HELP)]
+    pub archived: bool,
 }
 
 #[derive(Args, Debug)]
@@ -142,6 +303,12 @@ pub struct PlanSearch {
     pub search_field: Option<PlanSearchFieldArg>,
     #[arg(long)]
     pub match_case: bool,
+    #[arg(long = "tag", value_name = "TAG", help = TAG_FILTER_HELP)]
+    pub tags: Vec<String>,
+    #[arg(long, value_enum, help = "Match any or all --tag values (default: all)")]
+    pub tag_mode: Option<TagModeArg>,
+    #[arg(long, help = ARCHIVED_HELP)]
+    pub archived: bool,
 }
 
 #[derive(Args, Debug)]
@@ -152,7 +319,28 @@ pub struct PlanShow {
 #[derive(Args, Debug)]
 pub struct PlanExport {
     pub id: i64,
+    #[arg(help = "Output file, or - for stdout")]
     pub path: PathBuf,
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub format: PlanExportFormatArg,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanImportMd {
+    #[arg(help = "Markdown task list, or a plan.md written by planpilot")]
+    pub path: PathBuf,
+    #[arg(long, help = "Plan title (default: the first heading, then the file name)")]
+    pub title: Option<String>,
+    #[arg(long, help = "Print the equivalent add-tree command instead of importing")]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanImportGithub {
+    #[arg(help = "JSON from `gh issue view --json title,body,labels` (or an array of issues)")]
+    pub path: PathBuf,
+    #[arg(long, help = "Print the equivalent add-tree command instead of importing")]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Debug)]
@@ -166,6 +354,10 @@ pub struct PlanUpdate {
     pub status: Option<PlanStatusArg>,
     #[arg(long)]
     pub comment: Option<String>,
+    #[arg(long, value_enum)]
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -186,6 +378,12 @@ pub struct PlanActivate {
         help = "Allow taking over a plan already active in another session"
     )]
     pub force: bool,
+    #[arg(
+        long,
+        conflicts_with = "force",
+        help = "Work the plan in parallel with other sessions that have it active"
+    )]
+    pub shared: bool,
 }
 
 #[derive(Args, Debug)]
@@ -194,6 +392,33 @@ pub struct PlanActive {}
 #[derive(Args, Debug)]
 pub struct PlanDeactivate {}
 
+#[derive(Args, Debug)]
+pub struct PlanPush {
+    pub id: i64,
+    #[arg(
+        long,
+        help = "Allow taking over a plan already active in another session"
+    )]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanPop {}
+
+#[derive(Args, Debug)]
+pub struct PlanTag {
+    pub id: i64,
+    #[arg(value_name = "TAG", num_args = 1..)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PlanUntag {
+    pub id: i64,
+    #[arg(value_name = "TAG", num_args = 1..)]
+    pub tags: Vec<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct StepAdd {
     pub plan_id: i64,
@@ -201,18 +426,37 @@ pub struct StepAdd {
     pub contents: Vec<String>,
     #[arg(long)]
     pub at: Option<usize>,
-    #[arg(long, value_enum, default_value = "ai")]
-    pub executor: StepExecutorArg,
+    #[arg(long, value_name = "STEP_ID", conflicts_with = "at", help = PARENT_HELP)]
+    pub parent: Option<i64>,
+    #[arg(
+        long,
+        value_name = "MILESTONE_ID",
+        conflicts_with = "parent",
+        help = "Add the steps to milestone MILESTONE_ID"
+    )]
+    pub milestone: Option<i64>,
+    #[arg(long, value_name = "EXECUTOR", default_value = "ai", help = EXECUTOR_HELP)]
+    pub executor: String,
+    #[arg(long, value_enum)]
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
 }
 
 #[derive(Args, Debug)]
 pub struct StepAddTree {
     pub plan_id: i64,
     pub content: String,
+    #[arg(long, value_name = "EXECUTOR", help = EXECUTOR_HELP)]
+    pub executor: Option<String>,
     #[arg(long, value_enum)]
-    pub executor: Option<StepExecutorArg>,
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
     #[arg(long = "goal", value_name = "GOAL")]
     pub goals: Vec<String>,
+    #[arg(long, value_name = "STEP_ID", help = PARENT_HELP)]
+    pub parent: Option<i64>,
 }
 
 #[derive(Args, Debug)]
@@ -222,8 +466,8 @@ pub struct StepList {
     pub all: bool,
     #[arg(long, value_enum)]
     pub status: Option<StepStatusArg>,
-    #[arg(long, value_enum)]
-    pub executor: Option<StepExecutorArg>,
+    #[arg(long, value_name = "EXECUTOR", help = EXECUTOR_HELP)]
+    pub executor: Option<String>,
     #[arg(long)]
     pub limit: Option<u64>,
     #[arg(long)]
@@ -234,6 +478,10 @@ pub struct StepList {
     pub order: Option<StepOrderArg>,
     #[arg(long)]
     pub desc: bool,
+    #[arg(long = "tag", value_name = "TAG", help = TAG_FILTER_HELP)]
+    pub tags: Vec<String>,
+    #[arg(long, value_enum, help = "Match any or all --tag values (default: all)")]
+    pub tag_mode: Option<TagModeArg>,
 }
 
 #[derive(Args, Debug)]
@@ -242,7 +490,57 @@ pub struct StepShow {
 }
 
 #[derive(Args, Debug)]
-pub struct StepShowNext {}
+pub struct StepDiff {
+    pub id: i64,
+    #[arg(
+        long,
+        value_name = "REV",
+        help = "Diff from this revision instead of the previous step checkpoint"
+    )]
+    pub from: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StepShowNext {
+    #[arg(long, help = "Claim the surfaced step for this session (never human steps)")]
+    pub claim: bool,
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        default_value_t = DEFAULT_CLAIM_LEASE_MINUTES,
+        requires = "claim",
+        help = "Lease length for the claim taken with --claim"
+    )]
+    pub lease: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct StepClaim {
+    pub id: i64,
+    #[arg(long, value_name = "MINUTES", default_value_t = DEFAULT_CLAIM_LEASE_MINUTES)]
+    pub lease: i64,
+    #[arg(long, help = "Take over a claim held by another session")]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct StepRelease {
+    pub id: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct StepTag {
+    pub id: i64,
+    #[arg(value_name = "TAG", num_args = 1..)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StepUntag {
+    pub id: i64,
+    #[arg(value_name = "TAG", num_args = 1..)]
+    pub tags: Vec<String>,
+}
 
 #[derive(Args, Debug)]
 pub struct StepUpdate {
@@ -251,10 +549,14 @@ pub struct StepUpdate {
     pub content: Option<String>,
     #[arg(long, value_enum)]
     pub status: Option<StepStatusArg>,
-    #[arg(long, value_enum)]
-    pub executor: Option<StepExecutorArg>,
+    #[arg(long, value_name = "EXECUTOR", help = EXECUTOR_HELP)]
+    pub executor: Option<String>,
     #[arg(long)]
     pub comment: Option<String>,
+    #[arg(long, value_enum)]
+    pub priority: Option<PriorityArg>,
+    #[arg(long, value_name = "DATE", help = DUE_HELP)]
+    pub due: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -267,8 +569,38 @@ pub struct StepDone {
 #[derive(Args, Debug)]
 pub struct StepMove {
     pub id: i64,
-    #[arg(long)]
-    pub to: usize,
+    #[arg(long, required_unless_present = "plan")]
+    pub to: Option<usize>,
+    #[arg(long, value_name = "PLAN_ID", help = "Move the step and its sub-steps to another plan")]
+    pub plan: Option<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct GoalMove {
+    pub id: i64,
+    #[arg(long, required_unless_present = "step")]
+    pub to: Option<usize>,
+    #[arg(long, value_name = "STEP_ID", help = "Move the goal to another step")]
+    pub step: Option<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct StepSplit {
+    pub id: i64,
+    #[arg(long = "into", value_name = "CONTENT", required = true)]
+    pub parts: Vec<String>,
+    #[arg(
+        long = "assign",
+        value_name = "GOAL_ID=PART",
+        help = "Move a goal to the given part (1-based); other goals are spread in order"
+    )]
+    pub assignments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StepMerge {
+    pub target: i64,
+    pub source: i64,
 }
 
 #[derive(Args, Debug)]
@@ -315,6 +647,216 @@ pub struct GoalUpdate {
     pub comment: Option<String>,
 }
 
+#[derive(Args, Debug)]
+pub struct SessionList {
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Minutes without a heartbeat before a session is stale"
+    )]
+    pub stale_after: Option<i64>,
+    #[arg(long, help = "Only list stale sessions")]
+    pub stale: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SessionPrune {
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Minutes without a heartbeat before a session is stale"
+    )]
+    pub stale_after: Option<i64>,
+    #[arg(long, help = "Show what would be pruned without changing anything")]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct TagList {}
+
+#[derive(Args, Debug)]
+pub struct MilestoneAdd {
+    pub plan_id: i64,
+    pub title: String,
+    #[arg(
+        long = "step",
+        value_name = "STEP_ID",
+        help = "Move top-level step STEP_ID and its sub-steps into the milestone (repeatable)"
+    )]
+    pub steps: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct MilestoneList {
+    pub plan_id: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct MilestoneAssign {
+    pub id: i64,
+    #[arg(value_name = "STEP_ID", num_args = 1..)]
+    pub step_ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct MilestoneDone {
+    pub id: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct CriterionAdd {
+    pub plan_id: i64,
+    #[arg(value_name = "CONTENT", num_args = 1..)]
+    pub contents: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CriterionList {
+    pub plan_id: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct CriterionCheck {
+    #[arg(value_name = "ID", num_args = 1..)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct CriterionUncheck {
+    #[arg(value_name = "ID", num_args = 1..)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct CriterionRemove {
+    #[arg(value_name = "ID", num_args = 1..)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct TableExport {
+    #[arg(long, value_enum, default_value = "csv")]
+    pub format: TableFormatArg,
+    #[arg(long, value_name = "ID", help = "Export this plan (default: the active plan)")]
+    pub plan: Option<i64>,
+    #[arg(long, conflicts_with = "plan", help = "Export every plan that is not archived")]
+    pub all: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct Serve {
+    #[arg(long, default_value_t = 7345, help = "Port to listen on (0 picks a free one)")]
+    pub port: u16,
+    #[arg(long, default_value = "127.0.0.1", help = "Address to bind")]
+    pub host: String,
+    #[arg(long, help = "Enable the POST endpoints that complete and comment steps and goals")]
+    pub allow_writes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct Watch {
+    #[arg(long, value_name = "ID", help = "Only watch this plan (default: every plan)")]
+    pub plan: Option<i64>,
+    #[arg(
+        long,
+        conflicts_with = "plan",
+        help = "Follow the active plan of this session, switching when it changes"
+    )]
+    pub active: bool,
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 500,
+        value_parser = clap::value_parser!(u64).range(50..),
+        help = "How often to poll for changes, in milliseconds"
+    )]
+    pub interval: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct EventAdd {
+    #[arg(value_enum)]
+    pub event: EventKindArg,
+    #[arg(help = "Shell command to run; it receives the event as JSON on stdin")]
+    pub command: String,
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u32).range(1..=3600),
+        help = "Kill the command after this many seconds"
+    )]
+    pub timeout: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct EventList {}
+
+#[derive(Args, Debug)]
+pub struct EventRemove {
+    #[arg(value_name = "ID", num_args = 1..)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct WebhookAdd {
+    #[arg(value_name = "URL", help = "http:// or https:// URL to POST events to")]
+    pub url: String,
+    #[arg(long, help = "Key for the HMAC-SHA256 signature in X-Planpilot-Signature")]
+    pub secret: String,
+    #[arg(
+        long = "event",
+        value_name = "EVENT",
+        value_enum,
+        help = "Only post this event; repeat for more (default: every event)"
+    )]
+    pub events: Vec<EventKindArg>,
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u32).range(1..=60),
+        help = "Give up on a delivery attempt after this many seconds"
+    )]
+    pub timeout: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct WebhookList {}
+
+#[derive(Args, Debug)]
+pub struct WebhookRemove {
+    #[arg(value_name = "ID", num_args = 1..)]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Args, Debug)]
+pub struct WebhookOutbox {}
+
+#[derive(Args, Debug)]
+pub struct WebhookDeliver {
+    #[arg(long, help = "Only send deliveries whose retry delay has passed")]
+    pub due: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct GitScan {
+    #[arg(long, value_name = "REV", help = "Only scan commits after this revision")]
+    pub since: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct GitInstallHook {
+    #[arg(long, help = "Replace an existing commit-msg hook")]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct GitCheckMsg {
+    #[arg(value_name = "FILE", help = "Commit message file passed to the commit-msg hook")]
+    pub file: PathBuf,
+}
+
 #[derive(Args, Debug)]
 pub struct PlanComment {
     #[arg(value_name = "ARG", num_args = 2..)]
@@ -351,6 +893,36 @@ pub enum PlanStatusArg {
     Done,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PlanExportFormatArg {
+    Markdown,
+    Mermaid,
+    Dot,
+    Html,
+    Github,
+    GithubBody,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum EventKindArg {
+    #[value(name = "goal.done")]
+    GoalDone,
+    #[value(name = "step.done")]
+    StepDone,
+    #[value(name = "plan.done")]
+    PlanDone,
+    #[value(name = "plan.activated")]
+    PlanActivated,
+    #[value(name = "step.human_required")]
+    StepHumanRequired,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TableFormatArg {
+    Csv,
+    Tsv,
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum PlanSearchModeArg {
     Any,
@@ -374,12 +946,6 @@ pub enum StepStatusArg {
     Done,
 }
 
-#[derive(ValueEnum, Clone, Debug, Deserialize)]
-pub enum StepExecutorArg {
-    Ai,
-    Human,
-}
-
 #[derive(ValueEnum, Clone, Debug)]
 pub enum GoalStatusArg {
     Todo,
@@ -391,4 +957,29 @@ pub enum StepOrderArg {
     Order,
     Id,
     Created,
+    Priority,
+    Due,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum PlanSortArg {
+    Priority,
+    Due,
+    Updated,
+    Progress,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TagModeArg {
+    Any,
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PriorityArg {
+    P0,
+    P1,
+    P2,
+    P3,
+    None,
 }
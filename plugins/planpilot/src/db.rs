@@ -2,10 +2,16 @@ use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
 
 use sea_orm::sea_query::Index;
-use sea_orm::{ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, Schema, Statement};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, Schema,
+    Statement,
+};
 use url::Url;
 
-use crate::entities::{active_plan, goal, plan, step};
+use crate::entities::{
+    active_plan, criterion, event_hook, goal, milestone, plan, plan_stack, plan_tag, session, step,
+    step_claim, step_tag, tag, webhook, webhook_delivery,
+};
 use crate::error::AppError;
 
 pub fn resolve_db_path(claude_home: &Path) -> PathBuf {
@@ -24,6 +30,17 @@ pub fn resolve_plan_md_path(claude_home: &Path, plan_id: i64) -> PathBuf {
     resolve_plan_md_dir(claude_home).join(format!("plan_{plan_id}.md"))
 }
 
+pub fn resolve_archived_plan_md_path(claude_home: &Path, plan_id: i64) -> PathBuf {
+    resolve_plan_md_dir(claude_home)
+        .join("archive")
+        .join(format!("plan_{plan_id}.md"))
+}
+
+/// Holds the time `PLANPILOT_AUTO_ARCHIVE_DAYS` last ran, so it runs at most once a day.
+pub fn resolve_auto_archive_stamp_path(claude_home: &Path) -> PathBuf {
+    resolve_planpilot_dir(claude_home).join("auto-archive")
+}
+
 pub fn ensure_parent_dir(path: &Path) -> Result<(), AppError> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
@@ -35,18 +52,41 @@ pub fn open_lock(path: &Path) -> Result<fd_lock::RwLock<File>, AppError> {
     let lock_path = path.with_extension("lock");
     let file = OpenOptions::new()
         .create(true)
+        .truncate(false)
         .read(true)
         .write(true)
         .open(lock_path)?;
     Ok(fd_lock::RwLock::new(file))
 }
 
-pub async fn connect(path: &Path) -> Result<DatabaseConnection, AppError> {
+fn sqlite_url(path: &Path) -> Result<String, AppError> {
     let mut url = Url::from_file_path(path)
         .map_err(|_| AppError::InvalidInput(format!("invalid sqlite path: {}", path.display())))?;
     url.set_query(Some("mode=rwc"));
-    let sqlite_url = url.as_str().replacen("file://", "sqlite://", 1);
-    Ok(Database::connect(&sqlite_url).await?)
+    Ok(url.as_str().replacen("file://", "sqlite://", 1))
+}
+
+pub async fn connect(path: &Path) -> Result<DatabaseConnection, AppError> {
+    Ok(Database::connect(&sqlite_url(path)?).await?)
+}
+
+/// Opens a pool of exactly one connection, for per-connection pragmas such as `data_version`.
+pub async fn connect_single(path: &Path) -> Result<DatabaseConnection, AppError> {
+    let mut options = ConnectOptions::new(sqlite_url(path)?);
+    options.max_connections(1).min_connections(1);
+    Ok(Database::connect(options).await?)
+}
+
+/// Returns SQLite's `data_version`, which changes whenever another connection commits.
+pub async fn data_version(db: &DatabaseConnection) -> Result<i64, AppError> {
+    let row = db
+        .query_one(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "PRAGMA data_version;",
+        ))
+        .await?
+        .ok_or_else(|| AppError::NotFound("data_version".to_string()))?;
+    Ok(row.try_get_by_index::<i64>(0)?)
 }
 
 pub async fn ensure_schema(db: &DatabaseConnection) -> Result<(), AppError> {
@@ -71,10 +111,77 @@ pub async fn ensure_schema(db: &DatabaseConnection) -> Result<(), AppError> {
     goal_stmt.if_not_exists();
     db.execute(builder.build(&goal_stmt)).await?;
 
+    let mut milestone_stmt = schema.create_table_from_entity(milestone::Entity);
+    milestone_stmt.if_not_exists();
+    db.execute(builder.build(&milestone_stmt)).await?;
+
+    let mut criterion_stmt = schema.create_table_from_entity(criterion::Entity);
+    criterion_stmt.if_not_exists();
+    db.execute(builder.build(&criterion_stmt)).await?;
+
     let mut active_stmt = schema.create_table_from_entity(active_plan::Entity);
     active_stmt.if_not_exists();
     db.execute(builder.build(&active_stmt)).await?;
 
+    let mut claim_stmt = schema.create_table_from_entity(step_claim::Entity);
+    claim_stmt.if_not_exists();
+    db.execute(builder.build(&claim_stmt)).await?;
+
+    let mut stack_stmt = schema.create_table_from_entity(plan_stack::Entity);
+    stack_stmt.if_not_exists();
+    db.execute(builder.build(&stack_stmt)).await?;
+
+    let mut tag_stmt = schema.create_table_from_entity(tag::Entity);
+    tag_stmt.if_not_exists();
+    db.execute(builder.build(&tag_stmt)).await?;
+
+    let mut plan_tag_stmt = schema.create_table_from_entity(plan_tag::Entity);
+    plan_tag_stmt.if_not_exists();
+    db.execute(builder.build(&plan_tag_stmt)).await?;
+
+    let mut step_tag_stmt = schema.create_table_from_entity(step_tag::Entity);
+    step_tag_stmt.if_not_exists();
+    db.execute(builder.build(&step_tag_stmt)).await?;
+
+    let mut session_stmt = schema.create_table_from_entity(session::Entity);
+    session_stmt.if_not_exists();
+    db.execute(builder.build(&session_stmt)).await?;
+
+    let mut event_hook_stmt = schema.create_table_from_entity(event_hook::Entity);
+    event_hook_stmt.if_not_exists();
+    db.execute(builder.build(&event_hook_stmt)).await?;
+    let mut webhook_stmt = schema.create_table_from_entity(webhook::Entity);
+    webhook_stmt.if_not_exists();
+    db.execute(builder.build(&webhook_stmt)).await?;
+    let mut delivery_stmt = schema.create_table_from_entity(webhook_delivery::Entity);
+    delivery_stmt.if_not_exists();
+    db.execute(builder.build(&delivery_stmt)).await?;
+
+    ensure_column(db, "plans", "priority", "varchar NULL").await?;
+    ensure_column(db, "plans", "due", "date NULL").await?;
+    ensure_column(db, "plans", "source_plan_id", "bigint NULL").await?;
+    ensure_column(
+        db,
+        "plans",
+        "archived_at",
+        "timestamp_with_timezone_text NULL",
+    )
+    .await?;
+    ensure_column(db, "plans", "done_at", "timestamp_with_timezone_text NULL").await?;
+    ensure_column(db, "steps", "priority", "varchar NULL").await?;
+    ensure_column(db, "steps", "due", "date NULL").await?;
+    ensure_column(db, "steps", "parent_id", "bigint NULL").await?;
+    ensure_column(db, "steps", "milestone_id", "bigint NULL").await?;
+    ensure_column(db, "steps", "checkpoint_commit", "varchar NULL").await?;
+    ensure_column(db, "steps", "checkpoint_branch", "varchar NULL").await?;
+    ensure_column(db, "steps", "checkpoint_dirty", "boolean NULL").await?;
+    ensure_column(db, "steps", "checkpoint_at", "timestamp_with_timezone_text NULL").await?;
+    ensure_column(db, "goals", "sort_order", "integer NOT NULL DEFAULT 0").await?;
+    ensure_column(db, "goals", "checkpoint_commit", "varchar NULL").await?;
+    ensure_column(db, "goals", "checkpoint_branch", "varchar NULL").await?;
+    ensure_column(db, "goals", "checkpoint_dirty", "boolean NULL").await?;
+    ensure_column(db, "goals", "checkpoint_at", "timestamp_with_timezone_text NULL").await?;
+
     let builder = db.get_database_backend();
 
     let mut index_stmt = Index::create()
@@ -94,6 +201,32 @@ pub async fn ensure_schema(db: &DatabaseConnection) -> Result<(), AppError> {
     goal_index.if_not_exists();
     db.execute(builder.build(&goal_index)).await?;
 
+    let mut parent_index = Index::create()
+        .name("idx_steps_parent")
+        .table(step::Entity)
+        .col(step::Column::ParentId)
+        .to_owned();
+    parent_index.if_not_exists();
+    db.execute(builder.build(&parent_index)).await?;
+
+    let mut milestone_index = Index::create()
+        .name("idx_milestones_plan_order")
+        .table(milestone::Entity)
+        .col(milestone::Column::PlanId)
+        .col(milestone::Column::SortOrder)
+        .to_owned();
+    milestone_index.if_not_exists();
+    db.execute(builder.build(&milestone_index)).await?;
+
+    let mut criterion_index = Index::create()
+        .name("idx_criteria_plan_order")
+        .table(criterion::Entity)
+        .col(criterion::Column::PlanId)
+        .col(criterion::Column::SortOrder)
+        .to_owned();
+    criterion_index.if_not_exists();
+    db.execute(builder.build(&criterion_index)).await?;
+
     let mut active_index = Index::create()
         .name("idx_active_plan_session")
         .table(active_plan::Entity)
@@ -103,14 +236,101 @@ pub async fn ensure_schema(db: &DatabaseConnection) -> Result<(), AppError> {
     active_index.if_not_exists();
     db.execute(builder.build(&active_index)).await?;
 
+    db.execute(Statement::from_string(
+        DatabaseBackend::Sqlite,
+        "DROP INDEX IF EXISTS idx_active_plan_plan;",
+    ))
+    .await?;
+
     let mut active_plan_index = Index::create()
-        .name("idx_active_plan_plan")
+        .name("idx_active_plan_plan_id")
         .table(active_plan::Entity)
         .col(active_plan::Column::PlanId)
-        .unique()
         .to_owned();
     active_plan_index.if_not_exists();
     db.execute(builder.build(&active_plan_index)).await?;
 
+    let mut claim_index = Index::create()
+        .name("idx_step_claims_step")
+        .table(step_claim::Entity)
+        .col(step_claim::Column::StepId)
+        .unique()
+        .to_owned();
+    claim_index.if_not_exists();
+    db.execute(builder.build(&claim_index)).await?;
+
+    let mut stack_index = Index::create()
+        .name("idx_plan_stack_session")
+        .table(plan_stack::Entity)
+        .col(plan_stack::Column::SessionId)
+        .col(plan_stack::Column::Position)
+        .to_owned();
+    stack_index.if_not_exists();
+    db.execute(builder.build(&stack_index)).await?;
+
+    let mut tag_index = Index::create()
+        .name("idx_tags_name")
+        .table(tag::Entity)
+        .col(tag::Column::Name)
+        .unique()
+        .to_owned();
+    tag_index.if_not_exists();
+    db.execute(builder.build(&tag_index)).await?;
+
+    let mut plan_tag_index = Index::create()
+        .name("idx_plan_tags_plan_tag")
+        .table(plan_tag::Entity)
+        .col(plan_tag::Column::PlanId)
+        .col(plan_tag::Column::TagId)
+        .unique()
+        .to_owned();
+    plan_tag_index.if_not_exists();
+    db.execute(builder.build(&plan_tag_index)).await?;
+
+    let mut step_tag_index = Index::create()
+        .name("idx_step_tags_step_tag")
+        .table(step_tag::Entity)
+        .col(step_tag::Column::StepId)
+        .col(step_tag::Column::TagId)
+        .unique()
+        .to_owned();
+    step_tag_index.if_not_exists();
+    db.execute(builder.build(&step_tag_index)).await?;
+
+    let mut session_index = Index::create()
+        .name("idx_sessions_session")
+        .table(session::Entity)
+        .col(session::Column::SessionId)
+        .unique()
+        .to_owned();
+    session_index.if_not_exists();
+    db.execute(builder.build(&session_index)).await?;
+
+    Ok(())
+}
+
+async fn ensure_column(
+    db: &DatabaseConnection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), AppError> {
+    let rows = db
+        .query_all(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            format!("PRAGMA table_info({table});"),
+        ))
+        .await?;
+    let exists = rows.iter().any(|row| {
+        row.try_get::<String>("", "name")
+            .is_ok_and(|name| name == column)
+    });
+    if !exists {
+        db.execute(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            format!("ALTER TABLE {table} ADD COLUMN {column} {definition};"),
+        ))
+        .await?;
+    }
     Ok(())
 }
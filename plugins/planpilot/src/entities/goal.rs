@@ -10,7 +10,12 @@ pub struct Model {
     pub step_id: i64,
     pub content: String,
     pub status: String,
+    pub sort_order: i32,
     pub comment: Option<String>,
+    pub checkpoint_commit: Option<String>,
+    pub checkpoint_branch: Option<String>,
+    pub checkpoint_dirty: Option<bool>,
+    pub checkpoint_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
 }
@@ -1,4 +1,15 @@
 pub mod active_plan;
+pub mod criterion;
+pub mod event_hook;
 pub mod goal;
+pub mod milestone;
 pub mod plan;
+pub mod plan_stack;
+pub mod plan_tag;
+pub mod session;
 pub mod step;
+pub mod step_claim;
+pub mod step_tag;
+pub mod tag;
+pub mod webhook;
+pub mod webhook_delivery;
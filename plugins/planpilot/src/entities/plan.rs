@@ -12,6 +12,11 @@ pub struct Model {
     pub status: String,
     pub comment: Option<String>,
     pub last_session_id: Option<String>,
+    pub priority: Option<String>,
+    pub due: Option<Date>,
+    pub source_plan_id: Option<i64>,
+    pub archived_at: Option<DateTimeUtc>,
+    pub done_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
 }
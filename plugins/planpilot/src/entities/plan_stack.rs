@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+
+use super::plan;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "plan_stack")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub session_id: String,
+    pub plan_id: i64,
+    pub position: i32,
+    pub pushed_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Plan,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Plan => Entity::belongs_to(plan::Entity)
+                .from(Column::PlanId)
+                .to(plan::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<plan::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Plan.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
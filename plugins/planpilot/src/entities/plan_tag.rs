@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+
+use super::{plan, tag};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "plan_tags")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub plan_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Plan,
+    Tag,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Plan => Entity::belongs_to(plan::Entity)
+                .from(Column::PlanId)
+                .to(plan::Column::Id)
+                .into(),
+            Self::Tag => Entity::belongs_to(tag::Entity)
+                .from(Column::TagId)
+                .to(tag::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<plan::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Plan.def()
+    }
+}
+
+impl Related<tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tag.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -8,11 +8,19 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub plan_id: i64,
+    pub parent_id: Option<i64>,
+    pub milestone_id: Option<i64>,
     pub content: String,
     pub status: String,
     pub executor: String,
     pub sort_order: i32,
     pub comment: Option<String>,
+    pub priority: Option<String>,
+    pub due: Option<Date>,
+    pub checkpoint_commit: Option<String>,
+    pub checkpoint_branch: Option<String>,
+    pub checkpoint_dirty: Option<bool>,
+    pub checkpoint_at: Option<DateTimeUtc>,
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
 }
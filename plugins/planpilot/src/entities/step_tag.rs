@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+
+use super::{step, tag};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "step_tags")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub step_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Step,
+    Tag,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Step => Entity::belongs_to(step::Entity)
+                .from(Column::StepId)
+                .to(step::Column::Id)
+                .into(),
+            Self::Tag => Entity::belongs_to(tag::Entity)
+                .from(Column::TagId)
+                .to(tag::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<step::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Step.def()
+    }
+}
+
+impl Related<tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tag.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
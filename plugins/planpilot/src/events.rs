@@ -0,0 +1,260 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use crate::app::{App, StatusSnapshot};
+use crate::entities::{event_hook, webhook};
+use crate::error::AppError;
+use crate::model::{EventKind, GoalStatus, PlanStatus, StepExecutor, StepStatus};
+use crate::serve::{goal_json, plan_json, step_json};
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    pub payload: Value,
+}
+
+/// Remembers the statuses before a command so the events it caused can be found once it has
+/// committed. Only started when hooks or webhooks are registered, so other commands pay nothing.
+pub struct EventRecorder {
+    hooks: Vec<event_hook::Model>,
+    webhooks: Vec<webhook::Model>,
+    before: StatusSnapshot,
+}
+
+/// What a command left to do once the lock is released: the hooks to run for its events, and
+/// whether the webhook outbox has deliveries due.
+#[derive(Debug, Default)]
+pub struct Pending {
+    hooks: Vec<(event_hook::Model, Event)>,
+    webhooks: bool,
+}
+
+impl EventRecorder {
+    pub async fn start(app: &App) -> Result<Option<Self>, AppError> {
+        let hooks = app.list_event_hooks().await?;
+        let webhooks = app.list_webhooks().await?;
+        if hooks.is_empty() && webhooks.is_empty() {
+            return Ok(None);
+        }
+        let before = app.status_snapshot().await?;
+        Ok(Some(Self {
+            hooks,
+            webhooks,
+            before,
+        }))
+    }
+
+    /// Pairs each event with the hooks registered for it, in event then hook order, and queues
+    /// it in the outbox of every webhook subscribed to it. Called before the lock is released,
+    /// also when the command failed, so the events of whatever it committed are kept.
+    pub async fn finish(self, app: &App) -> Result<Pending, AppError> {
+        let after = app.status_snapshot().await?;
+        let mut hooks = Vec::new();
+        let mut deliveries = Vec::new();
+        for event in collect_events(app, &self.before, &after).await? {
+            for hook in &self.hooks {
+                if hook.event == event.kind.as_str() {
+                    hooks.push((hook.clone(), event.clone()));
+                }
+            }
+            let payload = event.payload.to_string();
+            for webhook in &self.webhooks {
+                let kind = event.kind.as_str();
+                if webhook.events.is_empty() || webhook.events.split(',').any(|name| name == kind)
+                {
+                    deliveries.push((webhook.id, event.kind, payload.clone()));
+                }
+            }
+        }
+        app.enqueue_webhook_deliveries(&deliveries).await?;
+        let webhooks = !self.webhooks.is_empty() && app.has_due_webhook_deliveries().await?;
+        Ok(Pending { hooks, webhooks })
+    }
+}
+
+/// Runs the hooks and starts delivering the due webhook events, including earlier ones still
+/// waiting for a retry. Called once the lock is released so hooks can call planpilot themselves;
+/// problems are reported on stderr and never fail the command.
+pub fn dispatch(pending: Pending, app: &App, cwd: Option<&Path>) {
+    run_hooks(&pending.hooks, cwd);
+    if pending.webhooks {
+        spawn_webhook_delivery(app.session_id());
+    }
+}
+
+/// Runs `planpilot webhook deliver --due` in the background so a slow or unreachable endpoint
+/// never holds up the command; failed deliveries keep their error in the outbox.
+fn spawn_webhook_delivery(session_id: &str) {
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("planpilot"));
+    let child = Command::new(exe)
+        .arg("--session-id")
+        .arg(session_id)
+        .args(["webhook", "deliver", "--due"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        // Reaped from a thread so long-running commands such as `serve` leave no zombies.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => eprintln!("Failed to start webhook delivery: {err}"),
+    }
+}
+
+/// Builds the events between two snapshots: goals, steps and plans that became `done` (in rollup
+/// order), plans that became active in some session, and a `step.human_required` for each plan
+/// whose next step not claimed by another session is now a human one after a step was completed
+/// or the plan was activated.
+pub async fn collect_events(
+    app: &App,
+    before: &StatusSnapshot,
+    after: &StatusSnapshot,
+) -> Result<Vec<Event>, AppError> {
+    let occurred_at = Utc::now();
+    let event = |kind: EventKind, fields: Value| {
+        let mut payload = json!({
+            "event": kind.as_str(),
+            "occurred_at": occurred_at,
+            "session_id": app.session_id(),
+        });
+        if let (Some(payload), Value::Object(fields)) = (payload.as_object_mut(), fields) {
+            payload.extend(fields);
+        }
+        Event { kind, payload }
+    };
+    let mut events = Vec::new();
+    let mut handoff_plans = BTreeSet::new();
+
+    for goal_id in became_done(&before.goals, &after.goals, GoalStatus::Done.as_str()) {
+        let goal = app.get_goal(goal_id).await?;
+        let step = app.get_step(goal.step_id).await?;
+        let plan = app.get_plan(step.plan_id).await?;
+        let goals = app.goals_for_step(step.id).await?;
+        events.push(event(
+            EventKind::GoalDone,
+            json!({
+                "goal": goal_json(&goal),
+                "step": step_json(&step, &goals),
+                "plan": plan_json(&plan),
+            }),
+        ));
+    }
+    for step_id in became_done(&before.steps, &after.steps, StepStatus::Done.as_str()) {
+        let step = app.get_step(step_id).await?;
+        let plan = app.get_plan(step.plan_id).await?;
+        let goals = app.goals_for_step(step.id).await?;
+        handoff_plans.insert(plan.id);
+        events.push(event(
+            EventKind::StepDone,
+            json!({ "step": step_json(&step, &goals), "plan": plan_json(&plan) }),
+        ));
+    }
+    for plan_id in became_done(&before.plans, &after.plans, PlanStatus::Done.as_str()) {
+        let plan = app.get_plan(plan_id).await?;
+        events.push(event(EventKind::PlanDone, json!({ "plan": plan_json(&plan) })));
+    }
+    let mut activations: Vec<&(String, i64)> =
+        after.activations.difference(&before.activations).collect();
+    activations.sort();
+    for (session_id, plan_id) in activations {
+        let plan = app.get_plan(*plan_id).await?;
+        handoff_plans.insert(plan.id);
+        events.push(event(
+            EventKind::PlanActivated,
+            json!({ "plan": plan_json(&plan), "active_session_id": session_id }),
+        ));
+    }
+    for plan_id in handoff_plans {
+        let plan = app.get_plan(plan_id).await?;
+        if plan.status == PlanStatus::Done.as_str() {
+            continue;
+        }
+        let Some(step) = app.next_unclaimed_step(plan_id).await? else {
+            continue;
+        };
+        let executor = StepExecutor::parse(&step.executor).unwrap_or_else(StepExecutor::ai);
+        if executor.is_human() {
+            let goals = app.goals_for_step(step.id).await?;
+            events.push(event(
+                EventKind::StepHumanRequired,
+                json!({ "step": step_json(&step, &goals), "plan": plan_json(&plan) }),
+            ));
+        }
+    }
+    Ok(events)
+}
+
+/// Ids that existed before with another status and are `done` now, in id order.
+fn became_done(
+    before: &HashMap<i64, String>,
+    after: &HashMap<i64, String>,
+    done: &str,
+) -> Vec<i64> {
+    let mut ids: Vec<i64> = after
+        .iter()
+        .filter(|(id, status)| {
+            status.as_str() == done && before.get(id).is_some_and(|status| status != done)
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Runs each hook with its event as JSON on stdin, one at a time, in `cwd` when given. Output is
+/// discarded; failures and timeouts are reported on stderr.
+fn run_hooks(pending: &[(event_hook::Model, Event)], cwd: Option<&Path>) {
+    for (hook, event) in pending {
+        if let Err(message) = run_hook(hook, event, cwd) {
+            eprintln!("Event hook ID: {} ({}) {message}.", hook.id, hook.event);
+        }
+    }
+}
+
+fn run_hook(hook: &event_hook::Model, event: &Event, cwd: Option<&Path>) -> Result<(), String> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&hook.command)
+        .env("PLANPILOT_EVENT", event.kind.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("failed to start: {err}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let payload = event.payload.to_string();
+        // Written from a thread so a hook that never reads its input still times out; a hook
+        // that exits without reading it is fine, so write errors are ignored.
+        std::thread::spawn(move || {
+            let _ = writeln!(stdin, "{payload}");
+        });
+    }
+    let timeout = Duration::from_secs(hook.timeout_secs.max(1) as u64);
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("failed: {status}")),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(err) => return Err(format!("failed: {err}")),
+        }
+    }
+}
@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::app::{plan_progress, PlanDetail};
+use crate::entities::{goal, step};
+use crate::model::{GoalStatus, StepExecutor, StepStatus};
+use crate::util::{
+    criteria_progress, format_datetime, format_due, milestone_groups, milestone_progress,
+};
+
+const DONE_FILL: &str = "#d4edda";
+const TODO_FILL: &str = "#fff3cd";
+const AI_STROKE: &str = "#1f6feb";
+const HUMAN_STROKE: &str = "#d9480f";
+const OTHER_STROKE: &str = "#6e7781";
+
+/// How two steps are connected in a plan diagram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EdgeKind {
+    /// The next sibling in plan order.
+    Next,
+    /// A parent step to its first sub-step.
+    SubStep,
+}
+
+fn node_id(step: &step::Model) -> String {
+    format!("s{}", step.id)
+}
+
+fn fill_color(status: &str) -> &'static str {
+    if status == StepStatus::Done.as_str() {
+        DONE_FILL
+    } else {
+        TODO_FILL
+    }
+}
+
+fn stroke_color(executor: &str) -> &'static str {
+    match StepExecutor::parse(executor).as_ref().map(StepExecutor::kind) {
+        Some(StepExecutor::AI) => AI_STROKE,
+        Some(StepExecutor::HUMAN) => HUMAN_STROKE,
+        _ => OTHER_STROKE,
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("(empty)")
+}
+
+/// Label lines for a step node: status and content, executor with goal progress, then one
+/// checkbox line per goal.
+fn step_label_lines(step: &step::Model, goals: Option<&Vec<goal::Model>>) -> Vec<String> {
+    let mut lines = vec![format!("[{}] {}", step.status, first_line(&step.content))];
+    let goals = goals.map(Vec::as_slice).unwrap_or_default();
+    if goals.is_empty() {
+        lines.push(format!("step {} · {}", step.id, step.executor));
+        return lines;
+    }
+    let done = goals
+        .iter()
+        .filter(|goal| goal.status == GoalStatus::Done.as_str())
+        .count();
+    lines.push(format!(
+        "step {} · {} · goals {done}/{}",
+        step.id,
+        step.executor,
+        goals.len()
+    ));
+    for goal in goals {
+        let mark = if goal.status == GoalStatus::Done.as_str() {
+            "x"
+        } else {
+            " "
+        };
+        lines.push(format!("[{mark}] {}", first_line(&goal.content)));
+    }
+    lines
+}
+
+/// Ordering edges: consecutive siblings in plan order, and each parent to its first sub-step.
+fn step_edges(order: &[&step::Model]) -> Vec<(i64, i64, EdgeKind)> {
+    let mut last_child: HashMap<Option<i64>, i64> = HashMap::new();
+    let mut edges = Vec::new();
+    for step in order {
+        match last_child.insert(step.parent_id, step.id) {
+            Some(previous) => edges.push((previous, step.id, EdgeKind::Next)),
+            None => {
+                if let Some(parent_id) = step.parent_id {
+                    edges.push((parent_id, step.id, EdgeKind::SubStep));
+                }
+            }
+        }
+    }
+    edges
+}
+
+fn mermaid_text(text: &str) -> String {
+    text.replace('&', "#amp;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+fn dot_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Renders a plan as a Mermaid flowchart: one node per step filled by status and outlined by
+/// executor kind, milestones as subgraphs, and ordering edges (dotted into sub-steps).
+pub fn format_plan_mermaid(detail: &PlanDetail) -> String {
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        milestones,
+        ..
+    } = detail;
+    let mut lines = vec![
+        "flowchart TD".to_string(),
+        format!(
+            "    %% Plan {}: {} [{}]",
+            plan.id,
+            first_line(&plan.title),
+            plan.status
+        ),
+    ];
+    let mut order = Vec::new();
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        let indent = if milestone.is_some() { "        " } else { "    " };
+        if let Some(milestone) = milestone {
+            lines.push(format!(
+                "    subgraph m{}[\"Milestone: {} [{}]\"]",
+                milestone.id,
+                mermaid_text(first_line(&milestone.title)),
+                milestone.status
+            ));
+        }
+        for (step, _) in entries {
+            let label = step_label_lines(step, goals.get(&step.id))
+                .iter()
+                .map(|line| mermaid_text(line))
+                .collect::<Vec<_>>()
+                .join("<br/>");
+            lines.push(format!("{indent}{}[\"{label}\"]", node_id(step)));
+            order.push(step);
+        }
+        if milestone.is_some() {
+            lines.push("    end".to_string());
+        }
+    }
+    for (from, to, kind) in step_edges(&order) {
+        let arrow = match kind {
+            EdgeKind::Next => "-->",
+            EdgeKind::SubStep => "-.->",
+        };
+        lines.push(format!("    s{from} {arrow} s{to}"));
+    }
+    for step in &order {
+        lines.push(format!(
+            "    style {} fill:{},stroke:{},stroke-width:2px",
+            node_id(step),
+            fill_color(&step.status),
+            stroke_color(&step.executor)
+        ));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Renders a plan as a Graphviz digraph with the same nodes, clusters and edges as
+/// [`format_plan_mermaid`].
+pub fn format_plan_dot(detail: &PlanDetail) -> String {
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        milestones,
+        ..
+    } = detail;
+    let mut lines = vec![
+        format!("digraph plan_{} {{", plan.id),
+        format!(
+            "    label=\"Plan {}: {} [{}]\";",
+            plan.id,
+            dot_text(first_line(&plan.title)),
+            plan.status
+        ),
+        "    labelloc=t;".to_string(),
+        "    rankdir=TB;".to_string(),
+        "    node [shape=box, style=\"rounded,filled\", penwidth=2, fontname=\"Helvetica\"];"
+            .to_string(),
+    ];
+    let mut order = Vec::new();
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        let indent = if milestone.is_some() { "        " } else { "    " };
+        if let Some(milestone) = milestone {
+            lines.push(format!("    subgraph cluster_m{} {{", milestone.id));
+            lines.push(format!(
+                "        label=\"Milestone: {} [{}]\";",
+                dot_text(first_line(&milestone.title)),
+                milestone.status
+            ));
+        }
+        for (step, _) in entries {
+            let label = step_label_lines(step, goals.get(&step.id))
+                .iter()
+                .map(|line| dot_text(line))
+                .collect::<Vec<_>>()
+                .join("\\l");
+            lines.push(format!(
+                "{indent}{} [label=\"{label}\\l\", fillcolor=\"{}\", color=\"{}\"];",
+                node_id(step),
+                fill_color(&step.status),
+                stroke_color(&step.executor)
+            ));
+            order.push(step);
+        }
+        if milestone.is_some() {
+            lines.push("    }".to_string());
+        }
+    }
+    for (from, to, kind) in step_edges(&order) {
+        match kind {
+            EdgeKind::Next => lines.push(format!("    s{from} -> s{to};")),
+            EdgeKind::SubStep => lines.push(format!("    s{from} -> s{to} [style=dashed];")),
+        }
+    }
+    lines.push("}".to_string());
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+const HTML_STYLE: &str = "\
+body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;margin:2rem auto;\
+max-width:960px;padding:0 1rem;color:#1f2328;line-height:1.5}\
+h1{margin-bottom:.25rem}h2{border-bottom:1px solid #d0d7de;padding-bottom:.25rem}\
+.meta{color:#656d76;font-size:.875rem}\
+.badge{display:inline-block;border-radius:1em;padding:0 .6em;font-size:.75rem;\
+font-weight:600;vertical-align:middle}\
+.badge.done{background:#d4edda;color:#155724}.badge.todo{background:#fff3cd;color:#533f03}\
+.progress{background:#eaeef2;border-radius:4px;height:8px;overflow:hidden;margin:.25rem 0}\
+.progress>span{display:block;height:100%;background:#2da44e}\
+.text{white-space:pre-wrap}\
+.comment{border-left:3px solid #d0d7de;background:#f6f8fa;margin:.5rem 0;padding:.25rem .75rem;\
+white-space:pre-wrap}\
+details{border:1px solid #d0d7de;border-radius:6px;margin:.5rem 0;padding:.25rem .75rem}\
+details details{margin-left:1rem}summary{cursor:pointer}\
+ul.goals,ul.criteria{list-style:none;padding-left:0}ul.goals li,ul.criteria li{margin:.25rem 0}";
+
+fn html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_badge(status: &str) -> String {
+    format!("<span class=\"badge {0}\">{0}</span>", html_text(status))
+}
+
+fn html_progress(done: usize, total: usize) -> String {
+    let percent = (done * 100).checked_div(total).unwrap_or(0);
+    format!(
+        "<div class=\"progress\" title=\"{done}/{total}\"><span style=\"width:{percent}%\">\
+         </span></div>"
+    )
+}
+
+fn html_comment(out: &mut String, comment: Option<&str>) {
+    if let Some(comment) = comment.filter(|text| !text.trim().is_empty()) {
+        out.push_str(&format!("<div class=\"comment\">{}</div>\n", html_text(comment)));
+    }
+}
+
+fn html_step(out: &mut String, step: &step::Model, goals: Option<&Vec<goal::Model>>) {
+    let goals = goals.map(Vec::as_slice).unwrap_or_default();
+    let done = goals
+        .iter()
+        .filter(|goal| goal.status == GoalStatus::Done.as_str())
+        .count();
+    let open = if step.status == StepStatus::Done.as_str() {
+        ""
+    } else {
+        " open"
+    };
+    let mut meta = format!("step id {} · {}", step.id, html_text(&step.executor));
+    if !goals.is_empty() {
+        meta.push_str(&format!(" · goals {done}/{}", goals.len()));
+    }
+    if let Some(priority) = step.priority.as_deref() {
+        meta.push_str(&format!(" · {}", html_text(priority)));
+    }
+    if let Some(due) = step.due {
+        meta.push_str(&format!(" · due {}", format_due(due, &step.status)));
+    }
+    out.push_str(&format!(
+        "<details class=\"step\"{open}><summary>{} {} <span class=\"meta\">{meta}</span>\
+         </summary>\n",
+        html_badge(&step.status),
+        html_text(first_line(&step.content))
+    ));
+    if step.content.trim().lines().nth(1).is_some() {
+        out.push_str(&format!("<div class=\"text\">{}</div>\n", html_text(&step.content)));
+    }
+    out.push_str(&format!(
+        "<p class=\"meta\">Created {} · Updated {}</p>\n",
+        format_datetime(step.created_at),
+        format_datetime(step.updated_at)
+    ));
+    html_comment(out, step.comment.as_deref());
+    if !goals.is_empty() {
+        out.push_str(&html_progress(done, goals.len()));
+        out.push_str("\n<ul class=\"goals\">\n");
+        for goal in goals {
+            out.push_str(&format!(
+                "<li>{} {} <span class=\"meta\">goal id {} · updated {}</span>\n",
+                html_badge(&goal.status),
+                html_text(&goal.content),
+                goal.id,
+                format_datetime(goal.updated_at)
+            ));
+            html_comment(out, goal.comment.as_deref());
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+}
+
+/// Renders a plan as a single self-contained HTML page: inline CSS, no scripts, and
+/// `<details>` elements so steps (with their sub-steps and goals) collapse without JavaScript.
+/// Pending steps start expanded and finished ones collapsed.
+pub fn format_plan_html(detail: &PlanDetail) -> String {
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        tags,
+        milestones,
+        criteria,
+    } = detail;
+    let title = html_text(first_line(&plan.title));
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>Plan {}: {title}</title>\n", plan.id));
+    out.push_str(&format!("<style>{HTML_STYLE}</style>\n</head>\n<body>\n"));
+    out.push_str(&format!("<h1>{title} {}</h1>\n", html_badge(&plan.status)));
+    let mut meta = format!(
+        "Plan ID {} · Created {} · Updated {}",
+        plan.id,
+        format_datetime(plan.created_at),
+        format_datetime(plan.updated_at)
+    );
+    if let Some(priority) = plan.priority.as_deref() {
+        meta.push_str(&format!(" · Priority {}", html_text(priority)));
+    }
+    if let Some(due) = plan.due {
+        meta.push_str(&format!(" · Due {}", format_due(due, &plan.status)));
+    }
+    if !tags.is_empty() {
+        meta.push_str(&format!(" · Tags {}", html_text(&tags.join(", "))));
+    }
+    out.push_str(&format!("<p class=\"meta\">{meta}</p>\n"));
+    let (done, total) = plan_progress(detail);
+    out.push_str(&html_progress(done, total));
+    out.push_str(&format!("\n<p class=\"meta\">Steps done {done}/{total}</p>\n"));
+    out.push_str(&format!("<div class=\"text\">{}</div>\n", html_text(&plan.content)));
+    html_comment(&mut out, plan.comment.as_deref());
+
+    if !criteria.is_empty() {
+        let (met, total) = criteria_progress(criteria);
+        out.push_str(&format!("<h2>Acceptance Criteria ({met}/{total})</h2>\n"));
+        out.push_str("<ul class=\"criteria\">\n");
+        for item in criteria {
+            out.push_str(&format!(
+                "<li>{} {} <span class=\"meta\">criterion id {}</span></li>\n",
+                html_badge(&item.status),
+                html_text(&item.content),
+                item.id
+            ));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("<h2>Steps</h2>\n");
+    if steps.is_empty() {
+        out.push_str("<p class=\"meta\">No steps.</p>\n");
+    }
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        if let Some(milestone) = milestone {
+            let (done, total) = milestone_progress(steps, milestone.id);
+            out.push_str(&format!(
+                "<h3>Milestone: {} {} <span class=\"meta\">milestone id {} · steps {done}/{total}\
+                 </span></h3>\n",
+                html_text(first_line(&milestone.title)),
+                html_badge(&milestone.status),
+                milestone.id
+            ));
+            out.push_str(&html_progress(done, total));
+            out.push('\n');
+        } else if !milestones.is_empty() && !entries.is_empty() {
+            out.push_str("<h3>No milestone</h3>\n");
+        }
+        let mut open = 0;
+        for (step, depth) in entries {
+            while open > depth {
+                out.push_str("</details>\n");
+                open -= 1;
+            }
+            html_step(&mut out, step, goals.get(&step.id));
+            open += 1;
+        }
+        out.push_str(&"</details>\n".repeat(open));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+const TABLE_COLUMNS: [&str; 12] = [
+    "plan_id",
+    "plan_title",
+    "step_id",
+    "step_content",
+    "step_executor",
+    "step_status",
+    "goal_id",
+    "goal_content",
+    "goal_status",
+    "comment",
+    "created_at",
+    "updated_at",
+];
+
+/// Quotes a field the RFC 4180 way when it contains the delimiter, a quote or a line break.
+fn table_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn table_timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Renders one row per goal across `details`, with a header line. Steps without goals still get
+/// a row with empty goal columns; comment and timestamps belong to the goal, or to the step on
+/// such rows. `delimiter` is `,` for CSV and `\t` for TSV; rows end with CRLF as RFC 4180 asks.
+pub fn format_goal_table(details: &[PlanDetail], delimiter: char) -> String {
+    let mut rows = vec![TABLE_COLUMNS.iter().map(|column| column.to_string()).collect()];
+    for detail in details {
+        for step in &detail.steps {
+            let step_fields = [
+                detail.plan.id.to_string(),
+                detail.plan.title.clone(),
+                step.id.to_string(),
+                step.content.clone(),
+                step.executor.clone(),
+                step.status.clone(),
+            ];
+            let goals = detail.goals.get(&step.id).map(Vec::as_slice).unwrap_or_default();
+            if goals.is_empty() {
+                let mut row = step_fields.to_vec();
+                row.extend([
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    step.comment.clone().unwrap_or_default(),
+                    table_timestamp(step.created_at),
+                    table_timestamp(step.updated_at),
+                ]);
+                rows.push(row);
+            }
+            for goal in goals {
+                let mut row = step_fields.to_vec();
+                row.extend([
+                    goal.id.to_string(),
+                    goal.content.clone(),
+                    goal.status.clone(),
+                    goal.comment.clone().unwrap_or_default(),
+                    table_timestamp(goal.created_at),
+                    table_timestamp(goal.updated_at),
+                ]);
+                rows.push(row);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|value| table_field(value, delimiter))
+            .collect();
+        out.push_str(&fields.join(&delimiter.to_string()));
+        out.push_str("\r\n");
+    }
+    out
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::AppError;
+
+/// Repository state recorded when a step or goal is completed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub commit: String,
+    pub branch: Option<String>,
+    pub dirty: bool,
+}
+
+/// Reads HEAD, the current branch and the worktree state of the repository containing `dir`.
+/// Returns `None` when `dir` is not inside a git repository with at least one commit.
+pub fn capture_checkpoint(dir: &Path) -> Option<Checkpoint> {
+    let commit = run_git(dir, &["rev-parse", "--verify", "-q", "HEAD"]).ok()?;
+    let branch = run_git(dir, &["symbolic-ref", "--short", "-q", "HEAD"])
+        .ok()
+        .filter(|branch| !branch.is_empty());
+    let status = run_git(dir, &["status", "--porcelain"]).ok()?;
+    Some(Checkpoint {
+        commit,
+        branch,
+        dirty: !status.is_empty(),
+    })
+}
+
+pub const GOAL_TRAILER: &str = "Planpilot-Goal";
+pub const STEP_TRAILER: &str = "Planpilot-Step";
+
+/// Goal and step ids referenced by `Planpilot-Goal` / `Planpilot-Step` trailers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrailerIds {
+    pub goal_ids: Vec<i64>,
+    pub step_ids: Vec<i64>,
+    /// Trailer values that are not ids, as `<key>: <value>`.
+    pub invalid: Vec<String>,
+}
+
+impl TrailerIds {
+    pub fn is_empty(&self) -> bool {
+        self.goal_ids.is_empty() && self.step_ids.is_empty() && self.invalid.is_empty()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CommitTrailers {
+    pub commit: String,
+    pub ids: TrailerIds,
+}
+
+/// Parses `Key: value` trailer lines; keys match case-insensitively and a value may list several
+/// ids separated by commas or spaces.
+pub fn parse_trailer_ids(trailers: &str) -> TrailerIds {
+    let mut ids = TrailerIds::default();
+    for line in trailers.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        let target = if key.eq_ignore_ascii_case(GOAL_TRAILER) {
+            &mut ids.goal_ids
+        } else if key.eq_ignore_ascii_case(STEP_TRAILER) {
+            &mut ids.step_ids
+        } else {
+            continue;
+        };
+        for part in value.split([',', ' ']).filter(|part| !part.trim().is_empty()) {
+            let part = part.trim().trim_start_matches('#');
+            match part.parse::<i64>() {
+                Ok(id) if id > 0 => {
+                    if !target.contains(&id) {
+                        target.push(id);
+                    }
+                }
+                _ => ids.invalid.push(format!("{key}: {part}")),
+            }
+        }
+    }
+    ids
+}
+
+/// Lists commits reachable from HEAD (after `since` when given), oldest first, keeping those
+/// with Planpilot trailers.
+pub fn scan_commit_trailers(
+    dir: &Path,
+    since: Option<&str>,
+) -> Result<(usize, Vec<CommitTrailers>), AppError> {
+    let range = match since {
+        Some(rev) => format!("{rev}..HEAD"),
+        None => "HEAD".to_string(),
+    };
+    let log = run_git(
+        dir,
+        &["log", "--reverse", "--format=%H%x1f%(trailers:only,unfold)%x1e", &range],
+    )?;
+    let mut scanned = 0;
+    let mut commits = Vec::new();
+    for record in log.split('\x1e') {
+        let Some((commit, trailers)) = record.trim_start().split_once('\x1f') else {
+            continue;
+        };
+        scanned += 1;
+        let ids = parse_trailer_ids(trailers);
+        if !ids.is_empty() {
+            commits.push(CommitTrailers {
+                commit: commit.to_string(),
+                ids,
+            });
+        }
+    }
+    Ok((scanned, commits))
+}
+
+/// Extracts the trailers of a commit message file the way git itself parses them.
+pub fn message_trailer_ids(dir: &Path, message_file: &Path) -> Result<TrailerIds, AppError> {
+    let path = message_file.to_string_lossy();
+    let trailers = run_git(dir, &["interpret-trailers", "--parse", &path])?;
+    Ok(parse_trailer_ids(&trailers))
+}
+
+/// Resolves where git looks for the `commit-msg` hook, honouring `core.hooksPath`.
+pub fn commit_msg_hook_path(dir: &Path) -> Result<PathBuf, AppError> {
+    let path = PathBuf::from(run_git(dir, &["rev-parse", "--git-path", "hooks/commit-msg"])?);
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        dir.join(path)
+    })
+}
+
+pub fn diff(dir: &Path, from: &str, to: &str) -> Result<String, AppError> {
+    run_git(dir, &["diff", from, to])
+}
+
+pub fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<String, AppError> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::InvalidInput(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trailer_ids_reads_goal_and_step_trailers() {
+        let ids = parse_trailer_ids(
+            "Planpilot-Goal: 42, #43\nplanpilot-step: 17 17\nSigned-off-by: A <a@b>\n\
+             Planpilot-Goal: next",
+        );
+        assert_eq!(ids.goal_ids, vec![42, 43]);
+        assert_eq!(ids.step_ids, vec![17]);
+        assert_eq!(ids.invalid, vec!["Planpilot-Goal: next".to_string()]);
+        assert!(parse_trailer_ids("Reviewed-by: someone").is_empty());
+    }
+}
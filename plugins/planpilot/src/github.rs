@@ -0,0 +1,196 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::app::PlanDetail;
+use crate::error::AppError;
+use crate::import::{parse_task_list, ImportedPlan};
+use crate::model::{normalize_tag, StepExecutor, StepStatus};
+use crate::util::milestone_groups;
+
+/// The fields Planpilot reads from `gh issue view --json number,title,body,labels`; other
+/// fields in the print are ignored.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GithubIssue {
+    #[serde(default)]
+    pub number: Option<i64>,
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<IssueLabel>,
+}
+
+/// `gh` prints labels as objects; plain strings are accepted too.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum IssueLabel {
+    Named { name: String },
+    Plain(String),
+}
+
+impl IssueLabel {
+    fn name(&self) -> &str {
+        match self {
+            Self::Named { name } | Self::Plain(name) => name,
+        }
+    }
+}
+
+impl GithubIssue {
+    pub fn source(&self) -> String {
+        match self.number {
+            Some(number) => format!("GitHub issue #{number}"),
+            None => "GitHub issue".to_string(),
+        }
+    }
+}
+
+/// Reads one issue (`gh issue view --json`) or an array of them (`gh issue list --json`).
+pub fn parse_issues(text: &str) -> Result<Vec<GithubIssue>, AppError> {
+    let value: Value = serde_json::from_str(text)?;
+    let issues = match value {
+        Value::Array(items) => items
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<GithubIssue>, _>>()?,
+        value => vec![serde_json::from_value(value)?],
+    };
+    if issues.is_empty() {
+        return Err(AppError::InvalidInput("no issues found".to_string()));
+    }
+    Ok(issues)
+}
+
+/// Maps an issue to a plan: the title is kept, the body's task list becomes steps and goals and
+/// labels become tags (spaces turn into `-`). Returns the labels that are not valid tag names.
+pub fn issue_to_plan(issue: &GithubIssue) -> (ImportedPlan, Vec<String>) {
+    let mut plan = parse_task_list(issue.body.as_deref().unwrap_or_default(), &issue.source());
+    plan.title = issue.title.trim().to_string();
+    let mut skipped = Vec::new();
+    for label in &issue.labels {
+        let name = label.name().split_whitespace().collect::<Vec<_>>().join("-");
+        match normalize_tag(&name) {
+            Some(tag) if !plan.tags.contains(&tag) => plan.tags.push(tag),
+            Some(_) => {}
+            None => skipped.push(label.name().to_string()),
+        }
+    }
+    (plan, skipped)
+}
+
+/// Renders the plan as an issue body for `gh issue create --body-file`: the plan content, then
+/// acceptance criteria and the steps as task lists with goals nested under their step. Sub-steps
+/// and non-`ai` steps carry an `*(exec: ...)*` suffix so `plan import-github` can tell them from
+/// goals.
+pub fn format_issue_body(detail: &PlanDetail) -> String {
+    fn checkbox(status: &str) -> &'static str {
+        if status == StepStatus::Done.as_str() {
+            "x"
+        } else {
+            " "
+        }
+    }
+
+    fn push_item(lines: &mut Vec<String>, indent: usize, status: &str, text: &str, suffix: &str) {
+        let mut rows = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let first = rows.next().unwrap_or("(empty)");
+        lines.push(format!(
+            "{}- [{}] {first}{suffix}",
+            " ".repeat(indent),
+            checkbox(status)
+        ));
+        for row in rows {
+            lines.push(format!("{}{row}", " ".repeat(indent + 2)));
+        }
+    }
+
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        milestones,
+        criteria,
+        ..
+    } = detail;
+    let mut lines = Vec::new();
+    let content = plan.content.trim();
+    if !content.is_empty() {
+        lines.push(content.to_string());
+        lines.push(String::new());
+    }
+    if !criteria.is_empty() {
+        lines.push("### Acceptance Criteria".to_string());
+        lines.push(String::new());
+        for item in criteria {
+            push_item(&mut lines, 0, &item.status, &item.content, "");
+        }
+        lines.push(String::new());
+    }
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        match milestone {
+            Some(milestone) => lines.push(format!("### Milestone: {}", milestone.title.trim())),
+            None if !milestones.is_empty() => lines.push("### No milestone".to_string()),
+            None => {}
+        }
+        if milestone.is_some() || !milestones.is_empty() {
+            lines.push(String::new());
+        }
+        for (step, depth) in &entries {
+            let base = depth * 2;
+            let suffix = if *depth > 0 || step.executor != StepExecutor::AI {
+                format!(" *(exec: {})*", step.executor)
+            } else {
+                String::new()
+            };
+            push_item(&mut lines, base, &step.status, &step.content, &suffix);
+            for goal in goals.get(&step.id).into_iter().flatten() {
+                push_item(&mut lines, base + 2, &goal.status, &goal.content, "");
+            }
+        }
+        if !entries.is_empty() {
+            lines.push(String::new());
+        }
+    }
+    let mut body = lines.join("\n").trim_end().to_string();
+    body.push('\n');
+    body
+}
+
+/// Renders the plan in the `gh issue view --json title,body,labels` shape, so it can be read
+/// back with `plan import-github` or fed to `gh issue create` by a script.
+pub fn format_issue_json(detail: &PlanDetail) -> String {
+    let labels: Vec<Value> = detail.tags.iter().map(|tag| json!({ "name": tag })).collect();
+    let issue = json!({
+        "title": detail.plan.title,
+        "body": format_issue_body(detail),
+        "labels": labels,
+    });
+    let mut text = serde_json::to_string_pretty(&issue).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issue_to_plan_maps_task_list_and_labels() {
+        let issues = parse_issues(
+            r#"{"number": 7, "title": "Add login", "state": "OPEN",
+                "body": "Users need to log in.\r\n\r\n- [ ] Backend\r\n  - [x] Schema\r\n- [x] UI",
+                "labels": [{"name": "good first issue"}, {"name": "Auth"}, "🚀"]}"#,
+        )
+        .expect("parse");
+        let (plan, skipped) = issue_to_plan(&issues[0]);
+        assert_eq!(plan.title, "Add login");
+        assert_eq!(plan.content, "Users need to log in.");
+        assert_eq!(plan.tags, vec!["good-first-issue".to_string(), "auth".to_string()]);
+        assert_eq!(skipped, vec!["🚀".to_string()]);
+        assert_eq!(plan.counts(), (2, 1, 1, 1));
+
+        let (empty, _) = issue_to_plan(&parse_issues(r#"[{"title": "Idea"}]"#).expect("parse")[0]);
+        assert!(empty.steps.is_empty());
+        assert_eq!(empty.content, "Imported from GitHub issue");
+    }
+}
@@ -5,6 +5,8 @@ use std::io::{self, Read};
 use std::path::PathBuf;
 use std::process::Command;
 
+use crate::model::StepExecutor;
+
 #[derive(Debug, Deserialize)]
 struct HookInput {
     session_id: Option<String>,
@@ -67,56 +69,70 @@ pub fn run_stop_hook() {
         return;
     }
 
-    let executor = extract_executor(stripped).unwrap_or_default();
-    if executor != "ai" {
-        print_approve();
+    if stripped.starts_with("Unmet acceptance criteria") {
+        let message = format!(
+            "Planpilot (auto):\nAll steps are done, but the plan still has unmet acceptance criteria. Verify each one and mark it met with `planpilot criterion check <id>`; if one cannot be met yet, add the steps it needs. Do not ask for confirmation; proceed and report results.\n\n{stripped}"
+        );
+        print_block(&message);
         return;
     }
 
+    let executor =
+        extract_field(stripped, "Executor").and_then(|value| StepExecutor::parse(&value));
+    let executor = match executor {
+        Some(executor) if executor.is_ai() => executor,
+        _ => {
+            print_approve();
+            return;
+        }
+    };
+
+    let delegation = match executor.agent() {
+        Some(agent) => format!(
+            " Delegate this step to the `{agent}` sub-agent and review its result before marking goals done."
+        ),
+        None => String::new(),
+    };
+    let milestone = match extract_field(stripped, "Milestone") {
+        Some(milestone) => format!(" Current milestone: {milestone}."),
+        None => String::new(),
+    };
     let message = format!(
-        "Planpilot (auto):\nBefore acting, think through the next step and its goals. Record implementation details using Planpilot comments (plan/step/goal --comment or comment commands). Continue with the next step (executor: ai). Do not ask for confirmation; proceed and report results.\n\n{stripped}"
+        "Planpilot (auto):\nBefore acting, think through the next step and its goals. Record implementation details using Planpilot comments (plan/step/goal --comment or comment commands). Continue with the next step (executor: {executor}).{milestone}{delegation} Do not ask for confirmation; proceed and report results.\n\n{stripped}"
     );
     print_block(&message);
 }
 
-pub fn run_pretooluse_hook() {
-    let payload = match read_stdin() {
-        Ok(payload) => payload,
-        Err(_) => {
-            return;
-        }
-    };
+/// Rewrites planpilot Bash commands to carry the session, and returns the session id and cwd of
+/// every tool call so the caller can record a heartbeat.
+pub fn run_pretooluse_hook() -> Option<(String, String)> {
+    let payload = read_stdin().ok()?;
 
     if payload.trim().is_empty() {
-        return;
+        return None;
     }
 
-    let input: HookInput = match serde_json::from_str(&payload) {
-        Ok(input) => input,
-        Err(_) => {
-            return;
-        }
-    };
+    let input: HookInput = serde_json::from_str(&payload).ok()?;
+
+    let session_id = input.session_id.unwrap_or_default();
+    let cwd = input.cwd.unwrap_or_default();
+    if session_id.trim().is_empty() || cwd.trim().is_empty() {
+        return None;
+    }
 
     if input.tool_name.as_deref() != Some("Bash") {
-        return;
+        return Some((session_id, cwd));
     }
 
     let command = match input.tool_input.and_then(|tool| tool.command) {
         Some(command) if !command.trim().is_empty() => command,
         _ => {
-            return;
+            return Some((session_id, cwd));
         }
     };
 
     if !command_matches(&command) {
-        return;
-    }
-
-    let session_id = input.session_id.unwrap_or_default();
-    let cwd = input.cwd.unwrap_or_default();
-    if session_id.trim().is_empty() || cwd.trim().is_empty() {
-        return;
+        return Some((session_id, cwd));
     }
 
     let permission_mode = input.permission_mode.unwrap_or_else(|| "allow".to_string());
@@ -135,7 +151,8 @@ pub fn run_pretooluse_hook() {
             "updatedInput": {"command": updated_command},
         }
     });
-    print!("{}", output.to_string());
+    print!("{}", output);
+    Some((session_id, cwd))
 }
 
 fn read_stdin() -> io::Result<String> {
@@ -168,10 +185,14 @@ fn planpilot_show_next(cwd: &str, session_id: &str) -> Option<String> {
     Some(combined)
 }
 
-fn extract_executor(output: &str) -> Option<String> {
+fn extract_field(output: &str, label: &str) -> Option<String> {
     output
         .lines()
-        .find_map(|line| line.strip_prefix("Executor: ").map(str::trim))
+        .find_map(|line| {
+            line.strip_prefix(label)
+                .and_then(|rest| rest.strip_prefix(": "))
+                .map(str::trim)
+        })
         .filter(|value| !value.is_empty())
         .map(|value| value.to_string())
 }
@@ -306,13 +327,13 @@ fn is_separator(byte: u8) -> bool {
 }
 
 fn print_approve() {
-    print!("{}", json!({"decision": "approve"}).to_string());
+    print!("{}", json!({"decision": "approve"}));
 }
 
 fn print_block(message: &str) {
     print!(
         "{}",
-        json!({"decision": "block", "reason": message}).to_string()
+        json!({"decision": "block", "reason": message})
     );
 }
 
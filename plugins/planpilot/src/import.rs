@@ -0,0 +1,489 @@
+use chrono::NaiveDate;
+
+use crate::error::AppError;
+use crate::model::{normalize_tag, parse_due_date, Priority, StepExecutor};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportedGoal {
+    pub content: String,
+    pub done: bool,
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedStep {
+    pub content: String,
+    pub executor: StepExecutor,
+    pub done: bool,
+    pub comment: Option<String>,
+    /// Index into [`ImportedPlan::milestones`]; sub-steps follow their top-level step.
+    pub milestone: Option<usize>,
+    pub goals: Vec<ImportedGoal>,
+    pub children: Vec<ImportedStep>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportedPlan {
+    pub title: String,
+    pub content: String,
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDate>,
+    /// Normalized tag names.
+    pub tags: Vec<String>,
+    pub milestones: Vec<String>,
+    /// Acceptance criteria as `(content, met)`.
+    pub criteria: Vec<(String, bool)>,
+    pub steps: Vec<ImportedStep>,
+}
+
+impl ImportedPlan {
+    /// Returns `(steps, done steps, goals, done goals)`, sub-steps included.
+    pub fn counts(&self) -> (usize, usize, usize, usize) {
+        fn walk(steps: &[ImportedStep], counts: &mut (usize, usize, usize, usize)) {
+            for step in steps {
+                counts.0 += 1;
+                counts.1 += usize::from(step.done);
+                counts.2 += step.goals.len();
+                counts.3 += step.goals.iter().filter(|goal| goal.done).count();
+                walk(&step.children, counts);
+            }
+        }
+        let mut counts = (0, 0, 0, 0);
+        walk(&self.steps, &mut counts);
+        counts
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Body,
+    Content,
+    Criteria,
+}
+
+/// A parsed step before nesting: `parent` indexes into the flat list.
+struct FlatStep {
+    step: ImportedStep,
+    parent: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum LastItem {
+    Step(usize, usize),
+    Goal(usize, usize, usize),
+}
+
+/// Parses a Markdown task list into a plan. Top-level `- [ ]` items become steps and nested
+/// items become goals of the step above them; `[x]` marks an item done. The first heading names
+/// the plan and the text before the first item becomes its content (`fallback_title` fills in
+/// either when missing). Files written by `format_plan_markdown` round-trip: their metadata,
+/// `Plan Content` quote, acceptance criteria, milestones, sub-steps and comments are read back.
+pub fn parse_markdown_plan(text: &str, fallback_title: &str) -> Result<ImportedPlan, AppError> {
+    let plan = parse_task_list(text, fallback_title);
+    if plan.steps.is_empty() && plan.criteria.is_empty() {
+        return Err(AppError::InvalidInput(
+            "no task list items (`- [ ]` / `- [x]`) found".to_string(),
+        ));
+    }
+    Ok(plan)
+}
+
+/// Like [`parse_markdown_plan`], but text without task items yields a plan with no steps.
+pub fn parse_task_list(text: &str, fallback_title: &str) -> ImportedPlan {
+    let mut plan = ImportedPlan::default();
+    let mut plan_title: Option<String> = None;
+    let mut first_heading: Option<String> = None;
+    let mut content_lines: Vec<String> = Vec::new();
+    let mut preamble: Vec<String> = Vec::new();
+    let mut section = Section::Body;
+    let mut milestone: Option<usize> = None;
+    let mut flat: Vec<FlatStep> = Vec::new();
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut top_indent: Option<usize> = None;
+    let mut last: Option<LastItem> = None;
+
+    for raw in text.lines() {
+        let expanded = raw.replace('\t', "    ");
+        let trimmed = expanded.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let indent = expanded.len() - expanded.trim_start().len();
+
+        if let Some(heading) = heading_text(trimmed) {
+            last = None;
+            section = Section::Body;
+            if let Some(title) = heading.strip_prefix("Plan: ") {
+                plan_title.get_or_insert_with(|| title.trim().to_string());
+            } else if heading == "Plan Content" {
+                section = Section::Content;
+            } else if heading.starts_with("Acceptance Criteria") {
+                section = Section::Criteria;
+            } else if let Some(title) = heading.strip_prefix("Milestone: ") {
+                plan.milestones.push(title.trim().to_string());
+                milestone = Some(plan.milestones.len() - 1);
+            } else if heading == "No milestone" {
+                milestone = None;
+            } else if heading != "Steps" && first_heading.is_none() {
+                first_heading = Some(heading.to_string());
+            }
+            continue;
+        }
+
+        if section == Section::Content {
+            if let Some(quoted) = trimmed.strip_prefix('>') {
+                content_lines.push(quoted.strip_prefix(' ').unwrap_or(quoted).to_string());
+            }
+            continue;
+        }
+
+        if let Some((done, text)) = task_item(trimmed) {
+            if section == Section::Criteria {
+                plan.criteria.push((strip_id_suffix(text).to_string(), done));
+                continue;
+            }
+            let (content, executor) = match step_metadata(text) {
+                Some((content, executor)) => (content, Some(executor)),
+                None => (strip_id_suffix(text).to_string(), None),
+            };
+            while stack.last().is_some_and(|(open, _)| *open >= indent) {
+                stack.pop();
+            }
+            let top = *top_indent.get_or_insert(indent);
+            if indent <= top || executor.is_some() || stack.is_empty() {
+                let parent = if indent <= top {
+                    stack.clear();
+                    None
+                } else {
+                    stack.last().map(|(_, idx)| *idx)
+                };
+                flat.push(FlatStep {
+                    step: ImportedStep {
+                        content,
+                        executor: executor.unwrap_or_else(StepExecutor::ai),
+                        done,
+                        comment: None,
+                        milestone: if parent.is_none() { milestone } else { None },
+                        goals: Vec::new(),
+                        children: Vec::new(),
+                    },
+                    parent,
+                });
+                let idx = flat.len() - 1;
+                stack.push((indent, idx));
+                last = Some(LastItem::Step(idx, indent));
+            } else {
+                let (_, step_idx) = *stack.last().expect("enclosing step");
+                let goals = &mut flat[step_idx].step.goals;
+                goals.push(ImportedGoal {
+                    content,
+                    done,
+                    comment: None,
+                });
+                last = Some(LastItem::Goal(step_idx, goals.len() - 1, indent));
+            }
+            continue;
+        }
+
+        if let Some(bullet) = bullet_text(trimmed) {
+            match last {
+                Some(LastItem::Step(idx, _) | LastItem::Goal(idx, _, _)) => {
+                    if let Some(comment) = bullet.strip_prefix("Comment: ") {
+                        flat[idx].step.comment = Some(comment.trim().to_string());
+                    }
+                }
+                None => read_plan_field(&mut plan, bullet),
+            }
+            continue;
+        }
+
+        match last {
+            Some(LastItem::Goal(step_idx, goal_idx, item_indent)) if indent > item_indent => {
+                let goal = &mut flat[step_idx].step.goals[goal_idx];
+                match trimmed.strip_prefix("Comment: ") {
+                    Some(comment) => goal.comment = Some(comment.trim().to_string()),
+                    None => append_line(&mut goal.content, trimmed),
+                }
+            }
+            Some(LastItem::Step(idx, item_indent)) if indent > item_indent => {
+                append_line(&mut flat[idx].step.content, trimmed);
+            }
+            _ if flat.is_empty() && trimmed != "*No steps*" => preamble.push(trimmed.to_string()),
+            _ => {}
+        }
+    }
+
+    plan.title = plan_title
+        .or(first_heading)
+        .unwrap_or_else(|| fallback_title.to_string());
+    let content = if content_lines.is_empty() {
+        preamble.join("\n")
+    } else {
+        content_lines.join("\n")
+    };
+    plan.content = if content.trim().is_empty() {
+        format!("Imported from {fallback_title}")
+    } else {
+        content.trim().to_string()
+    };
+    plan.steps = nest_steps(flat);
+    plan
+}
+
+/// Turns the flat list back into a tree; parents always precede their children.
+fn nest_steps(flat: Vec<FlatStep>) -> Vec<ImportedStep> {
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); flat.len()];
+    let mut roots = Vec::new();
+    for (idx, item) in flat.iter().enumerate() {
+        match item.parent {
+            Some(parent) => children[parent].push(idx),
+            None => roots.push(idx),
+        }
+    }
+    let mut slots: Vec<Option<ImportedStep>> =
+        flat.into_iter().map(|item| Some(item.step)).collect();
+    fn build(
+        idx: usize,
+        slots: &mut [Option<ImportedStep>],
+        children: &[Vec<usize>],
+    ) -> ImportedStep {
+        let mut step = slots[idx].take().expect("step built once");
+        step.children = children[idx]
+            .iter()
+            .map(|child| build(*child, slots, children))
+            .collect();
+        step
+    }
+    roots
+        .into_iter()
+        .map(|idx| build(idx, &mut slots, &children))
+        .collect()
+}
+
+fn append_line(target: &mut String, line: &str) {
+    target.push('\n');
+    target.push_str(line);
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches('#');
+    if rest.len() == line.len() || !rest.starts_with(' ') {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+fn bullet_text(line: &str) -> Option<&str> {
+    if let Some(rest) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(rest.trim());
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..]
+        .strip_prefix(". ")
+        .or_else(|| line[digits..].strip_prefix(") "))
+        .map(str::trim)
+}
+
+/// Splits `- [ ] text` / `- [x] text` into `(done, text)`.
+fn task_item(line: &str) -> Option<(bool, &str)> {
+    let rest = bullet_text(line)?;
+    let (done, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, text)
+    } else {
+        return None;
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some((done, text))
+}
+
+/// Drops the ` *(id: N)*` suffix `format_plan_markdown` appends to goals and criteria.
+fn strip_id_suffix(text: &str) -> &str {
+    match text.rfind(" *(id: ") {
+        Some(pos) if text.ends_with(")*") => text[..pos].trim(),
+        _ => text,
+    }
+}
+
+/// Reads `**content** *(id: N, exec: E, ...)*`, the step line of `format_plan_markdown`, and the
+/// shorter `content *(exec: E)*` used for sub-steps in GitHub issue bodies.
+fn step_metadata(text: &str) -> Option<(String, StepExecutor)> {
+    let pos = text.rfind(" *(")?;
+    let meta = text[pos..].trim().strip_prefix("*(")?.strip_suffix(")*")?;
+    let executor = meta
+        .split(", ")
+        .find_map(|field| field.strip_prefix("exec: "))
+        .and_then(StepExecutor::parse)?;
+    let content = text[..pos].trim();
+    let content = content
+        .strip_prefix("**")
+        .and_then(|inner| inner.strip_suffix("**"))
+        .unwrap_or(content);
+    Some((content.to_string(), executor))
+}
+
+/// Reads the `- **Priority:** `p1``, `- **Due:** 2025-01-31` and `- **Tags:** `a` `b`` plan
+/// fields.
+fn read_plan_field(plan: &mut ImportedPlan, bullet: &str) {
+    let value = |label: &str| {
+        bullet
+            .strip_prefix(label)
+            .map(|rest| rest.trim().trim_matches('`').to_string())
+    };
+    if let Some(priority) = value("**Priority:**") {
+        plan.priority = match priority.as_str() {
+            "p0" => Some(Priority::P0),
+            "p1" => Some(Priority::P1),
+            "p2" => Some(Priority::P2),
+            "p3" => Some(Priority::P3),
+            _ => None,
+        };
+    } else if let Some(due) = value("**Due:**") {
+        plan.due = parse_due_date(due.split_whitespace().next().unwrap_or_default());
+    } else if let Some(tags) = bullet.strip_prefix("**Tags:**") {
+        plan.tags = tags
+            .split_whitespace()
+            .filter_map(|tag| normalize_tag(tag.trim_matches('`')))
+            .collect();
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_.,:/@%+=".contains(ch));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Renders the import as the equivalent `plan add-tree` call, followed by `step add-tree` calls
+/// for sub-steps and comment lines for what add-tree cannot express (done state, milestones,
+/// acceptance criteria, comments).
+pub fn format_add_tree(plan: &ImportedPlan) -> String {
+    fn step_args(step: &ImportedStep) -> String {
+        let mut args = shell_quote(&step.content);
+        if !step.executor.is_ai() || step.executor.agent().is_some() {
+            args.push_str(&format!(" --executor {}", shell_quote(step.executor.as_str())));
+        }
+        for goal in &step.goals {
+            args.push_str(&format!(" --goal {}", shell_quote(&goal.content)));
+        }
+        args
+    }
+
+    fn notes(step: &ImportedStep, path: &str, out: &mut Vec<String>) {
+        if step.done {
+            out.push(format!("# then mark step {path} done"));
+        }
+        for (idx, goal) in step.goals.iter().enumerate() {
+            if goal.done {
+                out.push(format!("# then mark goal {} of step {path} done", idx + 1));
+            }
+            if let Some(comment) = &goal.comment {
+                out.push(format!("# goal {} of step {path} comment: {comment}", idx + 1));
+            }
+        }
+        if let Some(comment) = &step.comment {
+            out.push(format!("# step {path} comment: {comment}"));
+        }
+    }
+
+    fn sub_steps(step: &ImportedStep, path: &str, lines: &mut Vec<String>, out: &mut Vec<String>) {
+        for (idx, child) in step.children.iter().enumerate() {
+            let child_path = format!("{path}.{}", idx + 1);
+            lines.push(format!(
+                "planpilot step add-tree <plan_id> {} --parent <step {path} id>",
+                step_args(child)
+            ));
+            notes(child, &child_path, out);
+            sub_steps(child, &child_path, lines, out);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut command = format!(
+        "planpilot plan add-tree {} {}",
+        shell_quote(&plan.title),
+        shell_quote(&plan.content)
+    );
+    for step in &plan.steps {
+        command.push_str(&format!(" \\\n  --step {}", step_args(step)));
+    }
+    lines.push(command);
+    let mut out = Vec::new();
+    for (idx, step) in plan.steps.iter().enumerate() {
+        let path = (idx + 1).to_string();
+        notes(step, &path, &mut out);
+        sub_steps(step, &path, &mut lines, &mut out);
+    }
+    for (idx, title) in plan.milestones.iter().enumerate() {
+        let members: Vec<String> = plan
+            .steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| step.milestone == Some(idx))
+            .map(|(pos, _)| (pos + 1).to_string())
+            .collect();
+        out.push(format!(
+            "# milestone {}: {title} (steps {})",
+            idx + 1,
+            if members.is_empty() {
+                "none".to_string()
+            } else {
+                members.join(", ")
+            }
+        ));
+    }
+    if !plan.tags.is_empty() {
+        out.push(format!("# then tag the plan: {}", plan.tags.join(" ")));
+    }
+    for (content, met) in &plan.criteria {
+        let state = if *met { "met" } else { "unmet" };
+        out.push(format!("# acceptance criterion ({state}): {content}"));
+    }
+    lines.extend(out);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_markdown_plan_reads_github_checklists() {
+        let plan = parse_markdown_plan(
+            "# Launch\n\nShip the beta.\n\n- [x] Write docs\n  - [x] API\n  - [ ] CLI\n\
+             * [ ] Release\n    1. [ ] Tag\n- not a task\n",
+            "TODO.md",
+        )
+        .expect("parse");
+        assert_eq!(plan.title, "Launch");
+        assert_eq!(plan.content, "Ship the beta.");
+        assert_eq!(plan.steps.len(), 2);
+        assert!(plan.steps[0].done);
+        assert_eq!(plan.steps[0].goals.len(), 2);
+        assert!(plan.steps[0].goals[0].done && !plan.steps[0].goals[1].done);
+        assert_eq!(plan.steps[1].goals[0].content, "Tag");
+        assert_eq!(plan.counts(), (2, 1, 3, 1));
+
+        let err = parse_markdown_plan("# Nothing\n\n- plain\n", "TODO.md").unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+}
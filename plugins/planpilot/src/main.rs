@@ -3,40 +3,83 @@ mod cli;
 mod db;
 mod entities;
 mod error;
+mod events;
+mod export;
+mod git;
+mod github;
 mod hooks;
+mod import;
 mod model;
+mod serve;
+mod tui;
 mod util;
+mod watch;
+mod webhooks;
 
 use std::collections::HashSet;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 
-use clap::{CommandFactory, FromArgMatches};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
 use clap::parser::ValueSource;
 use serde::Deserialize;
+use tokio::net::TcpListener;
 
-use crate::app::{App, StatusChanges, StepInput};
+use crate::app::{
+    plan_progress, sort_plan_details_by_progress, App, ArchivedPlan, DeliveryOutcome,
+    SessionSummary, StatusChanges, StepInput, TagTarget, TagUsage, TrailerOutcome,
+    DEFAULT_SESSION_TTL_MINUTES,
+};
 use crate::cli::{
-    Cli, Command, GoalAdd, GoalCommand, GoalComment, GoalDone, GoalList, GoalRemove, GoalShow,
-    GoalStatusArg, GoalUpdate, HookCommand, PlanActivate, PlanAdd, PlanAddTree, PlanCommand,
-    PlanComment, PlanDone, PlanExport, PlanList, PlanRemove, PlanSearch,
-    PlanSearchFieldArg, PlanSearchModeArg, PlanShow, PlanStatusArg, PlanUpdate, StepAdd,
-    StepAddTree, StepCommand, StepComment, StepDone, StepExecutorArg, StepList, StepMove,
-    StepOrderArg, StepRemove, StepShow, StepSpec, StepStatusArg, StepUpdate,
+    Cli, Command, CriterionAdd, CriterionCommand, CriterionList, CriterionRemove, EventCommand,
+    EventKindArg, GoalAdd,
+    GoalCommand, GoalComment, GoalDone, GoalList, GoalMove, GoalRemove, GoalShow, GoalStatusArg,
+    GoalUpdate, GitCheckMsg, GitCommand, GitInstallHook, GitScan, HookCommand, MilestoneAdd,
+    MilestoneAssign,
+    MilestoneCommand, MilestoneDone, MilestoneList, PlanActivate, PlanAdd, PlanAddTree,
+    PlanArchive, PlanClone, PlanCommand, PlanComment, PlanDone, PlanExport, PlanExportFormatArg,
+    PlanImportGithub, PlanImportMd, PlanList, PlanPush,
+    PlanRemove, PlanSearch, PlanSortArg, PlanTag, PlanUnarchive, PlanUntag, PriorityArg,
+    TagCommand, TagModeArg,
+    PlanSearchFieldArg, PlanSearchModeArg, PlanShow, PlanStatusArg, PlanUpdate, SessionCommand,
+    SessionList, SessionPrune, StepAdd,
+    StepAddTree, StepClaim, StepCommand, StepComment, StepDiff, StepDone, StepList, StepMerge,
+    StepMove,
+    StepOrderArg, StepRelease, StepRemove, StepShow, StepShowNext, StepSpec, StepSplit,
+    Serve, StepStatusArg, Watch, WebhookCommand, WebhookDeliver, StepTag, StepUntag, StepUpdate,
+    TableExport,
+    TableFormatArg,
 };
 use crate::error::AppError;
+use crate::events::{EventRecorder, Pending};
+use crate::export::{format_goal_table, format_plan_dot, format_plan_html, format_plan_mermaid};
+use crate::github::{format_issue_body, format_issue_json, issue_to_plan, parse_issues};
+use crate::import::{format_add_tree, parse_markdown_plan, ImportedPlan};
 use crate::model::{
-    GoalChanges, GoalQuery, GoalStatus, PlanChanges, PlanInput, PlanStatus, StepChanges,
-    StepExecutor, StepOrder, StepQuery, StepStatus,
+    is_overdue, parse_due_date, CriterionStatus, EventKind, GoalChanges, GoalQuery, GoalStatus,
+    PlanChanges, PlanInput, PlanOrder, PlanStatus, Priority, StepChanges, StepExecutor, StepOrder,
+    StepQuery, StepSchedule, StepStatus, TagMatch,
 };
+use crate::serve::ServeOptions;
+use crate::tui::{Action, Page, Row, Screen};
 use crate::util::{
-    format_goal_detail, format_plan_detail, format_plan_markdown, format_step_detail,
+    format_datetime, format_goal_detail, format_plan_detail, format_plan_markdown,
+    format_step_detail,
 };
+use crate::watch::Snapshot;
 
 const CWD_FLAG: &str = "--cwd";
 const SESSION_ID_FLAG: &str = "--session-id";
 const CLAUDE_PLUGIN_ROOT_ENV: &str = "CLAUDE_PLUGIN_ROOT";
+const SESSION_TTL_ENV: &str = "PLANPILOT_SESSION_TTL_MINUTES";
+const AUTO_ARCHIVE_ENV: &str = "PLANPILOT_AUTO_ARCHIVE_DAYS";
+const COMMIT_MSG_HOOK_MARKER: &str = "planpilot commit-msg hook";
+const COMMIT_MSG_HOOK_SESSION: &str = "git-commit-msg";
+const SERVE_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const SERVE_WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const SERVE_ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(100);
 
 #[tokio::main]
 async fn main() {
@@ -58,102 +101,375 @@ async fn run() -> Result<(), AppError> {
 
     match command {
         Command::Hook(command) => {
-            handle_hook(command);
+            handle_hook(command).await;
             return Ok(());
         }
+        Command::Serve(args) => {
+            return handle_serve(args, cwd, session_id).await;
+        }
+        Command::Tui => {
+            return handle_tui(cwd, session_id).await;
+        }
+        Command::Watch(args) => {
+            return handle_watch(args, cwd, session_id).await;
+        }
+        Command::Webhook(WebhookCommand::Deliver(args)) => {
+            return handle_webhook_deliver(args, cwd, session_id).await;
+        }
         command => {
             let session_id = resolve_session_id(session_id)?;
             let claude_home = resolve_claude_home()?;
             let db_path = db::resolve_db_path(&claude_home);
             db::ensure_parent_dir(&db_path)?;
             let mut lock = db::open_lock(&db_path)?;
-            let _guard = lock.write()?;
+            let guard = lock.write()?;
 
             let db = db::connect(&db_path).await?;
             db::ensure_schema(&db).await?;
-            let app = App::new(db, session_id.clone());
-
-            match command {
-                Command::Plan(command) => {
-                    let should_sync = matches!(
-                        &command,
-                        PlanCommand::Add(_)
-                            | PlanCommand::AddTree(_)
-                            | PlanCommand::Comment(_)
-                            | PlanCommand::Update(_)
-                            | PlanCommand::Done(_)
-                            | PlanCommand::Remove(_)
-                            | PlanCommand::Activate(_)
-                            | PlanCommand::Deactivate(_)
-                    );
-                    let plan_ids = match command {
-                        PlanCommand::List(args) => {
-                            let context = PlanListContext {
-                                cwd: cwd.as_deref(),
-                                claude_home: &claude_home,
-                                cwd_flag_present,
-                            };
-                            handle_plan_list(&app, args, &context).await?
-                        }
-                        PlanCommand::Search(args) => {
-                            let context = PlanListContext {
-                                cwd: cwd.as_deref(),
-                                claude_home: &claude_home,
-                                cwd_flag_present,
-                            };
-                            handle_plan_search(&app, args, &context).await?
-                        }
-                        command => handle_plan(&app, command).await?,
-                    };
-                    if should_sync {
-                        sync_plan_md(&claude_home, &app, &plan_ids).await?;
-                    }
-                }
-                Command::Step(command) => {
-                    let should_sync = matches!(
-                        &command,
-                        StepCommand::Add(_)
-                            | StepCommand::AddTree(_)
-                            | StepCommand::Comment(_)
-                            | StepCommand::Update(_)
-                            | StepCommand::Done(_)
-                            | StepCommand::Move(_)
-                            | StepCommand::Remove(_)
-                    );
-                    let plan_ids = handle_step(&app, command).await?;
-                    if should_sync {
-                        sync_plan_md(&claude_home, &app, &plan_ids).await?;
-                    }
+            let session_ttl = resolve_session_ttl()?;
+            let mut app = App::new(db, session_id.clone()).with_session_ttl(session_ttl);
+            if let Some(cwd) = cwd.as_ref() {
+                app = app.with_repo_dir(cwd.clone());
+            }
+            let cwd_text = cwd.as_ref().map(|path| path.to_string_lossy().to_string());
+            app.record_heartbeat(cwd_text.as_deref()).await?;
+            // Auto-archiving is housekeeping: it reports on stderr so it never mixes into the
+            // command's output, and a failure must not fail the command.
+            if let Err(err) = auto_archive_done_plans(&app, &claude_home).await {
+                eprintln!("planpilot: auto-archive failed: {err}");
+            }
+
+            let context = PlanListContext {
+                cwd: cwd.as_deref(),
+                claude_home: &claude_home,
+                cwd_flag_present,
+            };
+            let recorder = EventRecorder::start(&app).await?;
+            let result = run_command(&app, command, &context, session_ttl).await;
+            let pending = match recorder {
+                Some(recorder) => recorder.finish(&app).await.unwrap_or_else(|err| {
+                    eprintln!("planpilot: failed to record events: {err}");
+                    Pending::default()
+                }),
+                None => Pending::default(),
+            };
+            drop(guard);
+            events::dispatch(pending, &app, cwd.as_deref());
+            result?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_command(
+    app: &App,
+    command: Command,
+    context: &PlanListContext<'_>,
+    session_ttl: Duration,
+) -> Result<(), AppError> {
+    match command {
+        Command::Plan(command) => {
+            let should_sync = matches!(
+                &command,
+                PlanCommand::Add(_)
+                    | PlanCommand::AddTree(_)
+                    | PlanCommand::ImportMd(_)
+                    | PlanCommand::ImportGithub(_)
+                    | PlanCommand::Comment(_)
+                    | PlanCommand::Update(_)
+                    | PlanCommand::Done(_)
+                    | PlanCommand::Remove(_)
+                    | PlanCommand::Activate(_)
+                    | PlanCommand::Deactivate(_)
+                    | PlanCommand::Push(_)
+                    | PlanCommand::Pop(_)
+                    | PlanCommand::Tag(_)
+                    | PlanCommand::Untag(_)
+                    | PlanCommand::Clone(_)
+                    | PlanCommand::Archive(_)
+                    | PlanCommand::Unarchive(_)
+            );
+            let plan_ids = match command {
+                PlanCommand::List(args) => {
+                    handle_plan_list(app, args, context).await?
                 }
-                Command::Goal(command) => {
-                    let should_sync = matches!(
-                        &command,
-                        GoalCommand::Add(_)
-                            | GoalCommand::Comment(_)
-                            | GoalCommand::Update(_)
-                            | GoalCommand::Done(_)
-                            | GoalCommand::Remove(_)
-                    );
-                    let plan_ids = handle_goal(&app, command).await?;
-                    if should_sync {
-                        sync_plan_md(&claude_home, &app, &plan_ids).await?;
-                    }
+                PlanCommand::Search(args) => {
+                    handle_plan_search(app, args, context).await?
                 }
-                Command::Hook(_) => {}
+                command => handle_plan(app, command).await?,
+            };
+            if should_sync {
+                sync_plan_md(context.claude_home, app, &plan_ids).await?;
             }
         }
+        Command::Step(command) => {
+            let should_sync = matches!(
+                &command,
+                StepCommand::Add(_)
+                    | StepCommand::AddTree(_)
+                    | StepCommand::Comment(_)
+                    | StepCommand::Update(_)
+                    | StepCommand::Done(_)
+                    | StepCommand::Move(_)
+                    | StepCommand::Split(_)
+                    | StepCommand::Merge(_)
+                    | StepCommand::Remove(_)
+            );
+            let plan_ids = handle_step(app, command).await?;
+            if should_sync {
+                sync_plan_md(context.claude_home, app, &plan_ids).await?;
+            }
+        }
+        Command::Goal(command) => {
+            let should_sync = matches!(
+                &command,
+                GoalCommand::Add(_)
+                    | GoalCommand::Comment(_)
+                    | GoalCommand::Update(_)
+                    | GoalCommand::Done(_)
+                    | GoalCommand::Move(_)
+                    | GoalCommand::Remove(_)
+            );
+            let plan_ids = handle_goal(app, command).await?;
+            if should_sync {
+                sync_plan_md(context.claude_home, app, &plan_ids).await?;
+            }
+        }
+        Command::Milestone(command) => {
+            let should_sync = !matches!(&command, MilestoneCommand::List(_));
+            let plan_ids = handle_milestone(app, command).await?;
+            if should_sync {
+                sync_plan_md(context.claude_home, app, &plan_ids).await?;
+            }
+        }
+        Command::Criterion(command) => {
+            let should_sync = !matches!(&command, CriterionCommand::List(_));
+            let plan_ids = handle_criterion(app, command).await?;
+            if should_sync {
+                sync_plan_md(context.claude_home, app, &plan_ids).await?;
+            }
+        }
+        Command::Session(command) => {
+            let plan_ids = handle_session(app, command, session_ttl).await?;
+            sync_plan_md(context.claude_home, app, &plan_ids).await?;
+        }
+        Command::Tag(command) => {
+            handle_tag(app, command).await?;
+        }
+        Command::Git(command) => {
+            let plan_ids = handle_git(app, command).await?;
+            sync_plan_md(context.claude_home, app, &plan_ids).await?;
+        }
+        Command::Export(args) => {
+            handle_table_export(app, args).await?;
+        }
+        Command::Event(command) => {
+            handle_event(app, command).await?;
+        }
+        Command::Webhook(command) => {
+            handle_webhook(app, command).await?;
+        }
+        Command::Hook(_) | Command::Serve(_) | Command::Tui | Command::Watch(_) => {}
     }
+    Ok(())
+}
 
+async fn handle_event(app: &App, command: EventCommand) -> Result<(), AppError> {
+    match command {
+        EventCommand::Add(args) => {
+            let event = event_kind_from_arg(args.event);
+            let hook = app.add_event_hook(event, &args.command, args.timeout).await?;
+            println!("Created event hook ID: {} for {}.", hook.id, hook.event);
+        }
+        EventCommand::List(_) => {
+            let hooks = app.list_event_hooks().await?;
+            if hooks.is_empty() {
+                println!("No event hooks.");
+                return Ok(());
+            }
+            println!("{:<4} {:<20} {:<8} COMMAND", "ID", "EVENT", "TIMEOUT");
+            for hook in &hooks {
+                let timeout = format!("{}s", hook.timeout_secs);
+                println!("{:<4} {:<20} {:<8} {}", hook.id, hook.event, timeout, hook.command);
+            }
+        }
+        EventCommand::Remove(args) => {
+            let removed = app.remove_event_hooks(&args.ids).await?;
+            if args.ids.len() == 1 {
+                println!("Event hook ID: {} removed.", args.ids[0]);
+            } else {
+                println!("Removed {removed} event hooks.");
+            }
+        }
+    }
     Ok(())
 }
 
-fn handle_hook(command: HookCommand) {
+async fn handle_webhook(app: &App, command: WebhookCommand) -> Result<(), AppError> {
     match command {
-        HookCommand::PreToolUse => hooks::run_pretooluse_hook(),
+        WebhookCommand::Add(args) => {
+            let events: Vec<EventKind> =
+                args.events.into_iter().map(event_kind_from_arg).collect();
+            let webhook = app
+                .add_webhook(&args.url, &args.secret, &events, args.timeout)
+                .await?;
+            println!(
+                "Created webhook ID: {} for {} ({}).",
+                webhook.id,
+                webhook.url,
+                webhook_events_label(&webhook.events)
+            );
+        }
+        WebhookCommand::List(_) => {
+            let webhooks = app.list_webhooks().await?;
+            if webhooks.is_empty() {
+                println!("No webhooks.");
+                return Ok(());
+            }
+            let deliveries = app.list_webhook_deliveries().await?;
+            println!("{:<4} {:<40} {:<8} URL", "ID", "EVENTS", "PENDING");
+            for webhook in &webhooks {
+                let pending = deliveries
+                    .iter()
+                    .filter(|delivery| delivery.webhook_id == webhook.id)
+                    .count();
+                println!(
+                    "{:<4} {:<40} {:<8} {}",
+                    webhook.id,
+                    webhook_events_label(&webhook.events),
+                    pending,
+                    webhook.url
+                );
+            }
+        }
+        WebhookCommand::Remove(args) => {
+            let removed = app.remove_webhooks(&args.ids).await?;
+            if args.ids.len() == 1 {
+                println!("Webhook ID: {} removed.", args.ids[0]);
+            } else {
+                println!("Removed {removed} webhooks.");
+            }
+        }
+        WebhookCommand::Outbox(_) => {
+            let deliveries = app.list_webhook_deliveries().await?;
+            if deliveries.is_empty() {
+                println!("No pending webhook deliveries.");
+                return Ok(());
+            }
+            println!(
+                "{:<4} {:<8} {:<20} {:<9} {:<17} LAST ERROR",
+                "ID", "WEBHOOK", "EVENT", "ATTEMPTS", "NEXT ATTEMPT"
+            );
+            let now = Utc::now();
+            for delivery in &deliveries {
+                let next_attempt = if delivery.next_attempt_at <= now {
+                    "now".to_string()
+                } else {
+                    format_datetime(delivery.next_attempt_at)
+                };
+                println!(
+                    "{:<4} {:<8} {:<20} {:<9} {:<17} {}",
+                    delivery.id,
+                    delivery.webhook_id,
+                    delivery.event,
+                    delivery.attempts,
+                    next_attempt,
+                    delivery.last_error.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        // Delivered from run() without holding the lock while the endpoints answer.
+        WebhookCommand::Deliver(_) => {}
+    }
+    Ok(())
+}
+
+async fn handle_webhook_deliver(
+    args: WebhookDeliver,
+    cwd: Option<PathBuf>,
+    session_id: Option<String>,
+) -> Result<(), AppError> {
+    let (app, _, mut lock) = open_app_unlocked(cwd, session_id).await?;
+    let attempts = webhooks::deliver(&app, &mut lock, !args.due).await?;
+    if attempts.is_empty() {
+        println!("No pending webhook deliveries.");
+        return Ok(());
+    }
+    for attempt in &attempts {
+        println!("{}", attempt.describe());
+    }
+    let delivered = attempts
+        .iter()
+        .filter(|attempt| attempt.outcome == DeliveryOutcome::Delivered)
+        .count();
+    println!("Delivered {delivered} of {} pending webhook deliveries.", attempts.len());
+    Ok(())
+}
+
+fn webhook_events_label(events: &str) -> String {
+    if events.is_empty() {
+        "all events".to_string()
+    } else {
+        events.replace(',', ", ")
+    }
+}
+
+async fn handle_hook(command: HookCommand) {
+    match command {
+        HookCommand::PreToolUse => {
+            // The heartbeat is recorded after the hook output, and failing to record it must
+            // never get in the way of the tool call.
+            if let Some((session_id, cwd)) = hooks::run_pretooluse_hook() {
+                if let Err(err) = record_hook_heartbeat(session_id, &cwd).await {
+                    eprintln!("planpilot: failed to record heartbeat: {err}");
+                }
+            }
+        }
         HookCommand::Stop => hooks::run_stop_hook(),
     }
 }
 
+/// Keeps a session live while its agent works with other tools. Only sessions that have already
+/// used planpilot are touched, and under the shared lock, so unrelated tool calls neither create
+/// the database or a session row nor queue up behind each other.
+async fn record_hook_heartbeat(session_id: String, cwd: &str) -> Result<(), AppError> {
+    let claude_home = resolve_claude_home()?;
+    let db_path = db::resolve_db_path(&claude_home);
+    if !db_path.exists() {
+        return Ok(());
+    }
+    let lock = db::open_lock(&db_path)?;
+    let _guard = lock.read()?;
+    let db = db::connect(&db_path).await?;
+    App::new(db, session_id).touch_session(cwd).await
+}
+
+async fn handle_tag(app: &App, command: TagCommand) -> Result<(), AppError> {
+    match command {
+        TagCommand::List(_) => {
+            let tags = app.list_tags().await?;
+            if tags.is_empty() {
+                println!("No tags found.");
+            } else {
+                print_tag_list(&tags);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_session(
+    app: &App,
+    command: SessionCommand,
+    session_ttl: Duration,
+) -> Result<Vec<i64>, AppError> {
+    match command {
+        SessionCommand::List(args) => handle_session_list(app, args, session_ttl).await,
+        SessionCommand::Prune(args) => handle_session_prune(app, args, session_ttl).await,
+    }
+}
+
 async fn handle_plan(app: &App, command: PlanCommand) -> Result<Vec<i64>, AppError> {
     match command {
         PlanCommand::Add(args) => handle_plan_add(app, args).await,
@@ -166,6 +482,8 @@ async fn handle_plan(app: &App, command: PlanCommand) -> Result<Vec<i64>, AppErr
         )),
         PlanCommand::Show(args) => handle_plan_show(app, args).await,
         PlanCommand::Export(args) => handle_plan_export(app, args).await,
+        PlanCommand::ImportMd(args) => handle_plan_import_md(app, args).await,
+        PlanCommand::ImportGithub(args) => handle_plan_import_github(app, args).await,
         PlanCommand::Comment(args) => handle_plan_comment(app, args).await,
         PlanCommand::Update(args) => handle_plan_update(app, args).await,
         PlanCommand::Done(args) => handle_plan_done(app, args).await,
@@ -173,6 +491,13 @@ async fn handle_plan(app: &App, command: PlanCommand) -> Result<Vec<i64>, AppErr
         PlanCommand::Activate(args) => handle_plan_activate(app, args).await,
         PlanCommand::Active(_) => handle_plan_active(app).await,
         PlanCommand::Deactivate(_) => handle_plan_deactivate(app).await,
+        PlanCommand::Push(args) => handle_plan_push(app, args).await,
+        PlanCommand::Pop(_) => handle_plan_pop(app).await,
+        PlanCommand::Tag(args) => handle_plan_tag(app, args).await,
+        PlanCommand::Clone(args) => handle_plan_clone(app, args).await,
+        PlanCommand::Archive(args) => handle_plan_archive(app, args).await,
+        PlanCommand::Unarchive(args) => handle_plan_unarchive(app, args).await,
+        PlanCommand::Untag(args) => handle_plan_untag(app, args).await,
     }
 }
 
@@ -182,15 +507,618 @@ async fn handle_step(app: &App, command: StepCommand) -> Result<Vec<i64>, AppErr
         StepCommand::AddTree(args) => handle_step_add_tree(app, args).await,
         StepCommand::List(args) => handle_step_list(app, args).await,
         StepCommand::Show(args) => handle_step_show(app, args).await,
-        StepCommand::ShowNext(_) => handle_step_show_next(app).await,
+        StepCommand::Diff(args) => handle_step_diff(app, args).await,
+        StepCommand::ShowNext(args) => handle_step_show_next(app, args).await,
+        StepCommand::Claim(args) => handle_step_claim(app, args).await,
+        StepCommand::Release(args) => handle_step_release(app, args).await,
+        StepCommand::Tag(args) => handle_step_tag(app, args).await,
+        StepCommand::Untag(args) => handle_step_untag(app, args).await,
         StepCommand::Comment(args) => handle_step_comment(app, args).await,
         StepCommand::Update(args) => handle_step_update(app, args).await,
         StepCommand::Done(args) => handle_step_done(app, args).await,
         StepCommand::Move(args) => handle_step_move(app, args).await,
+        StepCommand::Split(args) => handle_step_split(app, args).await,
+        StepCommand::Merge(args) => handle_step_merge(app, args).await,
         StepCommand::Remove(args) => handle_step_remove(app, args).await,
     }
 }
 
+async fn handle_table_export(app: &App, args: TableExport) -> Result<(), AppError> {
+    let plans = if args.all {
+        app.list_plans(Some(PlanOrder::Id), false)
+            .await?
+            .into_iter()
+            .filter(|plan| plan.archived_at.is_none())
+            .collect()
+    } else {
+        let plan_id = match args.plan {
+            Some(plan_id) => plan_id,
+            None => match app.get_active_plan().await? {
+                Some(active) => active.plan_id,
+                None => {
+                    return Err(AppError::InvalidInput(
+                        "no active plan; pass --plan <id> or --all".to_string(),
+                    ))
+                }
+            },
+        };
+        vec![app.get_plan(plan_id).await?]
+    };
+    let details = app.get_plan_details(&plans).await?;
+    let delimiter = match args.format {
+        TableFormatArg::Csv => ',',
+        TableFormatArg::Tsv => '\t',
+    };
+    print!("{}", format_goal_table(&details, delimiter));
+    Ok(())
+}
+
+/// Serves the API until interrupted. Unlike other commands it takes the database lock per
+/// request (shared for reads), so agents keep working while the dashboard is open.
+/// Opens the database for long-running commands, which take the lock per unit of work instead
+/// of holding it for the whole command.
+async fn open_app_unlocked(
+    cwd: Option<PathBuf>,
+    session_id: Option<String>,
+) -> Result<(App, PathBuf, fd_lock::RwLock<fs::File>), AppError> {
+    let session_id = resolve_session_id(session_id)?;
+    let claude_home = resolve_claude_home()?;
+    let db_path = db::resolve_db_path(&claude_home);
+    db::ensure_parent_dir(&db_path)?;
+    let mut lock = db::open_lock(&db_path)?;
+    let db = {
+        let _guard = lock.write()?;
+        let db = db::connect(&db_path).await?;
+        db::ensure_schema(&db).await?;
+        db
+    };
+    let mut app = App::new(db, session_id).with_session_ttl(resolve_session_ttl()?);
+    if let Some(cwd) = cwd {
+        app = app.with_repo_dir(cwd);
+    }
+    Ok((app, claude_home, lock))
+}
+
+async fn handle_serve(
+    args: Serve,
+    cwd: Option<PathBuf>,
+    session_id: Option<String>,
+) -> Result<(), AppError> {
+    let hook_dir = cwd.clone();
+    let (mut app, claude_home, mut lock) = open_app_unlocked(cwd, session_id).await?;
+    let options = ServeOptions {
+        allow_writes: args.allow_writes,
+        session_ttl: resolve_session_ttl()?,
+    };
+
+    let listener = TcpListener::bind((args.host.as_str(), args.port)).await?;
+    println!(
+        "Serving Planpilot on http://{}/ ({}); press Ctrl-C to stop.",
+        listener.local_addr()?,
+        if args.allow_writes { "read-write" } else { "read-only" }
+    );
+    loop {
+        // Accept errors such as running out of file descriptors are usually transient.
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                eprintln!("serve: failed to accept a connection: {err}");
+                tokio::time::sleep(SERVE_ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let request =
+            match tokio::time::timeout(SERVE_READ_TIMEOUT, serve::read_request(&mut stream)).await
+            {
+                Ok(Ok(Some(request))) => request,
+                Ok(Ok(None)) | Err(_) => continue,
+                Ok(Err(err)) => {
+                    eprintln!("serve: {err}");
+                    continue;
+                }
+            };
+        let (response, pending) = if request.is_write() {
+            let _guard = lock.write()?;
+            app.reset_checkpoint();
+            let recorder = EventRecorder::start(&app).await.unwrap_or_else(|err| {
+                eprintln!("serve: failed to record events: {err}");
+                None
+            });
+            let (response, plan_ids) = serve::route(&app, &request, &options).await;
+            if let Err(err) = sync_plan_md(&claude_home, &app, &plan_ids).await {
+                eprintln!("serve: failed to sync plan.md: {err}");
+            }
+            let pending = match recorder {
+                Some(recorder) => recorder.finish(&app).await.unwrap_or_else(|err| {
+                    eprintln!("serve: failed to record events: {err}");
+                    Pending::default()
+                }),
+                None => Pending::default(),
+            };
+            (response, pending)
+        } else {
+            let _guard = lock.read()?;
+            (serve::route(&app, &request, &options).await.0, Pending::default())
+        };
+        // Written on its own task so a client that stops reading cannot hold up other requests.
+        tokio::spawn(async move {
+            let write = serve::write_response(&mut stream, &response);
+            match tokio::time::timeout(SERVE_WRITE_TIMEOUT, write).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => eprintln!("serve: {err}"),
+                Err(_) => eprintln!("serve: timed out writing a response"),
+            }
+        });
+        events::dispatch(pending, &app, hook_dir.as_deref());
+    }
+}
+
+async fn handle_tui(cwd: Option<PathBuf>, session_id: Option<String>) -> Result<(), AppError> {
+    let hook_dir = cwd.clone();
+    let (mut app, claude_home, mut lock) = open_app_unlocked(cwd, session_id).await?;
+    // Keys drive a cursor on a terminal; otherwise one command is read per line, so the UI can
+    // be scripted.
+    let mut terminal = if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+        Some(tui::Terminal::start()?)
+    } else {
+        None
+    };
+    let clear_screen = std::io::stdout().is_terminal();
+    let mut screen = Screen::Plans;
+    let mut expanded: HashSet<i64> = HashSet::new();
+    let mut messages: Vec<String> = Vec::new();
+    let mut input = std::io::stdin().lock().lines();
+    loop {
+        let page = {
+            let _guard = lock.read()?;
+            match tui_page(&app, screen, &expanded).await {
+                Err(AppError::NotFound(what)) => {
+                    messages.push(format!("Not found: {what}"));
+                    screen = Screen::Plans;
+                    tui_page(&app, screen, &expanded).await?
+                }
+                result => result?,
+            }
+        };
+        let action = if let Some(terminal) = terminal.as_mut() {
+            terminal.next_action(&page, screen, &mut messages)?
+        } else {
+            if clear_screen {
+                print!("\x1b[2J\x1b[H");
+            }
+            println!("{}", page.text());
+            for message in messages.drain(..) {
+                println!("{message}");
+            }
+            print!("> ");
+            std::io::stdout().flush()?;
+
+            let Some(line) = input.next().transpose()? else {
+                println!();
+                return Ok(());
+            };
+            match tui::parse_action(&line) {
+                Ok(action) => action,
+                Err(message) => {
+                    messages.push(message);
+                    continue;
+                }
+            }
+        };
+        let selected_step = match (&action, &page.rows[..]) {
+            (Action::Select(n), rows) => match rows.get(n - 1) {
+                Some(Row::Step(id)) => Some(*id),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(step_id) = selected_step {
+            if !expanded.remove(&step_id) {
+                expanded.insert(step_id);
+            }
+            continue;
+        }
+        match (action, screen) {
+            (Action::Quit, _) => return Ok(()),
+            (Action::Refresh, _) => {}
+            (Action::Help, _) => messages.push(tui::HELP.to_string()),
+            (Action::Back, _) => screen = Screen::Plans,
+            (Action::Select(n), Screen::Plans) => match page.plan_ids.get(n - 1) {
+                Some(plan_id) => {
+                    screen = Screen::Plan(*plan_id);
+                    expanded.clear();
+                }
+                None => messages.push(format!("No plan {n} in the list.")),
+            },
+            (Action::ExpandAll, Screen::Plan(plan_id)) => {
+                let _guard = lock.read()?;
+                match app.get_plan_detail(plan_id).await {
+                    Ok(detail) if expanded.is_empty() => {
+                        expanded = detail.steps.iter().map(|step| step.id).collect();
+                    }
+                    Ok(_) => expanded.clear(),
+                    Err(err) => messages.push(format!("Error: {err}")),
+                }
+            }
+            (action, screen) => {
+                let guard = lock.write()?;
+                app.reset_checkpoint();
+                let recorder = EventRecorder::start(&app).await.unwrap_or_else(|err| {
+                    messages.push(format!("Failed to record events: {err}"));
+                    None
+                });
+                match tui_apply(&app, screen, &page, action, &mut messages).await {
+                    Ok(plan_ids) => {
+                        if let Err(err) = sync_plan_md(&claude_home, &app, &plan_ids).await {
+                            messages.push(format!("Failed to sync plan.md: {err}"));
+                        }
+                    }
+                    Err(err) => messages.push(format!("Error: {err}")),
+                }
+                let pending = match recorder {
+                    Some(recorder) => recorder.finish(&app).await.unwrap_or_else(|err| {
+                        messages.push(format!("Failed to record events: {err}"));
+                        Pending::default()
+                    }),
+                    None => Pending::default(),
+                };
+                drop(guard);
+                events::dispatch(pending, &app, hook_dir.as_deref());
+            }
+        }
+    }
+}
+
+async fn handle_watch(
+    args: Watch,
+    cwd: Option<PathBuf>,
+    session_id: Option<String>,
+) -> Result<(), AppError> {
+    let (app, claude_home, lock) = open_app_unlocked(cwd, session_id).await?;
+    // `data_version` is per connection, so it is read from a dedicated one.
+    let probe = db::connect_single(&db::resolve_db_path(&claude_home)).await?;
+    let interval = std::time::Duration::from_millis(args.interval);
+    let banner = match args.plan {
+        Some(plan_id) => {
+            let plan = {
+                let _guard = lock.read()?;
+                app.get_plan(plan_id).await?
+            };
+            format!("Watching plan ID: {}: {}", plan.id, plan.title)
+        }
+        None if args.active => "Watching the active plan of this session".to_string(),
+        None => "Watching all plans".to_string(),
+    };
+
+    let mut version = None;
+    let mut followed: Option<Option<i64>> = None;
+    let mut snapshot: Option<Snapshot> = None;
+    loop {
+        let current = db::data_version(&probe).await?;
+        if version != Some(current) {
+            let mut lines = Vec::new();
+            let _guard = lock.read()?;
+            let plan_id = if args.active {
+                let active = app.get_active_plan().await?.map(|state| state.plan_id);
+                if followed != Some(active) {
+                    followed = Some(active);
+                    snapshot = None;
+                    lines.push(match active {
+                        Some(plan_id) => {
+                            let plan = app.get_plan(plan_id).await?;
+                            format!("Now watching plan ID: {}: {}", plan.id, plan.title)
+                        }
+                        None => "No active plan; waiting for one.".to_string(),
+                    });
+                }
+                active
+            } else {
+                args.plan
+            };
+            if !args.active || plan_id.is_some() {
+                let next = watch_snapshot(&app, plan_id).await?;
+                let stamp = Utc::now().format("%H:%M:%S");
+                for event in snapshot.iter().flat_map(|previous| watch::diff(previous, &next)) {
+                    lines.push(format!("[{stamp}] {event}"));
+                }
+                snapshot = Some(next);
+            }
+            // The banner follows the first snapshot, so changes made after it are reported.
+            if version.is_none() {
+                println!("{banner}; press Ctrl-C to stop.");
+            }
+            version = Some(current);
+            for line in lines {
+                println!("{line}");
+            }
+            std::io::stdout().flush()?;
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Loads one plan, or every plan when `plan_id` is `None`, for `watch` to diff.
+async fn watch_snapshot(app: &App, plan_id: Option<i64>) -> Result<Snapshot, AppError> {
+    let details = match plan_id {
+        Some(plan_id) => match app.get_plan_detail(plan_id).await {
+            Ok(detail) => vec![detail],
+            Err(AppError::NotFound(_)) => Vec::new(),
+            Err(err) => return Err(err),
+        },
+        None => {
+            let plans = app.list_plans(Some(PlanOrder::Id), false).await?;
+            app.get_plan_details(&plans).await?
+        }
+    };
+    Ok(Snapshot::new(&details, &app.list_active_plans().await?))
+}
+
+async fn tui_page(app: &App, screen: Screen, expanded: &HashSet<i64>) -> Result<Page, AppError> {
+    let active = app.get_active_plan().await?.map(|state| state.plan_id);
+    match screen {
+        Screen::Plans => {
+            let plans: Vec<_> = app
+                .list_plans(Some(PlanOrder::Id), false)
+                .await?
+                .into_iter()
+                .filter(|plan| plan.archived_at.is_none())
+                .collect();
+            Ok(tui::render_plans(&app.get_plan_details(&plans).await?, active))
+        }
+        Screen::Plan(plan_id) => {
+            let detail = app.get_plan_detail(plan_id).await?;
+            Ok(tui::render_plan(&detail, expanded, active))
+        }
+    }
+}
+
+/// Runs a TUI action that changes the database and returns the plans to sync.
+async fn tui_apply(
+    app: &App,
+    screen: Screen,
+    page: &Page,
+    action: Action,
+    messages: &mut Vec<String>,
+) -> Result<Vec<i64>, AppError> {
+    let plan_id = match (screen, &action) {
+        (Screen::Plan(plan_id), _) => plan_id,
+        (Screen::Plans, Action::Activate(Some(n))) => {
+            let plan_id = page.plan_ids.get(n - 1).copied().ok_or_else(|| {
+                AppError::InvalidInput(format!("no plan {n} in the list"))
+            })?;
+            return tui_activate(app, plan_id, messages).await;
+        }
+        (Screen::Plans, _) => {
+            return Err(AppError::InvalidInput(
+                "open a plan first; type ? for help".to_string(),
+            ));
+        }
+    };
+    let row = |n: usize| {
+        page.rows
+            .get(n - 1)
+            .copied()
+            .ok_or_else(|| AppError::InvalidInput(format!("no row {n}")))
+    };
+    let changes = match action {
+        Action::Activate(None) => return tui_activate(app, plan_id, messages).await,
+        Action::Activate(Some(_)) => {
+            return Err(AppError::InvalidInput(
+                "use a without a number to activate the open plan".to_string(),
+            ));
+        }
+        Action::Select(n) | Action::Done(n) => {
+            let done = matches!(action, Action::Done(_));
+            match row(n)? {
+                Row::Step(id) => {
+                    let (step, changes) = app.set_step_done_with_goals(id, false).await?;
+                    messages.push(format!("Step ID: {} marked done.", step.id));
+                    changes
+                }
+                Row::Goal(id) => {
+                    let goal = app.get_goal(id).await?;
+                    let status = if done || goal.status != GoalStatus::Done.as_str() {
+                        GoalStatus::Done
+                    } else {
+                        GoalStatus::Todo
+                    };
+                    let (goal, changes) = app.set_goal_status(id, status).await?;
+                    messages.push(format!("Goal ID: {} marked {}.", goal.id, goal.status));
+                    changes
+                }
+            }
+        }
+        Action::Move(n, _) | Action::MoveUp(n) | Action::MoveDown(n) => {
+            let Row::Step(id) = row(n)? else {
+                return Err(AppError::InvalidInput("only steps can be moved".to_string()));
+            };
+            let to = match action {
+                Action::Move(_, to) => to,
+                _ => {
+                    let detail = app.get_plan_detail(plan_id).await?;
+                    let up = matches!(action, Action::MoveUp(_));
+                    match tui::sibling_position(&detail.steps, id, up) {
+                        Some(to) => to,
+                        None => return Ok(Vec::new()),
+                    }
+                }
+            };
+            app.move_step(id, to).await?;
+            messages.push(format!("Moved step ID: {id} to position {to}."));
+            StatusChanges::default()
+        }
+        Action::Comment(n, text) => {
+            match row(n)? {
+                Row::Step(id) => {
+                    app.comment_steps(vec![(id, text)]).await?;
+                    messages.push(format!("Updated comment for step ID: {id}."));
+                }
+                Row::Goal(id) => {
+                    app.comment_goals(vec![(id, text)]).await?;
+                    messages.push(format!("Updated comment for goal ID: {id}."));
+                }
+            }
+            StatusChanges::default()
+        }
+        Action::ExpandAll | Action::Back | Action::Refresh | Action::Help | Action::Quit => {
+            StatusChanges::default()
+        }
+    };
+    messages.extend(format_status_changes(&changes));
+    Ok(vec![plan_id])
+}
+
+async fn tui_activate(
+    app: &App,
+    plan_id: i64,
+    messages: &mut Vec<String>,
+) -> Result<Vec<i64>, AppError> {
+    let plan = app.get_plan(plan_id).await?;
+    if plan.status == PlanStatus::Done.as_str() {
+        return Err(AppError::InvalidInput(
+            "cannot activate plan; plan is done".to_string(),
+        ));
+    }
+    app.set_active_plan(plan.id, false).await?;
+    messages.push(format!("Active plan set to {}: {}", plan.id, plan.title));
+    Ok(vec![plan.id])
+}
+
+async fn handle_git(app: &App, command: GitCommand) -> Result<Vec<i64>, AppError> {
+    match command {
+        GitCommand::Scan(args) => handle_git_scan(app, args).await,
+        GitCommand::InstallHook(args) => handle_git_install_hook(app, args),
+        GitCommand::CheckMsg(args) => handle_git_check_msg(app, args).await,
+    }
+}
+
+async fn handle_git_scan(app: &App, args: GitScan) -> Result<Vec<i64>, AppError> {
+    let repo_dir = require_repo_dir(app, "git scan")?;
+    let (scanned, commits) = git::scan_commit_trailers(repo_dir, args.since.as_deref())?;
+    for trailers in &commits {
+        for invalid in &trailers.ids.invalid {
+            println!(
+                "Commit {}: ignored trailer `{invalid}`.",
+                git::short_commit(&trailers.commit)
+            );
+        }
+    }
+    let (results, changes) = app.apply_commit_trailers(&commits).await?;
+    let mut completed_goals = 0;
+    let mut completed_steps = 0;
+    let mut plan_ids = Vec::new();
+    for result in &results {
+        let commit = git::short_commit(&result.commit);
+        let label = if result.kind == "goal" { "Goal" } else { "Step" };
+        match &result.outcome {
+            TrailerOutcome::Completed => {
+                if result.kind == "goal" {
+                    completed_goals += 1;
+                } else {
+                    completed_steps += 1;
+                }
+                println!("Commit {commit}: {label} ID: {} done.", result.id);
+            }
+            TrailerOutcome::AlreadyDone => {}
+            TrailerOutcome::NotFound => {
+                println!("Commit {commit}: {label} ID: {} not found.", result.id);
+            }
+            TrailerOutcome::Blocked(reason) => {
+                println!("Commit {commit}: {label} ID: {} not completed; {reason}.", result.id);
+            }
+        }
+        if let Some(plan_id) = result.plan_id {
+            if result.outcome == TrailerOutcome::Completed && !plan_ids.contains(&plan_id) {
+                plan_ids.push(plan_id);
+            }
+        }
+    }
+    println!(
+        "Scanned {scanned} commits; completed {completed_goals} goals and {completed_steps} steps."
+    );
+    print_status_changes(&changes);
+    notify_plans_completed(app, &changes).await?;
+    Ok(plan_ids)
+}
+
+fn handle_git_install_hook(app: &App, args: GitInstallHook) -> Result<Vec<i64>, AppError> {
+    let repo_dir = require_repo_dir(app, "git install-hook")?;
+    let path = git::commit_msg_hook_path(repo_dir)?;
+    if path.exists() && !args.force {
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if !existing.contains(COMMIT_MSG_HOOK_MARKER) {
+            return Err(AppError::InvalidInput(format!(
+                "commit-msg hook already exists at {}; pass --force to replace it",
+                path.display()
+            )));
+        }
+    }
+    let exe = std::env::current_exe()?;
+    let plugin_root = std::env::var(CLAUDE_PLUGIN_ROOT_ENV).ok();
+    db::ensure_parent_dir(&path)?;
+    fs::write(&path, commit_msg_hook_script(&exe, plugin_root.as_deref()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    println!("Installed commit-msg hook at {}.", path.display());
+    Ok(Vec::new())
+}
+
+/// Builds the `commit-msg` hook script, pinning this binary and the plugin root used to find
+/// the database so commits made outside Claude still validate against the same plans.
+fn commit_msg_hook_script(exe: &Path, plugin_root: Option<&str>) -> String {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&format!(
+        "# {COMMIT_MSG_HOOK_MARKER}: rejects Planpilot-Goal/Planpilot-Step trailers naming \
+         unknown ids.\n"
+    ));
+    if let Some(root) = plugin_root {
+        let env = CLAUDE_PLUGIN_ROOT_ENV;
+        script.push_str(&format!("{env}=${{{env}:-{}}}\nexport {env}\n", quote(root)));
+    }
+    script.push_str(&format!(
+        "exec {} {CWD_FLAG} \"$(pwd)\" {SESSION_ID_FLAG} {COMMIT_MSG_HOOK_SESSION} \
+         git check-msg \"$1\"\n",
+        quote(&exe.to_string_lossy())
+    ));
+    script
+}
+
+async fn handle_git_check_msg(app: &App, args: GitCheckMsg) -> Result<Vec<i64>, AppError> {
+    let file = fs::canonicalize(&args.file)?;
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let repo_dir = app.repo_dir().unwrap_or(dir);
+    let ids = git::message_trailer_ids(repo_dir, &file)?;
+    app.validate_trailer_ids(&ids).await?;
+    Ok(Vec::new())
+}
+
+async fn handle_milestone(app: &App, command: MilestoneCommand) -> Result<Vec<i64>, AppError> {
+    match command {
+        MilestoneCommand::Add(args) => handle_milestone_add(app, args).await,
+        MilestoneCommand::List(args) => handle_milestone_list(app, args).await,
+        MilestoneCommand::Assign(args) => handle_milestone_assign(app, args).await,
+        MilestoneCommand::Done(args) => handle_milestone_done(app, args).await,
+    }
+}
+
+async fn handle_criterion(app: &App, command: CriterionCommand) -> Result<Vec<i64>, AppError> {
+    match command {
+        CriterionCommand::Add(args) => handle_criterion_add(app, args).await,
+        CriterionCommand::List(args) => handle_criterion_list(app, args).await,
+        CriterionCommand::Check(args) => {
+            set_criteria_status(app, &args.ids, CriterionStatus::Done).await
+        }
+        CriterionCommand::Uncheck(args) => {
+            set_criteria_status(app, &args.ids, CriterionStatus::Todo).await
+        }
+        CriterionCommand::Remove(args) => handle_criterion_remove(app, args).await,
+    }
+}
+
 async fn handle_goal(app: &App, command: GoalCommand) -> Result<Vec<i64>, AppError> {
     match command {
         GoalCommand::Add(args) => handle_goal_add(app, args).await,
@@ -199,6 +1127,7 @@ async fn handle_goal(app: &App, command: GoalCommand) -> Result<Vec<i64>, AppErr
         GoalCommand::Comment(args) => handle_goal_comment(app, args).await,
         GoalCommand::Update(args) => handle_goal_update(app, args).await,
         GoalCommand::Done(args) => handle_goal_done(app, args).await,
+        GoalCommand::Move(args) => handle_goal_move(app, args).await,
         GoalCommand::Remove(args) => handle_goal_remove(app, args).await,
     }
 }
@@ -209,6 +1138,8 @@ async fn handle_plan_add(app: &App, args: PlanAdd) -> Result<Vec<i64>, AppError>
         .add_plan(PlanInput {
             title: args.title,
             content: args.content,
+            priority: args.priority.and_then(priority_from_arg),
+            due: args.due.as_deref().map(parse_due).transpose()?.flatten(),
         })
         .await?;
 
@@ -216,6 +1147,20 @@ async fn handle_plan_add(app: &App, args: PlanAdd) -> Result<Vec<i64>, AppError>
     Ok(vec![plan.id])
 }
 
+async fn handle_plan_clone(app: &App, args: PlanClone) -> Result<Vec<i64>, AppError> {
+    if let Some(title) = &args.title {
+        require_non_empty("plan title", title)?;
+    }
+    let (plan, step_count, goal_count) = app
+        .clone_plan(args.id, args.title, args.reset_status, args.with_comments)
+        .await?;
+    println!(
+        "Created plan ID: {}: {} (cloned from plan ID: {}, steps: {}, goals: {})",
+        plan.id, plan.title, args.id, step_count, goal_count
+    );
+    Ok(vec![plan.id])
+}
+
 async fn handle_plan_add_tree(app: &App, args: PlanAddTree) -> Result<Vec<i64>, AppError> {
     require_non_empty("plan title", &args.title)?;
     require_non_empty("plan content", &args.content)?;
@@ -229,10 +1174,7 @@ async fn handle_plan_add_tree(app: &App, args: PlanAddTree) -> Result<Vec<i64>,
     let mut steps = Vec::with_capacity(specs.len());
     for spec in specs {
         require_non_empty("step content", &spec.content)?;
-        let executor = spec
-            .executor
-            .map(step_executor_from_arg)
-            .unwrap_or(StepExecutor::Ai);
+        let executor = spec.executor.unwrap_or_else(StepExecutor::ai);
         let mut goals = Vec::new();
         if let Some(items) = spec.goals {
             for goal in items {
@@ -243,6 +1185,7 @@ async fn handle_plan_add_tree(app: &App, args: PlanAddTree) -> Result<Vec<i64>,
         steps.push(StepInput {
             content: spec.content,
             executor,
+            schedule: spec.schedule,
             goals,
         });
     }
@@ -252,6 +1195,8 @@ async fn handle_plan_add_tree(app: &App, args: PlanAddTree) -> Result<Vec<i64>,
             PlanInput {
                 title: args.title,
                 content: args.content,
+                priority: args.priority.and_then(priority_from_arg),
+                due: args.due.as_deref().map(parse_due).transpose()?.flatten(),
             },
             steps,
         )
@@ -275,15 +1220,25 @@ async fn handle_plan_list(
     args: PlanList,
     context: &PlanListContext<'_>,
 ) -> Result<Vec<i64>, AppError> {
-    let PlanList { all, project } = args;
-    let desired = if all {
+    let PlanList {
+        all,
+        project,
+        sort,
+        desc,
+        overdue,
+        tags,
+        tag_mode,
+        archived,
+    } = args;
+    let desired = if all || archived {
         None
     } else {
         Some(PlanStatus::Todo)
     };
+    let order = sort.map(plan_order_from_sort);
 
     let cwd = require_cwd(context)?;
-    let plans = app.list_plans(None, false).await?;
+    let plans = app.list_plans(order, desc).await?;
     if plans.is_empty() {
         println!("No plans found.");
         return Ok(Vec::new());
@@ -291,12 +1246,19 @@ async fn handle_plan_list(
 
     let mut filtered: Vec<_> = plans
         .into_iter()
+        .filter(|plan| plan.archived_at.is_some() == archived)
         .filter(|plan| match desired {
             None => true,
             Some(status) => plan.status == status.as_str(),
         })
         .collect();
 
+    if overdue {
+        let today = Utc::now().date_naive();
+        filtered.retain(|plan| is_overdue(plan.due, &plan.status, today));
+    }
+    retain_plans_with_tags(app, &mut filtered, &tags, tag_mode).await?;
+
     if project {
         let session_ids = collect_session_ids_for_project(context.claude_home, &cwd)?;
         filtered.retain(|plan| {
@@ -311,7 +1273,10 @@ async fn handle_plan_list(
         return Ok(Vec::new());
     }
 
-    let details = app.get_plan_details(&filtered).await?;
+    let mut details = app.get_plan_details(&filtered).await?;
+    if matches!(order, Some(PlanOrder::Progress)) {
+        sort_plan_details_by_progress(&mut details, desc);
+    }
     print_plan_list(&details);
     Ok(Vec::new())
 }
@@ -328,8 +1293,11 @@ async fn handle_plan_search(
         search_mode,
         search_field,
         match_case,
+        tags,
+        tag_mode,
+        archived,
     } = args;
-    let desired = if all {
+    let desired = if all || archived {
         None
     } else {
         Some(PlanStatus::Todo)
@@ -344,11 +1312,13 @@ async fn handle_plan_search(
 
     let mut filtered: Vec<_> = plans
         .into_iter()
+        .filter(|plan| plan.archived_at.is_some() == archived)
         .filter(|plan| match desired {
             None => true,
             Some(status) => plan.status == status.as_str(),
         })
         .collect();
+    retain_plans_with_tags(app, &mut filtered, &tags, tag_mode).await?;
 
     if project {
         let session_ids = collect_session_ids_for_project(context.claude_home, &cwd)?;
@@ -384,10 +1354,7 @@ async fn handle_plan_search(
 
 async fn handle_plan_show(app: &App, args: PlanShow) -> Result<Vec<i64>, AppError> {
     let detail = app.get_plan_detail(args.id).await?;
-    println!(
-        "{}",
-        format_plan_detail(&detail.plan, &detail.steps, &detail.goals)
-    );
+    println!("{}", format_plan_detail(&detail));
     Ok(Vec::new())
 }
 
@@ -398,15 +1365,20 @@ async fn handle_plan_export(app: &App, args: PlanExport) -> Result<Vec<i64>, App
         Some(state) if state.plan_id == detail.plan.id => (true, Some(state.updated_at)),
         _ => (false, None),
     };
+    let output = match args.format {
+        PlanExportFormatArg::Markdown => format_plan_markdown(is_active, activated_at, &detail),
+        PlanExportFormatArg::Mermaid => format_plan_mermaid(&detail),
+        PlanExportFormatArg::Dot => format_plan_dot(&detail),
+        PlanExportFormatArg::Html => format_plan_html(&detail),
+        PlanExportFormatArg::Github => format_issue_json(&detail),
+        PlanExportFormatArg::GithubBody => format_issue_body(&detail),
+    };
+    if args.path.as_os_str() == "-" {
+        print!("{output}");
+        return Ok(Vec::new());
+    }
     db::ensure_parent_dir(&args.path)?;
-    let markdown = format_plan_markdown(
-        is_active,
-        activated_at,
-        &detail.plan,
-        &detail.steps,
-        &detail.goals,
-    );
-    fs::write(&args.path, markdown)?;
+    fs::write(&args.path, output)?;
     println!(
         "Exported plan ID: {} to {}",
         detail.plan.id,
@@ -415,6 +1387,71 @@ async fn handle_plan_export(app: &App, args: PlanExport) -> Result<Vec<i64>, App
     Ok(Vec::new())
 }
 
+async fn handle_plan_import_md(app: &App, args: PlanImportMd) -> Result<Vec<i64>, AppError> {
+    let text = fs::read_to_string(&args.path)?;
+    let source = args
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| args.path.display().to_string());
+    let mut imported = parse_markdown_plan(&text, &source)?;
+    if let Some(title) = args.title {
+        require_non_empty("plan title", &title)?;
+        imported.title = title;
+    }
+
+    if args.dry_run {
+        print_import_dry_run(&imported, &source);
+        return Ok(Vec::new());
+    }
+    let plan_id = import_plan_and_report(app, imported, &source).await?;
+    Ok(vec![plan_id])
+}
+
+async fn handle_plan_import_github(
+    app: &App,
+    args: PlanImportGithub,
+) -> Result<Vec<i64>, AppError> {
+    let text = fs::read_to_string(&args.path)?;
+    let mut plan_ids = Vec::new();
+    for issue in parse_issues(&text)? {
+        let source = issue.source();
+        let (imported, skipped) = issue_to_plan(&issue);
+        for label in skipped {
+            println!("Skipped label '{label}' of {source}: not a valid tag name.");
+        }
+        if args.dry_run {
+            print_import_dry_run(&imported, &source);
+        } else {
+            plan_ids.push(import_plan_and_report(app, imported, &source).await?);
+        }
+    }
+    Ok(plan_ids)
+}
+
+fn print_import_dry_run(imported: &ImportedPlan, source: &str) {
+    let (steps, steps_done, goals, goals_done) = imported.counts();
+    println!("{}", format_add_tree(imported));
+    println!(
+        "Dry run: would import plan \"{}\" from {} (steps: {} ({} done), goals: {} ({} done)).",
+        imported.title, source, steps, steps_done, goals, goals_done
+    );
+}
+
+async fn import_plan_and_report(
+    app: &App,
+    imported: ImportedPlan,
+    source: &str,
+) -> Result<i64, AppError> {
+    let (plan, step_count, goal_count, changes) = app.import_plan(imported).await?;
+    println!(
+        "Created plan ID: {}: {} (imported from {}, steps: {}, goals: {})",
+        plan.id, plan.title, source, step_count, goal_count
+    );
+    print_status_changes(&changes);
+    Ok(plan.id)
+}
+
 async fn handle_plan_comment(app: &App, args: PlanComment) -> Result<Vec<i64>, AppError> {
     let entries = parse_comment_pairs("plan", args.pairs)?;
     let plan_ids = app.comment_plans(entries).await?;
@@ -438,6 +1475,8 @@ async fn handle_plan_update(app: &App, args: PlanUpdate) -> Result<Vec<i64>, App
                 content: args.content,
                 status: args.status.clone().map(plan_status_from_arg),
                 comment: args.comment,
+                priority: args.priority.map(priority_from_arg),
+                due: args.due.as_deref().map(parse_due).transpose()?,
             },
         )
         .await?;
@@ -445,9 +1484,10 @@ async fn handle_plan_update(app: &App, args: PlanUpdate) -> Result<Vec<i64>, App
     println!("Updated plan ID: {}: {}", plan.id, plan.title);
     if cleared {
         println!("Active plan deactivated because plan is done.");
+        print_resumed_plan(app).await?;
     }
     if plan.status == PlanStatus::Done.as_str() {
-        notify_plan_completed(&plan);
+        notify_plan_completed(app, &plan).await?;
     }
     Ok(vec![plan.id])
 }
@@ -465,10 +1505,98 @@ async fn handle_plan_done(app: &App, args: PlanDone) -> Result<Vec<i64>, AppErro
     println!("Plan ID: {} marked done.", plan.id);
     if cleared {
         println!("Active plan deactivated because plan is done.");
+        print_resumed_plan(app).await?;
     }
     if plan.status == PlanStatus::Done.as_str() {
-        notify_plan_completed(&plan);
+        notify_plan_completed(app, &plan).await?;
+    }
+    Ok(vec![plan.id])
+}
+
+async fn handle_plan_archive(app: &App, args: PlanArchive) -> Result<Vec<i64>, AppError> {
+    if let Some(days) = args.done_days {
+        let archived = app.archive_done_plans(parse_done_days(days)?).await?;
+        if archived.is_empty() {
+            println!("No plans to archive.");
+        } else {
+            let labels: Vec<String> = archived
+                .iter()
+                .map(|archived| archived.plan.id.to_string())
+                .collect();
+            println!("Archived {} plans: {}.", archived.len(), labels.join(", "));
+        }
+        let (notes, plan_ids) = archived_plan_notes(&archived);
+        for note in notes {
+            println!("{note}");
+        }
+        return Ok(plan_ids);
+    }
+    let Some(id) = args.id else {
+        return Err(AppError::InvalidInput(
+            "plan archive requires <id> or --done-days".to_string(),
+        ));
+    };
+    let (plan, cleared, resumed) = app.archive_plan(id).await?;
+    println!("Plan ID: {} archived.", plan.id);
+    let mut plan_ids = vec![plan.id];
+    if cleared {
+        println!("Active plan deactivated because plan is archived.");
+    }
+    if let Some(resumed) = resumed {
+        println!("Resumed plan from focus stack: {}: {}", resumed.id, resumed.title);
+        plan_ids.push(resumed.id);
+    }
+    Ok(plan_ids)
+}
+
+/// Describes what archiving did to active plans and returns the plans whose `plan.md` changed.
+fn archived_plan_notes(archived: &[ArchivedPlan]) -> (Vec<String>, Vec<i64>) {
+    let mut notes = Vec::new();
+    let mut plan_ids = Vec::new();
+    for archived in archived {
+        plan_ids.push(archived.plan.id);
+        if archived.cleared {
+            notes.push(format!(
+                "Active plan deactivated because plan ID: {} is archived.",
+                archived.plan.id
+            ));
+        }
+        if let Some(resumed) = &archived.resumed {
+            notes.push(format!(
+                "Resumed plan from focus stack: {}: {}",
+                resumed.id, resumed.title
+            ));
+            plan_ids.push(resumed.id);
+        }
+    }
+    (notes, plan_ids)
+}
+
+/// Runs `plan archive --done-days` for `PLANPILOT_AUTO_ARCHIVE_DAYS`, at most once a day.
+async fn auto_archive_done_plans(app: &App, claude_home: &Path) -> Result<(), AppError> {
+    let Some(done_for) = resolve_auto_archive_after()? else {
+        return Ok(());
+    };
+    let stamp_path = db::resolve_auto_archive_stamp_path(claude_home);
+    let last_run = fs::read_to_string(&stamp_path)
+        .ok()
+        .and_then(|text| DateTime::parse_from_rfc3339(text.trim()).ok());
+    let now = Utc::now();
+    if last_run.is_some_and(|last_run| now - last_run.with_timezone(&Utc) < Duration::days(1)) {
+        return Ok(());
     }
+    let archived = app.archive_done_plans(done_for).await?;
+    fs::write(&stamp_path, now.to_rfc3339())?;
+    let (notes, plan_ids) = archived_plan_notes(&archived);
+    for note in notes {
+        eprintln!("{note}");
+    }
+    sync_plan_md(claude_home, app, &plan_ids).await
+}
+
+async fn handle_plan_unarchive(app: &App, args: PlanUnarchive) -> Result<Vec<i64>, AppError> {
+    let plan = app.unarchive_plan(args.id).await?;
+    println!("Plan ID: {} unarchived.", plan.id);
     Ok(vec![plan.id])
 }
 
@@ -485,7 +1613,11 @@ async fn handle_plan_activate(app: &App, args: PlanActivate) -> Result<Vec<i64>,
             "cannot activate plan; plan is done".to_string(),
         ));
     }
-    app.set_active_plan(plan.id, args.force).await?;
+    if args.shared {
+        app.share_active_plan(plan.id).await?;
+    } else {
+        app.set_active_plan(plan.id, args.force).await?;
+    }
     println!("Active plan set to {}: {}", plan.id, plan.title);
     Ok(vec![plan.id])
 }
@@ -505,10 +1637,8 @@ async fn handle_plan_active(app: &App) -> Result<Vec<i64>, AppError> {
         }
         Err(err) => return Err(err),
     };
-    println!(
-        "{}",
-        format_plan_detail(&detail.plan, &detail.steps, &detail.goals)
-    );
+    println!("{}", format_plan_detail(&detail));
+    print_focus_stack(app, Some(state.plan_id)).await?;
     Ok(Vec::new())
 }
 
@@ -519,6 +1649,209 @@ async fn handle_plan_deactivate(app: &App) -> Result<Vec<i64>, AppError> {
     Ok(active.map(|state| state.plan_id).into_iter().collect())
 }
 
+async fn handle_plan_push(app: &App, args: PlanPush) -> Result<Vec<i64>, AppError> {
+    let plan = app.get_plan(args.id).await?;
+    if plan.status == PlanStatus::Done.as_str() {
+        return Err(AppError::InvalidInput(
+            "cannot push plan; plan is done".to_string(),
+        ));
+    }
+    let (_, suspended) = app.push_active_plan(plan.id, args.force).await?;
+    if let Some(suspended_id) = suspended {
+        println!("Suspended plan ID: {suspended_id}.");
+    }
+    println!("Active plan set to {}: {}", plan.id, plan.title);
+    Ok(suspended.into_iter().chain([plan.id]).collect())
+}
+
+async fn handle_plan_pop(app: &App) -> Result<Vec<i64>, AppError> {
+    let (previous, resumed) = app.pop_active_plan().await?;
+    if let Some(previous_id) = previous {
+        println!("Plan ID: {previous_id} deactivated.");
+    }
+    let mut plan_ids: Vec<i64> = previous.into_iter().collect();
+    match resumed {
+        Some(plan) => {
+            println!("Active plan set to {}: {}", plan.id, plan.title);
+            plan_ids.push(plan.id);
+        }
+        None => println!("Focus stack is empty; no active plan."),
+    }
+    Ok(plan_ids)
+}
+
+async fn handle_plan_tag(app: &App, args: PlanTag) -> Result<Vec<i64>, AppError> {
+    let tags = app.tag(TagTarget::Plan, args.id, &args.tags).await?;
+    println!("Plan ID: {} tags: {}", args.id, format_tag_list(&tags));
+    Ok(vec![args.id])
+}
+
+async fn handle_plan_untag(app: &App, args: PlanUntag) -> Result<Vec<i64>, AppError> {
+    let tags = app.untag(TagTarget::Plan, args.id, &args.tags).await?;
+    println!("Plan ID: {} tags: {}", args.id, format_tag_list(&tags));
+    Ok(vec![args.id])
+}
+
+async fn print_resumed_plan(app: &App) -> Result<(), AppError> {
+    if let Some(state) = app.get_active_plan().await? {
+        let plan = app.get_plan(state.plan_id).await?;
+        println!("Resumed plan from focus stack: {}: {}", plan.id, plan.title);
+    }
+    Ok(())
+}
+
+async fn print_focus_stack(app: &App, active_id: Option<i64>) -> Result<(), AppError> {
+    let suspended = app.suspended_plans().await?;
+    if suspended.is_empty() {
+        return Ok(());
+    }
+    let mut entries: Vec<String> = active_id
+        .map(|id| format!("plan {id} (active)"))
+        .into_iter()
+        .collect();
+    entries.extend(
+        suspended
+            .iter()
+            .map(|plan| format!("plan {}: {}", plan.id, plan.title)),
+    );
+    println!();
+    println!("Focus stack: {}", entries.join(" > "));
+    Ok(())
+}
+
+async fn handle_milestone_add(app: &App, args: MilestoneAdd) -> Result<Vec<i64>, AppError> {
+    require_non_empty("milestone title", &args.title)?;
+    let (milestone, changes) = app
+        .add_milestone(args.plan_id, args.title, &args.steps)
+        .await?;
+    println!(
+        "Created milestone ID: {} for plan ID: {}: {}",
+        milestone.id, milestone.plan_id, milestone.title
+    );
+    print_status_changes(&changes);
+    Ok(vec![milestone.plan_id])
+}
+
+async fn handle_milestone_list(app: &App, args: MilestoneList) -> Result<Vec<i64>, AppError> {
+    let milestones = app.list_milestones(args.plan_id).await?;
+    if milestones.is_empty() {
+        println!("No milestones found for plan ID: {}.", args.plan_id);
+        return Ok(Vec::new());
+    }
+    println!("{:<4} {:<6} {:<9} TITLE", "ID", "STAT", "STEPS");
+    for progress in &milestones {
+        println!(
+            "{:<4} {:<6} {:<9} {}",
+            progress.milestone.id,
+            progress.milestone.status,
+            format!("{}/{}", progress.done, progress.total),
+            progress.milestone.title
+        );
+    }
+    Ok(Vec::new())
+}
+
+async fn handle_milestone_assign(
+    app: &App,
+    args: MilestoneAssign,
+) -> Result<Vec<i64>, AppError> {
+    let (milestone, changes) = app.assign_milestone(args.id, &args.step_ids).await?;
+    println!(
+        "Assigned steps {} to milestone ID: {}.",
+        args.step_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        milestone.id
+    );
+    print_status_changes(&changes);
+    Ok(vec![milestone.plan_id])
+}
+
+async fn handle_milestone_done(app: &App, args: MilestoneDone) -> Result<Vec<i64>, AppError> {
+    let milestone = app.complete_milestone(args.id).await?;
+    println!("Milestone ID: {} marked done.", milestone.id);
+    Ok(vec![milestone.plan_id])
+}
+
+async fn handle_criterion_add(app: &App, args: CriterionAdd) -> Result<Vec<i64>, AppError> {
+    for content in &args.contents {
+        require_non_empty("criterion content", content)?;
+    }
+    let (created, changes) = app.add_criteria(args.plan_id, args.contents).await?;
+    if created.len() == 1 {
+        println!(
+            "Created criterion ID: {} for plan ID: {}",
+            created[0].id, args.plan_id
+        );
+    } else {
+        println!(
+            "Created {} criteria for plan ID: {}",
+            created.len(),
+            args.plan_id
+        );
+    }
+    print_status_changes(&changes);
+    Ok(vec![args.plan_id])
+}
+
+async fn handle_criterion_list(app: &App, args: CriterionList) -> Result<Vec<i64>, AppError> {
+    let criteria = app.list_criteria(args.plan_id).await?;
+    if criteria.is_empty() {
+        println!("No criteria found for plan ID: {}.", args.plan_id);
+        return Ok(Vec::new());
+    }
+    println!("{:<4} {:<6} CONTENT", "ID", "STAT");
+    for item in &criteria {
+        println!("{:<4} {:<6} {}", item.id, item.status, item.content);
+    }
+    Ok(Vec::new())
+}
+
+async fn set_criteria_status(
+    app: &App,
+    ids: &[i64],
+    status: CriterionStatus,
+) -> Result<Vec<i64>, AppError> {
+    let (updated, changes) = app.set_criteria_status(ids, status).await?;
+    let verb = if status == CriterionStatus::Done {
+        "checked"
+    } else {
+        "unchecked"
+    };
+    if updated.len() == 1 {
+        println!("Criterion ID: {} {verb}.", updated[0].id);
+    } else {
+        println!("{} criteria {verb}.", updated.len());
+    }
+    print_status_changes(&changes);
+    notify_plans_completed(app, &changes).await?;
+    Ok(criteria_plan_ids(&updated))
+}
+
+async fn handle_criterion_remove(app: &App, args: CriterionRemove) -> Result<Vec<i64>, AppError> {
+    let (removed, changes) = app.delete_criteria(&args.ids).await?;
+    if removed.len() == 1 {
+        println!("Criterion ID: {} removed.", removed[0].id);
+    } else {
+        println!("Removed {} criteria.", removed.len());
+    }
+    print_status_changes(&changes);
+    notify_plans_completed(app, &changes).await?;
+    Ok(criteria_plan_ids(&removed))
+}
+
+fn criteria_plan_ids(criteria: &[crate::entities::criterion::Model]) -> Vec<i64> {
+    let mut plan_ids = Vec::new();
+    for item in criteria {
+        if !plan_ids.contains(&item.plan_id) {
+            plan_ids.push(item.plan_id);
+        }
+    }
+    plan_ids
+}
+
 async fn handle_step_add(app: &App, args: StepAdd) -> Result<Vec<i64>, AppError> {
     if args.contents.is_empty() {
         return Err(AppError::InvalidInput("no contents provided".to_string()));
@@ -531,15 +1864,49 @@ async fn handle_step_add(app: &App, args: StepAdd) -> Result<Vec<i64>, AppError>
             return Err(AppError::InvalidInput("position starts at 1".to_string()));
         }
     }
-    let (steps, changes) = app
-        .add_steps_batch(
-            args.plan_id,
-            args.contents.clone(),
-            StepStatus::Todo,
-            step_executor_from_arg(args.executor),
-            args.at,
-        )
-        .await?;
+    let schedule = StepSchedule {
+        priority: args.priority.and_then(priority_from_arg),
+        due: args.due.as_deref().map(parse_due).transpose()?.flatten(),
+    };
+    let executor = parse_step_executor(&args.executor)?;
+    if let Some(milestone_id) = args.milestone {
+        let milestone = app.get_milestone(milestone_id).await?;
+        if milestone.plan_id != args.plan_id {
+            return Err(AppError::InvalidInput(format!(
+                "milestone id {milestone_id} belongs to plan id {}, not plan id {}",
+                milestone.plan_id, args.plan_id
+            )));
+        }
+    }
+    let (steps, mut changes) = match args.parent {
+        Some(parent_id) => {
+            app.add_sub_steps(
+                args.plan_id,
+                parent_id,
+                args.contents.clone(),
+                StepStatus::Todo,
+                executor,
+                schedule,
+            )
+            .await?
+        }
+        None => {
+            app.add_steps_batch(
+                args.plan_id,
+                args.contents.clone(),
+                StepStatus::Todo,
+                executor,
+                schedule,
+                args.at,
+            )
+            .await?
+        }
+    };
+    if let Some(milestone_id) = args.milestone {
+        let ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
+        let (_, assigned) = app.assign_milestone(milestone_id, &ids).await?;
+        changes.merge(assigned);
+    }
     if steps.len() == 1 {
         println!(
             "Created step ID: {} for plan ID: {}",
@@ -561,12 +1928,16 @@ async fn handle_step_add_tree(app: &App, args: StepAddTree) -> Result<Vec<i64>,
     for goal in &args.goals {
         require_non_empty("goal content", goal)?;
     }
-    let executor = args
-        .executor
-        .map(step_executor_from_arg)
-        .unwrap_or(StepExecutor::Ai);
+    let executor = match args.executor.as_deref() {
+        Some(value) => parse_step_executor(value)?,
+        None => StepExecutor::ai(),
+    };
+    let schedule = StepSchedule {
+        priority: args.priority.and_then(priority_from_arg),
+        due: args.due.as_deref().map(parse_due).transpose()?.flatten(),
+    };
     let (step, goals, changes) = app
-        .add_step_tree(args.plan_id, args.content, executor, args.goals)
+        .add_step_tree(args.plan_id, args.content, executor, schedule, args.goals, args.parent)
         .await?;
     let goal_count = goals.len();
 
@@ -591,7 +1962,9 @@ async fn handle_step_list(app: &App, args: StepList) -> Result<Vec<i64>, AppErro
 
     let query = StepQuery {
         status,
-        executor: args.executor.map(step_executor_from_arg),
+        executor: args.executor.as_deref().map(parse_step_executor).transpose()?,
+        tags: args.tags,
+        tag_match: tag_match_from_arg(args.tag_mode),
         limit: args.limit,
         offset: args.offset,
         order: args.order.map(step_order_from_arg),
@@ -617,22 +1990,121 @@ async fn handle_step_list(app: &App, args: StepList) -> Result<Vec<i64>, AppErro
 
 async fn handle_step_show(app: &App, args: StepShow) -> Result<Vec<i64>, AppError> {
     let detail = app.get_step_detail(args.id).await?;
-    println!("{}", format_step_detail(&detail.step, &detail.goals));
+    println!(
+        "{}",
+        format_step_detail(
+            &detail.step,
+            &detail.goals,
+            &detail.tags,
+            detail.milestone.as_ref(),
+        )
+    );
+    Ok(Vec::new())
+}
+
+fn require_repo_dir<'a>(app: &'a App, command: &str) -> Result<&'a Path, AppError> {
+    app.repo_dir().ok_or_else(|| {
+        AppError::InvalidInput(format!("{command} needs --cwd pointing at the git repository"))
+    })
+}
+
+async fn handle_step_diff(app: &App, args: StepDiff) -> Result<Vec<i64>, AppError> {
+    let repo_dir = require_repo_dir(app, "step diff")?;
+    let (step, base) = app.step_checkpoint_base(args.id).await?;
+    let to = step.checkpoint_commit.clone().unwrap_or_default();
+    let (from, since) = match (args.from, base) {
+        (Some(rev), _) => (rev.clone(), rev),
+        (None, Some(base)) => (
+            base.checkpoint_commit.clone().unwrap_or_default(),
+            format!("step ID: {}", base.id),
+        ),
+        (None, None) => {
+            return Err(AppError::InvalidInput(format!(
+                "no earlier checkpoint in plan id {} before step id {}; pass --from <rev>",
+                step.plan_id, step.id
+            )))
+        }
+    };
+    println!(
+        "Step ID: {} changes since {since} ({}..{})",
+        step.id,
+        git::short_commit(&from),
+        git::short_commit(&to)
+    );
+    if step.checkpoint_dirty == Some(true) {
+        println!("Note: the step was completed with uncommitted changes; they are not included.");
+    }
+    let diff = git::diff(repo_dir, &from, &to)?;
+    if diff.is_empty() {
+        println!("No changes.");
+    } else {
+        println!("{diff}");
+    }
     Ok(Vec::new())
 }
 
-async fn handle_step_show_next(app: &App) -> Result<Vec<i64>, AppError> {
+async fn handle_step_show_next(app: &App, args: StepShowNext) -> Result<Vec<i64>, AppError> {
     let Some(active) = app.get_active_plan().await? else {
         println!("No active plan.");
+        print_focus_stack(app, None).await?;
         return Ok(Vec::new());
     };
-    let next = app.next_step(active.plan_id).await?;
+    let next = if args.claim {
+        let lease = parse_lease_minutes(args.lease)?;
+        app.claim_next_step(active.plan_id, lease).await?
+    } else {
+        app.next_unclaimed_step(active.plan_id).await?
+    };
     let Some(step) = next else {
-        println!("No pending step.");
+        let unmet = unmet_criteria_after_steps(app, active.plan_id).await?;
+        if unmet.is_empty() {
+            println!("No pending step.");
+        } else {
+            print_unmet_criteria(active.plan_id, &unmet);
+        }
+        print_focus_stack(app, Some(active.plan_id)).await?;
         return Ok(Vec::new());
     };
-    let goals = app.goals_for_step(step.id).await?;
-    println!("{}", format_step_detail(&step, &goals));
+    let detail = app.get_step_detail(step.id).await?;
+    println!(
+        "{}",
+        format_step_detail(
+            &detail.step,
+            &detail.goals,
+            &detail.tags,
+            detail.milestone.as_ref(),
+        )
+    );
+    print_focus_stack(app, Some(active.plan_id)).await?;
+    Ok(Vec::new())
+}
+
+async fn handle_step_claim(app: &App, args: StepClaim) -> Result<Vec<i64>, AppError> {
+    let lease = parse_lease_minutes(args.lease)?;
+    let claim = app.claim_step(args.id, lease, args.force).await?;
+    println!(
+        "Claimed step ID: {} until {}.",
+        claim.step_id,
+        format_datetime(claim.expires_at)
+    );
+    Ok(Vec::new())
+}
+
+async fn handle_step_release(app: &App, args: StepRelease) -> Result<Vec<i64>, AppError> {
+    app.release_step(args.id).await?;
+    println!("Released claim on step ID: {}.", args.id);
+    Ok(Vec::new())
+}
+
+async fn handle_step_tag(app: &App, args: StepTag) -> Result<Vec<i64>, AppError> {
+    let tags = app.tag(TagTarget::Step, args.id, &args.tags).await?;
+    println!("Step ID: {} tags: {}", args.id, format_tag_list(&tags));
+    Ok(Vec::new())
+}
+
+async fn handle_step_untag(app: &App, args: StepUntag) -> Result<Vec<i64>, AppError> {
+    let tags = app.untag(TagTarget::Step, args.id, &args.tags).await?;
+    println!("Step ID: {} tags: {}", args.id, format_tag_list(&tags));
     Ok(Vec::new())
 }
 
@@ -647,8 +2119,10 @@ async fn handle_step_update(app: &App, args: StepUpdate) -> Result<Vec<i64>, App
             StepChanges {
                 content: args.content,
                 status,
-                executor: args.executor.map(step_executor_from_arg),
+                executor: args.executor.as_deref().map(parse_step_executor).transpose()?,
                 comment: args.comment,
+                priority: args.priority.map(priority_from_arg),
+                due: args.due.as_deref().map(parse_due).transpose()?,
             },
         )
         .await?;
@@ -685,23 +2159,84 @@ async fn handle_step_done(app: &App, args: StepDone) -> Result<Vec<i64>, AppErro
 }
 
 async fn handle_step_move(app: &App, args: StepMove) -> Result<Vec<i64>, AppError> {
-    if args.to == 0 {
+    if args.to == Some(0) {
         return Err(AppError::InvalidInput("position starts at 1".to_string()));
     }
-    let steps = app.move_step(args.id, args.to).await?;
+    if let Some(plan_id) = args.plan {
+        let source_plan_id = app.get_step(args.id).await?.plan_id;
+        let (steps, changes) = app.move_step_to_plan(args.id, plan_id, args.to).await?;
+        println!("Moved step ID: {} to plan ID: {}:", args.id, plan_id);
+        let details = app.get_steps_detail(&steps).await?;
+        print_step_list(&details);
+        print_status_changes(&changes);
+        notify_plans_completed(app, &changes).await?;
+        return Ok(vec![source_plan_id, plan_id]);
+    }
+    let Some(to) = args.to else {
+        return Err(AppError::InvalidInput(
+            "step move requires --to or --plan".to_string(),
+        ));
+    };
+    let steps = app.move_step(args.id, to).await?;
     println!("Reordered steps for plan ID: {}:", steps[0].plan_id);
     let details = app.get_steps_detail(&steps).await?;
     print_step_list(&details);
     Ok(vec![steps[0].plan_id])
 }
 
+async fn handle_step_split(app: &App, args: StepSplit) -> Result<Vec<i64>, AppError> {
+    if args.parts.len() < 2 {
+        return Err(AppError::InvalidInput(
+            "step split needs at least two --into parts".to_string(),
+        ));
+    }
+    for part in &args.parts {
+        require_non_empty("step content", part)?;
+    }
+    let assignments = args
+        .assignments
+        .iter()
+        .map(|value| parse_goal_assignment(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (steps, changes) = app.split_step(args.id, args.parts, &assignments).await?;
+    let ids: Vec<i64> = steps.iter().map(|step| step.id).collect();
+    println!(
+        "Split step ID: {} into steps: {}.",
+        args.id,
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let details = app.get_steps_detail(&steps).await?;
+    print_step_list(&details);
+    print_status_changes(&changes);
+    Ok(vec![steps[0].plan_id])
+}
+
+async fn handle_step_merge(app: &App, args: StepMerge) -> Result<Vec<i64>, AppError> {
+    let (step, changes) = app.merge_steps(args.target, args.source).await?;
+    println!(
+        "Merged step ID: {} into step ID: {}.",
+        args.source, args.target
+    );
+    print_status_changes(&changes);
+    Ok(vec![step.plan_id])
+}
+
 async fn handle_step_remove(app: &App, args: StepRemove) -> Result<Vec<i64>, AppError> {
     if args.ids.is_empty() {
         return Err(AppError::InvalidInput("no step ids provided".to_string()));
     }
     let plan_ids = app.plan_ids_for_steps(&args.ids).await?;
     let (deleted, changes) = app.delete_steps(&args.ids).await?;
-    if args.ids.len() == 1 {
+    if args.ids.len() == 1 && deleted > 1 {
+        println!(
+            "Step ID: {} removed with {} sub-steps.",
+            args.ids[0],
+            deleted - 1
+        );
+    } else if args.ids.len() == 1 {
         println!("Step ID: {} removed.", args.ids[0]);
     } else {
         println!("Removed {} steps.", deleted);
@@ -830,6 +2365,27 @@ async fn handle_goal_done(app: &App, args: GoalDone) -> Result<Vec<i64>, AppErro
     Ok(plan_ids)
 }
 
+async fn handle_goal_move(app: &App, args: GoalMove) -> Result<Vec<i64>, AppError> {
+    if args.to == Some(0) {
+        return Err(AppError::InvalidInput("position starts at 1".to_string()));
+    }
+    let mut plan_ids = app.plan_ids_for_goals(&[args.id]).await?;
+    let (goals, changes) = app.move_goal(args.id, args.step, args.to).await?;
+    let step = app.get_step(goals[0].step_id).await?;
+    match args.step {
+        Some(step_id) => println!("Moved goal ID: {} to step ID: {}:", args.id, step_id),
+        None => println!("Reordered goals for step ID: {}:", step.id),
+    }
+    print_goal_list(&goals);
+    print_status_changes(&changes);
+    notify_after_step_changes(app, &changes).await?;
+    notify_plans_completed(app, &changes).await?;
+    if !plan_ids.contains(&step.plan_id) {
+        plan_ids.push(step.plan_id);
+    }
+    Ok(plan_ids)
+}
+
 async fn handle_goal_remove(app: &App, args: GoalRemove) -> Result<Vec<i64>, AppError> {
     if args.ids.is_empty() {
         return Err(AppError::InvalidInput("no goal ids provided".to_string()));
@@ -847,6 +2403,52 @@ async fn handle_goal_remove(app: &App, args: GoalRemove) -> Result<Vec<i64>, App
     Ok(plan_ids)
 }
 
+async fn handle_session_list(
+    app: &App,
+    args: SessionList,
+    session_ttl: Duration,
+) -> Result<Vec<i64>, AppError> {
+    let stale_after = resolve_stale_after(args.stale_after, session_ttl)?;
+    let mut sessions = app.list_sessions(stale_after).await?;
+    if args.stale {
+        sessions.retain(|summary| summary.stale);
+    }
+    if sessions.is_empty() {
+        println!("No sessions found.");
+        return Ok(Vec::new());
+    }
+    print_session_list(&sessions);
+    Ok(Vec::new())
+}
+
+async fn handle_session_prune(
+    app: &App,
+    args: SessionPrune,
+    session_ttl: Duration,
+) -> Result<Vec<i64>, AppError> {
+    let stale_after = resolve_stale_after(args.stale_after, session_ttl)?;
+    if args.dry_run {
+        let mut sessions = app.list_sessions(stale_after).await?;
+        sessions.retain(|summary| summary.stale && summary.session_id != app.session_id());
+        if sessions.is_empty() {
+            println!("No stale sessions.");
+        } else {
+            println!("Would prune {} stale sessions:", sessions.len());
+            print_session_list(&sessions);
+        }
+        return Ok(Vec::new());
+    }
+
+    let (pruned, plan_ids) = app.prune_sessions(stale_after).await?;
+    if pruned.is_empty() {
+        println!("No stale sessions.");
+        return Ok(Vec::new());
+    }
+    println!("Pruned {} stale sessions:", pruned.len());
+    print_session_list(&pruned);
+    Ok(plan_ids)
+}
+
 async fn sync_plan_md(claude_home: &Path, app: &App, plan_ids: &[i64]) -> Result<(), AppError> {
     if plan_ids.is_empty() {
         return Ok(());
@@ -871,15 +2473,18 @@ async fn sync_plan_md(claude_home: &Path, app: &App, plan_ids: &[i64]) -> Result
 
         let is_active = active_id == Some(*plan_id);
         let activated_at = if is_active { active_updated } else { None };
-        let md_path = db::resolve_plan_md_path(claude_home, *plan_id);
+        let current_path = db::resolve_plan_md_path(claude_home, *plan_id);
+        let archived_path = db::resolve_archived_plan_md_path(claude_home, *plan_id);
+        let (md_path, stale_path) = if detail.plan.archived_at.is_some() {
+            (archived_path, current_path)
+        } else {
+            (current_path, archived_path)
+        };
+        if stale_path.exists() {
+            fs::remove_file(&stale_path)?;
+        }
         db::ensure_parent_dir(&md_path)?;
-        let markdown = format_plan_markdown(
-            is_active,
-            activated_at,
-            &detail.plan,
-            &detail.steps,
-            &detail.goals,
-        );
+        let markdown = format_plan_markdown(is_active, activated_at, &detail);
         fs::write(md_path, markdown)?;
     }
 
@@ -1122,7 +2727,8 @@ fn resolve_session_id(session_id: Option<String>) -> Result<String, AppError> {
 #[derive(Debug)]
 struct StepSpecBuilder {
     content: String,
-    executor: Option<StepExecutorArg>,
+    executor: Option<StepExecutor>,
+    schedule: StepSchedule,
     goals: Vec<String>,
 }
 
@@ -1131,6 +2737,7 @@ impl StepSpecBuilder {
         Self {
             content: content.to_string(),
             executor: None,
+            schedule: StepSchedule::default(),
             goals: Vec::new(),
         }
     }
@@ -1139,6 +2746,7 @@ impl StepSpecBuilder {
         StepSpec {
             content: self.content,
             executor: self.executor,
+            schedule: self.schedule,
             goals: if self.goals.is_empty() {
                 None
             } else {
@@ -1179,7 +2787,7 @@ fn parse_plan_add_tree_steps(args: &[String]) -> Result<Vec<StepSpec>, AppError>
                 let value = args.get(idx + 1).ok_or_else(|| {
                     AppError::InvalidInput("plan add-tree --executor requires a value".to_string())
                 })?;
-                let executor = parse_step_executor_arg(value)?;
+                let executor = parse_step_executor(value)?;
                 match current.as_mut() {
                     Some(step) => {
                         step.executor = Some(executor);
@@ -1192,6 +2800,44 @@ fn parse_plan_add_tree_steps(args: &[String]) -> Result<Vec<StepSpec>, AppError>
                 }
                 idx += 2;
             }
+            "--priority" => {
+                let value = args.get(idx + 1).ok_or_else(|| {
+                    AppError::InvalidInput("plan add-tree --priority requires a value".to_string())
+                })?;
+                let priority = PriorityArg::from_str(value, true).map_err(|_| {
+                    AppError::InvalidInput(format!(
+                        "invalid priority '{value}', expected p0, p1, p2, p3 or none"
+                    ))
+                })?;
+                match current.as_mut() {
+                    Some(step) => {
+                        step.schedule.priority = priority_from_arg(priority);
+                    }
+                    None => {
+                        return Err(AppError::InvalidInput(
+                            "plan add-tree --priority must follow a --step".to_string(),
+                        ));
+                    }
+                }
+                idx += 2;
+            }
+            "--due" => {
+                let value = args.get(idx + 1).ok_or_else(|| {
+                    AppError::InvalidInput("plan add-tree --due requires a value".to_string())
+                })?;
+                let due = parse_due(value)?;
+                match current.as_mut() {
+                    Some(step) => {
+                        step.schedule.due = due;
+                    }
+                    None => {
+                        return Err(AppError::InvalidInput(
+                            "plan add-tree --due must follow a --step".to_string(),
+                        ));
+                    }
+                }
+                idx += 2;
+            }
             "--goal" => {
                 let value = args.get(idx + 1).ok_or_else(|| {
                     AppError::InvalidInput("plan add-tree --goal requires a value".to_string())
@@ -1229,14 +2875,12 @@ fn parse_plan_add_tree_steps(args: &[String]) -> Result<Vec<StepSpec>, AppError>
     Ok(steps)
 }
 
-fn parse_step_executor_arg(value: &str) -> Result<StepExecutorArg, AppError> {
-    match value.trim().to_ascii_lowercase().as_str() {
-        "ai" => Ok(StepExecutorArg::Ai),
-        "human" => Ok(StepExecutorArg::Human),
-        _ => Err(AppError::InvalidInput(format!(
-            "invalid executor '{value}', expected ai|human"
-        ))),
-    }
+fn parse_step_executor(value: &str) -> Result<StepExecutor, AppError> {
+    StepExecutor::parse(value).ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "invalid executor '{value}', expected ai|human|<kind>[:<name>]"
+        ))
+    })
 }
 
 fn parse_step_spec_value(value: &str) -> Result<StepSpecBuilder, AppError> {
@@ -1248,13 +2892,25 @@ fn parse_step_spec_value(value: &str) -> Result<StepSpecBuilder, AppError> {
     }
     if trimmed.starts_with('{') {
         return Err(AppError::InvalidInput(
-            "plan add-tree no longer accepts JSON step specs; use --step <content> [--executor ai|human] [--goal <goal> ...]"
+            "plan add-tree no longer accepts JSON step specs; use --step <content> [--executor <executor>] [--goal <goal> ...]"
                 .to_string(),
         ));
     }
     Ok(StepSpecBuilder::new(value))
 }
 
+fn parse_goal_assignment(value: &str) -> Result<(i64, usize), AppError> {
+    let invalid = || {
+        AppError::InvalidInput(format!(
+            "invalid goal assignment '{value}', expected <goal_id>=<part>"
+        ))
+    };
+    let (goal_id, part) = value.split_once('=').ok_or_else(invalid)?;
+    let goal_id = goal_id.trim().parse::<i64>().map_err(|_| invalid())?;
+    let part = part.trim().parse::<usize>().map_err(|_| invalid())?;
+    Ok((goal_id, part))
+}
+
 fn parse_comment_pairs(kind: &str, pairs: Vec<String>) -> Result<Vec<(i64, String)>, AppError> {
     if pairs.is_empty() {
         return Err(AppError::InvalidInput(format!(
@@ -1262,7 +2918,7 @@ fn parse_comment_pairs(kind: &str, pairs: Vec<String>) -> Result<Vec<(i64, Strin
         )));
     }
 
-    if pairs.len() % 2 != 0 {
+    if !pairs.len().is_multiple_of(2) {
         return Err(AppError::InvalidInput(format!(
             "{kind} comment expects <id> <comment> pairs"
         )));
@@ -1296,13 +2952,6 @@ fn step_status_from_arg(arg: StepStatusArg) -> StepStatus {
     }
 }
 
-fn step_executor_from_arg(arg: StepExecutorArg) -> StepExecutor {
-    match arg {
-        StepExecutorArg::Ai => StepExecutor::Ai,
-        StepExecutorArg::Human => StepExecutor::Human,
-    }
-}
-
 fn goal_status_from_arg(arg: GoalStatusArg) -> GoalStatus {
     match arg {
         GoalStatusArg::Todo => GoalStatus::Todo,
@@ -1315,7 +2964,136 @@ fn step_order_from_arg(arg: StepOrderArg) -> StepOrder {
         StepOrderArg::Order => StepOrder::Order,
         StepOrderArg::Id => StepOrder::Id,
         StepOrderArg::Created => StepOrder::Created,
+        StepOrderArg::Priority => StepOrder::Priority,
+        StepOrderArg::Due => StepOrder::Due,
+    }
+}
+
+fn tag_match_from_arg(arg: Option<TagModeArg>) -> TagMatch {
+    match arg {
+        Some(TagModeArg::Any) => TagMatch::Any,
+        Some(TagModeArg::All) | None => TagMatch::All,
+    }
+}
+
+async fn retain_plans_with_tags(
+    app: &App,
+    plans: &mut Vec<crate::entities::plan::Model>,
+    tags: &[String],
+    mode: Option<TagModeArg>,
+) -> Result<(), AppError> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    let ids = app
+        .ids_with_tags(TagTarget::Plan, tags, tag_match_from_arg(mode))
+        .await?;
+    plans.retain(|plan| ids.contains(&plan.id));
+    Ok(())
+}
+
+fn format_tag_list(tags: &[String]) -> String {
+    if tags.is_empty() {
+        "(none)".to_string()
+    } else {
+        tags.join(", ")
+    }
+}
+
+fn plan_order_from_sort(arg: PlanSortArg) -> PlanOrder {
+    match arg {
+        PlanSortArg::Priority => PlanOrder::Priority,
+        PlanSortArg::Due => PlanOrder::Due,
+        PlanSortArg::Updated => PlanOrder::Updated,
+        PlanSortArg::Progress => PlanOrder::Progress,
+    }
+}
+
+fn event_kind_from_arg(arg: EventKindArg) -> EventKind {
+    match arg {
+        EventKindArg::GoalDone => EventKind::GoalDone,
+        EventKindArg::StepDone => EventKind::StepDone,
+        EventKindArg::PlanDone => EventKind::PlanDone,
+        EventKindArg::PlanActivated => EventKind::PlanActivated,
+        EventKindArg::StepHumanRequired => EventKind::StepHumanRequired,
+    }
+}
+
+fn priority_from_arg(arg: PriorityArg) -> Option<Priority> {
+    match arg {
+        PriorityArg::P0 => Some(Priority::P0),
+        PriorityArg::P1 => Some(Priority::P1),
+        PriorityArg::P2 => Some(Priority::P2),
+        PriorityArg::P3 => Some(Priority::P3),
+        PriorityArg::None => None,
+    }
+}
+
+fn parse_due(value: &str) -> Result<Option<NaiveDate>, AppError> {
+    if value.trim().eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    parse_due_date(value).map(Some).ok_or_else(|| {
+        AppError::InvalidInput(format!("invalid due date '{value}', expected YYYY-MM-DD or none"))
+    })
+}
+
+fn resolve_session_ttl() -> Result<Duration, AppError> {
+    match std::env::var(SESSION_TTL_ENV) {
+        Ok(value) => {
+            let minutes = value.trim().parse::<i64>().map_err(|_| {
+                AppError::InvalidInput(format!("{SESSION_TTL_ENV} must be a number of minutes"))
+            })?;
+            parse_stale_minutes(minutes)
+        }
+        Err(_) => Ok(Duration::minutes(DEFAULT_SESSION_TTL_MINUTES)),
+    }
+}
+
+fn resolve_auto_archive_after() -> Result<Option<Duration>, AppError> {
+    match std::env::var(AUTO_ARCHIVE_ENV) {
+        Ok(value) => {
+            let days = value.trim().parse::<i64>().map_err(|_| {
+                AppError::InvalidInput(format!("{AUTO_ARCHIVE_ENV} must be a number of days"))
+            })?;
+            parse_done_days(days).map(Some)
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn parse_done_days(days: i64) -> Result<Duration, AppError> {
+    if days < 0 {
+        return Err(AppError::InvalidInput(
+            "done days must be 0 or more".to_string(),
+        ));
+    }
+    Ok(Duration::days(days))
+}
+
+fn resolve_stale_after(minutes: Option<i64>, session_ttl: Duration) -> Result<Duration, AppError> {
+    match minutes {
+        Some(minutes) => parse_stale_minutes(minutes),
+        None => Ok(session_ttl),
+    }
+}
+
+fn parse_stale_minutes(minutes: i64) -> Result<Duration, AppError> {
+    if minutes <= 0 {
+        return Err(AppError::InvalidInput(
+            "stale period must be at least 1 minute".to_string(),
+        ));
     }
+    Ok(Duration::minutes(minutes))
+}
+
+fn parse_lease_minutes(minutes: i64) -> Result<Duration, AppError> {
+    if minutes <= 0 {
+        return Err(AppError::InvalidInput(
+            "lease must be at least 1 minute".to_string(),
+        ));
+    }
+    Ok(Duration::minutes(minutes))
 }
 
 fn require_non_empty(label: &str, value: &str) -> Result<(), AppError> {
@@ -1326,29 +3104,48 @@ fn require_non_empty(label: &str, value: &str) -> Result<(), AppError> {
 }
 
 fn print_status_changes(changes: &StatusChanges) {
+    for line in format_status_changes(changes) {
+        println!("{line}");
+    }
+}
+
+fn format_status_changes(changes: &StatusChanges) -> Vec<String> {
     if changes.is_empty() {
-        return;
+        return Vec::new();
     }
 
-    println!("Auto status updates:");
+    let mut lines = vec!["Auto status updates:".to_string()];
     for change in &changes.steps {
-        println!(
+        lines.push(format!(
             "- Step ID: {} status auto-updated from {} to {} ({}).",
             change.step_id, change.from, change.to, change.reason
-        );
+        ));
+    }
+    for change in &changes.milestones {
+        lines.push(format!(
+            "- Milestone ID: {} status auto-updated from {} to {} ({}).",
+            change.milestone_id, change.from, change.to, change.reason
+        ));
     }
     for change in &changes.plans {
-        println!(
+        lines.push(format!(
             "- Plan ID: {} status auto-updated from {} to {} ({}).",
             change.plan_id, change.from, change.to, change.reason
-        );
+        ));
     }
     for change in &changes.active_plans_cleared {
-        println!(
+        lines.push(format!(
             "- Active plan deactivated for plan ID: {} ({}).",
             change.plan_id, change.reason
-        );
+        ));
+    }
+    for change in &changes.active_plans_resumed {
+        lines.push(format!(
+            "- Active plan set to plan ID: {} ({}).",
+            change.plan_id, change.reason
+        ));
     }
+    lines
 }
 
 async fn notify_after_step_changes(app: &App, changes: &StatusChanges) -> Result<(), AppError> {
@@ -1359,6 +3156,9 @@ async fn notify_after_step_changes(app: &App, changes: &StatusChanges) -> Result
             plan_ids.insert(step.plan_id);
         }
     }
+    for change in &changes.active_plans_resumed {
+        plan_ids.insert(change.plan_id);
+    }
     for plan_id in plan_ids {
         notify_next_step_for_plan(app, plan_id).await?;
     }
@@ -1375,68 +3175,138 @@ async fn notify_plans_completed(app: &App, changes: &StatusChanges) -> Result<()
     for plan_id in plan_ids {
         let plan = app.get_plan(plan_id).await?;
         if plan.status == PlanStatus::Done.as_str() {
-            notify_plan_completed(&plan);
+            notify_plan_completed(app, &plan).await?;
         }
     }
     Ok(())
 }
 
-fn notify_plan_completed(plan: &crate::entities::plan::Model) {
+async fn notify_plan_completed(
+    app: &App,
+    plan: &crate::entities::plan::Model,
+) -> Result<(), AppError> {
+    let criteria = app.list_criteria(plan.id).await?;
+    if criteria.is_empty() {
+        println!(
+            "Plan ID: {} is complete. Summarize the completed results to the user, then end this turn.",
+            plan.id
+        );
+        return Ok(());
+    }
     println!(
-        "Plan ID: {} is complete. Summarize the completed results to the user, then end this turn.",
+        "Plan ID: {} is complete. Summarize the completed results to the user, covering each acceptance criterion below, then end this turn.",
         plan.id
     );
+    println!("Acceptance criteria:");
+    for item in &criteria {
+        println!("- [{}] {} (criterion id {})", item.status, item.content, item.id);
+    }
+    Ok(())
 }
 
 async fn notify_next_step_for_plan(app: &App, plan_id: i64) -> Result<(), AppError> {
-    let next = app.next_step(plan_id).await?;
+    let next = app.next_unclaimed_step(plan_id).await?;
     let Some(step) = next else {
+        let unmet = unmet_criteria_after_steps(app, plan_id).await?;
+        if !unmet.is_empty() {
+            print_unmet_criteria(plan_id, &unmet);
+        }
         return Ok(());
     };
-    if step.executor == StepExecutor::Ai.as_str() {
+    let executor = StepExecutor::parse(&step.executor).unwrap_or_else(StepExecutor::ai);
+    if executor.is_ai() {
+        println!(
+            "Next step is assigned to {} (step ID: {}). Please end this turn so Planpilot can surface it.",
+            executor, step.id
+        );
+        return Ok(());
+    }
+    if !executor.is_human() {
         println!(
-            "Next step is assigned to ai (step ID: {}). Please end this turn so Planpilot can surface it.",
-            step.id
+            "Next step is assigned to {} (step ID: {}). Wait for it to be completed outside this session, then end this turn.",
+            executor, step.id
         );
         return Ok(());
     }
 
-    let goals = app.goals_for_step(step.id).await?;
+    let detail = app.get_step_detail(step.id).await?;
     println!("Next step requires human action:");
-    println!("{}", format_step_detail(&step, &goals));
+    println!(
+        "{}",
+        format_step_detail(
+            &detail.step,
+            &detail.goals,
+            &detail.tags,
+            detail.milestone.as_ref(),
+        )
+    );
     println!(
         "Tell the user to complete the above step and goals. Confirm each goal when done, then end this turn."
     );
     Ok(())
 }
 
+/// The plan's unmet acceptance criteria once every step is done; empty while a step is left.
+async fn unmet_criteria_after_steps(
+    app: &App,
+    plan_id: i64,
+) -> Result<Vec<crate::entities::criterion::Model>, AppError> {
+    let (_, steps) = app.plan_with_steps(plan_id).await?;
+    if steps.iter().any(|step| step.status != StepStatus::Done.as_str()) {
+        return Ok(Vec::new());
+    }
+    let criteria = app.list_criteria(plan_id).await?;
+    Ok(criteria
+        .into_iter()
+        .filter(|item| item.status != CriterionStatus::Done.as_str())
+        .collect())
+}
+
+fn print_unmet_criteria(plan_id: i64, unmet: &[crate::entities::criterion::Model]) {
+    println!("Unmet acceptance criteria for plan ID: {plan_id}:");
+    for item in unmet {
+        println!("- [ ] {} (criterion id {})", item.content, item.id);
+    }
+    println!(
+        "All steps are done. Verify each criterion, then run `planpilot criterion check <id>` for the ones that are met."
+    );
+}
+
 fn print_plan_list(details: &[crate::app::PlanDetail]) {
     println!(
-        "{:<4} {:<6} {:<7} {:<30} {}",
-        "ID", "STAT", "STEPS", "TITLE", "COMMENT"
+        "{:<4} {:<6} {:<7} {:<4} {:<10} {:<30} COMMENT",
+        "ID", "STAT", "STEPS", "PRI", "DUE", "TITLE"
     );
     for detail in details {
-        let total = detail.steps.len();
-        let done = detail
-            .steps
-            .iter()
-            .filter(|step| step.status == StepStatus::Done.as_str())
-            .count();
+        let (done, total) = plan_progress(detail);
         println!(
-            "{:<4} {:<6} {:<7} {:<30} {}",
+            "{:<4} {:<6} {:<7} {:<4} {:<10} {:<30} {}",
             detail.plan.id,
             detail.plan.status,
             format!("{}/{}", done, total),
+            detail.plan.priority.as_deref().unwrap_or("-"),
+            detail
+                .plan
+                .due
+                .map(|due| due.to_string())
+                .unwrap_or_else(|| "-".to_string()),
             detail.plan.title,
             detail.plan.comment.as_deref().unwrap_or("")
         );
     }
 }
 
+fn print_tag_list(tags: &[TagUsage]) {
+    println!("{:<20} {:<6} STEPS", "TAG", "PLANS");
+    for tag in tags {
+        println!("{:<20} {:<6} {}", tag.name, tag.plans, tag.steps);
+    }
+}
+
 fn print_step_list(details: &[crate::app::StepDetail]) {
     println!(
-        "{:<4} {:<6} {:<6} {:<9} {:<30} {}",
-        "ID", "STAT", "EXEC", "GOALS", "CONTENT", "COMMENT"
+        "{:<4} {:<6} {:<6} {:<9} {:<30} COMMENT",
+        "ID", "STAT", "EXEC", "GOALS", "CONTENT"
     );
     for detail in details {
         let total = detail.goals.len();
@@ -1457,8 +3327,29 @@ fn print_step_list(details: &[crate::app::StepDetail]) {
     }
 }
 
+fn print_session_list(sessions: &[SessionSummary]) {
+    println!(
+        "{:<36} {:<6} {:<16} {:<6} {:<6} CWD",
+        "SESSION", "STATE", "LAST SEEN", "PLAN", "CLAIMS"
+    );
+    for summary in sessions {
+        println!(
+            "{:<36} {:<6} {:<16} {:<6} {:<6} {}",
+            summary.session_id,
+            if summary.stale { "stale" } else { "live" },
+            format_datetime(summary.last_seen_at),
+            summary
+                .plan_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            summary.claims,
+            summary.cwd.as_deref().unwrap_or("")
+        );
+    }
+}
+
 fn print_goal_list(goals: &[crate::entities::goal::Model]) {
-    println!("{:<4} {:<6} {:<30} {}", "ID", "STAT", "CONTENT", "COMMENT");
+    println!("{:<4} {:<6} {:<30} COMMENT", "ID", "STAT", "CONTENT");
     for goal in goals {
         println!(
             "{:<4} {:<6} {:<30} {}",
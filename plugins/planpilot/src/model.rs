@@ -1,3 +1,6 @@
+use std::fmt;
+
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -30,6 +33,58 @@ impl StepStatus {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MilestoneStatus {
+    Todo,
+    Done,
+}
+
+impl MilestoneStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Todo => "todo",
+            Self::Done => "done",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CriterionStatus {
+    Todo,
+    Done,
+}
+
+impl CriterionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Todo => "todo",
+            Self::Done => "done",
+        }
+    }
+}
+
+/// Things that happen to plans which event hooks can run commands for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventKind {
+    GoalDone,
+    StepDone,
+    PlanDone,
+    PlanActivated,
+    StepHumanRequired,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GoalDone => "goal.done",
+            Self::StepDone => "step.done",
+            Self::PlanDone => "plan.done",
+            Self::PlanActivated => "plan.activated",
+            Self::StepHumanRequired => "step.human_required",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum GoalStatus {
     Todo,
@@ -45,25 +100,107 @@ impl GoalStatus {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub enum StepExecutor {
-    Ai,
-    Human,
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Priority {
+    P0,
+    P1,
+    P2,
+    P3,
 }
 
-impl StepExecutor {
+impl Priority {
     pub fn as_str(&self) -> &'static str {
         match self {
-            Self::Ai => "ai",
-            Self::Human => "human",
+            Self::P0 => "p0",
+            Self::P1 => "p1",
+            Self::P2 => "p2",
+            Self::P3 => "p3",
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+pub fn parse_due_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+pub fn is_overdue(due: Option<NaiveDate>, status: &str, today: NaiveDate) -> bool {
+    status != PlanStatus::Done.as_str() && due.is_some_and(|date| date < today)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StepExecutor(String);
+
+impl StepExecutor {
+    pub const AI: &'static str = "ai";
+    pub const HUMAN: &'static str = "human";
+
+    pub fn ai() -> Self {
+        Self(Self::AI.to_string())
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let trimmed = value.trim();
+        let (kind, agent) = match trimmed.split_once(':') {
+            Some((kind, agent)) => (kind, Some(agent)),
+            None => (trimmed, None),
+        };
+        let kind = kind.to_ascii_lowercase();
+        if !is_executor_token(&kind) {
+            return None;
+        }
+        match agent {
+            Some(agent) if is_executor_token(agent) => Some(Self(format!("{kind}:{agent}"))),
+            Some(_) => None,
+            None => Some(Self(kind)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn kind(&self) -> &str {
+        self.0.split_once(':').map_or(&self.0, |(kind, _)| kind)
+    }
+
+    pub fn agent(&self) -> Option<&str> {
+        self.0.split_once(':').map(|(_, agent)| agent)
+    }
+
+    pub fn is_ai(&self) -> bool {
+        self.kind() == Self::AI
+    }
+
+    pub fn is_human(&self) -> bool {
+        self.kind() == Self::HUMAN
+    }
+}
+
+impl fmt::Display for StepExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn is_executor_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PlanInput {
     pub title: String,
     pub content: String,
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDate>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StepSchedule {
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDate>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -72,6 +209,8 @@ pub struct PlanChanges {
     pub content: Option<String>,
     pub status: Option<PlanStatus>,
     pub comment: Option<String>,
+    pub priority: Option<Option<Priority>>,
+    pub due: Option<Option<NaiveDate>>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -80,12 +219,32 @@ pub struct StepChanges {
     pub status: Option<StepStatus>,
     pub executor: Option<StepExecutor>,
     pub comment: Option<String>,
+    pub priority: Option<Option<Priority>>,
+    pub due: Option<Option<NaiveDate>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TagMatch {
+    Any,
+    #[default]
+    All,
+}
+
+pub fn normalize_tag(value: &str) -> Option<String> {
+    let tag = value.trim().to_ascii_lowercase();
+    let valid = !tag.is_empty()
+        && tag
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '/' | ':'));
+    valid.then_some(tag)
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct StepQuery {
     pub status: Option<StepStatus>,
     pub executor: Option<StepExecutor>,
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
     pub order: Option<StepOrder>,
@@ -98,6 +257,9 @@ pub enum PlanOrder {
     Title,
     Created,
     Updated,
+    Priority,
+    Due,
+    Progress,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -105,6 +267,8 @@ pub enum StepOrder {
     Order,
     Id,
     Created,
+    Priority,
+    Due,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::app::{plan_progress, App, PlanDetail, StatusChanges};
+use crate::entities::{criterion, goal, milestone, plan, step};
+use crate::error::AppError;
+use crate::model::{CriterionStatus, GoalStatus, StepQuery};
+
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+pub struct ServeOptions {
+    /// Enables the `POST` endpoints; without it they answer 403.
+    pub allow_writes: bool,
+    /// Sessions idle for longer are reported as stale.
+    pub session_ttl: Duration,
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Header values keyed by lowercased name.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn is_write(&self) -> bool {
+        self.method != "GET" && self.method != "HEAD"
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.query
+            .get(name)
+            .is_some_and(|value| matches!(value.as_str(), "" | "1" | "true"))
+    }
+}
+
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: format!("{value:#}\n"),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+}
+
+/// Reads one HTTP/1.1 request. Returns `None` when the peer closed the connection before
+/// sending anything.
+pub async fn read_request<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> Result<Option<Request>, AppError> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(AppError::InvalidInput("request headers too large".to_string()));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            return Err(AppError::InvalidInput("incomplete request".to_string()));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(AppError::InvalidInput("malformed request line".to_string()));
+    };
+    let mut content_length = 0usize;
+    let mut headers = HashMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_ascii_lowercase();
+            if name == "content-length" {
+                content_length = value.trim().parse().map_err(|_| {
+                    AppError::InvalidInput("invalid Content-Length".to_string())
+                })?;
+            }
+            headers.insert(name, value.trim().to_string());
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(AppError::InvalidInput("request body too large".to_string()));
+    }
+
+    let mut body = buffer[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(AppError::InvalidInput("incomplete request body".to_string()));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Some(Request {
+        method: method.to_ascii_uppercase(),
+        path: path.to_string(),
+        query: url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        headers,
+        body,
+    }))
+}
+
+pub async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: &Response,
+) -> Result<(), AppError> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {reason}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Answers one request. Returns the response and the ids of plans a write endpoint changed, so
+/// the caller can refresh their `plan.md`.
+pub async fn route(app: &App, request: &Request, options: &ServeOptions) -> (Response, Vec<i64>) {
+    let segments: Vec<&str> = request
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.is_empty() {
+        if request.method != "GET" {
+            return (Response::error(405, "method not allowed"), Vec::new());
+        }
+        let page = Response {
+            status: 200,
+            content_type: "text/html",
+            body: DASHBOARD_HTML.to_string(),
+        };
+        return (page, Vec::new());
+    }
+    if request.is_write() && !options.allow_writes {
+        let message = "write endpoints are disabled; start serve with --allow-writes";
+        return (Response::error(403, message), Vec::new());
+    }
+    if request.is_write() {
+        if let Err(message) = check_same_origin(request) {
+            return (Response::error(403, message), Vec::new());
+        }
+    }
+    match dispatch(app, request, &segments, options).await {
+        Ok(Some((value, plan_ids))) => (Response::json(200, &value), plan_ids),
+        Ok(None) => (Response::error(404, "no such endpoint"), Vec::new()),
+        Err(AppError::NotFound(message)) => {
+            (Response::error(404, &format!("not found: {message}")), Vec::new())
+        }
+        Err(AppError::InvalidInput(message)) => (Response::error(400, &message), Vec::new()),
+        Err(err) => (Response::error(500, &err.to_string()), Vec::new()),
+    }
+}
+
+/// Any page open in the browser can send a plain cross-origin POST to this server. Writes must
+/// therefore be JSON, which browsers only send cross-origin after a CORS preflight this server
+/// never grants, and must not name another page as their `Origin`.
+fn check_same_origin(request: &Request) -> Result<(), &'static str> {
+    let content_type = request
+        .header("content-type")
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    if !content_type.is_some_and(|value| value.eq_ignore_ascii_case("application/json")) {
+        return Err("write requests must be sent with Content-Type: application/json");
+    }
+    if let Some(origin) = request.header("origin") {
+        let host = request.header("host").unwrap_or_default();
+        let same = origin
+            .strip_prefix("http://")
+            .is_some_and(|origin| !host.is_empty() && origin.eq_ignore_ascii_case(host));
+        if !same {
+            return Err("cross-origin write requests are not allowed");
+        }
+    }
+    Ok(())
+}
+
+type Routed = Option<(Value, Vec<i64>)>;
+
+async fn dispatch(
+    app: &App,
+    request: &Request,
+    segments: &[&str],
+    options: &ServeOptions,
+) -> Result<Routed, AppError> {
+    let read = |value: Value| Ok(Some((value, Vec::new())));
+    match (request.method.as_str(), segments) {
+        ("GET", ["api", "plans"]) => {
+            let archived = request.flag("archived");
+            let plans: Vec<plan::Model> = app
+                .list_plans(None, false)
+                .await?
+                .into_iter()
+                .filter(|plan| archived || plan.archived_at.is_none())
+                .collect();
+            let details = app.get_plan_details(&plans).await?;
+            read(Value::Array(details.iter().map(plan_summary_json).collect()))
+        }
+        ("GET", ["api", "plans", id]) => {
+            let detail = app.get_plan_detail(parse_id(id)?).await?;
+            read(plan_detail_json(&detail))
+        }
+        ("GET", ["api", "plans", id, "steps"]) => {
+            let steps = app
+                .list_steps_filtered(parse_id(id)?, &StepQuery::default())
+                .await?;
+            let details = app.get_steps_detail(&steps).await?;
+            let items = details
+                .iter()
+                .map(|detail| {
+                    let mut value = step_json(&detail.step, &detail.goals);
+                    value["tags"] = json!(detail.tags);
+                    value
+                })
+                .collect();
+            read(Value::Array(items))
+        }
+        ("GET", ["api", "steps", id]) => {
+            let detail = app.get_step_detail(parse_id(id)?).await?;
+            let mut value = step_json(&detail.step, &detail.goals);
+            value["tags"] = json!(detail.tags);
+            read(value)
+        }
+        ("GET", ["api", "steps", id, "goals"]) => {
+            let detail = app.get_step_detail(parse_id(id)?).await?;
+            read(Value::Array(detail.goals.iter().map(goal_json).collect()))
+        }
+        ("GET", ["api", "goals", id]) => {
+            let detail = app.get_goal_detail(parse_id(id)?).await?;
+            let mut value = goal_json(&detail.goal);
+            value["plan_id"] = json!(detail.step.plan_id);
+            read(value)
+        }
+        ("GET", ["api", "sessions"]) => {
+            let sessions = app.list_sessions(options.session_ttl).await?;
+            let titles: HashMap<i64, String> = app
+                .list_plans(None, false)
+                .await?
+                .into_iter()
+                .map(|plan| (plan.id, plan.title))
+                .collect();
+            let items = sessions
+                .iter()
+                .map(|session| {
+                    json!({
+                        "session_id": session.session_id,
+                        "cwd": session.cwd,
+                        "last_seen_at": session.last_seen_at,
+                        "plan_id": session.plan_id,
+                        "plan_title": session.plan_id.and_then(|id| titles.get(&id)),
+                        "claims": session.claims,
+                        "stale": session.stale,
+                    })
+                })
+                .collect();
+            read(Value::Array(items))
+        }
+        ("POST", ["api", "steps", id, "done"]) => {
+            let all_goals = request.flag("all_goals") || body_flag(request, "all_goals")?;
+            let (step, changes) = app
+                .set_step_done_with_goals(parse_id(id)?, all_goals)
+                .await?;
+            let goals = app.get_step_detail(step.id).await?.goals;
+            let value = json!({
+                "step": step_json(&step, &goals),
+                "changes": changes_json(&changes),
+            });
+            Ok(Some((value, vec![step.plan_id])))
+        }
+        ("POST", ["api", "goals", id, "done"]) => {
+            let (goal, changes) = app.set_goal_status(parse_id(id)?, GoalStatus::Done).await?;
+            let plan_id = app.get_step(goal.step_id).await?.plan_id;
+            let value = json!({
+                "goal": goal_json(&goal),
+                "changes": changes_json(&changes),
+            });
+            Ok(Some((value, vec![plan_id])))
+        }
+        ("POST", ["api", "steps", id, "comment"]) => {
+            let id = parse_id(id)?;
+            let plan_ids = app
+                .comment_steps(vec![(id, body_comment(request)?)])
+                .await?;
+            let detail = app.get_step_detail(id).await?;
+            Ok(Some((step_json(&detail.step, &detail.goals), plan_ids)))
+        }
+        ("POST", ["api", "goals", id, "comment"]) => {
+            let id = parse_id(id)?;
+            let plan_ids = app
+                .comment_goals(vec![(id, body_comment(request)?)])
+                .await?;
+            Ok(Some((goal_json(&app.get_goal(id).await?), plan_ids)))
+        }
+        (_, ["api", ..]) if known_path(segments) => {
+            Err(AppError::InvalidInput(format!(
+                "{} is not supported on {}",
+                request.method, request.path
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn known_path(segments: &[&str]) -> bool {
+    matches!(
+        segments,
+        ["api", "plans" | "sessions"]
+            | ["api", "plans" | "steps" | "goals", _]
+            | ["api", "plans", _, "steps"]
+            | ["api", "steps", _, "goals" | "done" | "comment"]
+            | ["api", "goals", _, "done" | "comment"]
+    )
+}
+
+fn parse_id(value: &str) -> Result<i64, AppError> {
+    value
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("invalid id '{value}'")))
+}
+
+fn body_json(request: &Request) -> Result<Value, AppError> {
+    if request.body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Value::Null);
+    }
+    Ok(serde_json::from_slice(&request.body)?)
+}
+
+fn body_flag(request: &Request, name: &str) -> Result<bool, AppError> {
+    Ok(body_json(request)?[name].as_bool().unwrap_or(false))
+}
+
+fn body_comment(request: &Request) -> Result<String, AppError> {
+    let body = body_json(request)?;
+    match body["comment"].as_str() {
+        Some(comment) if !comment.trim().is_empty() => Ok(comment.to_string()),
+        _ => Err(AppError::InvalidInput(
+            "request body must be JSON with a non-empty \"comment\"".to_string(),
+        )),
+    }
+}
+
+pub fn plan_json(plan: &plan::Model) -> Value {
+    json!({
+        "id": plan.id,
+        "title": plan.title,
+        "content": plan.content,
+        "status": plan.status,
+        "comment": plan.comment,
+        "priority": plan.priority,
+        "due": plan.due,
+        "archived_at": plan.archived_at,
+        "last_session_id": plan.last_session_id,
+        "created_at": plan.created_at,
+        "updated_at": plan.updated_at,
+    })
+}
+
+fn plan_summary_json(detail: &PlanDetail) -> Value {
+    let (done, total) = plan_progress(detail);
+    let mut value = plan_json(&detail.plan);
+    value["tags"] = json!(detail.tags);
+    value["steps_done"] = json!(done);
+    value["steps_total"] = json!(total);
+    value
+}
+
+fn plan_detail_json(detail: &PlanDetail) -> Value {
+    let empty = Vec::new();
+    let mut value = plan_summary_json(detail);
+    value["milestones"] = Value::Array(detail.milestones.iter().map(milestone_json).collect());
+    value["criteria"] = Value::Array(detail.criteria.iter().map(criterion_json).collect());
+    value["steps"] = Value::Array(
+        detail
+            .steps
+            .iter()
+            .map(|step| step_json(step, detail.goals.get(&step.id).unwrap_or(&empty)))
+            .collect(),
+    );
+    value
+}
+
+pub fn step_json(step: &step::Model, goals: &[goal::Model]) -> Value {
+    let goals_done = goals
+        .iter()
+        .filter(|goal| goal.status == GoalStatus::Done.as_str())
+        .count();
+    json!({
+        "id": step.id,
+        "plan_id": step.plan_id,
+        "parent_id": step.parent_id,
+        "milestone_id": step.milestone_id,
+        "content": step.content,
+        "status": step.status,
+        "executor": step.executor,
+        "sort_order": step.sort_order,
+        "comment": step.comment,
+        "priority": step.priority,
+        "due": step.due,
+        "checkpoint_commit": step.checkpoint_commit,
+        "created_at": step.created_at,
+        "updated_at": step.updated_at,
+        "goals_done": goals_done,
+        "goals_total": goals.len(),
+        "goals": goals.iter().map(goal_json).collect::<Vec<_>>(),
+    })
+}
+
+pub fn goal_json(goal: &goal::Model) -> Value {
+    json!({
+        "id": goal.id,
+        "step_id": goal.step_id,
+        "content": goal.content,
+        "status": goal.status,
+        "sort_order": goal.sort_order,
+        "comment": goal.comment,
+        "checkpoint_commit": goal.checkpoint_commit,
+        "created_at": goal.created_at,
+        "updated_at": goal.updated_at,
+    })
+}
+
+fn milestone_json(milestone: &milestone::Model) -> Value {
+    json!({
+        "id": milestone.id,
+        "title": milestone.title,
+        "status": milestone.status,
+        "sort_order": milestone.sort_order,
+    })
+}
+
+fn criterion_json(criterion: &criterion::Model) -> Value {
+    json!({
+        "id": criterion.id,
+        "content": criterion.content,
+        "met": criterion.status == CriterionStatus::Done.as_str(),
+        "sort_order": criterion.sort_order,
+    })
+}
+
+fn changes_json(changes: &StatusChanges) -> Value {
+    let mut items: Vec<Value> = Vec::new();
+    for change in &changes.steps {
+        items.push(json!({
+            "kind": "step",
+            "id": change.step_id,
+            "from": change.from,
+            "to": change.to,
+            "reason": change.reason,
+        }));
+    }
+    for change in &changes.milestones {
+        items.push(json!({
+            "kind": "milestone",
+            "id": change.milestone_id,
+            "from": change.from,
+            "to": change.to,
+            "reason": change.reason,
+        }));
+    }
+    for change in &changes.plans {
+        items.push(json!({
+            "kind": "plan",
+            "id": change.plan_id,
+            "from": change.from,
+            "to": change.to,
+            "reason": change.reason,
+        }));
+    }
+    Value::Array(items)
+}
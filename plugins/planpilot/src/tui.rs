@@ -0,0 +1,571 @@
+use std::collections::HashSet;
+use std::io::{self, Stdout, Write};
+
+use crossterm::cursor::{self, MoveTo};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::app::{plan_progress, PlanDetail};
+use crate::entities::step;
+use crate::model::GoalStatus;
+use crate::util::step_tree;
+
+pub const HELP: &str = "\
+Commands:
+  <n>            open plan <n> / expand or collapse step <n> / toggle goal <n>
+  d <n>          mark step or goal <n> done
+  m <n> <pos>    move step <n> to position <pos> in the plan
+  c <n> <text>   set the comment of step or goal <n>
+  a [<n>]        make plan <n> (or the open plan) the active plan
+  e              expand or collapse all steps
+  b              back to the plan list
+  r              refresh
+  q              quit";
+
+pub const KEY_HELP: &str = "\
+Keys:
+  Up/Down, k/j   select the previous or next row (Home/End: first or last)
+  Enter, Space   open the plan / expand or collapse the step / toggle the goal
+  d              mark the step or goal done
+  K / J          move the step above its previous or below its next sibling
+  c              set the comment of the step or goal
+  a              make the selected (or open) plan the active plan
+  e              expand or collapse all steps
+  Left, Esc, b   back to the plan list
+  r              refresh
+  q              quit";
+
+const PLANS_KEYS: &str = "Up/Down select, Enter open, a activate, ? help, q quit";
+const PLAN_KEYS: &str = "Up/Down select, Enter expand/toggle, d done, J/K move, c comment, \
+                         a activate, Left back, ? help";
+
+/// Which list the UI shows; rows are numbered from 1 on both.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Screen {
+    Plans,
+    Plan(i64),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Row {
+    Step(i64),
+    Goal(i64),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    Select(usize),
+    Done(usize),
+    Move(usize, usize),
+    MoveUp(usize),
+    MoveDown(usize),
+    Comment(usize, String),
+    Activate(Option<usize>),
+    ExpandAll,
+    Back,
+    Refresh,
+    Help,
+    Quit,
+}
+
+/// What was drawn: the lines plus the plan ids (plan list) or rows (plan screen) the numbers
+/// refer to.
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    pub lines: Vec<String>,
+    /// The line of each numbered entry, so the key-driven UI can highlight the selected one.
+    pub entry_lines: Vec<usize>,
+    /// The commands shown under the page in line mode.
+    pub hint: &'static str,
+    pub plan_ids: Vec<i64>,
+    pub rows: Vec<Row>,
+}
+
+impl Page {
+    /// The page as printed in line mode.
+    pub fn text(&self) -> String {
+        let mut lines = self.lines.clone();
+        lines.push(String::new());
+        lines.push(self.hint.to_string());
+        lines.join("\n")
+    }
+
+    fn anchors(&self) -> Vec<Anchor> {
+        match self.rows.is_empty() {
+            true => self.plan_ids.iter().map(|id| Anchor::Plan(*id)).collect(),
+            false => self.rows.iter().map(|row| Anchor::Row(*row)).collect(),
+        }
+    }
+}
+
+/// What the cursor is on, so it stays there when rows come and go around it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Anchor {
+    Plan(i64),
+    Row(Row),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum KeyCommand {
+    Up,
+    Down,
+    First,
+    Last,
+    Help,
+    Comment(usize),
+    Act(Action),
+    Ignore,
+}
+
+/// The key-driven UI used when stdin and stdout are terminals: the selected row is highlighted
+/// and acted on, instead of typing row numbers. The terminal is restored when this is dropped.
+pub struct Terminal {
+    out: Stdout,
+    screen: Option<Screen>,
+    cursor: usize,
+    anchor: Option<Anchor>,
+}
+
+impl Terminal {
+    pub fn start() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        if let Err(err) = execute!(out, EnterAlternateScreen, cursor::Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(err);
+        }
+        Ok(Self {
+            out,
+            screen: None,
+            cursor: 0,
+            anchor: None,
+        })
+    }
+
+    /// Draws the page with `messages` under it and waits for a key that maps to an action;
+    /// moving the cursor, help and typing a comment are handled here.
+    pub fn next_action(
+        &mut self,
+        page: &Page,
+        screen: Screen,
+        messages: &mut Vec<String>,
+    ) -> io::Result<Action> {
+        let anchors = page.anchors();
+        if self.screen != Some(screen) {
+            // Coming back to the plan list keeps the plan that was open selected.
+            self.anchor = match self.screen {
+                Some(Screen::Plan(plan_id)) => Some(Anchor::Plan(plan_id)),
+                _ => None,
+            };
+            self.screen = Some(screen);
+            self.cursor = 0;
+        }
+        if let Some(index) = self
+            .anchor
+            .and_then(|anchor| anchors.iter().position(|candidate| *candidate == anchor))
+        {
+            self.cursor = index;
+        }
+        loop {
+            self.cursor = self.cursor.min(anchors.len().saturating_sub(1));
+            self.anchor = anchors.get(self.cursor).copied();
+            self.draw(page, screen, messages, None)?;
+            let Some(key) = read_key()? else {
+                continue;
+            };
+            messages.clear();
+            let selected = (!anchors.is_empty()).then_some(self.cursor + 1);
+            match key_command(key, screen, selected) {
+                KeyCommand::Up => self.cursor = self.cursor.saturating_sub(1),
+                KeyCommand::Down => self.cursor += 1,
+                KeyCommand::First => self.cursor = 0,
+                KeyCommand::Last => self.cursor = anchors.len().saturating_sub(1),
+                KeyCommand::Help => messages.extend(KEY_HELP.lines().map(str::to_string)),
+                KeyCommand::Comment(n) => {
+                    if let Some(text) = self.read_comment(page, screen)? {
+                        return Ok(Action::Comment(n, text));
+                    }
+                }
+                KeyCommand::Act(action) => return Ok(action),
+                KeyCommand::Ignore => {}
+            }
+        }
+    }
+
+    fn read_comment(&mut self, page: &Page, screen: Screen) -> io::Result<Option<String>> {
+        let mut text = String::new();
+        loop {
+            let prompt = format!("Comment (Enter saves, Esc cancels): {text}");
+            self.draw(page, screen, &[], Some(&prompt))?;
+            let Some(key) = read_key()? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Enter => {
+                    let text = text.trim();
+                    return Ok((!text.is_empty()).then(|| text.to_string()));
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    text.push(ch)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Redraws the whole screen, scrolled so the selected row is visible, with the messages and
+    /// the key hints (or `prompt`) at the bottom.
+    fn draw(
+        &mut self,
+        page: &Page,
+        screen: Screen,
+        messages: &[String],
+        prompt: Option<&str>,
+    ) -> io::Result<()> {
+        // Some terminals (e.g. a fresh pseudo-terminal) report no size.
+        let (width, height) = match terminal::size()? {
+            (0, _) | (_, 0) => (80, 24),
+            size => size,
+        };
+        let (width, height) = (usize::from(width), usize::from(height).max(2));
+        let hint = match (prompt, screen) {
+            (Some(prompt), _) => prompt,
+            (None, Screen::Plans) => PLANS_KEYS,
+            (None, Screen::Plan(_)) => PLAN_KEYS,
+        };
+        let mut footer = vec![String::new()];
+        footer.extend(messages.iter().flat_map(|message| message.lines()).map(str::to_string));
+        footer.push(hint.to_string());
+        let footer = &footer[footer.len().saturating_sub(height - 1)..];
+        let body = height - footer.len();
+        let selected = page.entry_lines.get(self.cursor).copied();
+        let offset = selected.map_or(0, |line| (line + 1).saturating_sub(body));
+
+        queue!(self.out, Clear(ClearType::All))?;
+        for (y, line) in page.lines.iter().skip(offset).take(body).enumerate() {
+            let text: String = line.chars().take(width).collect();
+            queue!(self.out, MoveTo(0, y as u16))?;
+            if selected == Some(offset + y) {
+                queue!(
+                    self.out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(text),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(self.out, Print(text))?;
+            }
+        }
+        for (y, line) in footer.iter().enumerate() {
+            let text: String = line.chars().take(width).collect();
+            queue!(self.out, MoveTo(0, (body + y) as u16), Print(text))?;
+        }
+        self.out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Waits for the next key press; other events (such as a resize) return `None` to redraw.
+fn read_key() -> io::Result<Option<KeyEvent>> {
+    Ok(match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Some(key),
+        _ => None,
+    })
+}
+
+/// Maps a key to what it does on `screen`; `selected` is the number of the selected row, if any.
+fn key_command(key: KeyEvent, screen: Screen, selected: Option<usize>) -> KeyCommand {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return match key.code {
+            KeyCode::Char('c') => KeyCommand::Act(Action::Quit),
+            _ => KeyCommand::Ignore,
+        };
+    }
+    let on_plan = matches!(screen, Screen::Plan(_));
+    let row = |action: fn(usize) -> Action| {
+        selected.map_or(KeyCommand::Ignore, |n| KeyCommand::Act(action(n)))
+    };
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => KeyCommand::Up,
+        KeyCode::Down | KeyCode::Char('j') => KeyCommand::Down,
+        KeyCode::Home | KeyCode::Char('g') => KeyCommand::First,
+        KeyCode::End | KeyCode::Char('G') => KeyCommand::Last,
+        KeyCode::Enter | KeyCode::Right | KeyCode::Char(' ' | 'l') => row(Action::Select),
+        KeyCode::Left | KeyCode::Backspace | KeyCode::Esc | KeyCode::Char('h' | 'b') if on_plan => {
+            KeyCommand::Act(Action::Back)
+        }
+        KeyCode::Char('d') if on_plan => row(Action::Done),
+        KeyCode::Char('K') if on_plan => row(Action::MoveUp),
+        KeyCode::Char('J') if on_plan => row(Action::MoveDown),
+        KeyCode::Char('c') if on_plan => selected.map_or(KeyCommand::Ignore, KeyCommand::Comment),
+        KeyCode::Char('a') if on_plan => KeyCommand::Act(Action::Activate(None)),
+        KeyCode::Char('a') => row(|n| Action::Activate(Some(n))),
+        KeyCode::Char('e') if on_plan => KeyCommand::Act(Action::ExpandAll),
+        KeyCode::Char('r') => KeyCommand::Act(Action::Refresh),
+        KeyCode::Char('?') => KeyCommand::Help,
+        KeyCode::Char('q') => KeyCommand::Act(Action::Quit),
+        _ => KeyCommand::Ignore,
+    }
+}
+
+/// The plan position, as `App::move_step` counts it, that moves a step just above its previous
+/// (`up`) or below its next sibling; `None` when it is already first or last among them.
+pub fn sibling_position(steps: &[step::Model], step_id: i64, up: bool) -> Option<usize> {
+    let mut ordered: Vec<&step::Model> = steps.iter().collect();
+    ordered.sort_by_key(|step| (step.sort_order, step.id));
+    let index = ordered.iter().position(|step| step.id == step_id)?;
+    let parent_id = ordered[index].parent_id;
+    let siblings: Vec<usize> = ordered
+        .iter()
+        .enumerate()
+        .filter(|(_, step)| step.parent_id == parent_id)
+        .map(|(position, _)| position)
+        .collect();
+    let neighbour = if up {
+        siblings.iter().rev().find(|position| **position < index)
+    } else {
+        siblings.iter().find(|position| **position > index)
+    };
+    neighbour.map(|position| position + 1)
+}
+
+pub fn parse_action(line: &str) -> Result<Action, String> {
+    fn row(value: Option<&str>) -> Result<usize, String> {
+        match value.map(str::parse::<usize>) {
+            Some(Ok(n)) if n > 0 => Ok(n),
+            _ => Err("expected a row number".to_string()),
+        }
+    }
+
+    let line = line.trim();
+    let (command, rest) = line
+        .split_once(char::is_whitespace)
+        .map(|(command, rest)| (command, rest.trim()))
+        .unwrap_or((line, ""));
+    let mut args = rest.split_whitespace();
+    let action = match command {
+        "" | "r" => Action::Refresh,
+        "q" | "quit" => Action::Quit,
+        "b" | "back" => Action::Back,
+        "?" | "h" | "help" => Action::Help,
+        "e" => Action::ExpandAll,
+        "d" => Action::Done(row(args.next())?),
+        "a" if rest.is_empty() => Action::Activate(None),
+        "a" => Action::Activate(Some(row(args.next())?)),
+        "m" => {
+            let n = row(args.next())?;
+            match args.next().map(str::parse::<usize>) {
+                Some(Ok(pos)) if pos > 0 => Action::Move(n, pos),
+                _ => return Err("position starts at 1".to_string()),
+            }
+        }
+        "c" => {
+            let n = row(args.next())?;
+            match rest.split_once(char::is_whitespace).map(|(_, text)| text.trim()) {
+                Some(text) if !text.is_empty() => Action::Comment(n, text.to_string()),
+                _ => return Err("expected a comment after the row number".to_string()),
+            }
+        }
+        other => match other.parse::<usize>() {
+            Ok(n) if n > 0 && rest.is_empty() => Action::Select(n),
+            _ => return Err(format!("unknown command '{line}'; type ? for help")),
+        },
+    };
+    if !matches!(action, Action::Comment(..) | Action::Move(..)) && args.next().is_some() {
+        return Err(format!("unexpected arguments in '{line}'; type ? for help"));
+    }
+    Ok(action)
+}
+
+/// Lists the steps depth-first; goals and sub-steps only show under expanded steps.
+pub fn plan_rows(detail: &PlanDetail, expanded: &HashSet<i64>) -> Vec<(Row, usize)> {
+    let mut rows = Vec::new();
+    let mut collapsed_at: Option<usize> = None;
+    for (step, depth) in step_tree(&detail.steps) {
+        match collapsed_at {
+            Some(level) if depth > level => continue,
+            _ => collapsed_at = None,
+        }
+        rows.push((Row::Step(step.id), depth));
+        if expanded.contains(&step.id) {
+            for goal in detail.goals.get(&step.id).into_iter().flatten() {
+                rows.push((Row::Goal(goal.id), depth + 1));
+            }
+        } else {
+            collapsed_at = Some(depth);
+        }
+    }
+    rows
+}
+
+pub fn render_plans(details: &[PlanDetail], active: Option<i64>) -> Page {
+    let mut lines = vec!["Planpilot plans".to_string(), String::new()];
+    let mut entry_lines = Vec::new();
+    if details.is_empty() {
+        lines.push("No plans.".to_string());
+    }
+    for (idx, detail) in details.iter().enumerate() {
+        let (done, total) = plan_progress(detail);
+        let marker = if active == Some(detail.plan.id) { "  (active)" } else { "" };
+        entry_lines.push(lines.len());
+        lines.push(format!(
+            "{:>3}  [{}] {}  (plan {}, steps {done}/{total}){marker}",
+            idx + 1,
+            detail.plan.status,
+            detail.plan.title,
+            detail.plan.id
+        ));
+    }
+    Page {
+        lines,
+        entry_lines,
+        hint: "<n> open, a <n> activate, ? help, q quit",
+        plan_ids: details.iter().map(|detail| detail.plan.id).collect(),
+        rows: Vec::new(),
+    }
+}
+
+pub fn render_plan(detail: &PlanDetail, expanded: &HashSet<i64>, active: Option<i64>) -> Page {
+    let (done, total) = plan_progress(detail);
+    let marker = if active == Some(detail.plan.id) { "  (active)" } else { "" };
+    let mut lines = vec![
+        format!(
+            "Plan {}: {} [{}]  steps {done}/{total}{marker}",
+            detail.plan.id, detail.plan.title, detail.plan.status
+        ),
+        String::new(),
+    ];
+    let rows = plan_rows(detail, expanded);
+    let mut entry_lines = Vec::new();
+    if rows.is_empty() {
+        lines.push("No steps.".to_string());
+    }
+    let goals: Vec<_> = detail.goals.values().flatten().collect();
+    for (idx, (row, depth)) in rows.iter().enumerate() {
+        let indent = "  ".repeat(*depth);
+        let (text, comment) = match row {
+            Row::Step(id) => {
+                let Some(step) = detail.steps.iter().find(|step| step.id == *id) else {
+                    continue;
+                };
+                let step_goals = detail.goals.get(id).map(Vec::as_slice).unwrap_or_default();
+                let has_children = !step_goals.is_empty()
+                    || detail.steps.iter().any(|child| child.parent_id == Some(*id));
+                let toggle = match (has_children, expanded.contains(id)) {
+                    (false, _) => ' ',
+                    (true, true) => '-',
+                    (true, false) => '+',
+                };
+                let goals_done = step_goals
+                    .iter()
+                    .filter(|goal| goal.status == GoalStatus::Done.as_str())
+                    .count();
+                let goals_text = if step_goals.is_empty() {
+                    String::new()
+                } else {
+                    format!(", goals {goals_done}/{}", step_goals.len())
+                };
+                (
+                    format!(
+                        "{toggle} [{}] {}  (step {}, {}{goals_text})",
+                        step.status, step.content, step.id, step.executor
+                    ),
+                    step.comment.as_deref(),
+                )
+            }
+            Row::Goal(id) => {
+                let Some(goal) = goals.iter().find(|goal| goal.id == *id) else {
+                    continue;
+                };
+                let check = if goal.status == GoalStatus::Done.as_str() { 'x' } else { ' ' };
+                (
+                    format!("  [{check}] {}  (goal {})", goal.content, goal.id),
+                    goal.comment.as_deref(),
+                )
+            }
+        };
+        entry_lines.push(lines.len());
+        lines.push(format!("{:>3}  {indent}{text}", idx + 1));
+        if let Some(comment) = comment.filter(|comment| !comment.trim().is_empty()) {
+            lines.push(format!("       {indent}    Comment: {}", comment.trim()));
+        }
+    }
+    Page {
+        lines,
+        entry_lines,
+        hint: "<n> expand/toggle, d <n> done, m <n> <pos> move, c <n> <text> comment, a activate, \
+               b back, ? help",
+        plan_ids: Vec::new(),
+        rows: rows.into_iter().map(|(row, _)| row).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_action_reads_commands_and_row_numbers() {
+        assert_eq!(parse_action(" 3 "), Ok(Action::Select(3)));
+        assert_eq!(parse_action(""), Ok(Action::Refresh));
+        assert_eq!(parse_action("d 2"), Ok(Action::Done(2)));
+        assert_eq!(parse_action("m 4 1"), Ok(Action::Move(4, 1)));
+        assert_eq!(
+            parse_action("c 2  needs  review "),
+            Ok(Action::Comment(2, "needs  review".to_string()))
+        );
+        assert_eq!(parse_action("a"), Ok(Action::Activate(None)));
+        assert_eq!(parse_action("a 1"), Ok(Action::Activate(Some(1))));
+        assert_eq!(parse_action("q"), Ok(Action::Quit));
+        assert!(parse_action("0").is_err());
+        assert!(parse_action("d").is_err());
+        assert!(parse_action("c 2").is_err());
+        assert!(parse_action("m 4 0").is_err());
+        assert!(parse_action("d 1 2").is_err());
+        assert!(parse_action("x").is_err());
+    }
+
+    #[test]
+    fn key_command_acts_on_the_selected_row() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let plan = Screen::Plan(1);
+        assert_eq!(
+            key_command(key(KeyCode::Enter), Screen::Plans, Some(2)),
+            KeyCommand::Act(Action::Select(2))
+        );
+        assert_eq!(
+            key_command(key(KeyCode::Char('a')), Screen::Plans, Some(2)),
+            KeyCommand::Act(Action::Activate(Some(2)))
+        );
+        assert_eq!(
+            key_command(key(KeyCode::Char('a')), plan, Some(2)),
+            KeyCommand::Act(Action::Activate(None))
+        );
+        assert_eq!(
+            key_command(key(KeyCode::Char('K')), plan, Some(3)),
+            KeyCommand::Act(Action::MoveUp(3))
+        );
+        assert_eq!(key_command(key(KeyCode::Char('c')), plan, Some(3)), KeyCommand::Comment(3));
+        assert_eq!(key_command(key(KeyCode::Char('d')), plan, None), KeyCommand::Ignore);
+        let plans_done = key_command(key(KeyCode::Char('d')), Screen::Plans, Some(1));
+        assert_eq!(plans_done, KeyCommand::Ignore);
+        assert_eq!(key_command(key(KeyCode::Esc), plan, None), KeyCommand::Act(Action::Back));
+        assert_eq!(key_command(key(KeyCode::Down), plan, Some(1)), KeyCommand::Down);
+        assert_eq!(
+            key_command(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), plan, None),
+            KeyCommand::Act(Action::Quit)
+        );
+    }
+}
@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
-use crate::entities::{goal, plan, step};
-use crate::model::GoalStatus;
+use crate::app::{MilestoneProgress, PlanDetail};
+use crate::entities::{criterion, goal, milestone, step};
+use crate::git::short_commit;
+use crate::model::{is_overdue, GoalStatus};
 
 fn has_text(value: &Option<String>) -> bool {
     value
@@ -12,16 +14,156 @@ fn has_text(value: &Option<String>) -> bool {
         .unwrap_or(false)
 }
 
+/// Renders a stored git checkpoint as `<short commit> on <branch>`, flagging detached HEADs and
+/// worktrees that had uncommitted changes.
+fn format_checkpoint(
+    commit: Option<&str>,
+    branch: Option<&str>,
+    dirty: Option<bool>,
+) -> Option<String> {
+    let commit = commit?;
+    let mut text = match branch {
+        Some(branch) => format!("{} on {branch}", short_commit(commit)),
+        None => format!("{} (detached)", short_commit(commit)),
+    };
+    if dirty == Some(true) {
+        text.push_str(" with uncommitted changes");
+    }
+    Some(text)
+}
+
 pub fn format_datetime(dt: DateTime<Utc>) -> String {
     dt.format("%Y-%m-%d %H:%M").to_string()
 }
 
-pub fn format_step_detail(step: &step::Model, goals: &[goal::Model]) -> String {
+pub fn format_due(due: NaiveDate, status: &str) -> String {
+    if is_overdue(Some(due), status, Utc::now().date_naive()) {
+        format!("{due} (overdue)")
+    } else {
+        due.to_string()
+    }
+}
+
+fn schedule_suffix(priority: Option<&str>, due: Option<NaiveDate>, separator: &str) -> String {
+    let mut parts = Vec::new();
+    if let Some(priority) = priority {
+        parts.push(format!("pri{separator}{priority}"));
+    }
+    if let Some(due) = due {
+        parts.push(format!("due{separator}{due}"));
+    }
+    parts.iter().map(|part| format!(", {part}")).collect()
+}
+
+/// Orders steps depth-first, keeping siblings in their given order, and pairs each with its depth.
+/// Steps whose parent is missing from `steps` are treated as roots.
+pub fn step_tree(steps: &[step::Model]) -> Vec<(&step::Model, usize)> {
+    fn walk<'a>(
+        steps: &'a [step::Model],
+        parent_id: i64,
+        depth: usize,
+        out: &mut Vec<(&'a step::Model, usize)>,
+    ) {
+        for step in steps.iter().filter(|step| step.parent_id == Some(parent_id)) {
+            out.push((step, depth));
+            walk(steps, step.id, depth + 1, out);
+        }
+    }
+
+    let ids: HashSet<i64> = steps.iter().map(|step| step.id).collect();
+    let mut out = Vec::with_capacity(steps.len());
+    for step in steps {
+        let is_root = step.parent_id.map(|id| !ids.contains(&id)).unwrap_or(true);
+        if is_root {
+            out.push((step, 0));
+            walk(steps, step.id, 1, &mut out);
+        }
+    }
+    out
+}
+
+/// A milestone (`None` for steps without one) with its part of [`step_tree`].
+pub type MilestoneGroup<'a, 'm> = (Option<&'m milestone::Model>, Vec<(&'a step::Model, usize)>);
+
+/// Splits [`step_tree`] by milestone: one group per milestone in the given order (empty ones
+/// included), then the steps without a milestone. Sub-steps stay with their top-level step.
+pub fn milestone_groups<'a, 'm>(
+    steps: &'a [step::Model],
+    milestones: &'m [milestone::Model],
+) -> Vec<MilestoneGroup<'a, 'm>> {
+    let mut groups: Vec<_> = milestones
+        .iter()
+        .map(|milestone| (Some(milestone), Vec::new()))
+        .collect();
+    let mut unassigned = Vec::new();
+    let mut current = None;
+    for (step, depth) in step_tree(steps) {
+        if depth == 0 {
+            current = step
+                .milestone_id
+                .and_then(|id| milestones.iter().position(|milestone| milestone.id == id));
+        }
+        match current {
+            Some(idx) => groups[idx].1.push((step, depth)),
+            None => unassigned.push((step, depth)),
+        }
+    }
+    if !unassigned.is_empty() || milestones.is_empty() {
+        groups.push((None, unassigned));
+    }
+    groups
+}
+
+/// Returns `(met, total)` for a plan's acceptance criteria.
+pub fn criteria_progress(criteria: &[criterion::Model]) -> (usize, usize) {
+    let met = criteria.iter().filter(|item| item.status == "done").count();
+    (met, criteria.len())
+}
+
+/// Returns `(done, total)` for the steps (sub-steps included) of a milestone.
+pub fn milestone_progress(steps: &[step::Model], milestone_id: i64) -> (usize, usize) {
+    let members = steps
+        .iter()
+        .filter(|step| step.milestone_id == Some(milestone_id));
+    let (mut done, mut total) = (0, 0);
+    for step in members {
+        total += 1;
+        if step.status == "done" {
+            done += 1;
+        }
+    }
+    (done, total)
+}
+
+pub fn format_step_detail(
+    step: &step::Model,
+    goals: &[goal::Model],
+    tags: &[String],
+    milestone: Option<&MilestoneProgress>,
+) -> String {
     let mut output = String::new();
     output.push_str(&format!("Step ID: {}\n", step.id));
     output.push_str(&format!("Plan ID: {}\n", step.plan_id));
+    if let Some(parent_id) = step.parent_id {
+        output.push_str(&format!("Parent Step ID: {parent_id}\n"));
+    }
+    if let Some(progress) = milestone {
+        output.push_str(&format!(
+            "Milestone: {} (milestone id {}, steps {}/{})\n",
+            progress.milestone.title, progress.milestone.id, progress.done, progress.total
+        ));
+    }
     output.push_str(&format!("Status: {}\n", step.status));
     output.push_str(&format!("Executor: {}\n", step.executor));
+    if let Some(priority) = step.priority.as_deref() {
+        output.push_str(&format!("Priority: {priority}\n"));
+    }
+    if let Some(due) = step.due {
+        output.push_str(&format!("Due: {}\n", format_due(due, &step.status)));
+    }
+    if !tags.is_empty() {
+        output.push_str(&format!("Tags: {}\n", tags.join(", ")));
+    }
     output.push_str(&format!("Content: {}\n", step.content));
     if has_text(&step.comment) {
         output.push_str(&format!(
@@ -29,6 +171,13 @@ pub fn format_step_detail(step: &step::Model, goals: &[goal::Model]) -> String {
             step.comment.as_deref().unwrap_or("")
         ));
     }
+    if let Some(checkpoint) = format_checkpoint(
+        step.checkpoint_commit.as_deref(),
+        step.checkpoint_branch.as_deref(),
+        step.checkpoint_dirty,
+    ) {
+        output.push_str(&format!("Checkpoint: {checkpoint}\n"));
+    }
     output.push_str(&format!("Created: {}\n", format_datetime(step.created_at)));
     output.push_str(&format!("Updated: {}\n", format_datetime(step.updated_at)));
     output.push('\n');
@@ -65,6 +214,13 @@ pub fn format_goal_detail(goal: &goal::Model, step: &step::Model) -> String {
             goal.comment.as_deref().unwrap_or("")
         ));
     }
+    if let Some(checkpoint) = format_checkpoint(
+        goal.checkpoint_commit.as_deref(),
+        goal.checkpoint_branch.as_deref(),
+        goal.checkpoint_dirty,
+    ) {
+        output.push_str(&format!("Checkpoint: {checkpoint}\n"));
+    }
     output.push_str(&format!("Created: {}\n", format_datetime(goal.created_at)));
     output.push_str(&format!("Updated: {}\n", format_datetime(goal.updated_at)));
     output.push('\n');
@@ -80,15 +236,34 @@ pub fn format_goal_detail(goal: &goal::Model, step: &step::Model) -> String {
     output.trim_end().to_string()
 }
 
-pub fn format_plan_detail(
-    plan: &plan::Model,
-    steps: &[step::Model],
-    goals: &HashMap<i64, Vec<goal::Model>>,
-) -> String {
+pub fn format_plan_detail(detail: &PlanDetail) -> String {
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        tags,
+        milestones,
+        criteria,
+    } = detail;
     let mut output = String::new();
     output.push_str(&format!("Plan ID: {}\n", plan.id));
     output.push_str(&format!("Title: {}\n", plan.title));
     output.push_str(&format!("Status: {}\n", plan.status));
+    if let Some(source_plan_id) = plan.source_plan_id {
+        output.push_str(&format!("Cloned From: plan {source_plan_id}\n"));
+    }
+    if let Some(archived_at) = plan.archived_at {
+        output.push_str(&format!("Archived: {}\n", format_datetime(archived_at)));
+    }
+    if let Some(priority) = plan.priority.as_deref() {
+        output.push_str(&format!("Priority: {priority}\n"));
+    }
+    if let Some(due) = plan.due {
+        output.push_str(&format!("Due: {}\n", format_due(due, &plan.status)));
+    }
+    if !tags.is_empty() {
+        output.push_str(&format!("Tags: {}\n", tags.join(", ")));
+    }
     output.push_str(&format!("Content: {}\n", plan.content));
     if has_text(&plan.comment) {
         output.push_str(&format!(
@@ -99,47 +274,84 @@ pub fn format_plan_detail(
     output.push_str(&format!("Created: {}\n", format_datetime(plan.created_at)));
     output.push_str(&format!("Updated: {}\n", format_datetime(plan.updated_at)));
     output.push('\n');
-    if steps.is_empty() {
+    if !criteria.is_empty() {
+        let (met, total) = criteria_progress(criteria);
+        output.push_str(&format!("Acceptance Criteria ({met}/{total}):\n"));
+        for item in criteria.iter() {
+            output.push_str(&format!(
+                "- [{}] {} (criterion id {})\n",
+                item.status, item.content, item.id
+            ));
+        }
+        output.push('\n');
+    }
+    if steps.is_empty() && milestones.is_empty() {
         output.push_str("Steps: (none)");
         return output;
     }
     output.push_str("Steps:\n");
-    for step in steps {
-        let counts = goals.get(&step.id).map(|items| {
-            let done = items
-                .iter()
-                .filter(|goal| goal.status == GoalStatus::Done.as_str())
-                .count();
-            (done, items.len())
-        });
-        if let Some((done, total)) = counts {
-            output.push_str(&format!(
-                "- [{}] {} (step id {}, exec {}, goals {}/{})\n",
-                step.status, step.content, step.id, step.executor, done, total
-            ));
-        } else {
-            output.push_str(&format!(
-                "- [{}] {} (step id {}, exec {})\n",
-                step.status, step.content, step.id, step.executor
-            ));
-        }
-        if has_text(&step.comment) {
-            output.push_str(&format!(
-                "  Comment: {}\n",
-                step.comment.as_deref().unwrap_or("")
-            ));
+    let offset = usize::from(!milestones.is_empty());
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        match milestone {
+            Some(milestone) => {
+                let (done, total) = milestone_progress(steps, milestone.id);
+                output.push_str(&format!(
+                    "Milestone: {} [{}] (milestone id {}, steps {done}/{total})\n",
+                    milestone.title, milestone.status, milestone.id
+                ));
+                if entries.is_empty() {
+                    output.push_str("  (no steps)\n");
+                }
+            }
+            None if offset > 0 => output.push_str("No milestone:\n"),
+            None => {}
         }
-        if let Some(goal_list) = goals.get(&step.id) {
-            for goal in goal_list {
+        for (step, depth) in entries {
+            let indent = "  ".repeat(depth + offset);
+            let counts = goals.get(&step.id).map(|items| {
+                let done = items
+                    .iter()
+                    .filter(|goal| goal.status == GoalStatus::Done.as_str())
+                    .count();
+                (done, items.len())
+            });
+            let schedule = schedule_suffix(step.priority.as_deref(), step.due, " ");
+            if let Some((done, total)) = counts {
+                output.push_str(&format!(
+                    "{indent}- [{}] {} (step id {}, exec {}{}, goals {}/{})\n",
+                    step.status, step.content, step.id, step.executor, schedule, done, total
+                ));
+            } else {
                 output.push_str(&format!(
-                    "  - [{}] {} (goal id {})\n",
-                    goal.status, goal.content, goal.id
+                    "{indent}- [{}] {} (step id {}, exec {}{})\n",
+                    step.status, step.content, step.id, step.executor, schedule
                 ));
-                if has_text(&goal.comment) {
+            }
+            if has_text(&step.comment) {
+                output.push_str(&format!(
+                    "{indent}  Comment: {}\n",
+                    step.comment.as_deref().unwrap_or("")
+                ));
+            }
+            if let Some(checkpoint) = format_checkpoint(
+                step.checkpoint_commit.as_deref(),
+                step.checkpoint_branch.as_deref(),
+                step.checkpoint_dirty,
+            ) {
+                output.push_str(&format!("{indent}  Checkpoint: {checkpoint}\n"));
+            }
+            if let Some(goal_list) = goals.get(&step.id) {
+                for goal in goal_list {
                     output.push_str(&format!(
-                        "    Comment: {}\n",
-                        goal.comment.as_deref().unwrap_or("")
+                        "{indent}  - [{}] {} (goal id {})\n",
+                        goal.status, goal.content, goal.id
                     ));
+                    if has_text(&goal.comment) {
+                        output.push_str(&format!(
+                            "{indent}    Comment: {}\n",
+                            goal.comment.as_deref().unwrap_or("")
+                        ));
+                    }
                 }
             }
         }
@@ -150,9 +362,7 @@ pub fn format_plan_detail(
 pub fn format_plan_markdown(
     active: bool,
     active_updated: Option<DateTime<Utc>>,
-    plan: &plan::Model,
-    steps: &[step::Model],
-    goals: &HashMap<i64, Vec<goal::Model>>,
+    detail: &PlanDetail,
 ) -> String {
     fn checkbox(status: &str) -> &'static str {
         if status == "done" {
@@ -216,6 +426,14 @@ pub fn format_plan_markdown(
         }
     }
 
+    let PlanDetail {
+        plan,
+        steps,
+        goals,
+        tags,
+        milestones,
+        criteria,
+    } = detail;
     let mut lines = Vec::new();
     push_line(&mut lines, 0, "# Plan");
     push_blank(&mut lines, 0);
@@ -233,6 +451,34 @@ pub fn format_plan_markdown(
     );
     push_line(&mut lines, 0, &format!("- **Plan ID:** `{}`", plan.id));
     push_line(&mut lines, 0, &format!("- **Status:** `{}`", plan.status));
+    if let Some(source_plan_id) = plan.source_plan_id {
+        push_line(
+            &mut lines,
+            0,
+            &format!("- **Cloned From:** plan `{source_plan_id}`"),
+        );
+    }
+    if let Some(archived_at) = plan.archived_at {
+        push_line(
+            &mut lines,
+            0,
+            &format!("- **Archived:** {}", format_datetime(archived_at)),
+        );
+    }
+    if let Some(priority) = plan.priority.as_deref() {
+        push_line(&mut lines, 0, &format!("- **Priority:** `{priority}`"));
+    }
+    if let Some(due) = plan.due {
+        push_line(
+            &mut lines,
+            0,
+            &format!("- **Due:** {}", format_due(due, &plan.status)),
+        );
+    }
+    if !tags.is_empty() {
+        let labels: Vec<String> = tags.iter().map(|tag| format!("`{tag}`")).collect();
+        push_line(&mut lines, 0, &format!("- **Tags:** {}", labels.join(" ")));
+    }
     if has_text(&plan.comment) {
         push_line(
             &mut lines,
@@ -281,107 +527,164 @@ pub fn format_plan_markdown(
     }
     push_blank(&mut lines, 0);
 
+    if !criteria.is_empty() {
+        let (met, total) = criteria_progress(criteria);
+        push_line(
+            &mut lines,
+            0,
+            &format!("### Acceptance Criteria ({met}/{total})"),
+        );
+        push_blank(&mut lines, 0);
+        for item in criteria.iter() {
+            push_line(
+                &mut lines,
+                0,
+                &format!(
+                    "- [{}] {} *(id: {})*",
+                    checkbox(&item.status),
+                    collapse_heading(&item.content),
+                    item.id
+                ),
+            );
+        }
+        push_blank(&mut lines, 0);
+    }
+
     push_line(&mut lines, 0, "### Steps");
     push_blank(&mut lines, 0);
-    if steps.is_empty() {
+    if steps.is_empty() && milestones.is_empty() {
         push_line(&mut lines, 0, "*No steps*");
         return lines.join("\n").trim_end().to_string();
     }
 
-    for (idx, step) in steps.iter().enumerate() {
-        let (first_line, rest_lines) = split_task_text(&step.content);
-        push_line(
-            &mut lines,
-            0,
-            &format!(
-                "- [{}] **{}** *(id: {}, exec: {}, order: {})*",
-                checkbox(&step.status),
-                first_line,
-                step.id,
-                step.executor,
-                step.sort_order
-            ),
-        );
-
-        let mut has_rest = false;
-        for line in rest_lines {
-            if line.trim().is_empty() {
-                continue;
+    for (milestone, entries) in milestone_groups(steps, milestones) {
+        match milestone {
+            Some(milestone) => {
+                let (done, total) = milestone_progress(steps, milestone.id);
+                push_line(
+                    &mut lines,
+                    0,
+                    &format!("#### Milestone: {}", collapse_heading(&milestone.title)),
+                );
+                push_blank(&mut lines, 0);
+                push_line(
+                    &mut lines,
+                    0,
+                    &format!("- **Milestone ID:** `{}`", milestone.id),
+                );
+                push_line(
+                    &mut lines,
+                    0,
+                    &format!("- **Status:** `{}`", milestone.status),
+                );
+                push_line(&mut lines, 0, &format!("- **Steps:** {done}/{total}"));
+                push_blank(&mut lines, 0);
+                if entries.is_empty() {
+                    push_line(&mut lines, 0, "*No steps*");
+                    push_blank(&mut lines, 0);
+                }
             }
-            if !has_rest {
-                push_blank(&mut lines, 2);
-                has_rest = true;
-            } else {
-                push_blank(&mut lines, 2);
+            None if !milestones.is_empty() => {
+                push_line(&mut lines, 0, "#### No milestone");
+                push_blank(&mut lines, 0);
             }
-            push_line(&mut lines, 2, &line);
+            None => {}
         }
+        for (step, depth) in entries {
+            let base = depth * 2;
+            let (first_line, rest_lines) = split_task_text(&step.content);
+            push_line(
+                &mut lines,
+                base,
+                &format!(
+                    "- [{}] **{}** *(id: {}, exec: {}, order: {}{})*",
+                    checkbox(&step.status),
+                    first_line,
+                    step.id,
+                    step.executor,
+                    step.sort_order,
+                    schedule_suffix(step.priority.as_deref(), step.due, ": ")
+                ),
+            );
 
-        push_blank(&mut lines, 2);
-        push_line(
-            &mut lines,
-            2,
-            &format!("- Created: {}", format_datetime(step.created_at)),
-        );
-        push_line(
-            &mut lines,
-            2,
-            &format!("- Updated: {}", format_datetime(step.updated_at)),
-        );
-        if has_text(&step.comment) {
+            let mut has_rest = false;
+            for line in rest_lines {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if !has_rest {
+                    push_blank(&mut lines, base + 2);
+                    has_rest = true;
+                } else {
+                    push_blank(&mut lines, base + 2);
+                }
+                push_line(&mut lines, base + 2, &line);
+            }
+
+            push_blank(&mut lines, base + 2);
             push_line(
                 &mut lines,
-                2,
-                &format!("- Comment: {}", step.comment.as_deref().unwrap_or("")),
+                base + 2,
+                &format!("- Created: {}", format_datetime(step.created_at)),
             );
-        }
+            push_line(
+                &mut lines,
+                base + 2,
+                &format!("- Updated: {}", format_datetime(step.updated_at)),
+            );
+            if has_text(&step.comment) {
+                push_line(
+                    &mut lines,
+                    base + 2,
+                    &format!("- Comment: {}", step.comment.as_deref().unwrap_or("")),
+                );
+            }
 
-        match goals.get(&step.id) {
-            Some(items) if !items.is_empty() => {
-                let done = items
-                    .iter()
-                    .filter(|goal| goal.status == GoalStatus::Done.as_str())
-                    .count();
-                push_line(&mut lines, 2, &format!("- Goals: {done}/{}", items.len()));
-
-                for goal in items {
-                    let (goal_first, goal_rest) = split_task_text(&goal.content);
-                    push_blank(&mut lines, 2);
-                    push_line(
-                        &mut lines,
-                        2,
-                        &format!(
-                            "- [{}] {} *(id: {})*",
-                            checkbox(&goal.status),
-                            goal_first,
-                            goal.id
-                        ),
-                    );
-                    for line in goal_rest {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        push_blank(&mut lines, 4);
-                        push_line(&mut lines, 4, &line);
-                    }
-                    if has_text(&goal.comment) {
-                        push_blank(&mut lines, 4);
+            match goals.get(&step.id) {
+                Some(items) if !items.is_empty() => {
+                    let done = items
+                        .iter()
+                        .filter(|goal| goal.status == GoalStatus::Done.as_str())
+                        .count();
+                    push_line(&mut lines, base + 2, &format!("- Goals: {done}/{}", items.len()));
+
+                    for goal in items {
+                        let (goal_first, goal_rest) = split_task_text(&goal.content);
+                        push_blank(&mut lines, base + 2);
                         push_line(
                             &mut lines,
-                            4,
-                            &format!("Comment: {}", goal.comment.as_deref().unwrap_or("")),
+                            base + 2,
+                            &format!(
+                                "- [{}] {} *(id: {})*",
+                                checkbox(&goal.status),
+                                goal_first,
+                                goal.id
+                            ),
                         );
+                        for line in goal_rest {
+                            if line.trim().is_empty() {
+                                continue;
+                            }
+                            push_blank(&mut lines, base + 4);
+                            push_line(&mut lines, base + 4, &line);
+                        }
+                        if has_text(&goal.comment) {
+                            push_blank(&mut lines, base + 4);
+                            push_line(
+                                &mut lines,
+                                base + 4,
+                                &format!("Comment: {}", goal.comment.as_deref().unwrap_or("")),
+                            );
+                        }
                     }
                 }
+                _ => {
+                    push_line(&mut lines, base + 2, "- Goals: 0/0");
+                    push_blank(&mut lines, base + 2);
+                    push_line(&mut lines, base + 2, "- (none)");
+                }
             }
-            _ => {
-                push_line(&mut lines, 2, "- Goals: 0/0");
-                push_blank(&mut lines, 2);
-                push_line(&mut lines, 2, "- (none)");
-            }
-        }
 
-        if idx + 1 < steps.len() {
             push_blank(&mut lines, 0);
         }
     }
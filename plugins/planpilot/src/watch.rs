@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::app::PlanDetail;
+use crate::entities::{active_plan, goal, plan, step};
+
+/// The rows `watch` compares between polls of the change feed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    plans: BTreeMap<i64, plan::Model>,
+    steps: BTreeMap<i64, step::Model>,
+    goals: BTreeMap<i64, goal::Model>,
+    activations: BTreeSet<(String, i64)>,
+}
+
+impl Snapshot {
+    /// Keeps only the activations of the given plans.
+    pub fn new(details: &[PlanDetail], activations: &[active_plan::Model]) -> Self {
+        let mut snapshot = Self::default();
+        for detail in details {
+            snapshot.plans.insert(detail.plan.id, detail.plan.clone());
+            for step in &detail.steps {
+                snapshot.steps.insert(step.id, step.clone());
+            }
+            for goal in detail.goals.values().flatten() {
+                snapshot.goals.insert(goal.id, goal.clone());
+            }
+        }
+        snapshot.activations = activations
+            .iter()
+            .filter(|state| snapshot.plans.contains_key(&state.plan_id))
+            .map(|state| (state.session_id.clone(), state.plan_id))
+            .collect();
+        snapshot
+    }
+}
+
+/// Describes what changed between two snapshots, one line per event. Additions come first, then
+/// updates from goals up to plans so status rollups read in the order they happened, then
+/// removals and activations. Rows removed along with their plan or step are not listed.
+pub fn diff(old: &Snapshot, new: &Snapshot) -> Vec<String> {
+    let mut events = Vec::new();
+
+    for (id, plan) in added(&old.plans, &new.plans) {
+        events.push(format!("Plan ID: {id} added: {}", plan.title));
+    }
+    for (id, step) in added(&old.steps, &new.steps) {
+        events.push(format!(
+            "Step ID: {id} added to plan ID: {} ({}): {}",
+            step.plan_id,
+            step.executor,
+            summary(&step.content)
+        ));
+    }
+    for (id, goal) in added(&old.goals, &new.goals) {
+        events.push(format!(
+            "Goal ID: {id} added to step ID: {}: {}",
+            goal.step_id,
+            summary(&goal.content)
+        ));
+    }
+
+    for (before, after) in changed(&old.goals, &new.goals) {
+        let label = format!("Goal ID: {}", after.id);
+        if before.step_id != after.step_id {
+            events.push(format!("{label} moved to step ID: {}.", after.step_id));
+        }
+        if before.content != after.content {
+            events.push(format!("{label} content updated: {}", summary(&after.content)));
+        }
+        if before.status != after.status {
+            events.push(format!(
+                "{label} status {} -> {}: {}",
+                before.status,
+                after.status,
+                summary(&after.content)
+            ));
+        }
+        push_comment(&mut events, &label, &before.comment, &after.comment);
+    }
+    for (before, after) in changed(&old.steps, &new.steps) {
+        let label = format!("Step ID: {}", after.id);
+        if before.plan_id != after.plan_id {
+            events.push(format!("{label} moved to plan ID: {}.", after.plan_id));
+        }
+        if before.content != after.content {
+            events.push(format!("{label} content updated: {}", summary(&after.content)));
+        }
+        if before.executor != after.executor {
+            events.push(format!(
+                "{label} executor {} -> {}.",
+                before.executor, after.executor
+            ));
+        }
+        if before.status != after.status {
+            events.push(format!(
+                "{label} status {} -> {}: {}",
+                before.status,
+                after.status,
+                summary(&after.content)
+            ));
+        }
+        push_comment(&mut events, &label, &before.comment, &after.comment);
+    }
+    let plan_ids: BTreeSet<i64> = new.steps.values().map(|step| step.plan_id).collect();
+    for plan_id in plan_ids {
+        if step_order(old, new, plan_id) != step_order(new, old, plan_id) {
+            events.push(format!("Steps reordered in plan ID: {plan_id}."));
+        }
+    }
+    for (before, after) in changed(&old.plans, &new.plans) {
+        let label = format!("Plan ID: {}", after.id);
+        if before.title != after.title {
+            events.push(format!("{label} renamed: {}", after.title));
+        }
+        if before.status != after.status {
+            events.push(format!(
+                "{label} status {} -> {}: {}",
+                before.status, after.status, after.title
+            ));
+        }
+        match (before.archived_at.is_some(), after.archived_at.is_some()) {
+            (false, true) => events.push(format!("{label} archived.")),
+            (true, false) => events.push(format!("{label} unarchived.")),
+            _ => {}
+        }
+        push_comment(&mut events, &label, &before.comment, &after.comment);
+    }
+
+    for (id, goal) in added(&new.goals, &old.goals) {
+        if new.steps.contains_key(&goal.step_id) {
+            events.push(format!("Goal ID: {id} removed: {}", summary(&goal.content)));
+        }
+    }
+    for (id, step) in added(&new.steps, &old.steps) {
+        if new.plans.contains_key(&step.plan_id) {
+            events.push(format!("Step ID: {id} removed: {}", summary(&step.content)));
+        }
+    }
+    for (id, plan) in added(&new.plans, &old.plans) {
+        events.push(format!("Plan ID: {id} removed: {}", plan.title));
+    }
+
+    for (session_id, plan_id) in new.activations.difference(&old.activations) {
+        let title = new.plans.get(plan_id).map(|plan| plan.title.as_str());
+        events.push(format!(
+            "Session {session_id} activated plan ID: {plan_id}: {}",
+            title.unwrap_or_default()
+        ));
+    }
+    for (session_id, plan_id) in old.activations.difference(&new.activations) {
+        if new.plans.contains_key(plan_id) {
+            events.push(format!("Session {session_id} deactivated plan ID: {plan_id}."));
+        }
+    }
+    events
+}
+
+/// Rows present in `new` but not in `old`.
+fn added<'a, T>(
+    old: &'a BTreeMap<i64, T>,
+    new: &'a BTreeMap<i64, T>,
+) -> impl Iterator<Item = (&'a i64, &'a T)> {
+    new.iter().filter(|(id, _)| !old.contains_key(id))
+}
+
+fn changed<'a, T: PartialEq>(
+    old: &'a BTreeMap<i64, T>,
+    new: &'a BTreeMap<i64, T>,
+) -> impl Iterator<Item = (&'a T, &'a T)> {
+    old.iter().filter_map(|(id, before)| {
+        new.get(id)
+            .filter(|after| *after != before)
+            .map(|after| (before, after))
+    })
+}
+
+/// The steps of `plan_id` that exist in both snapshots, in `snapshot`'s order with their parents,
+/// so added or removed steps alone do not count as a reorder.
+fn step_order(snapshot: &Snapshot, other: &Snapshot, plan_id: i64) -> Vec<(i64, Option<i64>)> {
+    let mut steps: Vec<&step::Model> = snapshot
+        .steps
+        .values()
+        .filter(|step| step.plan_id == plan_id)
+        .filter(|step| {
+            other
+                .steps
+                .get(&step.id)
+                .is_some_and(|twin| twin.plan_id == plan_id)
+        })
+        .collect();
+    steps.sort_by_key(|step| (step.sort_order, step.id));
+    steps.iter().map(|step| (step.id, step.parent_id)).collect()
+}
+
+fn push_comment(
+    events: &mut Vec<String>,
+    label: &str,
+    before: &Option<String>,
+    after: &Option<String>,
+) {
+    let text = |value: &Option<String>| {
+        value.as_deref().map(str::trim).unwrap_or_default().to_string()
+    };
+    let (before, after) = (text(before), text(after));
+    if before == after {
+        return;
+    }
+    if after.is_empty() {
+        events.push(format!("{label} comment cleared."));
+    } else {
+        events.push(format!("{label} comment: {}", summary(&after)));
+    }
+}
+
+fn summary(text: &str) -> &str {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use sha2::Sha256;
+use url::{Host, Position, Url};
+
+use crate::app::{webhook_retry_delay, App, DeliveryOutcome};
+use crate::entities::{webhook, webhook_delivery};
+use crate::error::AppError;
+
+pub const SIGNATURE_HEADER: &str = "X-Planpilot-Signature";
+
+/// A claimed delivery and what happened when it was sent.
+pub struct Attempt {
+    pub webhook: webhook::Model,
+    pub delivery: webhook_delivery::Model,
+    pub outcome: DeliveryOutcome,
+}
+
+impl Attempt {
+    pub fn describe(&self) -> String {
+        let label = format!(
+            "Webhook delivery ID: {} ({}) to webhook ID: {}",
+            self.delivery.id, self.delivery.event, self.webhook.id
+        );
+        match &self.outcome {
+            DeliveryOutcome::Delivered => format!("{label} delivered."),
+            DeliveryOutcome::Failed(error) => {
+                let retry_in = webhook_retry_delay(self.delivery.attempts + 1);
+                format!("{label} failed: {error}; retrying in {}s.", retry_in.num_seconds())
+            }
+        }
+    }
+}
+
+/// The `X-Planpilot-Signature` value for a body: the hex HMAC-SHA256 of the raw body keyed with
+/// the webhook secret, prefixed with `sha256=`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Sends the due deliveries (every pending one when `force` is set), one per webhook per round
+/// so each endpoint receives its events in order. Once a webhook fails, its later deliveries are
+/// left pending for the next run. The lock is only held while the outbox is claimed and updated,
+/// not while waiting on the endpoints.
+pub async fn deliver(
+    app: &App,
+    lock: &mut fd_lock::RwLock<File>,
+    force: bool,
+) -> Result<Vec<Attempt>, AppError> {
+    let mut attempts = Vec::new();
+    let mut failed = HashSet::new();
+    loop {
+        let claimed = {
+            let _guard = lock.write()?;
+            app.claim_webhook_deliveries(force, &failed).await?
+        };
+        if claimed.is_empty() {
+            return Ok(attempts);
+        }
+        let round: Vec<Attempt> = claimed
+            .into_iter()
+            .map(|(webhook, delivery)| {
+                let outcome = match post(&webhook, &delivery) {
+                    Ok(()) => DeliveryOutcome::Delivered,
+                    Err(error) => {
+                        failed.insert(webhook.id);
+                        DeliveryOutcome::Failed(error)
+                    }
+                };
+                Attempt {
+                    webhook,
+                    delivery,
+                    outcome,
+                }
+            })
+            .collect();
+        let outcomes: Vec<(webhook_delivery::Model, DeliveryOutcome)> = round
+            .iter()
+            .map(|attempt| (attempt.delivery.clone(), attempt.outcome.clone()))
+            .collect();
+        {
+            let _guard = lock.write()?;
+            app.record_webhook_outcomes(&outcomes).await?;
+        }
+        attempts.extend(round);
+    }
+}
+
+fn post(webhook: &webhook::Model, delivery: &webhook_delivery::Model) -> Result<(), String> {
+    let url = Url::parse(&webhook.url).map_err(|err| format!("invalid url: {err}"))?;
+    let timeout = Duration::from_secs(webhook.timeout_secs.max(1) as u64);
+    let addrs = url
+        .socket_addrs(|| None)
+        .map_err(|err| format!("failed to resolve {}: {err}", webhook.url))?;
+    let mut stream = None;
+    let mut last_error = format!("no address found for {}", webhook.url);
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(err) => last_error = format!("failed to connect to {addr}: {err}"),
+        }
+    }
+    let stream = stream.ok_or(last_error)?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    let body = delivery.payload.as_str();
+    let request = format!(
+        "POST {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         User-Agent: planpilot/{}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         X-Planpilot-Event: {}\r\n\
+         X-Planpilot-Delivery: {}\r\n\
+         {SIGNATURE_HEADER}: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        &url[Position::BeforePath..Position::AfterQuery],
+        &url[Position::BeforeHost..Position::AfterPort],
+        env!("CARGO_PKG_VERSION"),
+        body.len(),
+        delivery.event,
+        delivery.id,
+        sign(&webhook.secret, body),
+    );
+    let status = if url.scheme() == "https" {
+        let server_name = match url.host() {
+            Some(Host::Domain(domain)) => ServerName::try_from(domain.to_string())
+                .map_err(|err| format!("invalid host name: {err}"))?,
+            Some(Host::Ipv4(ip)) => ServerName::from(std::net::IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => ServerName::from(std::net::IpAddr::V6(ip)),
+            None => return Err("url has no host".to_string()),
+        };
+        let connection = ClientConnection::new(tls_config()?, server_name)
+            .map_err(|err| format!("tls error: {err}"))?;
+        exchange(StreamOwned::new(connection, stream), request.as_bytes())?
+    } else {
+        exchange(stream, request.as_bytes())?
+    };
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("endpoint responded with HTTP {status}"))
+    }
+}
+
+/// Writes the request and returns the status code of the response; the rest is ignored.
+fn exchange(mut stream: impl Read + Write, request: &[u8]) -> Result<u16, String> {
+    stream
+        .write_all(request)
+        .and_then(|_| stream.flush())
+        .map_err(|err| format!("failed to send: {err}"))?;
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .map_err(|err| format!("failed to read the response: {err}"))?;
+    status_line
+        .strip_prefix("HTTP/1.")
+        .and_then(|rest| rest.get(2..5))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("invalid response: '{}'", status_line.trim()))
+}
+
+fn tls_config() -> Result<Arc<ClientConfig>, String> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("tls error: {err}"))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_uses_hmac_sha256_of_the_body() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -1,10 +1,16 @@
 use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output, Stdio};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
+use hmac::{Hmac, Mac};
 use sea_orm::{ConnectionTrait, Database, DatabaseBackend, Statement};
 use serde_json::Value;
+use sha2::Sha256;
 use tempfile::TempDir;
 use url::Url;
 
@@ -121,6 +127,197 @@ fn project_dir(dir: &TempDir) -> PathBuf {
     path
 }
 
+fn git_output(dir: &Path, args: &[&str]) -> Output {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Planpilot", "-c", "user.email=planpilot@example.com"])
+        .args(args)
+        .output()
+        .expect("run git")
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = git_output(dir, args);
+    assert!(output.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).expect("git utf8").trim().to_string()
+}
+
+/// A running `planpilot serve`, killed on drop.
+struct Server {
+    child: Child,
+    port: u16,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn plugin_root(project: &Path) -> PathBuf {
+    let plugin_root = project
+        .parent()
+        .unwrap_or(project)
+        .join(".claude")
+        .join("plugins")
+        .join("planpilot");
+    fs::create_dir_all(&plugin_root).expect("create plugin root");
+    plugin_root
+}
+
+fn spawn_serve(project: &Path, args: &[&str]) -> Server {
+    let mut child = Command::new(bin_path())
+        .arg("--session-id")
+        .arg("dashboard")
+        .arg("serve")
+        .arg("--port")
+        .arg("0")
+        .args(args)
+        .env("CLAUDE_PLUGIN_ROOT", plugin_root(project))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn serve");
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().expect("stdout"))
+        .read_line(&mut line)
+        .expect("read serve banner");
+    let port = line
+        .split("http://127.0.0.1:")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .and_then(|port| port.parse().ok())
+        .unwrap_or_else(|| panic!("serve banner: {line}"));
+    Server { child, port }
+}
+
+/// A running `planpilot watch` whose output lines arrive on `lines`, killed on drop. Spawning
+/// waits for the banner, which is printed once the first snapshot is taken.
+struct Watcher {
+    child: Child,
+    lines: Receiver<String>,
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Watcher {
+    /// Waits for a line containing `needle`, returning it.
+    fn expect(&self, needle: &str) -> String {
+        let mut seen = Vec::new();
+        while let Ok(line) = self.lines.recv_timeout(Duration::from_secs(10)) {
+            if line.contains(needle) {
+                return line;
+            }
+            seen.push(line);
+        }
+        panic!("no line containing {needle:?}; saw: {seen:#?}");
+    }
+}
+
+fn spawn_watch(project: &Path, args: &[&str]) -> Watcher {
+    let mut child = Command::new(bin_path())
+        .arg("--session-id")
+        .arg("watcher")
+        .arg("watch")
+        .arg("--interval")
+        .arg("50")
+        .args(args)
+        .env("CLAUDE_PLUGIN_ROOT", plugin_root(project))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn watch");
+    let stdout = child.stdout.take().expect("stdout");
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let watcher = Watcher { child, lines };
+    watcher.expect("press Ctrl-C to stop");
+    watcher
+}
+
+/// Sends one request and returns the status code and body.
+fn http(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+    http_with_headers(port, method, path, "Content-Type: application/json\r\n", body)
+}
+
+/// Sends a request with extra header lines, each ending in `\r\n`.
+fn http_with_headers(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &str,
+    body: &str,
+) -> (u16, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("connect");
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n{headers}\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .expect("send request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("read response");
+    let (head, body) = response.split_once("\r\n\r\n").expect("response head");
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("status code");
+    (status, body.to_string())
+}
+
+/// A request received by the webhook stub: headers with lowercased names, and the body.
+type StubRequest = (Vec<(String, String)>, String);
+
+/// Answers `count` POSTs with 204 and returns them.
+fn spawn_webhook_stub(listener: TcpListener, count: usize) -> JoinHandle<Vec<StubRequest>> {
+    thread::spawn(move || {
+        let mut requests = Vec::new();
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.expect("accept");
+            let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("read header");
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+                }
+            }
+            let length: usize = headers
+                .iter()
+                .find(|(name, _)| name == "content-length")
+                .and_then(|(_, value)| value.parse().ok())
+                .expect("content length");
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).expect("read body");
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .expect("respond");
+            requests.push((headers, String::from_utf8(body).expect("utf8 body")));
+        }
+        requests
+    })
+}
+
 fn claude_home(dir: &TempDir) -> PathBuf {
     dir.path().join(".claude")
 }
@@ -141,13 +338,13 @@ fn add_step(dir: &TempDir, plan_id: i64, content: &str, executor: Option<&str>)
         args.push("--executor");
         args.push(executor);
     }
-    let stdout = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &args, None));
+    let stdout = output_stdout(run_cmd(Some(project_dir(dir).as_path()), &args, None));
     parse_step_id(&stdout)
 }
 
 fn add_goal(dir: &TempDir, step_id: i64, content: &str) -> i64 {
     let stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
+        Some(project_dir(dir).as_path()),
         &["goal", "add", &step_id.to_string(), content],
         None,
     ));
@@ -156,7 +353,7 @@ fn add_goal(dir: &TempDir, step_id: i64, content: &str) -> i64 {
 
 fn activate_plan(dir: &TempDir, plan_id: i64) {
     output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
+        Some(project_dir(dir).as_path()),
         &["plan", "activate", &plan_id.to_string()],
         None,
     ));
@@ -371,431 +568,2304 @@ fn hook_stop_blocks_for_ai_step() {
 }
 
 #[test]
-fn list_count_only_outputs_total() {
+fn unmet_criteria_are_surfaced_once_all_steps_are_done() {
     let dir = TempDir::new().expect("temp dir");
-    let plan_stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "add", "Plan", "Content"],
-        None,
-    ));
-    let plan_id = parse_plan_id(&plan_stdout);
-
-    let step_stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "add", &plan_id.to_string(), "Step"],
-        None,
-    ));
-    let step_id = parse_step_id(&step_stdout);
-
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
     output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "add", &step_id.to_string(), "Goal 1", "Goal 2"],
+        Some(project.as_path()),
+        &["criterion", "add", &plan_id.to_string(), "Docs updated"],
         None,
     ));
+    activate_plan(&dir, plan_id);
 
-    let step_count = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "list", &plan_id.to_string(), "--count"],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &step_id.to_string()],
         None,
     ));
-    assert_eq!(step_count.trim(), "Total: 1");
-    assert!(!step_count.contains("ID"));
+    assert!(
+        stdout.contains(&format!("Unmet acceptance criteria for plan ID: {plan_id}:")),
+        "stdout: {stdout}"
+    );
+    assert!(stdout.contains("- [ ] Docs updated (criterion id "), "stdout: {stdout}");
+    assert!(stdout.contains("planpilot criterion check <id>"), "stdout: {stdout}");
 
-    let goal_count = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "list", &step_id.to_string(), "--count"],
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["step", "show-next"], None));
+    assert!(
+        stdout.starts_with(&format!("Unmet acceptance criteria for plan ID: {plan_id}:")),
+        "stdout: {stdout}"
+    );
+
+    let payload = serde_json::json!({
+        "session_id": "test-session",
+        "cwd": project.to_string_lossy()
+    });
+    let stdout = output_stdout(run_cmd_with_env(
+        Some(project.as_path()),
         None,
+        &["hook", "stop"],
+        Some(&payload.to_string()),
     ));
-    assert_eq!(goal_count.trim(), "Total: 2");
-    assert!(!goal_count.contains("ID"));
+    let value: Value = serde_json::from_str(&stdout).expect("json output");
+    assert_eq!(value["decision"], "block");
+    let reason = value["reason"].as_str().expect("reason");
+    assert!(reason.contains("criterion check"), "reason: {reason}");
+    assert!(reason.contains("Docs updated"), "reason: {reason}");
 }
 
 #[test]
-fn plan_md_created_and_updated() {
+fn hook_stop_names_sub_agent_for_named_ai_executor() {
     let dir = TempDir::new().expect("temp dir");
     let plan_id = create_plan(&dir);
+    add_step(&dir, plan_id, "Review changes", Some("ai:reviewer"));
     activate_plan(&dir, plan_id);
 
-    let md_path = plan_md_path(&dir, plan_id);
-    assert!(md_path.exists());
-    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
-    assert!(contents.contains("## Plan: Plan\n"));
-    assert!(contents.contains("**Active:** `true`"));
-
-    output_stdout(run_cmd(
+    let payload = serde_json::json!({
+        "session_id": "test-session",
+        "cwd": project_dir(&dir).to_string_lossy()
+    });
+    let output = run_cmd_with_env(
         Some(project_dir(&dir).as_path()),
-        &[
-            "plan",
-            "update",
-            &plan_id.to_string(),
-            "--title",
-            "Plan Updated",
-        ],
         None,
-    ));
-
-    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
-    assert!(contents.contains("## Plan: Plan Updated\n"));
-    assert!(!contents.contains("## Plan: Plan\n"));
+        &["hook", "stop"],
+        Some(&payload.to_string()),
+    );
+    let stdout = output_stdout(output);
+    let value: Value = serde_json::from_str(&stdout).expect("json output");
+    assert_eq!(value["decision"], "block");
+    let reason = value["reason"].as_str().expect("reason");
+    assert!(reason.contains("(executor: ai:reviewer)"));
+    assert!(reason.contains("`reviewer` sub-agent"));
+    assert!(reason.contains("Executor: ai:reviewer"));
 }
 
 #[test]
-fn plan_md_marked_inactive_on_deactivate() {
+fn acceptance_criteria_gate_plan_completion() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
     let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Ship it", Some("ai"));
     activate_plan(&dir, plan_id);
 
-    let md_path = plan_md_path(&dir, plan_id);
-    assert!(md_path.exists());
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["criterion", "add", &plan_id.to_string(), "Latency under 200ms", "Docs updated"],
+        None,
+    ));
+    assert!(
+        stdout.contains(&format!("Created 2 criteria for plan ID: {plan_id}")),
+        "stdout: {stdout}"
+    );
+    let contents = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(contents.contains("### Acceptance Criteria (0/2)"), "plan.md: {contents}");
 
-    output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "deactivate"], None));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &step_id.to_string()],
+        None,
+    ));
+    assert!(!stdout.contains("is complete"), "stdout: {stdout}");
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["plan", "done", &plan_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unmet acceptance criterion: Latency under 200ms"), "stderr: {stderr}");
 
-    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
-    assert!(contents.contains("**Active:** `false`"));
-}
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["criterion", "list", &plan_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("todo   Docs updated"), "stdout: {stdout}");
+    let ids: Vec<String> = stdout
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next().map(str::to_string))
+        .collect();
+    assert_eq!(ids.len(), 2);
 
-#[test]
-fn plan_add_tree_creates_steps_and_goals() {
-    let dir = TempDir::new().expect("temp dir");
     let stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &[
-            "plan",
-            "add-tree",
-            "Tree Plan",
-            "Plan content",
-            "--step",
-            "Step A",
-            "--executor",
-            "ai",
-            "--goal",
-            "Goal A1",
-            "--goal",
-            "Goal A2",
-            "--step",
-            "Step B",
-            "--executor",
-            "human",
-        ],
+        Some(project.as_path()),
+        &["criterion", "check", &ids[0], &ids[1]],
         None,
     ));
-    let plan_id = parse_plan_id(&stdout);
+    assert!(stdout.contains("2 criteria checked."), "stdout: {stdout}");
+    assert!(stdout.contains(&format!("Plan ID: {plan_id} is complete.")), "stdout: {stdout}");
+    assert!(stdout.contains("Acceptance criteria:"), "stdout: {stdout}");
+    assert!(stdout.contains("- [done] Latency under 200ms"), "stdout: {stdout}");
 
-    let detail = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
         &["plan", "show", &plan_id.to_string()],
         None,
     ));
-    assert!(detail.contains("Step A"));
-    assert!(detail.contains("Goal A1"));
-    assert!(detail.contains("Goal A2"));
-    assert!(detail.contains("Step B"));
+    assert!(stdout.contains("Acceptance Criteria (2/2):"), "stdout: {stdout}");
 }
 
 #[test]
-fn plan_add_tree_rejects_json_step_spec() {
+fn step_done_records_git_checkpoint_and_step_diff() {
     let dir = TempDir::new().expect("temp dir");
-    let output = run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &[
-            "plan",
-            "add-tree",
-            "Plan",
-            "Content",
-            "--step",
-            r#"{"content":"Step"}"#,
-        ],
+    let project = project_dir(&dir);
+    git(&project, &["init", "-q", "-b", "main"]);
+    fs::write(project.join("a.txt"), "one\n").expect("write a.txt");
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "first"]);
+    let first = git(&project, &["rev-parse", "HEAD"]);
+
+    let plan_id = create_plan(&dir);
+    let scaffold = add_step(&dir, plan_id, "Scaffold", Some("ai"));
+    let feature = add_step(&dir, plan_id, "Feature", Some("ai"));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &scaffold.to_string()],
+        None,
+    ));
+    fs::write(project.join("b.txt"), "two\n").expect("write b.txt");
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "second"]);
+    let second = git(&project, &["rev-parse", "HEAD"]);
+    fs::write(project.join("a.txt"), "changed\n").expect("dirty a.txt");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &feature.to_string()],
+        None,
+    ));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &feature.to_string()],
         None,
+    ));
+    assert!(
+        stdout.contains(&format!("Checkpoint: {} on main with uncommitted changes", &second[..7])),
+        "stdout: {stdout}"
+    );
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &plan_id.to_string()],
+        None,
+    ));
+    assert!(
+        stdout.contains(&format!("  Checkpoint: {} on main\n", &first[..7])),
+        "stdout: {stdout}"
     );
-    assert!(!output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("no longer accepts JSON"));
-}
 
-#[test]
-fn plan_add_tree_rejects_empty_step_content() {
-    let dir = TempDir::new().expect("temp dir");
-    let output = run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "add-tree", "Plan", "Content", "--step", "   "],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "diff", &feature.to_string()],
         None,
+    ));
+    assert!(
+        stdout.contains(&format!(
+            "Step ID: {feature} changes since step ID: {scaffold} ({}..{})",
+            &first[..7],
+            &second[..7]
+        )),
+        "stdout: {stdout}"
     );
-    assert!(!output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("plan add-tree --step cannot be empty"));
-}
+    assert!(stdout.contains("uncommitted changes; they are not included"), "stdout: {stdout}");
+    assert!(stdout.contains("+++ b/b.txt") && stdout.contains("+two"), "stdout: {stdout}");
+    assert!(!stdout.contains("changed"), "stdout: {stdout}");
 
-#[test]
-fn plan_add_tree_rejects_empty_goal_content() {
-    let dir = TempDir::new().expect("temp dir");
     let output = run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &[
-            "plan",
-            "add-tree",
-            "Plan",
-            "Content",
-            "--step",
-            "Step",
-            "--goal",
-            "   ",
-        ],
+        Some(project.as_path()),
+        &["step", "diff", &scaffold.to_string()],
         None,
     );
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("goal content cannot be empty"));
-}
-
-#[test]
-fn step_add_tree_creates_goals() {
-    let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
+    assert!(stderr.contains("no earlier checkpoint"), "stderr: {stderr}");
     let stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &[
-            "step",
-            "add-tree",
-            &plan_id.to_string(),
-            "Step with goals",
-            "--executor",
-            "ai",
-            "--goal",
-            "G1",
-            "--goal",
-            "G2",
-        ],
+        Some(project.as_path()),
+        &["step", "diff", &feature.to_string(), "--from", &second],
         None,
     ));
-    let step_id = parse_step_id(&stdout);
+    assert!(stdout.contains("No changes."), "stdout: {stdout}");
 
-    let goals = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "list", &step_id.to_string(), "--all"],
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "update", &feature.to_string(), "--status", "todo"],
         None,
     ));
-    assert!(goals.contains("G1"));
-    assert!(goals.contains("G2"));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &feature.to_string()],
+        None,
+    ));
+    assert!(!stdout.contains("Checkpoint:"), "stdout: {stdout}");
 }
 
 #[test]
-fn step_comment_rejects_empty_comment() {
+fn git_scan_completes_trailer_ids_and_hook_rejects_unknown_ids() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    git(&project, &["init", "-q", "-b", "main"]);
+    git(&project, &["commit", "-q", "--allow-empty", "-m", "init"]);
     let plan_id = create_plan(&dir);
-    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let output = run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "comment", &step_id.to_string(), "   "],
+    let design = add_step(&dir, plan_id, "Design", Some("ai"));
+    let sketch = add_goal(&dir, design, "Sketch");
+    let review = add_goal(&dir, design, "Review");
+    let rollout = add_step(&dir, plan_id, "Roll out", Some("human"));
+    add_goal(&dir, rollout, "Flip the flag");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["git", "install-hook"],
         None,
+    ));
+    assert!(stdout.contains("Installed commit-msg hook at "), "stdout: {stdout}");
+    let output = git_output(
+        &project,
+        &["commit", "-q", "--allow-empty", "-m", "Oops\n\nPlanpilot-Goal: 9999"],
     );
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("comment cannot be empty"));
+    assert!(stderr.contains("goal id(s) not found: 9999"), "stderr: {stderr}");
+
+    let message =
+        format!("Sketch and roll out\n\nPlanpilot-Goal: {sketch}\nPlanpilot-Step: {rollout}");
+    git(&project, &["commit", "-q", "--allow-empty", "-m", &message]);
+    let commit = git(&project, &["rev-parse", "HEAD"]);
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["git", "scan"], None));
+    let short = &commit[..7];
+    for expected in [
+        format!("Commit {short}: Goal ID: {sketch} done."),
+        format!("Commit {short}: Step ID: {rollout} done."),
+        "Scanned 2 commits; completed 1 goals and 1 steps.".to_string(),
+    ] {
+        assert!(stdout.contains(&expected), "stdout: {stdout}");
+    }
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "show", &sketch.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Status: done"), "stdout: {stdout}");
+    assert!(stdout.contains(&format!("Checkpoint: {short} on main")), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &rollout.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Status: done"), "stdout: {stdout}");
+    assert!(stdout.contains("- [done] Flip the flag"), "stdout: {stdout}");
+
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["git", "scan"], None));
+    assert!(stdout.contains("completed 0 goals and 0 steps."), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["git", "scan", "--since", "HEAD"],
+        None,
+    ));
+    assert!(stdout.contains("Scanned 0 commits;"), "stdout: {stdout}");
+
+    // A step completed by rolling up a trailer goal records that commit, not a later HEAD.
+    let message = format!("Review the sketch\n\nPlanpilot-Goal: {review}");
+    git(&project, &["commit", "-q", "--allow-empty", "-m", &message]);
+    let trailer_commit = git(&project, &["rev-parse", "HEAD"]);
+    git(&project, &["commit", "-q", "--allow-empty", "-m", "later"]);
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["git", "scan"], None));
+    assert!(stdout.contains("completed 1 goals and 0 steps."), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &design.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Status: done"), "stdout: {stdout}");
+    let expected = format!("Checkpoint: {} on main", &trailer_commit[..7]);
+    assert!(stdout.contains(&expected), "stdout: {stdout}");
+}
+
+#[test]
+fn milestones_group_steps_and_name_current_milestone() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let design = add_step(&dir, plan_id, "Write design doc", Some("ai"));
+    add_step(&dir, plan_id, "Unplanned cleanup", Some("ai"));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["milestone", "add", &plan_id.to_string(), "Design", "--step", &design.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("for plan ID: {plan_id}: Design")), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["milestone", "add", &plan_id.to_string(), "Build"],
+        None,
+    ));
+    let build_id: i64 = stdout
+        .split("Created milestone ID: ")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+        .expect("milestone id");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "add", &plan_id.to_string(), "Implement", "--milestone", &build_id.to_string()],
+        None,
+    ));
+    let implement = parse_step_id(&stdout);
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &plan_id.to_string()],
+        None,
+    ));
+    let design_pos = stdout.find("Milestone: Design [todo]").expect("design header");
+    let build_pos = stdout
+        .find(&format!("Milestone: Build [todo] (milestone id {build_id}, steps 0/1)"))
+        .expect("build header");
+    let loose_pos = stdout.find("No milestone:").expect("unassigned header");
+    assert!(design_pos < build_pos && build_pos < loose_pos, "stdout: {stdout}");
+    assert!(stdout.contains("  - [todo] Implement"), "stdout: {stdout}");
+    let contents = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(contents.contains("#### Milestone: Build"));
+    assert!(contents.contains("#### No milestone"));
+
+    activate_plan(&dir, plan_id);
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &design.to_string()],
+        None,
+    ));
+    let payload = serde_json::json!({
+        "session_id": "test-session",
+        "cwd": project.to_string_lossy()
+    });
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        None,
+        &["hook", "stop"],
+        Some(&payload.to_string()),
+    );
+    let stdout = output_stdout(output);
+    let value: Value = serde_json::from_str(&stdout).expect("json output");
+    let reason = value["reason"].as_str().expect("reason");
+    assert!(reason.contains(&format!("Step ID: {implement}")), "reason: {reason}");
+    assert!(
+        reason.contains(&format!("Current milestone: Build (milestone id {build_id}, steps 0/1).")),
+        "reason: {reason}"
+    );
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["milestone", "list", &plan_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("done   1/1       Design"), "stdout: {stdout}");
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["milestone", "done", &build_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot mark milestone done"));
+}
+
+#[test]
+fn hook_stop_approves_for_ci_step() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    add_step(&dir, plan_id, "Run pipeline", Some("ci"));
+    activate_plan(&dir, plan_id);
+
+    let payload = serde_json::json!({
+        "session_id": "test-session",
+        "cwd": project_dir(&dir).to_string_lossy()
+    });
+    let output = run_cmd_with_env(
+        Some(project_dir(&dir).as_path()),
+        None,
+        &["hook", "stop"],
+        Some(&payload.to_string()),
+    );
+    let stdout = output_stdout(output);
+    let value: Value = serde_json::from_str(&stdout).expect("json output");
+    assert_eq!(value["decision"], "approve");
+}
+
+#[test]
+fn step_add_rejects_invalid_executor() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "add", &plan_id.to_string(), "Step", "--executor", "ai:"],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid executor 'ai:'"), "stderr: {stderr}");
+}
+
+#[test]
+fn session_list_includes_stop_hook_heartbeat() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let payload = serde_json::json!({
+        "session_id": "hook-session",
+        "cwd": project.to_string_lossy()
+    });
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        None,
+        &["hook", "stop"],
+        Some(&payload.to_string()),
+    );
+    output_stdout(output);
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["session", "list"],
+        None,
+    ));
+    let line = stdout
+        .lines()
+        .find(|line| line.starts_with("hook-session"))
+        .expect("hook session listed");
+    assert!(line.contains("live"), "line: {line}");
+    assert!(line.contains(&*project.to_string_lossy()), "line: {line}");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["session", "prune", "--dry-run"],
+        None,
+    ));
+    assert_eq!(stdout.trim(), "No stale sessions.");
+}
+
+#[tokio::test]
+async fn hook_pretooluse_refreshes_session_heartbeat() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    output_stdout(run_cmd_with_env(
+        Some(project.as_path()),
+        Some("busy-session"),
+        &["plan", "list"],
+        None,
+    ));
+    let db_path = claude_home(&dir).join(".planpilot").join("planpilot.db");
+    let mut url = Url::from_file_path(&db_path).expect("db path");
+    url.set_query(Some("mode=rwc"));
+    let sqlite_url = url.as_str().replacen("file://", "sqlite://", 1);
+    let db = Database::connect(&sqlite_url).await.expect("connect db");
+    db.execute(Statement::from_string(
+        DatabaseBackend::Sqlite,
+        "UPDATE sessions SET last_seen_at = '2000-01-01T00:00:00+00:00' \
+         WHERE session_id = 'busy-session';"
+            .to_string(),
+    ))
+    .await
+    .expect("backdate session");
+    let state = || {
+        let stdout = output_stdout(run_cmd(Some(project.as_path()), &["session", "list"], None));
+        stdout
+            .lines()
+            .find(|line| line.starts_with("busy-session"))
+            .expect("busy session listed")
+            .to_string()
+    };
+    assert!(state().contains("stale"), "line: {}", state());
+
+    // Any tool call counts, not only planpilot commands.
+    let payload = serde_json::json!({
+        "tool_name": "Read",
+        "tool_input": {},
+        "session_id": "busy-session",
+        "cwd": project.to_string_lossy(),
+    });
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        None,
+        &["hook", "pretooluse"],
+        Some(&payload.to_string()),
+    );
+    assert_eq!(output_stdout(output), "");
+    let line = state();
+    assert!(line.contains("live"), "line: {line}");
+}
+
+#[test]
+fn list_count_only_outputs_total() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "add", "Plan", "Content"],
+        None,
+    ));
+    let plan_id = parse_plan_id(&plan_stdout);
+
+    let step_stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "add", &plan_id.to_string(), "Step"],
+        None,
+    ));
+    let step_id = parse_step_id(&step_stdout);
+
+    output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "add", &step_id.to_string(), "Goal 1", "Goal 2"],
+        None,
+    ));
+
+    let step_count = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "list", &plan_id.to_string(), "--count"],
+        None,
+    ));
+    assert_eq!(step_count.trim(), "Total: 1");
+    assert!(!step_count.contains("ID"));
+
+    let goal_count = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "list", &step_id.to_string(), "--count"],
+        None,
+    ));
+    assert_eq!(goal_count.trim(), "Total: 2");
+    assert!(!goal_count.contains("ID"));
+}
+
+#[test]
+fn plan_md_created_and_updated() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    activate_plan(&dir, plan_id);
+
+    let md_path = plan_md_path(&dir, plan_id);
+    assert!(md_path.exists());
+    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
+    assert!(contents.contains("## Plan: Plan\n"));
+    assert!(contents.contains("**Active:** `true`"));
+
+    output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "update",
+            &plan_id.to_string(),
+            "--title",
+            "Plan Updated",
+        ],
+        None,
+    ));
+
+    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
+    assert!(contents.contains("## Plan: Plan Updated\n"));
+    assert!(!contents.contains("## Plan: Plan\n"));
+}
+
+#[test]
+fn plan_md_marked_inactive_on_deactivate() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    activate_plan(&dir, plan_id);
+
+    let md_path = plan_md_path(&dir, plan_id);
+    assert!(md_path.exists());
+
+    output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "deactivate"], None));
+
+    let contents = std::fs::read_to_string(&md_path).expect("read plan.md");
+    assert!(contents.contains("**Active:** `false`"));
+}
+
+#[test]
+fn plan_push_and_pop_track_focus_stack() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let feature_id = create_plan(&dir);
+    add_step(&dir, feature_id, "Feature step", Some("ai"));
+    let bugfix_id = create_plan(&dir);
+    add_step(&dir, bugfix_id, "Fix step", Some("ai"));
+    activate_plan(&dir, feature_id);
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "push", &bugfix_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Suspended plan ID: {feature_id}.")));
+    assert!(stdout.contains(&format!("Active plan set to {bugfix_id}")));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show-active"],
+        None,
+    ));
+    assert!(stdout.contains(&format!(
+        "Focus stack: plan {bugfix_id} (active) > plan {feature_id}: Plan"
+    )));
+
+    let payload = serde_json::json!({
+        "session_id": "test-session",
+        "cwd": project.to_string_lossy()
+    });
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        None,
+        &["hook", "stop"],
+        Some(&payload.to_string()),
+    );
+    let stdout = String::from_utf8(output.stdout).expect("stdout utf8");
+    let value: Value = serde_json::from_str(&stdout).expect("json output");
+    assert_eq!(value["decision"], "block");
+    let reason = value["reason"].as_str().expect("reason");
+    assert!(reason.contains("Fix step"), "reason: {reason}");
+    assert!(reason.contains("Focus stack:"), "reason: {reason}");
+
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["plan", "pop"], None));
+    assert!(stdout.contains(&format!("Plan ID: {bugfix_id} deactivated.")));
+    assert!(stdout.contains(&format!("Active plan set to {feature_id}")));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show-active"],
+        None,
+    ));
+    assert!(!stdout.contains("Focus stack:"));
+}
+
+#[test]
+fn finishing_pushed_plan_skips_suspended_plan_active_elsewhere() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let feature_id = create_plan(&dir);
+    add_step(&dir, feature_id, "Feature step", Some("ai"));
+    let bugfix_id = create_plan(&dir);
+    let fix_step = add_step(&dir, bugfix_id, "Fix step", Some("ai"));
+    let run_as = |session_id: &str, args: &[&str]| {
+        output_stdout(run_cmd_with_env(Some(project.as_path()), Some(session_id), args, None))
+    };
+    run_as("s1", &["plan", "activate", &feature_id.to_string()]);
+    run_as("s1", &["plan", "push", &bugfix_id.to_string()]);
+    run_as("s2", &["plan", "activate", &feature_id.to_string()]);
+
+    let stdout = run_as("s1", &["step", "done", &fix_step.to_string()]);
+    assert!(stdout.contains(&format!("Step ID: {fix_step} marked done.")), "stdout: {stdout}");
+    let stdout = run_as("s1", &["plan", "show-active"]);
+    assert_eq!(stdout.trim(), "No active plan.");
+    let stdout = run_as("s2", &["plan", "show-active"]);
+    assert!(stdout.starts_with(&format!("Plan ID: {feature_id}\n")), "stdout: {stdout}");
+}
+
+#[test]
+fn plan_add_tree_creates_steps_and_goals() {
+    let dir = TempDir::new().expect("temp dir");
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "add-tree",
+            "Tree Plan",
+            "Plan content",
+            "--step",
+            "Step A",
+            "--executor",
+            "ai",
+            "--goal",
+            "Goal A1",
+            "--goal",
+            "Goal A2",
+            "--step",
+            "Step B",
+            "--executor",
+            "human",
+        ],
+        None,
+    ));
+    let plan_id = parse_plan_id(&stdout);
+
+    let detail = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "show", &plan_id.to_string()],
+        None,
+    ));
+    assert!(detail.contains("Step A"));
+    assert!(detail.contains("Goal A1"));
+    assert!(detail.contains("Goal A2"));
+    assert!(detail.contains("Step B"));
+}
+
+#[test]
+fn plan_add_tree_sets_plan_and_step_schedule() {
+    let dir = TempDir::new().expect("temp dir");
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "add-tree",
+            "--priority",
+            "p0",
+            "--due",
+            "2999-01-31",
+            "Tree Plan",
+            "Plan content",
+            "--step",
+            "Step A",
+            "--priority",
+            "p1",
+            "--due",
+            "2999-01-15",
+            "--goal",
+            "Goal A1",
+            "--step",
+            "Step B",
+        ],
+        None,
+    ));
+    let plan_id = parse_plan_id(&stdout);
+
+    let plan = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "show", &plan_id.to_string()],
+        None,
+    ));
+    assert!(plan.contains("Priority: p0\n"), "plan: {plan}");
+    assert!(plan.contains("Due: 2999-01-31\n"), "plan: {plan}");
+    let step_a = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "show", "1"],
+        None,
+    ));
+    assert!(step_a.contains("Priority: p1\n"), "step: {step_a}");
+    assert!(step_a.contains("Due: 2999-01-15\n"), "step: {step_a}");
+    let step_b = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "show", "2"],
+        None,
+    ));
+    assert!(!step_b.contains("Priority:"), "step: {step_b}");
+
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "add-tree", "Plan", "Content", "--step", "Step", "--priority", "p9"],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid priority 'p9'"), "stderr: {stderr}");
+}
+
+#[test]
+fn plan_add_tree_rejects_json_step_spec() {
+    let dir = TempDir::new().expect("temp dir");
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "add-tree",
+            "Plan",
+            "Content",
+            "--step",
+            r#"{"content":"Step"}"#,
+        ],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no longer accepts JSON"));
+}
+
+#[test]
+fn plan_add_tree_rejects_empty_step_content() {
+    let dir = TempDir::new().expect("temp dir");
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "add-tree", "Plan", "Content", "--step", "   "],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("plan add-tree --step cannot be empty"));
+}
+
+#[test]
+fn plan_add_tree_rejects_empty_goal_content() {
+    let dir = TempDir::new().expect("temp dir");
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "add-tree",
+            "Plan",
+            "Content",
+            "--step",
+            "Step",
+            "--goal",
+            "   ",
+        ],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("goal content cannot be empty"));
+}
+
+#[test]
+fn step_add_tree_creates_goals() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "step",
+            "add-tree",
+            &plan_id.to_string(),
+            "Step with goals",
+            "--executor",
+            "ai",
+            "--goal",
+            "G1",
+            "--goal",
+            "G2",
+        ],
+        None,
+    ));
+    let step_id = parse_step_id(&stdout);
+
+    let goals = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "list", &step_id.to_string(), "--all"],
+        None,
+    ));
+    assert!(goals.contains("G1"));
+    assert!(goals.contains("G2"));
+}
+
+#[test]
+fn step_add_tree_sets_priority_and_due() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "step",
+            "add-tree",
+            &plan_id.to_string(),
+            "Scheduled step",
+            "--priority",
+            "p2",
+            "--due",
+            "2999-02-01",
+            "--goal",
+            "G1",
+        ],
+        None,
+    ));
+    let step_id = parse_step_id(&stdout);
+
+    let detail = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "show", &step_id.to_string()],
+        None,
+    ));
+    assert!(detail.contains("Priority: p2\n"), "detail: {detail}");
+    assert!(detail.contains("Due: 2999-02-01\n"), "detail: {detail}");
+}
+
+#[test]
+fn step_comment_rejects_empty_comment() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "comment", &step_id.to_string(), "   "],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("comment cannot be empty"));
+}
+
+#[test]
+fn goal_done_multiple_ids_marks_all_done() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let g1 = add_goal(&dir, step_id, "G1");
+    let g2 = add_goal(&dir, step_id, "G2");
+
+    let output = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "done", &g1.to_string(), &g2.to_string()],
+        None,
+    ));
+    assert!(output.contains("Goals marked done"));
+
+    let goals = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "list", &step_id.to_string(), "--all"],
+        None,
+    ));
+    assert!(goals.contains("done"));
+}
+
+#[test]
+fn goal_comment_rejects_empty_comment() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let goal_id = add_goal(&dir, step_id, "Goal 1");
+    let output = run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "comment", &goal_id.to_string(), "   "],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("comment cannot be empty"));
+}
+
+#[test]
+fn step_done_all_goals_marks_goals_and_step() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let _g1 = add_goal(&dir, step_id, "G1");
+    let _g2 = add_goal(&dir, step_id, "G2");
+
+    let output = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "done", &step_id.to_string(), "--all-goals"],
+        None,
+    ));
+    assert!(output.contains("Step ID"));
+
+    let goals = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "list", &step_id.to_string(), "--all"],
+        None,
+    ));
+    assert!(goals.contains("done"));
+}
+
+#[test]
+fn step_show_next_no_active_plan() {
+    let dir = TempDir::new().expect("temp dir");
+    let output = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["step", "show-next"], None));
+    assert_eq!(output.trim(), "No active plan.");
+}
+
+#[test]
+fn step_show_next_displays_detail() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let _step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    activate_plan(&dir, plan_id);
+
+    let output = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["step", "show-next"], None));
+    assert!(output.contains("Step ID"));
+    assert!(output.contains("Step 1"));
+}
+
+#[test]
+fn step_done_with_next_ai_step_prompts_end_turn() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step1 = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let _step2 = add_step(&dir, plan_id, "Step 2", Some("ai"));
+
+    let output = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "done", &step1.to_string()],
+        None,
+    ));
+    assert!(output.contains("Next step is assigned to ai"));
+}
+
+#[test]
+fn goal_done_with_next_human_step_prints_detail() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step1 = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let _step2 = add_step(&dir, plan_id, "Step 2", Some("human"));
+    let goal_id = add_goal(&dir, step1, "Goal 1");
+
+    let output = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["goal", "done", &goal_id.to_string()],
+        None,
+    ));
+    assert!(output.contains("Next step requires human action:"));
+    assert!(output.contains("Step ID"));
+    assert!(output.contains("Step 2"));
+    assert!(output.contains(
+        "Tell the user to complete the above step and goals. Confirm each goal when done, then end this turn."
+    ));
+}
+
+#[test]
+fn plan_done_prompts_summary_and_end_turn() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+
+    let output = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "done", &plan_id.to_string()],
+        None,
+    ));
+    assert!(output.contains("Plan ID:"));
+    assert!(output.contains("Summarize the completed results to the user, then end this turn."));
+}
+
+#[test]
+fn shared_plan_sessions_claim_different_steps() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let first_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let second_id = add_step(&dir, plan_id, "Step 2", Some("ai"));
+    activate_plan(&dir, plan_id);
+    let project = project_dir(&dir);
+
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        Some("other-session"),
+        &["plan", "activate", &plan_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--shared"), "stderr: {stderr}");
+
+    output_stdout(run_cmd_with_env(
+        Some(project.as_path()),
+        Some("other-session"),
+        &["plan", "activate", &plan_id.to_string(), "--shared"],
+        None,
+    ));
+
+    // Without --claim, show-next only looks.
+    let stdout = output_stdout(run_cmd_with_env(
+        Some(project.as_path()),
+        Some("other-session"),
+        &["step", "show-next"],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Step ID: {first_id}")));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show-next", "--claim"],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Step ID: {first_id}")));
+
+    let stdout = output_stdout(run_cmd_with_env(
+        Some(project.as_path()),
+        Some("other-session"),
+        &["step", "show-next", "--claim"],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Step ID: {second_id}")));
+
+    let output = run_cmd_with_env(
+        Some(project.as_path()),
+        Some("other-session"),
+        &["step", "claim", &first_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is claimed by session test-session"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn plan_md_updates_on_step_add() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    activate_plan(&dir, plan_id);
+
+    output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "add", &plan_id.to_string(), "First step"],
+        None,
+    ));
+
+    let contents = std::fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(contents.contains("First step"));
+}
+
+#[test]
+fn plan_export_writes_markdown() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    add_goal(&dir, step_id, "Goal 1");
+    activate_plan(&dir, plan_id);
+
+    let export_path = dir.path().join("export.md");
+    output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &[
+            "plan",
+            "export",
+            &plan_id.to_string(),
+            export_path.to_str().expect("export path"),
+        ],
+        None,
+    ));
+
+    let contents = std::fs::read_to_string(&export_path).expect("read export.md");
+    assert!(contents.contains("## Plan: Plan"));
+    assert!(contents.contains("Step 1"));
+    assert!(contents.contains("Goal 1"));
+    assert!(contents.contains("**Active:** `true`"));
+}
+
+#[test]
+fn plan_export_renders_mermaid_and_dot_diagrams() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let design = add_step(&dir, plan_id, "Design \"v2\"", Some("ai"));
+    add_goal(&dir, design, "Sketch");
+    let rollout = add_step(&dir, plan_id, "Roll out", Some("human"));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "add", &plan_id.to_string(), "Prototype", "--parent", &design.to_string()],
+        None,
+    ));
+    let prototype = parse_step_id(&stdout);
+
+    let mermaid = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "export", &plan_id.to_string(), "-", "--format", "mermaid"],
+        None,
+    ));
+    assert!(mermaid.starts_with("flowchart TD\n"), "mermaid: {mermaid}");
+    let design_node = format!(
+        "s{design}[\"[todo] Design #quot;v2#quot;<br/>step {design} · ai · goals 0/1\
+         <br/>[ ] Sketch\"]"
+    );
+    for expected in [
+        design_node,
+        format!("s{design} --> s{rollout}"),
+        format!("s{design} -.-> s{prototype}"),
+        format!("style s{rollout} fill:#fff3cd,stroke:#d9480f"),
+    ] {
+        assert!(mermaid.contains(&expected), "missing {expected}: {mermaid}");
+    }
+
+    let export_path = dir.path().join("plan.dot");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &[
+            "plan",
+            "export",
+            &plan_id.to_string(),
+            export_path.to_str().expect("export path"),
+            "--format",
+            "dot",
+        ],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Exported plan ID: {plan_id}")));
+    let dot = fs::read_to_string(&export_path).expect("read plan.dot");
+    assert!(dot.starts_with(&format!("digraph plan_{plan_id} {{")), "dot: {dot}");
+    assert!(dot.contains("[todo] Design \\\"v2\\\"\\l"), "dot: {dot}");
+    assert!(dot.contains(&format!("s{design} -> s{rollout};")), "dot: {dot}");
+    assert!(dot.contains(&format!("s{design} -> s{prototype} [style=dashed];")), "dot: {dot}");
+}
+
+#[test]
+fn plan_export_writes_self_contained_html() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Ship <beta>", Some("ai"));
+    let goal_id = add_goal(&dir, step_id, "Write notes");
+    add_goal(&dir, step_id, "Tag release");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "comment", &goal_id.to_string(), "Drafted & reviewed"],
+        None,
+    ));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "done", &goal_id.to_string()],
+        None,
+    ));
+
+    let export_path = dir.path().join("report.html");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &[
+            "plan",
+            "export",
+            &plan_id.to_string(),
+            export_path.to_str().expect("export path"),
+            "--format",
+            "html",
+        ],
+        None,
+    ));
+    let html = fs::read_to_string(&export_path).expect("read report.html");
+    assert!(html.starts_with("<!DOCTYPE html>"), "html: {html}");
+    assert!(!html.contains("<script") && !html.contains("<link"), "html: {html}");
+    for expected in [
+        "<details class=\"step\" open><summary><span class=\"badge todo\">todo</span> \
+         Ship &lt;beta&gt;",
+        "goals 1/2",
+        "<span style=\"width:50%\">",
+        "<div class=\"comment\">Drafted &amp; reviewed</div>",
+        "<span class=\"badge done\">done</span> Write notes",
+    ] {
+        assert!(html.contains(expected), "missing {expected}: {html}");
+    }
+}
+
+#[test]
+fn plan_import_md_reads_checklists_and_exported_plans() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let todo_path = dir.path().join("TODO.md");
+    fs::write(
+        &todo_path,
+        "# Beta launch\n\nShip the beta.\n\n- [x] Write docs\n  - [x] API\n  - [ ] CLI\n\
+         - [ ] Release it\n",
+    )
+    .expect("write TODO.md");
+    let todo = todo_path.to_str().expect("todo path");
+
+    let dry_run = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "import-md", todo, "--dry-run"],
+        None,
+    ));
+    assert!(
+        dry_run.starts_with(
+            "planpilot plan add-tree 'Beta launch' 'Ship the beta.' \\\n  \
+             --step 'Write docs' --goal API --goal CLI \\\n  --step 'Release it'\n"
+        ),
+        "stdout: {dry_run}"
+    );
+    assert!(dry_run.contains("# then mark step 1 done"), "stdout: {dry_run}");
+    assert!(dry_run.contains("steps: 2 (1 done), goals: 2 (1 done)"), "stdout: {dry_run}");
+    let list = output_stdout(run_cmd(Some(project.as_path()), &["plan", "list", "--all"], None));
+    assert!(!list.contains("Beta launch"), "stdout: {list}");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "import-md", todo],
+        None,
+    ));
+    assert!(stdout.contains("(imported from TODO.md, steps: 2, goals: 2)"), "stdout: {stdout}");
+    assert!(
+        stdout.contains("status auto-updated from done to todo (goals done 1/2)"),
+        "stdout: {stdout}"
+    );
+    let plan_id = parse_plan_id(&stdout);
+
+    let step_id = add_step(&dir, plan_id, "Follow up", Some("human"));
+    add_goal(&dir, step_id, "Email users");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "comment", &step_id.to_string(), "after launch"],
+        None,
+    ));
+    let export_path = dir.path().join("plan.md");
+    let export = export_path.to_str().expect("export path");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "export", &plan_id.to_string(), export],
+        None,
+    ));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "import-md", export, "--title", "Beta launch again"],
+        None,
+    ));
+    assert!(stdout.contains("(imported from plan.md, steps: 3, goals: 3)"), "stdout: {stdout}");
+    let copy_id = parse_plan_id(&stdout);
+    let show = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &copy_id.to_string()],
+        None,
+    ));
+    for expected in [
+        "Title: Beta launch again",
+        "Content: Ship the beta.",
+        "- [done] API",
+        "- [todo] CLI",
+        "- [todo] Follow up (step id",
+        "exec human, goals 0/1)",
+        "Comment: after launch",
+    ] {
+        assert!(show.contains(expected), "missing {expected}: {show}");
+    }
+}
+
+#[test]
+fn plan_github_issue_json_round_trips() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Review", Some("human"));
+    let goal_id = add_goal(&dir, step_id, "Security sign-off");
+    add_goal(&dir, step_id, "Docs sign-off");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "done", &goal_id.to_string()],
+        None,
+    ));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "tag", &plan_id.to_string(), "release"],
+        None,
+    ));
+
+    let body = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "export", &plan_id.to_string(), "-", "--format", "github-body"],
+        None,
+    ));
+    assert!(
+        body.ends_with(
+            "- [ ] Review *(exec: human)*\n  - [x] Security sign-off\n  - [ ] Docs sign-off\n"
+        ),
+        "stdout: {body}"
+    );
+
+    let issue_path = dir.path().join("issue.json");
+    let issue = issue_path.to_str().expect("issue path");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "export", &plan_id.to_string(), issue, "--format", "github"],
+        None,
+    ));
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&issue_path).expect("read issue.json"))
+            .expect("issue json");
+    assert_eq!(json["title"], "Plan");
+    assert_eq!(json["body"], body.as_str());
+    assert_eq!(json["labels"][0]["name"], "release");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "import-github", issue],
+        None,
+    ));
+    assert!(
+        stdout.contains("(imported from GitHub issue, steps: 1, goals: 2)"),
+        "stdout: {stdout}"
+    );
+    let show = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &parse_plan_id(&stdout).to_string()],
+        None,
+    ));
+    for expected in [
+        "Tags: release",
+        "- [todo] Review (step id",
+        "exec human, goals 1/2)",
+        "- [done] Security sign-off",
+    ] {
+        assert!(show.contains(expected), "missing {expected}: {show}");
+    }
+
+    let list_path = dir.path().join("issues.json");
+    fs::write(
+        &list_path,
+        r#"[{"number": 12, "title": "Add login", "labels": [{"name": "good first issue"}],
+            "body": "- [x] Schema\n- [ ] Form"}]"#,
+    )
+    .expect("write issues.json");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "import-github", list_path.to_str().expect("list path")],
+        None,
+    ));
+    assert!(
+        stdout.contains("Add login (imported from GitHub issue #12, steps: 2, goals: 0)"),
+        "stdout: {stdout}"
+    );
+    let list = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--tag", "good-first-issue"],
+        None,
+    ));
+    assert!(list.contains("Add login"), "stdout: {list}");
+}
+
+#[test]
+fn serve_exposes_plans_and_gates_writes() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Ship", None);
+    let goal_id = add_goal(&dir, step_id, "Tag release");
+    activate_plan(&dir, plan_id);
+
+    let server = spawn_serve(&project, &[]);
+    let (status, page) = http(server.port, "GET", "/", "");
+    assert_eq!(status, 200);
+    assert!(page.starts_with("<!DOCTYPE html>") && page.contains("/api/plans"), "page: {page}");
+
+    let (status, body) = http(server.port, "GET", "/api/plans", "");
+    assert_eq!(status, 200, "body: {body}");
+    let plans: Value = serde_json::from_str(&body).expect("plans json");
+    assert_eq!(plans[0]["id"], plan_id);
+    assert_eq!(plans[0]["steps_total"], 1);
+
+    let (_, body) = http(server.port, "GET", &format!("/api/plans/{plan_id}"), "");
+    let detail: Value = serde_json::from_str(&body).expect("plan json");
+    assert_eq!(detail["steps"][0]["goals"][0]["content"], "Tag release");
+
+    let (_, body) = http(server.port, "GET", "/api/sessions", "");
+    let sessions: Value = serde_json::from_str(&body).expect("sessions json");
+    let active = sessions
+        .as_array()
+        .expect("sessions")
+        .iter()
+        .find(|session| session["session_id"] == "test-session")
+        .expect("test session");
+    assert_eq!(active["plan_id"], plan_id);
+
+    assert_eq!(http(server.port, "GET", "/api/steps/999", "").0, 404);
+    let (status, body) = http(server.port, "POST", &format!("/api/goals/{goal_id}/done"), "");
+    assert_eq!(status, 403, "body: {body}");
+    assert!(body.contains("--allow-writes"), "body: {body}");
+    drop(server);
+
+    let server = spawn_serve(&project, &["--allow-writes"]);
+    let comment_path = format!("/api/goals/{goal_id}/comment");
+    let comment = r#"{"comment": "via dashboard"}"#;
+    let (status, body) = http_with_headers(
+        server.port,
+        "POST",
+        &comment_path,
+        "Content-Type: text/plain\r\n",
+        comment,
+    );
+    assert_eq!(status, 403, "body: {body}");
+    assert!(body.contains("application/json"), "body: {body}");
+    let (status, body) = http_with_headers(
+        server.port,
+        "POST",
+        &comment_path,
+        "Content-Type: application/json\r\nOrigin: http://evil.example\r\n",
+        comment,
+    );
+    assert_eq!(status, 403, "body: {body}");
+    assert!(body.contains("cross-origin"), "body: {body}");
+    let same_origin = format!(
+        "Content-Type: application/json\r\nOrigin: http://127.0.0.1:{}\r\n",
+        server.port
+    );
+    let (status, body) =
+        http_with_headers(server.port, "POST", &comment_path, &same_origin, comment);
+    assert_eq!(status, 200, "body: {body}");
+    let (status, body) = http(server.port, "POST", &format!("/api/goals/{goal_id}/done"), "");
+    assert_eq!(status, 200, "body: {body}");
+    let done: Value = serde_json::from_str(&body).expect("done json");
+    assert_eq!(done["goal"]["status"], "done");
+    assert!(done["changes"]
+        .as_array()
+        .expect("changes")
+        .iter()
+        .any(|change| change["kind"] == "plan" && change["to"] == "done"));
+
+    let show = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "show", &goal_id.to_string()],
+        None,
+    ));
+    assert!(show.contains("Comment: via dashboard"), "stdout: {show}");
+    let plan_md = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(plan_md.contains("- [x] Tag release"), "plan.md: {plan_md}");
+}
+
+#[test]
+fn watch_streams_changes_to_the_watched_plan() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let other_plan = create_plan(&dir);
+    let build = add_step(&dir, plan_id, "Build", Some("human"));
+    let schema = add_goal(&dir, build, "Schema");
+
+    let watcher = spawn_watch(&project, &["--plan", &plan_id.to_string()]);
+    add_step(&dir, other_plan, "Elsewhere", None);
+    let ship = add_step(&dir, plan_id, "Ship", None);
+    let first = watcher.lines.recv_timeout(Duration::from_secs(10)).expect("first event");
+    assert!(
+        first.ends_with(&format!("Step ID: {ship} added to plan ID: {plan_id} (ai): Ship")),
+        "line: {first}"
+    );
+
+    activate_plan(&dir, plan_id);
+    watcher.expect(&format!("Session test-session activated plan ID: {plan_id}: Plan"));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "done", &schema.to_string()],
+        None,
+    ));
+    let goal_line = watcher.expect(&format!("Goal ID: {schema} status todo -> done: Schema"));
+    assert!(goal_line.starts_with('['), "line: {goal_line}");
+    watcher.expect(&format!("Step ID: {build} status todo -> done: Build"));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "comment", &ship.to_string(), "almost there"],
+        None,
+    ));
+    let comment = watcher.expect("comment:");
+    assert_eq!(
+        comment.split("] ").nth(1),
+        Some(format!("Step ID: {ship} comment: almost there").as_str())
+    );
+}
+
+#[test]
+fn event_hooks_receive_status_transitions_after_commit() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let build = add_step(&dir, plan_id, "Build", None);
+    let schema = add_goal(&dir, build, "Schema");
+    let review = add_step(&dir, plan_id, "Review", Some("human"));
+
+    // Hooks run in the project dir after the lock is released, so they can call planpilot.
+    let record = "cat >> events.jsonl";
+    for event in ["goal.done", "step.done", "plan.done", "plan.activated", "step.human_required"] {
+        let stdout = output_stdout(run_cmd(
+            Some(project.as_path()),
+            &["event", "add", event, record],
+            None,
+        ));
+        assert!(stdout.starts_with("Created event hook ID: "), "stdout: {stdout}");
+    }
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["event", "add", "goal.done", "sleep 5", "--timeout", "1"],
+        None,
+    ));
+    let list = output_stdout(run_cmd(Some(project.as_path()), &["event", "list"], None));
+    assert!(list.contains("step.human_required  10s      cat >> events.jsonl"), "list: {list}");
+
+    activate_plan(&dir, plan_id);
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["goal", "done", &schema.to_string()],
+        None,
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(stderr.contains("(goal.done) timed out after 1s."), "stderr: {stderr}");
+    output_stdout(output);
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &review.to_string()],
+        None,
+    ));
+
+    let log = fs::read_to_string(project.join("events.jsonl")).expect("read events");
+    let events: Vec<Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("event json"))
+        .collect();
+    let names: Vec<&str> = events.iter().filter_map(|event| event["event"].as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "plan.activated",
+            "goal.done",
+            "step.done",
+            "step.human_required",
+            "step.done",
+            "plan.done",
+        ]
+    );
+    assert_eq!(events[0]["active_session_id"], "test-session");
+    assert_eq!(events[1]["goal"]["id"], schema);
+    assert_eq!(events[1]["step"]["goals_done"], 1);
+    assert_eq!(events[2]["step"]["status"], "done");
+    assert_eq!(events[3]["step"]["id"], review);
+    assert_eq!(events[3]["step"]["executor"], "human");
+    assert_eq!(events[5]["plan"]["status"], "done");
+    assert!(events.iter().all(|event| event["session_id"] == "test-session"));
+
+    let removed = output_stdout(run_cmd(Some(project.as_path()), &["event", "remove", "1"], None));
+    assert!(removed.contains("Event hook ID: 1 removed."), "stdout: {removed}");
+    let output = run_cmd(Some(project.as_path()), &["event", "remove", "1"], None);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn webhooks_keep_events_in_the_outbox_until_the_endpoint_is_up() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let build = add_step(&dir, plan_id, "Build", None);
+    let review = add_step(&dir, plan_id, "Review", Some("human"));
+    // Reserve a port, then leave it closed so the first deliveries fail.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let url = format!("http://127.0.0.1:{port}/hooks");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &[
+            "webhook",
+            "add",
+            &url,
+            "--secret",
+            "s3cret",
+            "--event",
+            "step.human_required",
+            "--event",
+            "step.done",
+        ],
+        None,
+    ));
+    assert!(
+        stdout.contains(&format!("for {url} (step.done, step.human_required).")),
+        "stdout: {stdout}"
+    );
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["webhook", "add", "ftp://x", "--secret", "s"],
+        None,
+    );
+    assert!(!output.status.success());
+
+    activate_plan(&dir, plan_id);
+    let output = run_cmd(Some(project.as_path()), &["step", "done", &build.to_string()], None);
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(!stderr.contains("webhook"), "stderr: {stderr}");
+    output_stdout(output);
+    // Delivery runs in a background process; wait for its first failed attempt to be recorded.
+    let mut outbox = String::new();
+    for _ in 0..100 {
+        outbox = output_stdout(run_cmd(Some(project.as_path()), &["webhook", "outbox"], None));
+        if outbox.contains("failed to connect to") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let rows: Vec<&str> = outbox.lines().skip(1).collect();
+    assert_eq!(rows.len(), 2, "outbox: {outbox}");
+    assert!(rows[0].contains("step.done            1 "), "outbox: {outbox}");
+    assert!(rows[0].contains("failed to connect to 127.0.0.1:"), "outbox: {outbox}");
+    assert!(rows[1].contains("step.human_required  0         now"), "outbox: {outbox}");
+    let list = output_stdout(run_cmd(Some(project.as_path()), &["webhook", "list"], None));
+    assert!(list.contains("2        http://127.0.0.1:"), "list: {list}");
+
+    let stub = spawn_webhook_stub(TcpListener::bind(("127.0.0.1", port)).expect("bind"), 2);
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["webhook", "deliver"], None));
+    assert!(
+        stdout.contains("Delivered 2 of 2 pending webhook deliveries."),
+        "stdout: {stdout}"
+    );
+    let requests = stub.join().expect("stub");
+    let header = |headers: &[(String, String)], name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+    let mut events = Vec::new();
+    for (headers, body) in &requests {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").expect("hmac");
+        mac.update(body.as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert_eq!(header(headers, "x-planpilot-signature"), signature);
+        assert_eq!(header(headers, "content-type"), "application/json");
+        let event: Value = serde_json::from_str(body).expect("event json");
+        assert_eq!(header(headers, "x-planpilot-event"), event["event"].as_str().unwrap());
+        events.push(event);
+    }
+    assert_eq!(events[0]["event"], "step.done");
+    assert_eq!(events[0]["step"]["id"], build);
+    assert_eq!(events[1]["event"], "step.human_required");
+    assert_eq!(events[1]["step"]["id"], review);
+    let outbox = output_stdout(run_cmd(Some(project.as_path()), &["webhook", "outbox"], None));
+    assert!(outbox.contains("No pending webhook deliveries."), "outbox: {outbox}");
+
+    let removed = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["webhook", "remove", "1"],
+        None,
+    ));
+    assert!(removed.contains("Webhook ID: 1 removed."), "stdout: {removed}");
+}
+
+#[test]
+fn tui_toggles_goals_and_reorders_steps_from_stdin() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let review = add_step(&dir, plan_id, "Review", Some("human"));
+    let read = add_goal(&dir, review, "Read the diff");
+    let approve = add_goal(&dir, review, "Approve");
+    let ship = add_step(&dir, plan_id, "Ship", None);
+
+    // Open the plan, expand Review, tick both goals, comment one, move Ship first, activate.
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["tui"],
+        Some("1\n1\n2\n3\nc 3 looks good\nm 4 1\nd 9\na\nq\n"),
+    ));
+    assert!(stdout.contains(&format!("Goal ID: {read} marked done.")), "stdout: {stdout}");
+    assert!(
+        stdout.contains(&format!("- Step ID: {review} status auto-updated from todo to done")),
+        "stdout: {stdout}"
+    );
+    assert!(stdout.contains(&format!("Moved step ID: {ship} to position 1.")), "stdout: {stdout}");
+    assert!(stdout.contains("Error: Invalid input: no row 9"), "stdout: {stdout}");
+    assert!(stdout.contains(&format!("Active plan set to {plan_id}: Plan")), "stdout: {stdout}");
+
+    let show = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "show", &approve.to_string()],
+        None,
+    ));
+    assert!(show.contains("Status: done"), "stdout: {show}");
+    assert!(show.contains("Comment: looks good"), "stdout: {show}");
+    let plan_md = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    let ship_at = plan_md.find("Ship").expect("Ship in plan.md");
+    let review_at = plan_md.find("Review").expect("Review in plan.md");
+    assert!(ship_at < review_at, "plan.md: {plan_md}");
+}
+
+#[test]
+fn export_prints_one_quoted_row_per_goal() {
+    let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
+    let plan_id = create_plan(&dir);
+    let step_id = add_step(&dir, plan_id, "Ship \"v2\", then\nannounce", Some("human"));
+    let goal_id = add_goal(&dir, step_id, "Tag\trelease");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "comment", &goal_id.to_string(), "done, mostly"],
+        None,
+    ));
+    add_step(&dir, plan_id, "Follow up", None);
+    let other_plan = create_plan(&dir);
+    add_step(&dir, other_plan, "Elsewhere", None);
+
+    let csv = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["export", "--plan", &plan_id.to_string()],
+        None,
+    ));
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(lines.len(), 3, "stdout: {csv}");
+    assert_eq!(
+        lines[0],
+        "plan_id,plan_title,step_id,step_content,step_executor,step_status,goal_id,\
+         goal_content,goal_status,comment,created_at,updated_at"
+    );
+    assert!(
+        lines[1].starts_with(&format!(
+            "{plan_id},Plan,{step_id},\"Ship \"\"v2\"\", then\nannounce\",human,todo,\
+             {goal_id},Tag\trelease,todo,\"done, mostly\","
+        )),
+        "stdout: {csv}"
+    );
+    assert!(lines[2].contains(",Follow up,ai,todo,,,,,"), "stdout: {csv}");
+    assert!(!csv.contains("Elsewhere"), "stdout: {csv}");
+
+    let tsv = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["export", "--format", "tsv", "--all"],
+        None,
+    ));
+    assert_eq!(tsv.split_terminator("\r\n").count(), 4, "stdout: {tsv}");
+    assert!(tsv.contains("\t\"Tag\trelease\"\ttodo\tdone, mostly\t"), "stdout: {tsv}");
+    assert!(tsv.contains("\tElsewhere\t"), "stdout: {tsv}");
+
+    let output = run_cmd(Some(project.as_path()), &["export"], None);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no active plan; pass --plan <id> or --all"), "stderr: {stderr}");
+}
+
+#[test]
+fn plan_list_filters_status() {
+    let dir = TempDir::new().expect("temp dir");
+    let todo_stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "add", "Todo Plan", "Content"],
+        None,
+    ));
+    let _todo_id = parse_plan_id(&todo_stdout);
+
+    let done_stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "add", "Done Plan", "Content"],
+        None,
+    ));
+    let done_id = parse_plan_id(&done_stdout);
+    output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["plan", "done", &done_id.to_string()],
+        None,
+    ));
+
+    let stdout = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "list"], None));
+    assert!(stdout.contains("Todo Plan"));
+    assert!(!stdout.contains("Done Plan"));
+
+    let stdout_all = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "list", "--all"], None));
+    assert!(stdout_all.contains("Todo Plan"));
+    assert!(stdout_all.contains("Done Plan"));
 }
 
 #[test]
-fn goal_done_multiple_ids_marks_all_done() {
+fn plan_list_sorts_by_priority_and_filters_overdue() {
     let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
-    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let g1 = add_goal(&dir, step_id, "G1");
-    let g2 = add_goal(&dir, step_id, "G2");
+    let project = project_dir(&dir);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Later Plan", "Content", "--priority", "p2"],
+        None,
+    ));
+    let later_id = parse_plan_id(&stdout);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Urgent Plan", "Content", "--priority", "p0", "--due", "2000-01-01"],
+        None,
+    ));
+    let urgent_id = parse_plan_id(&stdout);
 
-    let output = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "done", &g1.to_string(), &g2.to_string()],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--sort", "priority"],
         None,
     ));
-    assert!(output.contains("Goals marked done"));
+    assert!(stdout.contains("PRI  DUE"), "stdout: {stdout}");
+    let urgent_pos = stdout.find("Urgent Plan").expect("urgent listed");
+    let later_pos = stdout.find("Later Plan").expect("later listed");
+    assert!(urgent_pos < later_pos, "stdout: {stdout}");
 
-    let goals = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "list", &step_id.to_string(), "--all"],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--overdue"],
         None,
     ));
-    assert!(goals.contains("done"));
-}
+    assert!(stdout.contains("Urgent Plan"));
+    assert!(!stdout.contains("Later Plan"));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &urgent_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Priority: p0"));
+    assert!(stdout.contains("Due: 2000-01-01 (overdue)"));
+
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "update", &later_id.to_string(), "--due", "2000-02-01"],
+        None,
+    ));
+    let contents = fs::read_to_string(plan_md_path(&dir, later_id)).expect("read plan.md");
+    assert!(contents.contains("- **Priority:** `p2`"), "contents: {contents}");
+    assert!(contents.contains("- **Due:** 2000-02-01 (overdue)"));
 
-#[test]
-fn goal_comment_rejects_empty_comment() {
-    let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
-    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let goal_id = add_goal(&dir, step_id, "Goal 1");
     let output = run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "comment", &goal_id.to_string(), "   "],
+        Some(project.as_path()),
+        &["plan", "update", &later_id.to_string(), "--due", "tomorrow"],
         None,
     );
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("comment cannot be empty"));
+    assert!(stderr.contains("invalid due date 'tomorrow'"), "stderr: {stderr}");
 }
 
 #[test]
-fn step_done_all_goals_marks_goals_and_step() {
+fn plan_and_step_tags_filter_lists() {
     let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
-    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let _g1 = add_goal(&dir, step_id, "G1");
-    let _g2 = add_goal(&dir, step_id, "G2");
+    let project = project_dir(&dir);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Backend Plan", "Content"],
+        None,
+    ));
+    let backend_id = parse_plan_id(&stdout);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Docs Plan", "Content"],
+        None,
+    ));
+    let docs_id = parse_plan_id(&stdout);
 
-    let output = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "done", &step_id.to_string(), "--all-goals"],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "tag", &backend_id.to_string(), "backend", "infra"],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Plan ID: {backend_id} tags: backend, infra")));
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "tag", &docs_id.to_string(), "docs"],
         None,
     ));
-    assert!(output.contains("Step ID"));
 
-    let goals = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "list", &step_id.to_string(), "--all"],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--tag", "backend", "--tag", "docs"],
         None,
     ));
-    assert!(goals.contains("done"));
-}
+    assert!(stdout.contains("No plans found."), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--tag", "backend", "--tag", "docs", "--tag-mode", "any"],
+        None,
+    ));
+    assert!(stdout.contains("Backend Plan") && stdout.contains("Docs Plan"));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "search", "--search", "Plan", "--tag", "docs"],
+        None,
+    ));
+    assert!(stdout.contains("Docs Plan") && !stdout.contains("Backend Plan"));
 
-#[test]
-fn step_show_next_no_active_plan() {
-    let dir = TempDir::new().expect("temp dir");
-    let output = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["step", "show-next"], None));
-    assert_eq!(output.trim(), "No active plan.");
+    let step_id = add_step(&dir, backend_id, "Tagged step", None);
+    add_step(&dir, backend_id, "Plain step", None);
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "tag", &step_id.to_string(), "docs"],
+        None,
+    ));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "list", &backend_id.to_string(), "--tag", "docs"],
+        None,
+    ));
+    assert!(stdout.contains("Tagged step") && !stdout.contains("Plain step"));
+
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["tag", "list"], None));
+    assert!(stdout.contains("TAG"), "stdout: {stdout}");
+    let docs_line = stdout
+        .lines()
+        .find(|line| line.starts_with("docs "))
+        .expect("docs line");
+    assert_eq!(docs_line.split_whitespace().collect::<Vec<_>>(), vec!["docs", "1", "1"]);
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &backend_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Tags: backend, infra"));
+    let contents = fs::read_to_string(plan_md_path(&dir, backend_id)).expect("read plan.md");
+    assert!(contents.contains("- **Tags:** `backend` `infra`"));
 }
 
 #[test]
-fn step_show_next_displays_detail() {
+fn step_add_parent_nests_sub_steps() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
     let plan_id = create_plan(&dir);
-    let _step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
+    let parent_id = add_step(&dir, plan_id, "Parent step", None);
+    add_step(&dir, plan_id, "Later step", None);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "add", &plan_id.to_string(), "Child step", "--parent", &parent_id.to_string()],
+        None,
+    ));
+    let child_id = parse_step_id(&stdout);
     activate_plan(&dir, plan_id);
 
-    let output = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["step", "show-next"], None));
-    assert!(output.contains("Step ID"));
-    assert!(output.contains("Step 1"));
-}
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &plan_id.to_string()],
+        None,
+    ));
+    let parent_line = stdout.find("- [todo] Parent step").expect("parent line");
+    let child_line = stdout
+        .find(&format!("  - [todo] Child step (step id {child_id}"))
+        .expect("child line");
+    let later_line = stdout.find("- [todo] Later step").expect("later line");
+    assert!(parent_line < child_line && child_line < later_line, "stdout: {stdout}");
+    let contents = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(contents.contains(&format!("\n  - [ ] **Child step** *(id: {child_id}")));
+
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["step", "show-next"], None));
+    assert!(stdout.contains(&format!("Step ID: {child_id}")), "stdout: {stdout}");
+    assert!(stdout.contains(&format!("Parent Step ID: {parent_id}")));
 
-#[test]
-fn step_done_with_next_ai_step_prompts_end_turn() {
-    let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
-    let step1 = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let _step2 = add_step(&dir, plan_id, "Step 2", Some("ai"));
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &parent_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("next pending sub-step: Child step"), "stderr: {stderr}");
 
-    let output = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "done", &step1.to_string()],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "done", &child_id.to_string()],
         None,
     ));
-    assert!(output.contains("Next step is assigned to ai"));
+    assert!(stdout.contains(&format!("Step ID: {parent_id} status auto-updated")), "{stdout}");
 }
 
 #[test]
-fn goal_done_with_next_human_step_prints_detail() {
+fn step_split_and_merge_keep_goals() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
     let plan_id = create_plan(&dir);
-    let step1 = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    let _step2 = add_step(&dir, plan_id, "Step 2", Some("human"));
-    let goal_id = add_goal(&dir, step1, "Goal 1");
+    let step_id = add_step(&dir, plan_id, "Big step", None);
+    let done_goal = add_goal(&dir, step_id, "Done goal");
+    let todo_goal = add_goal(&dir, step_id, "Todo goal");
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "done", &done_goal.to_string()],
+        None,
+    ));
 
-    let output = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["goal", "done", &goal_id.to_string()],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "split", &step_id.to_string(), "--into", "Part one", "--into", "Part two"],
         None,
     ));
-    assert!(output.contains("Next step requires human action:"));
-    assert!(output.contains("Step ID"));
-    assert!(output.contains("Step 2"));
-    assert!(output.contains(
-        "Tell the user to complete the above step and goals. Confirm each goal when done, then end this turn."
+    let new_id = step_id + 1;
+    assert!(
+        stdout.contains(&format!("Split step ID: {step_id} into steps: {step_id}, {new_id}.")),
+        "stdout: {stdout}"
+    );
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &step_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Status: done") && stdout.contains("Done goal"), "{stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "show", &new_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("- [todo] Todo goal (goal id {todo_goal})")), "{stdout}");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "merge", &step_id.to_string(), &new_id.to_string()],
+        None,
     ));
+    assert!(stdout.contains(&format!("Merged step ID: {new_id} into step ID: {step_id}.")));
+    assert!(stdout.contains(&format!("Step ID: {step_id} status auto-updated from done to todo")));
+    let contents = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(contents.contains("Todo goal") && !contents.contains("Part two"));
 }
 
 #[test]
-fn plan_done_prompts_summary_and_end_turn() {
+fn goal_and_step_move_across_parents() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
     let plan_id = create_plan(&dir);
+    let first = add_step(&dir, plan_id, "First step", None);
+    let second = add_step(&dir, plan_id, "Second step", None);
+    let goal_a = add_goal(&dir, first, "Goal A");
+    let goal_b = add_goal(&dir, first, "Goal B");
 
-    let output = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "done", &plan_id.to_string()],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "move", &goal_b.to_string(), "--to", "1"],
         None,
     ));
-    assert!(output.contains("Plan ID:"));
-    assert!(output.contains("Summarize the completed results to the user, then end this turn."));
+    assert!(stdout.contains(&format!("Reordered goals for step ID: {first}:")));
+    let b_pos = stdout.find("Goal B").expect("goal b");
+    let a_pos = stdout.find("Goal A").expect("goal a");
+    assert!(b_pos < a_pos, "stdout: {stdout}");
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["goal", "move", &goal_a.to_string(), "--step", &second.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Moved goal ID: {goal_a} to step ID: {second}:")));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Other Plan", "Content"],
+        None,
+    ));
+    let other_id = parse_plan_id(&stdout);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["step", "move", &second.to_string(), "--plan", &other_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Moved step ID: {second} to plan ID: {other_id}:")));
+    assert!(stdout.contains("Second step"));
+    let contents = fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
+    assert!(!contents.contains("Second step"));
+    let contents = fs::read_to_string(plan_md_path(&dir, other_id)).expect("read plan.md");
+    assert!(contents.contains("Second step") && contents.contains("Goal A"));
+
+    let output = run_cmd(Some(project.as_path()), &["step", "move", &first.to_string()], None);
+    assert!(!output.status.success());
 }
 
 #[test]
-fn plan_md_updates_on_step_add() {
+fn plan_clone_copies_steps_and_shows_lineage() {
     let dir = TempDir::new().expect("temp dir");
+    let project = project_dir(&dir);
     let plan_id = create_plan(&dir);
-    activate_plan(&dir, plan_id);
-
+    let step_id = add_step(&dir, plan_id, "Run migration", Some("human"));
+    let goal_id = add_goal(&dir, step_id, "Backup database");
     output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["step", "add", &plan_id.to_string(), "First step"],
+        Some(project.as_path()),
+        &["goal", "done", &goal_id.to_string()],
         None,
     ));
 
-    let contents = std::fs::read_to_string(plan_md_path(&dir, plan_id)).expect("read plan.md");
-    assert!(contents.contains("First step"));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "clone", &plan_id.to_string(), "--title", "Rerun", "--reset-status"],
+        None,
+    ));
+    assert!(
+        stdout.contains(&format!("(cloned from plan ID: {plan_id}, steps: 1, goals: 1)")),
+        "stdout: {stdout}"
+    );
+    let clone_id = parse_plan_id(&stdout);
+
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "show", &clone_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains("Title: Rerun"));
+    assert!(stdout.contains(&format!("Cloned From: plan {plan_id}")));
+    assert!(stdout.contains("- [todo] Run migration") && stdout.contains("exec human"));
+    assert!(stdout.contains("- [todo] Backup database"), "stdout: {stdout}");
+    let contents = fs::read_to_string(plan_md_path(&dir, clone_id)).expect("read plan.md");
+    assert!(contents.contains(&format!("- **Cloned From:** plan `{plan_id}`")));
 }
 
 #[test]
-fn plan_export_writes_markdown() {
+fn plan_archive_hides_plan_and_moves_snapshot() {
     let dir = TempDir::new().expect("temp dir");
-    let plan_id = create_plan(&dir);
-    let step_id = add_step(&dir, plan_id, "Step 1", Some("ai"));
-    add_goal(&dir, step_id, "Goal 1");
+    let project = project_dir(&dir);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "add", "Shelved Plan", "Content"],
+        None,
+    ));
+    let plan_id = parse_plan_id(&stdout);
     activate_plan(&dir, plan_id);
 
-    let export_path = dir.path().join("export.md");
-    output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &[
-            "plan",
-            "export",
-            &plan_id.to_string(),
-            export_path.to_str().expect("export path"),
-        ],
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "archive", &plan_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Plan ID: {plan_id} archived.")));
+    assert!(stdout.contains("Active plan deactivated because plan is archived."));
+    let archived_md = plan_md_path(&dir, plan_id)
+        .parent()
+        .expect("plans dir")
+        .join("archive")
+        .join(format!("plan_{plan_id}.md"));
+    assert!(archived_md.exists());
+    assert!(!plan_md_path(&dir, plan_id).exists());
+    let contents = fs::read_to_string(&archived_md).expect("read archived plan.md");
+    assert!(contents.contains("- **Archived:**"));
+
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["plan", "list", "--all"], None));
+    assert!(!stdout.contains("Shelved Plan"), "stdout: {stdout}");
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--archived"],
         None,
     ));
+    assert!(stdout.contains("Shelved Plan"), "stdout: {stdout}");
+    let output = run_cmd(
+        Some(project.as_path()),
+        &["plan", "activate", &plan_id.to_string()],
+        None,
+    );
+    assert!(!output.status.success());
 
-    let contents = std::fs::read_to_string(&export_path).expect("read export.md");
-    assert!(contents.contains("## Plan: Plan"));
-    assert!(contents.contains("Step 1"));
-    assert!(contents.contains("Goal 1"));
-    assert!(contents.contains("**Active:** `true`"));
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "unarchive", &plan_id.to_string()],
+        None,
+    ));
+    assert!(stdout.contains(&format!("Plan ID: {plan_id} unarchived.")));
+    assert!(plan_md_path(&dir, plan_id).exists());
+    assert!(!archived_md.exists());
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["plan", "list"], None));
+    assert!(stdout.contains("Shelved Plan"), "stdout: {stdout}");
 }
 
 #[test]
-fn plan_list_filters_status() {
+fn auto_archive_runs_at_most_once_a_day() {
     let dir = TempDir::new().expect("temp dir");
-    let todo_stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "add", "Todo Plan", "Content"],
+    let project = project_dir(&dir);
+    let auto_archive = |args: &[&str]| {
+        let output = Command::new(bin_path())
+            .arg("--cwd")
+            .arg(&project)
+            .arg("--session-id")
+            .arg("test-session")
+            .args(args)
+            .env("CLAUDE_PLUGIN_ROOT", plugin_root(&project))
+            .env("PLANPILOT_AUTO_ARCHIVE_DAYS", "0")
+            .output()
+            .expect("run planpilot");
+        output_stdout(output)
+    };
+    let first = create_plan(&dir);
+    output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "done", &first.to_string()],
         None,
     ));
-    let _todo_id = parse_plan_id(&todo_stdout);
 
-    let done_stdout = output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "add", "Done Plan", "Content"],
+    auto_archive(&["plan", "list", "--all"]);
+    let stdout = output_stdout(run_cmd(
+        Some(project.as_path()),
+        &["plan", "list", "--archived"],
         None,
     ));
-    let done_id = parse_plan_id(&done_stdout);
+    assert!(stdout.contains(&format!("{first} ")), "stdout: {stdout}");
+    assert!(claude_home(&dir).join(".planpilot").join("auto-archive").exists());
+
+    let second = create_plan(&dir);
     output_stdout(run_cmd(
-        Some(project_dir(&dir).as_path()),
-        &["plan", "done", &done_id.to_string()],
+        Some(project.as_path()),
+        &["plan", "done", &second.to_string()],
         None,
     ));
-
-    let stdout = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "list"], None));
-    assert!(stdout.contains("Todo Plan"));
-    assert!(!stdout.contains("Done Plan"));
-
-    let stdout_all = output_stdout(run_cmd(Some(project_dir(&dir).as_path()), &["plan", "list", "--all"], None));
-    assert!(stdout_all.contains("Todo Plan"));
-    assert!(stdout_all.contains("Done Plan"));
+    auto_archive(&["plan", "list", "--all"]);
+    let stdout = output_stdout(run_cmd(Some(project.as_path()), &["plan", "list", "--all"], None));
+    assert!(stdout.contains(&format!("{second} ")), "stdout: {stdout}");
+
+    // A broken setting is reported without failing the command.
+    let output = Command::new(bin_path())
+        .arg("--cwd")
+        .arg(&project)
+        .arg("--session-id")
+        .arg("test-session")
+        .args(["plan", "list", "--all"])
+        .env("CLAUDE_PLUGIN_ROOT", plugin_root(&project))
+        .env("PLANPILOT_AUTO_ARCHIVE_DAYS", "soon")
+        .output()
+        .expect("run planpilot");
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(
+        stderr.contains("auto-archive failed: Invalid input: PLANPILOT_AUTO_ARCHIVE_DAYS must be"),
+        "stderr: {stderr}"
+    );
+    assert!(output_stdout(output).contains(&format!("{second} ")));
 }
 
 #[test]
@@ -993,6 +3063,32 @@ fn step_list_filters_status_and_executor() {
     assert!(!stdout.contains("Bravo"));
 }
 
+#[test]
+fn step_list_filters_named_executors() {
+    let dir = TempDir::new().expect("temp dir");
+    let plan_id = create_plan(&dir);
+    add_step(&dir, plan_id, "Alpha", Some("ai"));
+    add_step(&dir, plan_id, "Bravo", Some("ai:reviewer"));
+    add_step(&dir, plan_id, "Charlie", Some("human:alice"));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "list", &plan_id.to_string(), "--executor", "ai"],
+        None,
+    ));
+    assert!(stdout.contains("Alpha"));
+    assert!(stdout.contains("Bravo"));
+    assert!(!stdout.contains("Charlie"));
+
+    let stdout = output_stdout(run_cmd(
+        Some(project_dir(&dir).as_path()),
+        &["step", "list", &plan_id.to_string(), "--executor", "human:alice"],
+        None,
+    ));
+    assert!(stdout.contains("Charlie"));
+    assert!(!stdout.contains("Alpha"));
+}
+
 #[test]
 fn goal_list_reports_missing_step() {
     let dir = TempDir::new().expect("temp dir");